@@ -26,6 +26,9 @@ Source7: host-ctr-toml
 Source8: oci-default-hooks-json
 Source9: cfsignal-toml
 Source10: warm-pool-wait-toml
+Source11: signpost-toml
+Source12: apiserver-access-policy-toml
+Source13: user-data-keys
 
 # 1xx sources: systemd units
 Source100: apiserver.service
@@ -46,6 +49,11 @@ Source117: cfsignal.service
 Source118: generate-network-config.service
 Source119: reboot-if-required.service
 Source120: warm-pool-wait.service
+Source121: update-maintenance-window.service
+Source122: update-maintenance-window.timer
+Source123: check-update-health.service
+Source124: metricdog-exporter.service
+Source125: netdog-dns-stub.service
 
 # 2xx sources: tmpfilesd configs
 Source200: migration-tmpfiles.conf
@@ -53,6 +61,7 @@ Source201: host-containers-tmpfiles.conf
 Source202: thar-be-updates-tmpfiles.conf
 Source203: bootstrap-containers-tmpfiles.conf
 Source204: netdog-tmpfiles.conf
+Source205: apiserver-tmpfiles.conf
 
 # 3xx sources: udev rules
 Source300: ephemeral-storage.rules
@@ -309,6 +318,11 @@ static_output="$(mktemp)"
 # Save the PID so we can wait for it later.
 static_pid="$!"
 
+%if %{with systemd_networkd}
+# netdog's build script checks this to generate config for systemd-networkd rather than wicked.
+export SYSTEMD_NETWORKD=1
+%endif
+
 # Run non-static builds in the foreground.
 echo "** Output from non-static builds:"
 %cargo_build --manifest-path %{_builddir}/sources/Cargo.toml \
@@ -403,13 +417,14 @@ install -d %{buildroot}%{_cross_datadir}/updog
 install -p -m 0644 %{_cross_repo_root_json} %{buildroot}%{_cross_datadir}/updog
 
 install -d %{buildroot}%{_cross_templatedir}
-install -p -m 0644 %{S:5} %{S:6} %{S:7} %{S:8} %{buildroot}%{_cross_templatedir}
+install -p -m 0644 %{S:5} %{S:6} %{S:7} %{S:8} %{S:11} %{buildroot}%{_cross_templatedir}
 
 install -d %{buildroot}%{_cross_unitdir}
 install -p -m 0644 \
   %{S:100} %{S:101} %{S:102} %{S:103} %{S:105} \
   %{S:106} %{S:107} %{S:110} %{S:111} %{S:112} \
   %{S:113} %{S:114} %{S:118} %{S:119} \
+  %{S:121} %{S:122} %{S:123} %{S:124} %{S:125} \
   %{buildroot}%{_cross_unitdir}
 
 %if %{with nvidia_flavor}
@@ -430,12 +445,24 @@ install -p -m 0644 %{S:9} %{buildroot}%{_cross_templatedir}
 install -p -m 0644 %{S:117} %{buildroot}%{_cross_unitdir}
 %endif
 
+# Variants can trust their own keys for signed user data with a user-data-keys-VARIANT file.
+install -d %{buildroot}%{_cross_datadir}/bottlerocket
+user_data_keys=%{S:13}
+if [ -f %{_sourcedir}/user-data-keys-%{_cross_variant} ]; then
+  user_data_keys=%{_sourcedir}/user-data-keys-%{_cross_variant}
+fi
+install -p -m 0644 "${user_data_keys}" %{buildroot}%{_cross_datadir}/bottlerocket/user-data-keys
+
 install -d %{buildroot}%{_cross_tmpfilesdir}
 install -p -m 0644 %{S:200} %{buildroot}%{_cross_tmpfilesdir}/migration.conf
 install -p -m 0644 %{S:201} %{buildroot}%{_cross_tmpfilesdir}/host-containers.conf
 install -p -m 0644 %{S:202} %{buildroot}%{_cross_tmpfilesdir}/thar-be-updates.conf
 install -p -m 0644 %{S:203} %{buildroot}%{_cross_tmpfilesdir}/bootstrap-containers.conf
 install -p -m 0644 %{S:204} %{buildroot}%{_cross_tmpfilesdir}/netdog.conf
+install -p -m 0644 %{S:205} %{buildroot}%{_cross_tmpfilesdir}/apiserver.conf
+
+install -d %{buildroot}%{_cross_factorydir}%{_cross_sysconfdir}/bottlerocket
+install -p -m 0644 %{S:12} %{buildroot}%{_cross_factorydir}%{_cross_sysconfdir}/bottlerocket/apiserver-access-policy.toml
 
 install -d %{buildroot}%{_cross_udevrulesdir}
 install -p -m 0644 %{S:300} %{buildroot}%{_cross_udevrulesdir}/80-ephemeral-storage.rules
@@ -451,6 +478,9 @@ install -p -m 0644 %{S:300} %{buildroot}%{_cross_udevrulesdir}/80-ephemeral-stor
 %{_cross_unitdir}/apiserver.service
 %{_cross_unitdir}/migrator.service
 %{_cross_sysusersdir}/api.conf
+%{_cross_tmpfilesdir}/apiserver.conf
+%dir %{_cross_factorydir}%{_cross_sysconfdir}/bottlerocket
+%{_cross_factorydir}%{_cross_sysconfdir}/bottlerocket/apiserver-access-policy.toml
 
 %files -n %{_cross_os}apiclient
 %{_cross_bindir}/apiclient
@@ -458,11 +488,13 @@ install -p -m 0644 %{S:300} %{buildroot}%{_cross_udevrulesdir}/80-ephemeral-stor
 %files -n %{_cross_os}early-boot-config
 %{_cross_bindir}/early-boot-config
 %{_cross_unitdir}/early-boot-config.service
+%{_cross_datadir}/bottlerocket/user-data-keys
 
 %files -n %{_cross_os}netdog
 %{_cross_bindir}/netdog
 %{_cross_tmpfilesdir}/netdog.conf
 %{_cross_unitdir}/generate-network-config.service
+%{_cross_unitdir}/netdog-dns-stub.service
 
 %files -n %{_cross_os}corndog
 %{_cross_bindir}/corndog
@@ -484,6 +516,8 @@ install -p -m 0644 %{S:300} %{buildroot}%{_cross_udevrulesdir}/80-ephemeral-stor
 %files -n %{_cross_os}thar-be-updates
 %{_cross_bindir}/thar-be-updates
 %{_cross_tmpfilesdir}/thar-be-updates.conf
+%{_cross_unitdir}/update-maintenance-window.service
+%{_cross_unitdir}/update-maintenance-window.timer
 
 %files -n %{_cross_os}host-containers
 %{_cross_bindir}/host-containers
@@ -514,6 +548,9 @@ install -p -m 0644 %{S:300} %{buildroot}%{_cross_udevrulesdir}/80-ephemeral-stor
 %files -n %{_cross_os}signpost
 %{_cross_bindir}/signpost
 %{_cross_unitdir}/mark-successful-boot.service
+%{_cross_unitdir}/check-update-health.service
+%dir %{_cross_templatedir}
+%{_cross_templatedir}/signpost-toml
 
 %files -n %{_cross_os}updog
 %{_cross_bindir}/updog
@@ -527,6 +564,7 @@ install -p -m 0644 %{S:300} %{buildroot}%{_cross_udevrulesdir}/80-ephemeral-stor
 %{_cross_templatedir}/metricdog-toml
 %{_cross_unitdir}/metricdog.service
 %{_cross_unitdir}/metricdog.timer
+%{_cross_unitdir}/metricdog-exporter.service
 %{_cross_unitdir}/send-boot-success.service
 
 %files -n %{_cross_os}logdog
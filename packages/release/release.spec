@@ -21,6 +21,7 @@ Source204: modprobe-conf.template
 Source205: netdog.template
 Source206: aws-config
 Source207: aws-credentials
+Source208: netdog-interfaces.template
 
 Source1001: multi-user.target
 Source1002: configured.target
@@ -39,9 +40,11 @@ Source1012: opt-cni-bin.mount
 Source1013: local.mount
 Source1014: root-.aws.mount
 
-# CD-ROM mount & associated udev rules
+# CD-ROM and configuration disk mounts & associated udev rules
 Source1015: media-cdrom.mount
 Source1016: mount-cdrom.rules
+Source1017: media-seed.mount
+Source1018: mount-seed.rules
 
 # Mounts that require build-time edits.
 Source1020: var-lib-kernel-devel-lower.mount.in
@@ -138,7 +141,7 @@ install -p -m 0644 \
   %{S:1001} %{S:1002} %{S:1003} %{S:1004} %{S:1005} %{S:1006} %{S:1007} \
   %{S:1008} %{S:1009} %{S:1010} %{S:1011} %{S:1012} %{S:1013} %{S:1015} \
   %{S:1040} %{S:1041} %{S:1042} %{S:1043} %{S:1044} %{S:1045} %{S:1046} \
-  %{S:1060} %{S:1061} %{S:1062} %{S:1080} %{S:1014} \
+  %{S:1060} %{S:1061} %{S:1062} %{S:1080} %{S:1014} %{S:1017} \
   %{buildroot}%{_cross_unitdir}
 
 install -d %{buildroot}%{_cross_unitdir}/systemd-tmpfiles-setup.service.d
@@ -173,9 +176,11 @@ install -p -m 0644 %{S:204} %{buildroot}%{_cross_templatedir}/modprobe-conf
 install -p -m 0644 %{S:205} %{buildroot}%{_cross_templatedir}/netdog-toml
 install -p -m 0644 %{S:206} %{buildroot}%{_cross_templatedir}/aws-config
 install -p -m 0644 %{S:207} %{buildroot}%{_cross_templatedir}/aws-credentials
+install -p -m 0644 %{S:208} %{buildroot}%{_cross_templatedir}/netdog-interfaces-toml
 
 install -d %{buildroot}%{_cross_udevrulesdir}
 install -p -m 0644 %{S:1016} %{buildroot}%{_cross_udevrulesdir}/61-mount-cdrom.rules
+install -p -m 0644 %{S:1018} %{buildroot}%{_cross_udevrulesdir}/61-mount-seed.rules
 
 ln -s preconfigured.target %{buildroot}%{_cross_unitdir}/default.target
 
@@ -207,6 +212,7 @@ ln -s preconfigured.target %{buildroot}%{_cross_unitdir}/default.target
 %{_cross_unitdir}/etc-cni.mount
 %{_cross_unitdir}/opt-cni-bin.mount
 %{_cross_unitdir}/media-cdrom.mount
+%{_cross_unitdir}/media-seed.mount
 %{_cross_unitdir}/local.mount
 %{_cross_unitdir}/*-lower.mount
 %{_cross_unitdir}/*-kernels.mount
@@ -224,6 +230,7 @@ ln -s preconfigured.target %{buildroot}%{_cross_unitdir}/default.target
 %dir %{_cross_templatedir}
 %{_cross_templatedir}/modprobe-conf
 %{_cross_templatedir}/netdog-toml
+%{_cross_templatedir}/netdog-interfaces-toml
 %{_cross_templatedir}/motd
 %{_cross_templatedir}/proxy-env
 %{_cross_templatedir}/hostname-env
@@ -231,5 +238,6 @@ ln -s preconfigured.target %{buildroot}%{_cross_unitdir}/default.target
 %{_cross_templatedir}/aws-config
 %{_cross_templatedir}/aws-credentials
 %{_cross_udevrulesdir}/61-mount-cdrom.rules
+%{_cross_udevrulesdir}/61-mount-seed.rules
 
 %changelog
@@ -27,6 +27,31 @@ Or, request some specific settings:
 apiclient get settings.motd settings.kernel.lockdown
 ```
 
+Some settings hold secrets, like tokens, credentials, and container user data.
+To share your settings without exposing them, add `--redact`, which leaves out any setting marked sensitive:
+```shell
+apiclient get --redact settings
+```
+
+To see where settings came from, add `--explain`.
+Each setting is shown with its value, its source, and when it was set:
+```shell
+apiclient get --explain settings.motd
+```
+
+```json
+{
+  "settings.motd": {
+    "value": "hi there",
+    "source": "apiclient set",
+    "timestamp": "2022-01-01T00:00:00.000000000Z"
+  }
+}
+```
+
+Sources include `storewolf` for defaults, `early-boot-config` with the user data source, `sundog` for generated settings, and `apiclient set` or `apply` for changes made with apiclient.
+Settings changed through the API without naming a source show `api`, and settings with no recorded source only show their value.
+
 ### Set mode
 
 This allows you to change settings on the system.
@@ -75,6 +100,36 @@ You can use JSON form to set it:
 apiclient set --json '{"motd": "42"}'
 ```
 
+#### Previewing changes
+
+If you want to see what a change would do before making it, add `--dry-run`:
+
+```shell
+apiclient set --dry-run motd="hi there"
+```
+
+Nothing is committed or applied.
+Instead, the affected configuration files are rendered with the new settings, and you'll see a unified diff for each file that would change, along with the list of services that would be restarted.
+`apiclient apply --dry-run` works the same way for settings files.
+
+### History and revert modes
+
+Each committed settings change is recorded, with the old and new value of each setting it changed.
+You can see the recent history like this:
+
+```shell
+apiclient history
+```
+
+If a change turns out to be bad, you can revert it using the ID shown in the history:
+
+```shell
+apiclient revert 42
+```
+
+This restores the previous values and applies them to the system, the same way `set` does.
+A change can't be reverted if any of its settings have been changed again since; revert the later change first.
+
 ### Update mode
 
 To start, you can check what updates are available:
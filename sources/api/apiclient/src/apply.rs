@@ -2,6 +2,7 @@
 //! TOML settings files, in the same format as user data, or the JSON equivalent.  The inputs are
 //! pulled and applied to the API server in a single transaction.
 
+use crate::dry_run::{self, DryRunReport};
 use crate::rando;
 use futures::future::{join, ready, TryFutureExt};
 use futures::stream::{self, StreamExt};
@@ -14,6 +15,36 @@ use tokio::io::AsyncReadExt;
 /// Reads settings in TOML or JSON format from files at the requested URIs (or from stdin, if given
 /// "-"), then commits them in a single transaction and applies them to the system.
 pub async fn apply<P>(socket_path: P, input_sources: Vec<String>) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    let transaction = stage(&socket_path, input_sources).await?;
+
+    // Commit the transaction and apply it to the system.
+    let uri = format!("/tx/commit_and_apply?tx={}", transaction);
+    let method = "POST";
+    let (_status, _body) = crate::raw_request(&socket_path, &uri, method, None)
+        .await
+        .context(error::CommitApplySnafu { uri })?;
+
+    Ok(())
+}
+
+/// Reads settings like `apply`, but rather than committing them, reports the changes that applying
+/// them would make to configuration files, and the services that would be restarted.
+pub async fn apply_dry_run<P>(socket_path: P, input_sources: Vec<String>) -> Result<DryRunReport>
+where
+    P: AsRef<Path>,
+{
+    let transaction = stage(&socket_path, input_sources).await?;
+    dry_run::dry_run(&socket_path, &transaction)
+        .await
+        .context(error::DryRunSnafu)
+}
+
+/// Reads settings from the requested URIs and sends them to the API in a new transaction, without
+/// committing it.  Returns the name of the transaction.
+async fn stage<P>(socket_path: P, input_sources: Vec<String>) -> Result<String>
 where
     P: AsRef<Path>,
 {
@@ -45,7 +76,7 @@ where
     // Send the settings changes to the server in the same transaction.  (They're quick local
     // requests, so don't add the complexity of making them run concurrently.)
     for (input_source, json) in changes {
-        let uri = format!("/settings?tx={}", transaction);
+        let uri = format!("/settings?tx={}&source=apiclient%20apply", transaction);
         let method = "PATCH";
         let (_status, _body) = crate::raw_request(&socket_path, &uri, method, Some(json))
             .await
@@ -56,14 +87,7 @@ where
             })?;
     }
 
-    // Commit the transaction and apply it to the system.
-    let uri = format!("/tx/commit_and_apply?tx={}", transaction);
-    let method = "POST";
-    let (_status, _body) = crate::raw_request(&socket_path, &uri, method, None)
-        .await
-        .context(error::CommitApplySnafu { uri })?;
-
-    Ok(())
+    Ok(transaction)
 }
 
 /// Retrieves the given source location and returns the result in a String.
@@ -160,6 +184,9 @@ mod error {
             source: Box<crate::Error>,
         },
 
+        #[snafu(display("Failed to preview settings changes: {}", source))]
+        DryRun { source: crate::dry_run::Error },
+
         #[snafu(display("Failed to read given file '{}': {}", input_source, source))]
         FileRead {
             input_source: String,
@@ -0,0 +1,89 @@
+//! This module previews what applying a pending settings transaction would do to the system - the
+//! changes to configuration files and the services that would be restarted - without committing
+//! or applying anything.
+
+use serde::Deserialize;
+use snafu::ResultExt;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::path::Path;
+
+/// The API's report of what applying a pending transaction would do.
+#[derive(Debug, Deserialize)]
+pub struct DryRunReport {
+    /// The settings keys set in the pending transaction.
+    pub changed_settings: BTreeSet<String>,
+    /// Unified diffs from the current configuration files to the newly rendered ones, keyed by
+    /// file path.
+    pub file_diffs: BTreeMap<String, String>,
+    /// The services that would have their restart-commands run.
+    pub services: BTreeSet<String>,
+}
+
+impl fmt::Display for DryRunReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.file_diffs.is_empty() {
+            writeln!(f, "No configuration files would change.")?;
+        }
+        for diff in self.file_diffs.values() {
+            write!(f, "{}", diff)?;
+        }
+
+        if self.services.is_empty() {
+            writeln!(f, "No services would be restarted.")
+        } else {
+            writeln!(f, "Services that would be restarted:")?;
+            for service in &self.services {
+                writeln!(f, "  {}", service)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Asks the API what committing and applying the given pending transaction would do, then
+/// deletes the transaction, since the caller only wanted a preview.
+pub async fn dry_run<P>(socket_path: P, transaction: &str) -> Result<DryRunReport>
+where
+    P: AsRef<Path>,
+{
+    let uri = format!("/tx/dry-run?tx={}", transaction);
+    let method = "GET";
+    let response = crate::raw_request(&socket_path, &uri, method, None)
+        .await
+        .context(error::RequestSnafu { uri, method });
+
+    // Clean up the transaction whether or not the preview worked.
+    let uri = format!("/tx?tx={}", transaction);
+    let method = "DELETE";
+    let (_status, _body) = crate::raw_request(&socket_path, &uri, method, None)
+        .await
+        .context(error::RequestSnafu { uri, method })?;
+
+    let (_status, body) = response?;
+    serde_json::from_str(&body).context(error::ResponseJsonSnafu { body })
+}
+
+mod error {
+    use snafu::Snafu;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(super)))]
+    pub enum Error {
+        #[snafu(display("Failed {} request to '{}': {}", method, uri, source))]
+        Request {
+            method: String,
+            uri: String,
+            #[snafu(source(from(crate::Error, Box::new)))]
+            source: Box<crate::Error>,
+        },
+
+        #[snafu(display("Response contained invalid JSON '{}' - {}", body, source))]
+        ResponseJson {
+            body: String,
+            source: serde_json::Error,
+        },
+    }
+}
+pub use error::Error;
+pub type Result<T> = std::result::Result<T, error::Error>;
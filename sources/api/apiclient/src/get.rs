@@ -1,12 +1,21 @@
+use datastore::serialization::to_pairs;
+use datastore::{deserialize_scalar, ScalarError, Source};
+use serde::Serialize;
 use snafu::{OptionExt, ResultExt};
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
 mod merge_json;
-use merge_json::merge_json;
+pub use merge_json::merge_json;
 
 /// Fetches the given prefixes from the API and merges them into a single Value.  (It's not
-/// expected that given prefixes would overlap, but if they do, later ones take precedence.)
-pub async fn get_prefixes<P>(socket_path: P, prefixes: Vec<String>) -> Result<serde_json::Value>
+/// expected that given prefixes would overlap, but if they do, later ones take precedence.)  If
+/// `redact` is true, the API leaves out sensitive settings.
+pub async fn get_prefixes<P>(
+    socket_path: P,
+    prefixes: Vec<String>,
+    redact: bool,
+) -> Result<serde_json::Value>
 where
     P: AsRef<Path>,
 {
@@ -14,7 +23,10 @@ where
 
     // Fetch all given prefixes into separate Values.
     for prefix in prefixes {
-        let uri = format!("/?prefix={}", prefix);
+        let mut uri = format!("/?prefix={}", prefix);
+        if redact {
+            uri.push_str("&redact=true");
+        }
         let method = "GET";
         let (_status, body) = crate::raw_request(&socket_path, &uri, method, None)
             .await
@@ -33,6 +45,46 @@ where
         .context(error::NoPrefixesSnafu)
 }
 
+/// A setting's value, with where it came from and when it was set, if that was recorded.
+#[derive(Debug, Serialize)]
+pub struct Explanation {
+    pub value: serde_json::Value,
+    #[serde(flatten)]
+    pub source: Option<Source>,
+}
+
+/// Fetches the given prefixes like `get_prefixes`, and explains each setting under them, keyed by
+/// setting name.
+pub async fn explain<P>(
+    socket_path: P,
+    prefixes: Vec<String>,
+    redact: bool,
+) -> Result<BTreeMap<String, Explanation>>
+where
+    P: AsRef<Path>,
+{
+    let value = get_prefixes(&socket_path, prefixes, redact).await?;
+    let pairs = to_pairs(&value).context(error::FlattenSnafu)?;
+
+    let uri = "/metadata/sources".to_string();
+    let method = "GET";
+    let (_status, body) = crate::raw_request(&socket_path, &uri, method, None)
+        .await
+        .context(error::RequestSnafu { uri, method })?;
+    let mut sources: HashMap<String, Source> =
+        serde_json::from_str(&body).context(error::ResponseJsonSnafu { body })?;
+
+    let mut explanations = BTreeMap::new();
+    for (key, value_str) in pairs {
+        let value = deserialize_scalar::<_, ScalarError>(&value_str)
+            .context(error::ResponseJsonSnafu { body: value_str })?;
+        let name = key.name().to_string();
+        let source = sources.remove(&name);
+        explanations.insert(name, Explanation { value, source });
+    }
+    Ok(explanations)
+}
+
 /// Fetches the given URI from the API and returns the result as an untyped Value.
 pub async fn get_uri<P>(socket_path: P, uri: String) -> Result<serde_json::Value>
 where
@@ -51,6 +103,11 @@ mod error {
     #[derive(Debug, Snafu)]
     #[snafu(visibility(pub(super)))]
     pub enum Error {
+        #[snafu(display("Unable to flatten settings into keys: {}", source))]
+        Flatten {
+            source: datastore::serialization::Error,
+        },
+
         #[snafu(display("Must give prefixes to query"))]
         NoPrefixes,
 
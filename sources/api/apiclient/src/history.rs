@@ -0,0 +1,75 @@
+//! This module allows reviewing the history of committed settings transactions, and reverting a
+//! committed transaction so that its changes are undone on the system.
+
+use datastore::CommittedTransaction;
+use snafu::ResultExt;
+use std::collections::HashSet;
+use std::path::Path;
+
+/// Fetches the history of committed transactions from the API, oldest first.
+pub async fn history<P>(socket_path: P) -> Result<Vec<CommittedTransaction>>
+where
+    P: AsRef<Path>,
+{
+    let uri = "/tx/history";
+    let method = "GET";
+    let (_status, body) = crate::raw_request(&socket_path, uri, method, None)
+        .await
+        .context(error::RequestSnafu { uri, method })?;
+    serde_json::from_str(&body).context(error::ResponseJsonSnafu { body })
+}
+
+/// Reverts the committed transaction with the given ID, then applies the resulting changes to the
+/// system.  Returns the names of the changed keys.
+pub async fn revert<P>(socket_path: P, id: u64) -> Result<HashSet<String>>
+where
+    P: AsRef<Path>,
+{
+    let uri = format!("/tx/revert?id={}", id);
+    let method = "POST";
+    let (_status, body) = crate::raw_request(&socket_path, &uri, method, None)
+        .await
+        .context(error::RequestSnafu { uri, method })?;
+    let changed: HashSet<String> =
+        serde_json::from_str(&body).context(error::ResponseJsonSnafu { body })?;
+
+    // Apply only the reverted keys, so we don't restart unrelated services.  Key names can
+    // contain quotes, so they have to be encoded for the query string.
+    let keys = changed
+        .iter()
+        .map(|k| k.as_str())
+        .collect::<Vec<_>>()
+        .join(",");
+    let keys: String = url::form_urlencoded::byte_serialize(keys.as_bytes()).collect();
+    let uri = format!("/tx/apply?keys={}", keys);
+    let method = "POST";
+    let (_status, _body) = crate::raw_request(&socket_path, &uri, method, None)
+        .await
+        .context(error::RequestSnafu { uri, method })?;
+
+    Ok(changed)
+}
+
+mod error {
+    use snafu::Snafu;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(super)))]
+    pub enum Error {
+        #[snafu(display("Failed {} request to '{}': {}", method, uri, source))]
+        Request {
+            method: String,
+            uri: String,
+            #[snafu(source(from(crate::Error, Box::new)))]
+            source: Box<crate::Error>,
+        },
+
+        #[snafu(display("Response contained invalid JSON '{}' - {}", body, source))]
+        ResponseJson {
+            body: String,
+            source: serde_json::Error,
+        },
+    }
+}
+pub use error::Error;
+pub type Result<T> = std::result::Result<T, error::Error>;
@@ -6,7 +6,7 @@
 // library calls based on the given flags, etc.)  The library modules contain the code for talking
 // to the API, which is intended to be reusable by other crates.
 
-use apiclient::{apply, exec, get, reboot, set, update};
+use apiclient::{apply, exec, get, history, reboot, set, update};
 use datastore::{serialize_scalar, Key, KeyType};
 use log::{info, log_enabled, trace, warn};
 use simplelog::{
@@ -44,8 +44,10 @@ enum Subcommand {
     Apply(ApplyArgs),
     Exec(ExecArgs),
     Get(GetArgs),
+    History(HistoryArgs),
     Raw(RawArgs),
     Reboot(RebootArgs),
+    Revert(RevertArgs),
     Set(SetArgs),
     Update(UpdateSubcommand),
 }
@@ -54,6 +56,7 @@ enum Subcommand {
 #[derive(Debug)]
 struct ApplyArgs {
     input_sources: Vec<String>,
+    dry_run: bool,
 }
 
 /// Stores user-supplied arguments for the 'exec' subcommand.
@@ -67,10 +70,18 @@ struct ExecArgs {
 /// Stores user-supplied arguments for the 'get' subcommand.
 #[derive(Debug)]
 enum GetArgs {
-    Prefixes(Vec<String>),
+    Prefixes {
+        prefixes: Vec<String>,
+        redact: bool,
+        explain: bool,
+    },
     Uri(String),
 }
 
+/// Stores user-supplied arguments for the 'history' subcommand.
+#[derive(Debug)]
+struct HistoryArgs {}
+
 /// Stores user-supplied arguments for the 'raw' subcommand.
 #[derive(Debug)]
 struct RawArgs {
@@ -83,9 +94,22 @@ struct RawArgs {
 #[derive(Debug)]
 struct RebootArgs {}
 
+/// Stores user-supplied arguments for the 'revert' subcommand.
+#[derive(Debug)]
+struct RevertArgs {
+    id: u64,
+}
+
 /// Stores user-supplied arguments for the 'set' subcommand.
 #[derive(Debug)]
-enum SetArgs {
+struct SetArgs {
+    input: SetInput,
+    dry_run: bool,
+}
+
+/// Stores the settings the user gave to the 'set' subcommand, in the format they were given.
+#[derive(Debug)]
+enum SetInput {
     Simple(HashMap<Key, String>),
     Json(serde_json::Value),
 }
@@ -131,6 +155,9 @@ fn usage() -> ! {
                                        or from stdin.
             get                        Retrieve and print settings.
             set                        Changes settings and applies them to the system.
+            history                    Prints the history of committed settings changes.
+            revert                     Reverts a committed settings change and applies the
+                                       previous values to the system.
             update check               Prints information about available updates.
             update apply               Applies available updates.
             update cancel              Deactivates an applied update.
@@ -146,6 +173,9 @@ fn usage() -> ! {
             [ URI ...]                 The list of URIs to TOML or JSON settings files that you
                                        want to apply to the system.  If no URI is specified, or
                                        if "-" is given, reads from stdin.
+            --dry-run                  Rather than applying the settings, print the changes they
+                                       would make to config files, and the services that would
+                                       be restarted.
 
         reboot options:
             None.
@@ -154,6 +184,10 @@ fn usage() -> ! {
             [ PREFIX [PREFIX ...] ]    The settings you want to get.  Full settings names work fine,
                                        or you can specify prefixes to fetch all settings under them.
             [ /desired-uri ]           The API URI to fetch.  Cannot be specified with prefixes.
+            --redact                   Leave out sensitive settings, like tokens and credentials.
+            --explain                  Show each setting with where its value came from, like
+                                       defaults, user data, or 'apiclient set', and when it was
+                                       set.  Cannot be specified with a URI.
 
                                        If neither prefixes nor URI are specified, get will show
                                        settings and OS info.
@@ -168,6 +202,16 @@ fn usage() -> ! {
                                        which can simplify setting multiple values, and is necessary
                                        for some numeric settings.  For example:
                                           -j '{{"kernel": {{"sysctl": {{"vm.max_map_count": "262144"}}}}}}'
+            --dry-run                  Rather than applying the settings, print the changes they
+                                       would make to config files, and the services that would
+                                       be restarted.
+
+        history options:
+            None.
+
+        revert options:
+            ID                         Required; the ID of the committed transaction to revert,
+                                       as shown by 'history'.
 
         update check options:
             None.
@@ -233,7 +277,8 @@ fn parse_args(args: env::Args) -> (Args, Subcommand) {
             }
 
             // Subcommands
-            "raw" | "apply" | "exec" | "get" | "reboot" | "set" | "update"
+            "raw" | "apply" | "exec" | "get" | "history" | "reboot" | "revert" | "set"
+            | "update"
                 if subcommand.is_none() && !arg.starts_with('-') =>
             {
                 subcommand = Some(arg)
@@ -250,7 +295,9 @@ fn parse_args(args: env::Args) -> (Args, Subcommand) {
         Some("apply") => (global_args, parse_apply_args(subcommand_args)),
         Some("exec") => (global_args, parse_exec_args(subcommand_args)),
         Some("get") => (global_args, parse_get_args(subcommand_args)),
+        Some("history") => (global_args, parse_history_args(subcommand_args)),
         Some("reboot") => (global_args, parse_reboot_args(subcommand_args)),
+        Some("revert") => (global_args, parse_revert_args(subcommand_args)),
         Some("set") => (global_args, parse_set_args(subcommand_args)),
         Some("update") => (global_args, parse_update_args(subcommand_args)),
         _ => usage_msg("Missing or unknown subcommand"),
@@ -302,13 +349,16 @@ fn parse_raw_args(args: Vec<String>) -> Subcommand {
 /// Parses arguments for the 'apply' subcommand.
 fn parse_apply_args(args: Vec<String>) -> Subcommand {
     let mut input_sources = Vec::new();
+    let mut dry_run = false;
 
     for arg in args.into_iter() {
         match arg {
+            x if x == "--dry-run" => dry_run = true,
+
             // Allow "-" for stdin, but we have no other parameters.
-            x if x.starts_with('-') && x != "-" => {
-                usage_msg("apiclient apply takes no parameters, just a list of URIs.")
-            }
+            x if x.starts_with('-') && x != "-" => usage_msg(
+                "apiclient apply takes no parameters other than --dry-run, just a list of URIs.",
+            ),
 
             x => input_sources.push(x),
         }
@@ -319,7 +369,10 @@ fn parse_apply_args(args: Vec<String>) -> Subcommand {
         input_sources.push("-".to_string());
     }
 
-    Subcommand::Apply(ApplyArgs { input_sources })
+    Subcommand::Apply(ApplyArgs {
+        input_sources,
+        dry_run,
+    })
 }
 
 /// Parses arguments for the 'exec' subcommand.
@@ -366,9 +419,14 @@ fn parse_exec_args(args: Vec<String>) -> Subcommand {
 fn parse_get_args(args: Vec<String>) -> Subcommand {
     let mut prefixes = vec![];
     let mut uri = None;
+    let mut redact = false;
+    let mut explain = false;
 
     for arg in args.into_iter() {
         match &arg {
+            x if x == "--redact" => redact = true,
+            x if x == "--explain" => explain = true,
+
             x if x.starts_with('-') => usage_msg(format!("Unknown argument '{}'", x)),
 
             x if x.starts_with('/') => {
@@ -382,23 +440,43 @@ fn parse_get_args(args: Vec<String>) -> Subcommand {
         }
     }
 
-    if let Some(uri) = uri {
+    if let Some(mut uri) = uri {
         if !prefixes.is_empty() {
             usage_msg("You can specify prefixes or a URI, but not both.");
         }
+        if explain {
+            usage_msg("You can only explain settings prefixes, not a URI.");
+        }
+        if redact {
+            uri.push(if uri.contains('?') { '&' } else { '?' });
+            uri.push_str("redact=true");
+        }
         Subcommand::Get(GetArgs::Uri(uri))
     } else if !prefixes.is_empty() {
         if uri.is_some() {
             usage_msg("You can specify prefixes or a URI, but not both.");
         }
-        Subcommand::Get(GetArgs::Prefixes(prefixes))
+        Subcommand::Get(GetArgs::Prefixes {
+            prefixes,
+            redact,
+            explain,
+        })
     } else {
         // A reasonable default is showing OS info and settings.
-        Subcommand::Get(GetArgs::Prefixes(vec![
-            "os.".to_string(),
-            "settings.".to_string(),
-        ]))
+        Subcommand::Get(GetArgs::Prefixes {
+            prefixes: vec!["os.".to_string(), "settings.".to_string()],
+            redact,
+            explain,
+        })
+    }
+}
+
+/// Parses arguments for the 'history' subcommand.
+fn parse_history_args(args: Vec<String>) -> Subcommand {
+    if !args.is_empty() {
+        usage_msg(format!("Unknown arguments: {}", args.join(", ")));
     }
+    Subcommand::History(HistoryArgs {})
 }
 
 /// Parses arguments for the 'reboot' subcommand.
@@ -409,6 +487,29 @@ fn parse_reboot_args(args: Vec<String>) -> Subcommand {
     Subcommand::Reboot(RebootArgs {})
 }
 
+/// Parses arguments for the 'revert' subcommand.
+fn parse_revert_args(args: Vec<String>) -> Subcommand {
+    let mut id = None;
+
+    for arg in args.into_iter() {
+        match arg.as_str() {
+            x if x.starts_with('-') => usage_msg(format!("Unknown argument '{}'", x)),
+
+            x if id.is_none() => {
+                id = Some(x.parse().unwrap_or_else(|_| {
+                    usage_msg(format!("Transaction ID must be a number, got '{}'", x))
+                }))
+            }
+
+            _ => usage_msg("You can only revert one transaction at a time."),
+        }
+    }
+
+    Subcommand::Revert(RevertArgs {
+        id: id.unwrap_or_else(|| usage_msg("Missing required argument 'ID'")),
+    })
+}
+
 /// Parses arguments for the 'set' subcommand.
 // Note: the API doesn't allow setting non-settings keys, e.g. services, configuration-files, and
 // metadata.  If we allow it in the future, we should revisit this 'set' parsing code and decide
@@ -417,10 +518,13 @@ fn parse_reboot_args(args: Vec<String>) -> Subcommand {
 fn parse_set_args(args: Vec<String>) -> Subcommand {
     let mut simple = HashMap::new();
     let mut json = None;
+    let mut dry_run = false;
 
     let mut iter = args.into_iter();
     while let Some(arg) = iter.next() {
         match arg.as_ref() {
+            "--dry-run" => dry_run = true,
+
             "-j" | "--json" if json.is_some() => {
                 usage_msg(
                     "Can't specify the --json argument multiple times.  You can set as many \
@@ -479,15 +583,17 @@ fn parse_set_args(args: Vec<String>) -> Subcommand {
         }
     }
 
-    if json.is_some() && !simple.is_empty() {
+    let input = if json.is_some() && !simple.is_empty() {
         usage_msg("Cannot specify key=value pairs and --json settings with 'set'");
     } else if let Some(json) = json {
-        Subcommand::Set(SetArgs::Json(json))
+        SetInput::Json(json)
     } else if !simple.is_empty() {
-        Subcommand::Set(SetArgs::Simple(simple))
+        SetInput::Simple(simple)
     } else {
         usage_msg("Must specify key=value settings or --json settings with 'set'");
-    }
+    };
+
+    Subcommand::Set(SetArgs { input, dry_run })
 }
 
 /// Parses the desired subcommand of 'update'.
@@ -646,9 +752,16 @@ async fn run() -> Result<()> {
         }
 
         Subcommand::Apply(apply) => {
-            apply::apply(&args.socket_path, apply.input_sources)
-                .await
-                .context(error::ApplySnafu)?;
+            if apply.dry_run {
+                let report = apply::apply_dry_run(&args.socket_path, apply.input_sources)
+                    .await
+                    .context(error::ApplySnafu)?;
+                print!("{}", report);
+            } else {
+                apply::apply(&args.socket_path, apply.input_sources)
+                    .await
+                    .context(error::ApplySnafu)?;
+            }
         }
 
         Subcommand::Exec(exec) => {
@@ -660,7 +773,18 @@ async fn run() -> Result<()> {
         Subcommand::Get(get) => {
             let result = match get {
                 GetArgs::Uri(uri) => get::get_uri(&args.socket_path, uri).await,
-                GetArgs::Prefixes(prefixes) => get::get_prefixes(&args.socket_path, prefixes).await,
+                GetArgs::Prefixes {
+                    prefixes,
+                    redact,
+                    explain: true,
+                } => get::explain(&args.socket_path, prefixes, redact)
+                    .await
+                    .map(|explanations| {
+                        serde_json::to_value(explanations).expect("Explanations serialize to JSON")
+                    }),
+                GetArgs::Prefixes {
+                    prefixes, redact, ..
+                } => get::get_prefixes(&args.socket_path, prefixes, redact).await,
             };
             let value = result.context(error::GetSnafu)?;
             let pretty =
@@ -668,15 +792,32 @@ async fn run() -> Result<()> {
             println!("{}", pretty);
         }
 
+        Subcommand::History(_history) => {
+            let history = history::history(&args.socket_path)
+                .await
+                .context(error::HistorySnafu)?;
+            let pretty = serde_json::to_string_pretty(&history).context(error::SerializeSnafu)?;
+            println!("{}", pretty);
+        }
+
         Subcommand::Reboot(_reboot) => {
             reboot::reboot(&args.socket_path)
                 .await
                 .context(error::RebootSnafu)?;
         }
 
+        Subcommand::Revert(revert) => {
+            let changed = history::revert(&args.socket_path, revert.id)
+                .await
+                .context(error::RevertSnafu { id: revert.id })?;
+            let mut changed: Vec<_> = changed.into_iter().collect();
+            changed.sort();
+            info!("Reverted transaction {}: {}", revert.id, changed.join(", "));
+        }
+
         Subcommand::Set(set) => {
-            let settings = match set {
-                SetArgs::Simple(input_map) => {
+            let settings = match set.input {
+                SetInput::Simple(input_map) => {
                     // For key=val, we need some type information to deserialize into a Settings.
                     trace!("Original key=value input: {:#?}", input_map);
                     let massaged_map = massage_set_input(input_map)?;
@@ -687,16 +828,23 @@ async fn run() -> Result<()> {
                     datastore::deserialization::from_map(&massaged_map)
                         .context(error::DeserializeMapSnafu)?
                 }
-                SetArgs::Json(json) => {
+                SetInput::Json(json) => {
                     // No processing to do on JSON input; the format determines the types.  serde
                     // can turn a Value into the nested Settings structure itself.
                     serde_json::from_value(json).context(error::DeserializeJsonSnafu)?
                 }
             };
 
-            set::set(&args.socket_path, &settings)
-                .await
-                .context(error::SetSnafu)?;
+            if set.dry_run {
+                let report = set::set_dry_run(&args.socket_path, &settings)
+                    .await
+                    .context(error::SetSnafu)?;
+                print!("{}", report);
+            } else {
+                set::set(&args.socket_path, &settings)
+                    .await
+                    .context(error::SetSnafu)?;
+            }
         }
 
         Subcommand::Update(subcommand) => match subcommand {
@@ -752,7 +900,7 @@ async fn main() {
 }
 
 mod error {
-    use apiclient::{apply, exec, get, reboot, set, update};
+    use apiclient::{apply, exec, get, history, reboot, set, update};
     use snafu::Snafu;
 
     #[derive(Debug, Snafu)]
@@ -778,12 +926,18 @@ mod error {
         #[snafu(display("Failed to get settings: {}", source))]
         Get { source: get::Error },
 
+        #[snafu(display("Failed to get settings history: {}", source))]
+        History { source: history::Error },
+
         #[snafu(display("Logger setup error: {}", source))]
         Logger { source: log::SetLoggerError },
 
         #[snafu(display("Failed to reboot: {}", source))]
         Reboot { source: reboot::Error },
 
+        #[snafu(display("Failed to revert transaction {}: {}", id, source))]
+        Revert { id: u64, source: history::Error },
+
         #[snafu(display("Failed {} request to '{}': {}", method, uri, source))]
         Request {
             method: String,
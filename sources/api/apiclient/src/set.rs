@@ -1,3 +1,4 @@
+use crate::dry_run::{self, DryRunReport};
 use crate::rando;
 use snafu::ResultExt;
 use std::path::Path;
@@ -7,6 +8,37 @@ use std::path::Path;
 /// the settings you want to change.  If you're deserializing a request from a user, for example,
 /// the created Settings will only have the requested keys populated.
 pub async fn set<P>(socket_path: P, settings: &model::Settings) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    let transaction = stage(&socket_path, settings).await?;
+
+    // Commit the transaction and apply it to the system.
+    let uri = format!("/tx/commit_and_apply?tx={}", transaction);
+    let method = "POST";
+    let (_status, _body) = crate::raw_request(&socket_path, &uri, method, None)
+        .await
+        .context(error::RequestSnafu { uri, method })?;
+
+    Ok(())
+}
+
+/// Changes the requested settings through the API like `set`, but rather than committing them,
+/// reports the changes that applying them would make to configuration files, and the services that
+/// would be restarted.
+pub async fn set_dry_run<P>(socket_path: P, settings: &model::Settings) -> Result<DryRunReport>
+where
+    P: AsRef<Path>,
+{
+    let transaction = stage(&socket_path, settings).await?;
+    dry_run::dry_run(&socket_path, &transaction)
+        .await
+        .context(error::DryRunSnafu)
+}
+
+/// Sends the requested settings changes to the API in a new transaction, without committing it.
+/// Returns the name of the transaction.
+async fn stage<P>(socket_path: P, settings: &model::Settings) -> Result<String>
 where
     P: AsRef<Path>,
 {
@@ -14,21 +46,14 @@ where
     let transaction = format!("apiclient-set-{}", rando());
 
     // Send the settings changes to the server.
-    let uri = format!("/settings?tx={}", transaction);
+    let uri = format!("/settings?tx={}&source=apiclient%20set", transaction);
     let method = "PATCH";
     let request_body = serde_json::to_string(&settings).context(error::SerializeSnafu)?;
     let (_status, _body) = crate::raw_request(&socket_path, &uri, method, Some(request_body))
         .await
         .context(error::RequestSnafu { uri, method })?;
 
-    // Commit the transaction and apply it to the system.
-    let uri = format!("/tx/commit_and_apply?tx={}", transaction);
-    let method = "POST";
-    let (_status, _body) = crate::raw_request(&socket_path, &uri, method, None)
-        .await
-        .context(error::RequestSnafu { uri, method })?;
-
-    Ok(())
+    Ok(transaction)
 }
 
 mod error {
@@ -37,6 +62,9 @@ mod error {
     #[derive(Debug, Snafu)]
     #[snafu(visibility(pub(super)))]
     pub enum Error {
+        #[snafu(display("Failed to preview settings changes: {}", source))]
+        DryRun { source: crate::dry_run::Error },
+
         #[snafu(display("Unable to serialize data: {}", source))]
         Serialize { source: serde_json::Error },
 
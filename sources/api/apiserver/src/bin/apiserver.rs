@@ -15,6 +15,7 @@ use std::process;
 use std::str::FromStr;
 
 use apiserver::serve;
+use apiserver::server::AccessPolicy;
 
 /// By default, this is where we create the Unix-domain socket that exposes our API.
 const DEFAULT_BIND_PATH: &str = "/run/api.sock";
@@ -36,6 +37,9 @@ mod error {
         #[snafu(display("{}", source))]
         Server { source: apiserver::server::Error },
 
+        #[snafu(display("{}", source))]
+        AccessPolicy { source: apiserver::server::Error },
+
         #[snafu(display("Logger setup error: {}", source))]
         Logger { source: log::SetLoggerError },
     }
@@ -48,6 +52,7 @@ struct Args {
     socket_gid: Option<Gid>,
     socket_path: String,
     exec_socket_path: String,
+    access_policy_path: Option<String>,
 }
 
 /// Informs the user about proper usage of the program and exits.
@@ -59,11 +64,14 @@ fn usage() -> ! {
             [ --socket-path PATH ]
             [ --socket-gid GROUP_ID ]
             [ --exec-socket-path PATH ]
+            [ --access-policy PATH ]
             [ --no-color ]
             [ --log-level trace|debug|info|warn|error ]
 
     --socket-path defaults to {}
-    --exec-socket-path (for apiclient exec) defaults to {}",
+    --exec-socket-path (for apiclient exec) defaults to {}
+    --access-policy limits what clients can do based on their user and group IDs; by default,
+      anyone who can open the socket has full access",
         program_name, DEFAULT_BIND_PATH, DEFAULT_EXEC_SOCKET
     );
     process::exit(2);
@@ -82,6 +90,7 @@ fn parse_args(args: env::Args) -> Args {
     let mut socket_gid = None;
     let mut socket_path = None;
     let mut exec_socket_path = None;
+    let mut access_policy_path = None;
 
     let mut iter = args.skip(1);
     while let Some(arg) = iter.next() {
@@ -129,6 +138,13 @@ fn parse_args(args: env::Args) -> Args {
                     }))
             }
 
+            "--access-policy" => {
+                access_policy_path = Some(
+                    iter.next()
+                        .unwrap_or_else(|| usage_msg("Did not give argument to --access-policy")),
+                )
+            }
+
             _ => usage(),
         }
     }
@@ -139,6 +155,7 @@ fn parse_args(args: env::Args) -> Args {
         log_level: log_level.unwrap_or(LevelFilter::Info),
         socket_path: socket_path.unwrap_or_else(|| DEFAULT_BIND_PATH.to_string()),
         exec_socket_path: exec_socket_path.unwrap_or_else(|| DEFAULT_EXEC_SOCKET.to_string()),
+        access_policy_path,
     }
 }
 
@@ -161,6 +178,14 @@ async fn run() -> Result<()> {
     // automatically handled in new threads and won't block others.
     let threads = 1;
 
+    let access_policy = match &args.access_policy_path {
+        Some(path) => {
+            info!("Limiting client access with policy at {}", path);
+            Some(AccessPolicy::from_file(path).context(error::AccessPolicySnafu)?)
+        }
+        None => None,
+    };
+
     let threads_suffix = match threads {
         n if n > 1 => "s",
         _ => "",
@@ -176,6 +201,7 @@ async fn run() -> Result<()> {
         threads,
         args.socket_gid,
         args.exec_socket_path,
+        access_policy,
     )
     .await
     .context(error::ServerSnafu)
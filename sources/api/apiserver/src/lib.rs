@@ -25,6 +25,21 @@ Upon making a `/tx/commit` POST call, the pending transaction is made live.
 Upon making an `/tx/apply` POST call, an external settings applier tool is called to apply the changes to the system and restart services as necessary.
 There's also `/tx/commit_and_apply` to do both, which is the most common case.
 
+Before committing, `GET /tx/dry-run` shows what applying the pending transaction would do.
+It runs the settings applier in a mode that renders the affected config files without writing them, and returns a unified diff for each file that would change, along with the services that would be restarted.
+
+Committed transactions are recorded in a bounded history, which you can see with `GET /tx/history`.
+`POST /tx/revert?id=ID` restores the values from before the given commit; like a commit, you then apply the changes with `/tx/apply`.
+
+The settings applier records an event each time it starts applying changes, writes a config file, runs a restart command, and finishes.
+`GET /events` returns these events, oldest first, so you can see which settings change restarted which service.
+Events from one run of the settings applier share a `run_id`; add a `run_id` parameter to see only the events from that run.
+When `/tx/commit_and_apply` starts the settings applier, its events also have the commit's ID from `/tx/history` as their `transaction_id`; add a `transaction_id` parameter to see only the events from applying that commit.
+
+Clients that need to react to settings changes can `GET /settings/watch` instead of polling.
+It streams a server-sent event listing the changed keys each time a transaction is committed.
+A `prefix` parameter limits the events to keys starting with that prefix.
+
 If you don't specify a transaction, the "default" transaction is used, so you usually don't have to think about it.
 If you want to group changes into transactions yourself, you can add a `tx` parameter to the APIs mentioned above.
 For example, if you want the name "FOO", you can `PATCH` to `/settings?tx=FOO` and `POST` to `/tx/commit_and_apply?tx=FOO`.
@@ -42,6 +57,14 @@ The data model describes system settings, services using those settings, and con
 It also has a more general structure for metadata.
 Metadata entries can be stored for any data field in the model.
 
+Every write records where each value came from in `source` metadata for the key, along with the time the value was committed in `source-timestamp`; the source of a pending value is kept with the transaction until then.
+storewolf records `storewolf` for defaults, and reverting a transaction records the revert.
+`PATCH /settings?source=...` records the given source, like `sundog`, `apiclient set`, or the user data source early-boot-config read the settings from; without it, the source is `api`.
+`GET /metadata/sources` returns the recorded source and timestamp of each setting, optionally limited with `?keys=...`, and `apiclient get --explain` shows them next to the values.
+
+`GET /schema` returns a [JSON Schema](https://json-schema.org/) document describing the user data accepted by the variant, generated from the model.
+It includes the constraints of modeled types, like valid hostnames and sysctl keys, so clients can validate settings or user data before sending them.
+
 ## Data store
 
 Data from the model is stored in a key/value data store.
@@ -54,10 +77,49 @@ The default data store location is `/var/lib/bottlerocket/datastore/current`, an
 
 For more detail, see [datastore](../datastore).
 
+## Access control
+
+By default, any process that can open the socket has full access to the API.
+You can limit that with `--access-policy PATH`, which points to a TOML file mapping the user and group IDs of clients to the access they get.
+The server learns the IDs of the process on the other end of each connection from the kernel (`SO_PEERCRED`); the group is the client's primary group.
+
+On Bottlerocket, `apiserver.service` passes `--access-policy /etc/bottlerocket/apiserver-access-policy.toml`.
+That file is copied at boot from the `apiserver-access-policy-toml` source in the `os` package, which gives full access to every client, since only root and the `api` group can open the socket.
+Variants that want tighter access replace that source file.
+
+```toml
+# Clients that don't match a rule get this access.
+[default]
+access = "none"
+
+[[rule]]
+uids = [0]
+access = "full"
+
+[[rule]]
+gids = [274]
+access = "read-only"
+deny = ["settings.kubernetes.bootstrap-token", "settings.aws"]
+```
+
+Rules are checked in order, and the first rule listing the client's user or group ID applies.
+`access` can be `full`, `read-only`, or `none`.
+Read-only clients can only make GET requests, and can't use `/exec`.
+Keys listed in `deny`, and any keys under them, are left out of responses like `/settings`, `/tx`, `/tx/history`, and `/events`, and can't be changed through `PATCH /settings`.
+Clients with denied keys also can't use `/exec` or `/tx/dry-run`, since those could reveal any value.
+Requests that aren't allowed get a 403 response.
+
+## Sensitive settings
+
+Settings that hold secrets are marked sensitive, either in the model with `#[model(sensitive)]` or with the metadata `sensitive = true`, for example in `defaults.d`.
+`GET /settings?redact=true` and `GET /?redact=true` leave sensitive settings out of the response, so settings can be shared, for example in logs, without exposing secrets.
+`GET /tx/history` leaves out changes to sensitive settings by default, since it holds their old values too; use `GET /tx/history?redact=false` to see them.
+Redaction is something clients ask for; use an access policy to stop clients from reading secrets at all.
+
 # Current limitations
 
 * Data store locking is coarse; read requests can happen in parallel, but a write request will block everything else.
-* There's no support for rolling back commits.
+* Only recent commits can be rolled back, and only if their keys haven't been changed since.
 * There are no metrics.
 
 # Example usage
@@ -0,0 +1,368 @@
+//! The access module decides what each client of the API may do.  For Unix-domain sockets, the
+//! kernel tells us the user and group of the process that opened each connection (SO_PEERCRED),
+//! and an optional policy maps those credentials to an access level and a list of data keys the
+//! client can't see or change.
+
+use super::error::{self, Result};
+use actix_web::dev::Payload;
+use actix_web::http::Method;
+use actix_web::{FromRequest, HttpMessage, HttpRequest};
+use datastore::{Key, KeyType};
+use futures::future::{ready, Ready};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use snafu::ResultExt;
+use std::fs;
+use std::path::Path;
+
+/// The credentials of the process that opened a connection to the API socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PeerCredentials {
+    pub(crate) uid: u32,
+    pub(crate) gid: u32,
+}
+
+/// What a client is allowed to do with the API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub(crate) enum AccessLevel {
+    /// No requests are allowed.
+    None,
+    /// Only requests that read data are allowed.
+    ReadOnly,
+    /// All requests are allowed.
+    Full,
+}
+
+/// The access given to one client: its level, and the data keys it can't see or change.
+#[derive(Debug, Clone)]
+pub(crate) struct Access {
+    level: AccessLevel,
+    deny: Vec<Key>,
+}
+
+/// AccessPolicy maps the credentials of clients to the access they're given.  Rules are checked
+/// in order, and the first rule that lists the client's user ID or group ID applies; clients that
+/// don't match any rule get the default access.
+#[derive(Debug)]
+pub struct AccessPolicy {
+    default: Access,
+    rules: Vec<Rule>,
+}
+
+#[derive(Debug)]
+struct Rule {
+    uids: Vec<u32>,
+    gids: Vec<u32>,
+    access: Access,
+}
+
+/// The format of an access policy file, for example:
+///
+/// ```toml
+/// [default]
+/// access = "full"
+///
+/// [[rule]]
+/// uids = [1000]
+/// gids = [1000]
+/// access = "read-only"
+/// deny = ["settings.kubernetes.bootstrap-token", "settings.aws"]
+/// ```
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct PolicyFile {
+    default: Option<AccessSpec>,
+    #[serde(default)]
+    rule: Vec<RuleSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct AccessSpec {
+    access: AccessLevel,
+    #[serde(default)]
+    deny: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct RuleSpec {
+    #[serde(default)]
+    uids: Vec<u32>,
+    #[serde(default)]
+    gids: Vec<u32>,
+    access: AccessLevel,
+    #[serde(default)]
+    deny: Vec<String>,
+}
+
+impl AccessPolicy {
+    /// Loads an access policy from the TOML file at the given path.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let policy_str = fs::read_to_string(path).context(error::AccessPolicyReadSnafu { path })?;
+        let policy: PolicyFile =
+            toml::from_str(&policy_str).context(error::AccessPolicyParseSnafu { path })?;
+
+        Self::from_spec(policy)
+    }
+
+    fn from_spec(policy: PolicyFile) -> Result<Self> {
+        let default = match policy.default {
+            Some(spec) => Access::new(spec.access, &spec.deny)?,
+            None => Access::full(),
+        };
+        let rules = policy
+            .rule
+            .into_iter()
+            .map(|spec| {
+                Ok(Rule {
+                    uids: spec.uids,
+                    gids: spec.gids,
+                    access: Access::new(spec.access, &spec.deny)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { default, rules })
+    }
+
+    /// Returns the access given to the client with the given credentials.  If we couldn't read the
+    /// client's credentials, we can't tell which rule applies, so we don't give it any access.
+    pub(crate) fn access_for(&self, peer: Option<&PeerCredentials>) -> Access {
+        let peer = match peer {
+            Some(peer) => peer,
+            None => return Access::none(),
+        };
+        self.rules
+            .iter()
+            .find(|rule| rule.uids.contains(&peer.uid) || rule.gids.contains(&peer.gid))
+            .map(|rule| &rule.access)
+            .unwrap_or(&self.default)
+            .clone()
+    }
+}
+
+impl Access {
+    /// Creates access at the given level that hides the given data keys and everything under them.
+    pub(crate) fn new<S: AsRef<str>>(level: AccessLevel, deny: &[S]) -> Result<Self> {
+        let deny = deny
+            .iter()
+            .map(|name| {
+                Key::new(KeyType::Data, name).context(error::NewKeySnafu {
+                    key_type: "data",
+                    name: name.as_ref(),
+                })
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { level, deny })
+    }
+
+    /// Access for clients when no policy is configured.
+    pub(crate) fn full() -> Self {
+        Self {
+            level: AccessLevel::Full,
+            deny: Vec::new(),
+        }
+    }
+
+    fn none() -> Self {
+        Self {
+            level: AccessLevel::None,
+            deny: Vec::new(),
+        }
+    }
+
+    /// Checks whether the client may make a request with the given method to the given path.  The
+    /// path must be percent-decoded like the one requests are routed on, so an encoded path like
+    /// `/e%78ec` is checked as the route it reaches.
+    pub(crate) fn check_request(&self, method: &Method, path: &str) -> Result<()> {
+        let denied = |reason: &str| {
+            error::AccessDeniedSnafu {
+                reason: format!("{} {} {}", reason, method, path),
+            }
+            .fail()
+        };
+
+        if self.level == AccessLevel::None {
+            return denied("no access to");
+        }
+        // /exec runs commands in containers, so it's a write even though it starts with a GET.
+        let write = method != Method::GET || path == "/exec";
+        if self.level == AccessLevel::ReadOnly && write {
+            return denied("read-only access can't");
+        }
+        // These can expose the values of any key, either through a shell or through rendered
+        // configuration files, so we can't hide keys from them.
+        if !self.deny.is_empty() && (path == "/exec" || path == "/tx/dry-run") {
+            return denied("access with denied keys can't");
+        }
+        Ok(())
+    }
+
+    /// Returns whether the client may see and change the given data key.
+    pub(crate) fn allows(&self, key: &Key) -> bool {
+        !self
+            .deny
+            .iter()
+            .any(|denied| key.starts_with_segments(denied.segments()))
+    }
+
+    /// Checks that the client may change all of the given data keys.
+    pub(crate) fn check_keys<'a, I>(&self, keys: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a Key>,
+    {
+        match keys.into_iter().find(|key| !self.allows(key)) {
+            Some(key) => error::AccessDeniedSnafu {
+                reason: format!("can't change '{}'", key),
+            }
+            .fail(),
+            None => Ok(()),
+        }
+    }
+
+    /// Serializes the given data, which is found under the data key with the given segments (for
+    /// example `["settings"]` for the response to /settings, or nothing for the whole model), and
+    /// removes any keys the client may not see.
+    pub(crate) fn filter<T: Serialize>(&self, root: &[&str], data: &T) -> Value {
+        let mut value = serde_json::to_value(data).expect("struct to value can't fail");
+        remove_keys(root, &self.deny, &mut value);
+        value
+    }
+}
+
+/// Removes the given data keys from a serialized value that's found under the data key with the
+/// given segments.  If any of the keys is the root or above it, all of the data is removed.
+pub(crate) fn remove_keys(root: &[&str], keys: &[Key], value: &mut Value) {
+    for key in keys {
+        let segments = key.segments();
+        // Only keys at or under the root can be in the data.
+        if !segments.iter().zip(root).all(|(a, b)| a == b) {
+            continue;
+        }
+        if segments.len() <= root.len() {
+            *value = Value::Object(serde_json::Map::new());
+            return;
+        }
+        let (last, parents) = segments.split_last().expect("keys have segments");
+        let parent = parents[root.len()..]
+            .iter()
+            .try_fold(&mut *value, |value, segment| value.get_mut(segment));
+        if let Some(Value::Object(parent)) = parent {
+            parent.remove(last);
+        }
+    }
+}
+
+/// Handlers can take an Access parameter to find out what the client may do; it's added to the
+/// request by the access check in `serve`.  If no policy is configured, clients have full access.
+impl FromRequest for Access {
+    type Error = actix_web::Error;
+    type Future = Ready<std::result::Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(req
+            .extensions()
+            .get::<Access>()
+            .cloned()
+            .unwrap_or_else(Access::full)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn policy(s: &str) -> AccessPolicy {
+        AccessPolicy::from_spec(toml::from_str(s).unwrap()).unwrap()
+    }
+
+    fn peer(uid: u32, gid: u32) -> Option<PeerCredentials> {
+        Some(PeerCredentials { uid, gid })
+    }
+
+    const POLICY: &str = r#"
+        [default]
+        access = "none"
+
+        [[rule]]
+        uids = [0]
+        access = "full"
+
+        [[rule]]
+        gids = [274]
+        access = "read-only"
+        deny = ["settings.kubernetes.bootstrap-token", "settings.aws"]
+    "#;
+
+    #[test]
+    fn rules_match_in_order() {
+        let policy = policy(POLICY);
+        assert_eq!(
+            policy.access_for(peer(0, 274).as_ref()).level,
+            AccessLevel::Full
+        );
+        assert_eq!(
+            policy.access_for(peer(1000, 274).as_ref()).level,
+            AccessLevel::ReadOnly
+        );
+        assert_eq!(
+            policy.access_for(peer(1000, 1000).as_ref()).level,
+            AccessLevel::None
+        );
+        assert_eq!(policy.access_for(None).level, AccessLevel::None);
+    }
+
+    #[test]
+    fn default_is_full() {
+        let policy = policy("");
+        let access = policy.access_for(peer(1000, 1000).as_ref());
+        assert_eq!(access.level, AccessLevel::Full);
+        assert!(access.check_request(&Method::POST, "/exec").is_ok());
+    }
+
+    #[test]
+    fn read_only_requests() {
+        let access = policy(POLICY).access_for(peer(1000, 274).as_ref());
+        assert!(access.check_request(&Method::GET, "/settings").is_ok());
+        assert!(access.check_request(&Method::PATCH, "/settings").is_err());
+        assert!(access.check_request(&Method::GET, "/exec").is_err());
+        assert!(access.check_request(&Method::GET, "/tx/dry-run").is_err());
+    }
+
+    #[test]
+    fn denied_keys() {
+        let access = policy(POLICY).access_for(peer(1000, 274).as_ref());
+        let key = |name| Key::new(KeyType::Data, name).unwrap();
+        assert!(!access.allows(&key("settings.aws.region")));
+        assert!(!access.allows(&key("settings.kubernetes.bootstrap-token")));
+        assert!(access.allows(&key("settings.kubernetes.cluster-name")));
+        assert!(access.allows(&key("settings.awsome")));
+        assert!(access
+            .check_keys(&[key("settings.motd"), key("settings.aws.region")])
+            .is_err());
+    }
+
+    #[test]
+    fn filter_hides_denied_keys() {
+        let access = policy(POLICY).access_for(peer(1000, 274).as_ref());
+        let settings = json!({
+            "motd": "hi",
+            "aws": {"region": "us-west-2"},
+            "kubernetes": {"bootstrap-token": "secret", "cluster-name": "c"},
+        });
+        let expected = json!({"motd": "hi", "kubernetes": {"cluster-name": "c"}});
+        assert_eq!(access.filter(&["settings"], &settings), expected);
+        assert_eq!(
+            access.filter(&[], &json!({"settings": settings}))["settings"],
+            expected
+        );
+        assert_eq!(
+            access.filter(&["settings", "aws"], &json!({"region": "x"})),
+            json!({})
+        );
+    }
+}
@@ -8,11 +8,16 @@ use std::collections::{HashMap, HashSet};
 use std::io::Write;
 use std::process::{Command, Stdio};
 
+use crate::server::access::Access;
 use crate::server::error::{self, Result};
 use actix_web::HttpResponse;
 use datastore::deserialization::{from_map, from_map_with_prefix};
 use datastore::serialization::to_pairs;
-use datastore::{deserialize_scalar, Committed, DataStore, Key, KeyType, ScalarError, Value};
+use datastore::source::SOURCE_METADATA_KEY;
+use datastore::{
+    deserialize_scalar, Committed, CommittedTransaction, DataStore, Key, KeyType, ScalarError,
+    Source, Value,
+};
 use model::{ConfigurationFiles, Services, Settings};
 use num::FromPrimitive;
 use std::os::unix::process::ExitStatusExt;
@@ -359,11 +364,13 @@ where
     Ok(result)
 }
 
-/// Given a Settings, takes any Some values and updates them in the datastore.
+/// Given a Settings, takes any Some values and updates them in the datastore, recording `source`
+/// as where each value came from.
 pub(crate) fn set_settings<D: DataStore>(
     datastore: &mut D,
     settings: &Settings,
     transaction: &str,
+    source: &str,
 ) -> Result<()> {
     trace!("Serializing Settings to write to data store");
     let pairs =
@@ -373,7 +380,47 @@ pub(crate) fn set_settings<D: DataStore>(
     };
     datastore
         .set_keys(&pairs, &pending)
-        .context(error::DataStoreSnafu { op: "set_keys" })
+        .context(error::DataStoreSnafu { op: "set_keys" })?;
+    datastore
+        .set_source(pairs.keys(), source, &pending)
+        .context(error::DataStoreSnafu { op: "set_source" })
+}
+
+/// Gets the recorded source of each of the requested data keys, or of every data key with a
+/// recorded source if none are requested.  Keys without a recorded source are left out.
+pub(crate) fn get_sources<D: DataStore>(
+    datastore: &D,
+    data_key_strs: Option<&HashSet<&str>>,
+) -> Result<HashMap<String, Source>> {
+    let data_keys = match data_key_strs {
+        Some(data_key_strs) => data_key_strs
+            .iter()
+            .map(|name| {
+                Key::new(KeyType::Data, name).context(error::NewKeySnafu {
+                    key_type: "data",
+                    name: *name,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?,
+        None => datastore
+            .list_populated_metadata("", &Some(SOURCE_METADATA_KEY))
+            .context(error::DataStoreSnafu {
+                op: "list_populated_metadata",
+            })?
+            .into_keys()
+            .collect(),
+    };
+
+    let mut result = HashMap::new();
+    for data_key in data_keys {
+        let source = datastore
+            .get_source(&data_key)
+            .context(error::DataStoreSnafu { op: "get_source" })?;
+        if let Some(source) = source {
+            result.insert(data_key.to_string(), source);
+        }
+    }
+    Ok(result)
 }
 
 // This is not as nice as get_settings, which uses Serializer/Deserializer to properly use the
@@ -453,16 +500,112 @@ where
         .context(error::DataStoreSnafu { op: "commit" })
 }
 
+/// Returns the history of committed transactions, oldest first.
+pub(crate) fn get_transaction_history<D>(datastore: &D) -> Result<Vec<CommittedTransaction>>
+where
+    D: DataStore,
+{
+    datastore
+        .transaction_history()
+        .context(error::DataStoreSnafu {
+            op: "transaction_history",
+        })
+}
+
+/// Finds the ID the history gave to the commit of `transaction` that just changed `changes`, so
+/// the config applier can record which commit it's applying.  Returns None if the commit wasn't
+/// recorded, which doesn't fail the commit.
+pub(crate) fn committed_transaction_id<D>(
+    datastore: &D,
+    transaction: &str,
+    changes: &HashSet<Key>,
+) -> Option<u64>
+where
+    D: DataStore,
+{
+    let history = match get_transaction_history(datastore) {
+        Ok(history) => history,
+        Err(e) => {
+            warn!("Unable to find committed transaction in history: {}", e);
+            return None;
+        }
+    };
+    // The commit is the most recent record, unless recording it failed
+    let committed = history.last()?;
+    let recorded = committed.transaction == transaction
+        && committed.changes.len() == changes.len()
+        && changes
+            .iter()
+            .all(|key| committed.changes.contains_key(key.name()));
+    if recorded {
+        Some(committed.id)
+    } else {
+        None
+    }
+}
+
+/// Reverts the committed transaction with the given ID in the live datastore, returning the
+/// changed keys.  Fails without changing anything if the client may not change any of the keys
+/// the transaction changed.
+pub(crate) fn revert_transaction<D>(
+    datastore: &mut D,
+    id: u64,
+    access: &Access,
+) -> Result<HashSet<Key>>
+where
+    D: DataStore,
+{
+    // Check access to everything the revert would change before touching the datastore.
+    let history = get_transaction_history(datastore)?;
+    let committed = history
+        .iter()
+        .find(|committed| committed.id == id)
+        .context(error::UnknownCommittedTransactionSnafu { id })?;
+    let keys = committed
+        .changes
+        .keys()
+        .map(|name| {
+            Key::new(KeyType::Data, name).context(error::NewKeySnafu {
+                key_type: "data",
+                name,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    access.check_keys(&keys)?;
+
+    // Separate out the errors caused by the request so we can return the right status code.
+    match datastore.revert_transaction(id) {
+        Ok(changes) => Ok(changes),
+        Err(datastore::Error::UnknownTransaction { .. }) => {
+            error::UnknownCommittedTransactionSnafu { id }.fail()
+        }
+        Err(datastore::Error::RevertConflict { key, .. }) => {
+            error::RevertConflictSnafu { id, key }.fail()
+        }
+        Err(e) => Err(e).context(error::DataStoreSnafu { op: "revert" }),
+    }
+}
+
 /// Launches the config applier to make appropriate changes to the system based on any settings
 /// that have been committed.  Can be called after a commit, with the keys that changed in that
 /// commit, or called on its own to reset configuration state with all known keys.
 ///
 /// If `keys_limit` is Some, gives those keys to the applier so only changes relevant to those
 /// keys are made.  Otherwise, tells the applier to apply changes for all known keys.
-pub(crate) fn apply_changes<S>(keys_limit: Option<&HashSet<S>>) -> Result<()>
+///
+/// If `transaction_id` is Some, it's the history ID of the commit being applied, which the applier
+/// records in its events.
+pub(crate) fn apply_changes<S>(
+    keys_limit: Option<&HashSet<S>>,
+    transaction_id: Option<u64>,
+) -> Result<()>
 where
     S: AsRef<str>,
 {
+    let transaction_id_args = match transaction_id {
+        Some(id) => vec!["--transaction-id".to_string(), id.to_string()],
+        None => Vec::new(),
+    };
     if let Some(keys_limit) = keys_limit {
         let keys_limit: Vec<&str> = keys_limit.iter().map(|s| s.as_ref()).collect();
         // Prepare input to config applier; it uses the changed keys to update the right config
@@ -477,6 +620,7 @@ where
         let mut cmd = Command::new("/usr/bin/thar-be-settings")
             // Ask it to fork itself so we don't block the API
             .arg("--daemon")
+            .args(&transaction_id_args)
             .stdin(Stdio::piped())
             // FIXME where to send output?
             //.stdout()
@@ -513,6 +657,7 @@ where
         let status = Command::new("/usr/bin/thar-be-settings")
             .arg("--daemon")
             .arg("--all")
+            .args(&transaction_id_args)
             // FIXME where to send output?
             //.stdout()
             //.stderr()
@@ -532,6 +677,35 @@ where
     Ok(())
 }
 
+/// Runs the config applier in dry-run mode against the given pending transaction, returning its
+/// JSON report of the config file changes and service restarts that committing the transaction
+/// would cause.  The applier reads settings from the API, so this must not be called while
+/// holding a data store lock, or from the server's only worker thread.
+pub(crate) fn dry_run_transaction(transaction: &str) -> Result<String> {
+    debug!(
+        "Launching thar-be-settings to preview transaction '{}'",
+        transaction
+    );
+    let output = Command::new("/usr/bin/thar-be-settings")
+        .arg("--dry-run")
+        .arg("--transaction")
+        .arg(transaction)
+        .output()
+        .context(error::ConfigApplierStartSnafu)?;
+    ensure!(
+        output.status.success(),
+        error::ConfigApplierDryRunSnafu {
+            code: output
+                .status
+                .code()
+                .map(|i| i.to_string())
+                .unwrap_or_else(|| "unknown".to_string()),
+            stderr: String::from_utf8_lossy(&output.stderr),
+        }
+    );
+    String::from_utf8(output.stdout).context(error::ConfigApplierOutputSnafu)
+}
+
 /// Dispatches an update command via `thar-be-updates`
 pub(crate) fn dispatch_update_command(args: &[&str]) -> Result<HttpResponse> {
     let status = Command::new("/usr/bin/thar-be-updates")
@@ -560,6 +734,7 @@ pub(crate) fn dispatch_update_command(args: &[&str]) -> Result<HttpResponse> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::server::access::AccessLevel;
     use datastore::memory::MemoryDataStore;
     use datastore::{Committed, DataStore, Key, KeyType};
     use maplit::{hashmap, hashset};
@@ -762,7 +937,7 @@ mod test {
         let mut ds = MemoryDataStore::new();
         let tx = "test transaction";
         let pending = Committed::Pending { tx: tx.into() };
-        set_settings(&mut ds, &settings, tx).unwrap();
+        set_settings(&mut ds, &settings, tx, "test").unwrap();
 
         // Retrieve directly
         let key = Key::new(KeyType::Data, "settings.motd").unwrap();
@@ -772,6 +947,33 @@ mod test {
         );
     }
 
+    #[test]
+    fn set_settings_records_source() {
+        let settings = model::Settings {
+            motd: Some("tz".into()),
+            ..Default::default()
+        };
+        let mut ds = MemoryDataStore::new();
+
+        set_settings(&mut ds, &settings, "tx", "user data").unwrap();
+        // The source isn't recorded until the value is committed
+        assert!(get_sources(&ds, None).unwrap().is_empty());
+        commit_transaction(&mut ds, "tx").unwrap();
+        let sources = get_sources(&ds, None).unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources["settings.motd"].source, "user data");
+
+        // A later change replaces the source, unless it's thrown away.
+        set_settings(&mut ds, &settings, "other", "apiclient set").unwrap();
+        ds.delete_transaction("other").unwrap();
+        set_settings(&mut ds, &settings, "tx", "apiclient set").unwrap();
+        commit_transaction(&mut ds, "tx").unwrap();
+        let sources = get_sources(&ds, Some(&hashset!("settings.motd", "settings.other"))).unwrap();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources["settings.motd"].source, "apiclient set");
+        assert!(sources["settings.motd"].timestamp.is_some());
+    }
+
     #[test]
     fn get_metadata_keys_works() {
         let mut ds = MemoryDataStore::new();
@@ -847,4 +1049,78 @@ mod test {
         let settings = get_settings(&ds, &Committed::Live).unwrap();
         assert_eq!(settings.motd, Some("json string".try_into().unwrap()));
     }
+
+    #[test]
+    fn commit_id_found() {
+        let mut ds = MemoryDataStore::new();
+        let motd = Key::new(KeyType::Data, "settings.motd").unwrap();
+        for tx in &["first", "second"] {
+            ds.set_key(&motd, "\"hi\"", &Committed::Pending { tx: tx.to_string() })
+                .unwrap();
+            let changes = commit_transaction(&mut ds, tx).unwrap();
+            let history = get_transaction_history(&ds).unwrap();
+            assert_eq!(
+                committed_transaction_id(&ds, tx, &changes),
+                Some(history.last().unwrap().id)
+            );
+        }
+
+        // An older record isn't mistaken for the commit that was just made
+        let changes = HashSet::from([motd]);
+        assert_eq!(committed_transaction_id(&ds, "first", &changes), None);
+        let other = HashSet::from([Key::new(KeyType::Data, "settings.other").unwrap()]);
+        assert_eq!(committed_transaction_id(&ds, "second", &other), None);
+    }
+
+    #[test]
+    fn revert_works() {
+        let mut ds = MemoryDataStore::new();
+        for motd in &["first", "second"] {
+            let settings = Settings {
+                motd: Some(motd.to_string()),
+                ..Default::default()
+            };
+            set_settings(&mut ds, &settings, motd, "test").unwrap();
+            commit_transaction(&mut ds, motd).unwrap();
+        }
+
+        let history = get_transaction_history(&ds).unwrap();
+        assert_eq!(history.len(), 2);
+
+        // Reverting the second commit restores the first value
+        revert_transaction(&mut ds, history[1].id, &Access::full()).unwrap();
+        let settings = get_settings(&ds, &Committed::Live).unwrap();
+        assert_eq!(settings.motd, Some("first".into()));
+
+        match revert_transaction(&mut ds, 42, &Access::full()) {
+            Err(error::Error::UnknownCommittedTransaction { .. }) => {}
+            other => panic!("Unexpected revert result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn revert_checks_access() {
+        let mut ds = MemoryDataStore::new();
+        for motd in &["first", "second"] {
+            let settings = Settings {
+                motd: Some(motd.to_string()),
+                ..Default::default()
+            };
+            set_settings(&mut ds, &settings, motd, "test").unwrap();
+            commit_transaction(&mut ds, motd).unwrap();
+        }
+        let history = get_transaction_history(&ds).unwrap();
+
+        // A client that may not change the key can't revert a commit that changed it
+        let access = Access::new(AccessLevel::Full, &["settings.motd"]).unwrap();
+        match revert_transaction(&mut ds, history[1].id, &access) {
+            Err(error::Error::AccessDenied { .. }) => {}
+            other => panic!("Unexpected revert result: {:?}", other),
+        }
+
+        // Nothing was changed or recorded
+        let settings = get_settings(&ds, &Committed::Live).unwrap();
+        assert_eq!(settings.motd, Some("second".into()));
+        assert_eq!(get_transaction_history(&ds).unwrap(), history);
+    }
 }
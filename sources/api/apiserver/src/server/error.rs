@@ -40,6 +40,13 @@ pub enum Error {
     #[snafu(display("Input '{}' cannot be empty", input))]
     EmptyInput { input: String },
 
+    #[snafu(display("Input '{}' is not a valid {}: {}", input, expected, given))]
+    InvalidInput {
+        input: String,
+        expected: String,
+        given: String,
+    },
+
     #[snafu(display("Another thread poisoned the data store lock by panicking"))]
     DataStoreLock,
 
@@ -55,6 +62,18 @@ pub enum Error {
     #[snafu(display("Tried to commit with no pending changes"))]
     CommitWithNoPending,
 
+    #[snafu(display("Access denied: {}", reason))]
+    AccessDenied { reason: String },
+
+    #[snafu(display("Unable to read access policy {}: {}", path.display(), source))]
+    AccessPolicyRead { path: PathBuf, source: io::Error },
+
+    #[snafu(display("Invalid access policy {}: {}", path.display(), source))]
+    AccessPolicyParse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
     #[snafu(display("Unable to get OS release data: {}", source))]
     ReleaseData { source: bottlerocket_release::Error },
 
@@ -127,6 +146,34 @@ pub enum Error {
     #[snafu(display("Unable to send input to config applier: {}", source))]
     ConfigApplierWrite { source: io::Error },
 
+    #[snafu(display("Unable to wait for config applier dry run: {}", source))]
+    ConfigApplierBlocking {
+        source: actix_web::error::BlockingError,
+    },
+
+    #[snafu(display(
+        "Config applier dry run failed, exit code: {}, stderr: {}",
+        code,
+        stderr
+    ))]
+    ConfigApplierDryRun { code: String, stderr: String },
+
+    #[snafu(display("Config applier dry run returned invalid output: {}", source))]
+    ConfigApplierOutput { source: std::string::FromUtf8Error },
+
+    #[snafu(display("Unable to read config applier events: {}", source))]
+    ConfigApplierEvents { source: thar_be_settings::Error },
+
+    #[snafu(display("No committed transaction with ID {} in history", id))]
+    UnknownCommittedTransaction { id: u64 },
+
+    #[snafu(display(
+        "Can't revert transaction {}: key '{}' has been changed since it was committed",
+        id,
+        key
+    ))]
+    RevertConflict { id: u64, key: String },
+
     #[snafu(display("Unable to start shutdown: {}", source))]
     Shutdown { source: io::Error },
 
@@ -10,6 +10,7 @@
 // the WebSocket actors and the child, it's not async either - it uses standard threads and
 // channels.  See its docs for more detail.
 
+use crate::server::access::Access;
 use actix::prelude::{Actor, ActorContext, AsyncContext, Handler, StreamHandler};
 use actix_web::{web, Error, HttpRequest, HttpResponse};
 use actix_web_actors::ws::{self, Message};
@@ -60,7 +61,12 @@ pub(crate) async fn ws_exec(
     r: HttpRequest,
     stream: web::Payload,
     data: web::Data<crate::server::SharedData>,
+    access: Access,
 ) -> Result<HttpResponse, Error> {
+    // A shell can read any key, so check here too, in case the request reached us by a path the
+    // access check didn't recognize.
+    access.check_request(r.method(), "/exec")?;
+
     info!(
         "Received exec request to {}:{}",
         r.connection_info().host(),
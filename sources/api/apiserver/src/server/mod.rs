@@ -1,32 +1,52 @@
 //! The server module owns the API surface.  It interfaces with the datastore through the
 //! server::controller module.
 
+mod access;
 mod controller;
 mod error;
 mod exec;
 
+pub use access::AccessPolicy;
 pub use error::Error;
 
+use access::{Access, PeerCredentials};
+use actix_web::dev::{Extensions, Service, ServiceRequest};
 use actix_web::{
-    body::BoxBody, error::ResponseError, web, App, HttpRequest, HttpResponse, HttpServer, Responder,
+    body::BoxBody, error::ResponseError, http::Method, rt::net::UnixStream, web, App, HttpMessage,
+    HttpRequest, HttpResponse, HttpServer, Responder,
+};
+use bytes::Bytes;
+use datastore::serialization::to_pairs_with_prefix;
+use datastore::{
+    Committed, CommittedTransaction, DataStore, FilesystemDataStore, Key, KeyType, Source, Value,
 };
-use datastore::{Committed, FilesystemDataStore, Key, Value};
 use error::Result;
 use fs2::FileExt;
+use futures::future::{ready, Either};
+use futures::stream;
 use http::StatusCode;
-use log::info;
+use log::{info, warn};
 use model::{ConfigurationFiles, Model, Services, Settings};
 use nix::unistd::{chown, Gid};
 use snafu::{ensure, OptionExt, ResultExt};
+use std::any::Any;
 use std::collections::{HashMap, HashSet};
 use std::env;
-use std::fs::{set_permissions, File, Permissions};
+use std::fs::{self, set_permissions, File, Permissions};
+use std::io;
 use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::UnixListener;
 use std::os::unix::process::ExitStatusExt;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync;
+use thar_be_settings::events::{Event, EventKind, EVENT_LOG_FILE};
 use thar_be_updates::status::{UpdateStatus, UPDATE_LOCKFILE};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// How many commits can be queued for a settings watcher before it starts missing them.  Watchers
+/// that fall this far behind are sent a 'lagged' event so they know to reread their settings.
+const WATCH_CHANNEL_CAPACITY: usize = 64;
 
 // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
 
@@ -53,13 +73,15 @@ fn notify_unix_socket_ready() -> Result<()> {
 
 /// This is the primary interface of the module.  It defines the server and application that actix
 /// spawns for requests.  It creates a shared datastore handle that can be used by handler methods
-/// to interface with the controller.
+/// to interface with the controller.  If an access policy is given, each request is checked
+/// against the access it gives the client, based on the client's credentials.
 pub async fn serve<P1, P2, P3>(
     socket_path: P1,
     datastore_path: P2,
     threads: usize,
     socket_gid: Option<Gid>,
     exec_socket_path: P3,
+    access_policy: Option<AccessPolicy>,
 ) -> Result<()>
 where
     P1: AsRef<Path>,
@@ -69,9 +91,12 @@ where
     // SharedData gives us a convenient way to make data available to handler methods when it
     // doesn't come from the request itself.  It's easier than the ownership tricks required to
     // pass parameters to the handler methods.
+    let (changes, _) = broadcast::channel(WATCH_CHANNEL_CAPACITY);
     let shared_data = web::Data::new(SharedData {
         ds: sync::RwLock::new(FilesystemDataStore::new(datastore_path)),
         exec_socket_path: exec_socket_path.into(),
+        changes,
+        access_policy,
     });
 
     let http_server = HttpServer::new(move || {
@@ -79,12 +104,18 @@ where
             // This makes the data store available to API methods merely by having a Data
             // parameter.
             .app_data(shared_data.clone())
+            // Check that the client is allowed to make the request before routing it.
+            .wrap_fn(|req, srv| match check_access(&req) {
+                Ok(()) => Either::Left(srv.call(req)),
+                Err(e) => Either::Right(ready(Ok(req.error_response(e)))),
+            })
             // Retrieve the full API model; not all data is writable, so we only support GET.
             .route("/", web::get().to(get_model))
             .service(
                 web::scope("/settings")
                     .route("", web::get().to(get_settings))
-                    .route("", web::patch().to(patch_settings)),
+                    .route("", web::patch().to(patch_settings))
+                    .route("/watch", web::get().to(watch_settings)),
             )
             .service(
                 // Transaction support
@@ -93,18 +124,23 @@ where
                     .route("", web::get().to(get_transaction))
                     .route("", web::delete().to(delete_transaction))
                     .route("/commit", web::post().to(commit_transaction))
+                    .route("/history", web::get().to(get_transaction_history))
+                    .route("/revert", web::post().to(revert_transaction))
                     .route("/apply", web::post().to(apply_changes))
+                    .route("/dry-run", web::get().to(dry_run_transaction))
                     .route(
                         "/commit_and_apply",
                         web::post().to(commit_transaction_and_apply),
                     ),
             )
             .service(web::scope("/os").route("", web::get().to(get_os_info)))
+            .service(web::scope("/schema").route("", web::get().to(get_schema)))
             .service(
                 web::scope("/metadata")
                     .route("/affected-services", web::get().to(get_affected_services))
                     .route("/setting-generators", web::get().to(get_setting_generators))
-                    .route("/templates", web::get().to(get_templates)),
+                    .route("/templates", web::get().to(get_templates))
+                    .route("/sources", web::get().to(get_sources)),
             )
             .service(web::scope("/services").route("", web::get().to(get_services)))
             .service(
@@ -120,10 +156,16 @@ where
                     .route("/deactivate-update", web::post().to(deactivate_update)),
             )
             .service(web::scope("/updates").route("/status", web::get().to(get_update_status)))
+            .service(web::scope("/events").route("", web::get().to(get_events)))
             .service(web::resource("/exec").route(web::get().to(exec::ws_exec)))
     })
     .workers(threads)
-    .bind_uds(socket_path.as_ref())
+    .on_connect(record_peer_credentials)
+    .listen_uds(
+        bind_socket(socket_path.as_ref()).context(error::BindSocketSnafu {
+            path: socket_path.as_ref(),
+        })?,
+    )
     .context(error::BindSocketSnafu {
         path: socket_path.as_ref(),
     })?;
@@ -144,6 +186,57 @@ where
     http_server.run().await.context(error::ServerStartSnafu)
 }
 
+/// Binds the API socket.  We bind it ourselves because actix-web only calls our `on_connect` hook,
+/// which records the credentials of clients, for Unix-domain listeners it's given.
+fn bind_socket(path: &Path) -> io::Result<UnixListener> {
+    // Like actix-web, remove the socket left by a previous run, since we can't bind over it.
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != io::ErrorKind::NotFound {
+            return Err(e);
+        }
+    }
+    UnixListener::bind(path)
+}
+
+/// Stores the credentials of the process on the other end of a new connection, so that requests
+/// on the connection can be checked against the access policy.
+fn record_peer_credentials(connection: &dyn Any, data: &mut Extensions) {
+    if let Some(stream) = connection.downcast_ref::<UnixStream>() {
+        match stream.peer_cred() {
+            Ok(cred) => {
+                data.insert(PeerCredentials {
+                    uid: cred.uid(),
+                    gid: cred.gid(),
+                });
+            }
+            Err(e) => warn!("Unable to get credentials of API client: {}", e),
+        }
+    }
+}
+
+/// Checks whether the client may make the given request according to the access policy, if any,
+/// and stores the client's access in the request so handlers can hide data it can't see.
+fn check_access(req: &ServiceRequest) -> Result<()> {
+    let data = req
+        .app_data::<web::Data<SharedData>>()
+        .expect("shared data is always set");
+    let policy = match &data.access_policy {
+        Some(policy) => policy,
+        None => return Ok(()),
+    };
+
+    let peer = req.conn_data::<PeerCredentials>();
+    let access = policy.access_for(peer);
+    // Check the decoded path that requests are routed on, not the raw one, so encoding
+    // characters can't reach a route without being checked as that route.
+    if let Err(e) = access.check_request(req.method(), req.match_info().as_str()) {
+        info!("Denied request from {:?}: {}", peer, e);
+        return Err(e);
+    }
+    req.extensions_mut().insert(access);
+    Ok(())
+}
+
 // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
 
 // Handler methods called by the router
@@ -153,17 +246,20 @@ where
 /// settings.  Returns a ModelResponse, which contains a serde_json Value instead of a Model so
 /// that we can include only matched fields; this is necessary because the 'os' field contains a
 /// BottlerocketRelease whose fields aren't optional.  (Its other users depend on those fields.)
+/// If 'redact' is true, sensitive settings are left out.
 async fn get_model(
     query: web::Query<HashMap<String, String>>,
     data: web::Data<SharedData>,
+    access: Access,
 ) -> Result<ModelResponse> {
     // When we query settings, services, etc., we query differently if the user gave a prefix - it
     // means they only want keys that start with their given prefix.  Prefix queries are more
     // forgiving because it's normal to return empty results if the prefix didn't match anything,
     // whereas without prefix matching, we should always have some data to return.  The logic is
     // fairly different, so we branch early.
+    let redact = bool_param(&query, "redact")?;
     if let Some(prefix) = query.get("prefix") {
-        return get_model_prefix(data, prefix).await;
+        return get_model_prefix(data, prefix, access, redact).await;
     }
 
     let datastore = data.ds.read().ok().context(error::DataStoreLockSnafu)?;
@@ -181,14 +277,23 @@ async fn get_model(
         os,
     };
 
-    // Turn the Model into a Value so we can match the type used when fetching by prefix.
-    let val = serde_json::to_value(model).expect("struct to value can't fail");
+    // Turn the Model into a Value so we can match the type used when fetching by prefix, leaving
+    // out anything the client isn't allowed to see.
+    let mut val = access.filter(&[], &model);
+    if redact {
+        redact_model(&*datastore, &mut val)?;
+    }
 
     Ok(ModelResponse(val))
 }
 
 /// Helper for get_model that handles the case of matching a user-specified prefix.
-async fn get_model_prefix(data: web::Data<SharedData>, prefix: &str) -> Result<ModelResponse> {
+async fn get_model_prefix(
+    data: web::Data<SharedData>,
+    prefix: &str,
+    access: Access,
+    redact: bool,
+) -> Result<ModelResponse> {
     if prefix.is_empty() {
         return error::EmptyInputSnafu { input: "prefix" }.fail();
     }
@@ -213,8 +318,12 @@ async fn get_model_prefix(data: web::Data<SharedData>, prefix: &str) -> Result<M
         os: None,
     };
 
-    // Turn the Model into a Value so we can insert an "os" value with filtered fields.
-    let mut val = serde_json::to_value(model).expect("struct to value can't fail");
+    // Turn the Model into a Value so we can insert an "os" value with filtered fields.  We also
+    // leave out anything the client isn't allowed to see.
+    let mut val = access.filter(&[], &model);
+    if redact {
+        redact_model(&*datastore, &mut val)?;
+    }
 
     // If the user gave a prefix unrelated to os, this will return None and so we'll leave the None
     // in the model.  Otherwise it'll give us back a Value that's like a BottlerocketRelease but
@@ -237,10 +346,12 @@ async fn get_model_prefix(data: web::Data<SharedData>, prefix: &str) -> Result<M
 // actix-web doesn't support Query for enums, so we use a HashMap and check for the expected keys
 // ourselves.
 /// Return the live settings from the data store; if 'keys' or 'prefix' are specified in query
-/// parameters, return the subset of matching settings.
+/// parameters, return the subset of matching settings.  If 'redact' is true, sensitive settings
+/// are left out.
 async fn get_settings(
     query: web::Query<HashMap<String, String>>,
     data: web::Data<SharedData>,
+    access: Access,
 ) -> Result<SettingsResponse> {
     let datastore = data.ds.read().ok().context(error::DataStoreLockSnafu)?;
 
@@ -263,7 +374,11 @@ async fn get_settings(
         controller::get_settings(&*datastore, &Committed::Live)
     }?;
 
-    Ok(SettingsResponse(settings))
+    let mut response = access.filter(&["settings"], &settings);
+    if bool_param(&query, "redact")? {
+        redact_settings(&*datastore, &mut response)?;
+    }
+    Ok(SettingsResponse(response))
 }
 
 /// Apply the requested settings to the pending data store
@@ -271,13 +386,65 @@ async fn patch_settings(
     settings: web::Json<Settings>,
     query: web::Query<HashMap<String, String>>,
     data: web::Data<SharedData>,
+    access: Access,
 ) -> Result<HttpResponse> {
+    let keys = to_pairs_with_prefix("settings", &*settings)
+        .context(error::DataStoreSerializationSnafu { given: "settings" })?;
+    access.check_keys(keys.keys())?;
+
     let transaction = transaction_name(&query);
+    // Clients say where settings came from, like user data; otherwise, all we know is the API.
+    let source = query.get("source").map(String::as_str).unwrap_or("api");
     let mut datastore = data.ds.write().ok().context(error::DataStoreLockSnafu)?;
-    controller::set_settings(&mut *datastore, &settings, transaction)?;
+    controller::set_settings(&mut *datastore, &settings, transaction, source)?;
     Ok(HttpResponse::NoContent().finish()) // 204
 }
 
+/// Streams the keys changed by each committed transaction as server-sent events, so clients can
+/// react to settings changes without polling.  If you pass a 'prefix' query string, only changed
+/// keys starting with that prefix are sent, and commits that don't change any of them are skipped.
+/// Keys the client may not see are never sent.
+async fn watch_settings(
+    query: web::Query<HashMap<String, String>>,
+    access: Access,
+    data: web::Data<SharedData>,
+) -> Result<HttpResponse> {
+    let prefix = match query.get("prefix") {
+        Some(prefix) if prefix.is_empty() => {
+            return error::EmptyInputSnafu { input: "prefix" }.fail();
+        }
+        // When watching /settings, the settings prefix is implied, so we add it if it wasn't given.
+        Some(prefix) if !prefix.starts_with("settings") => format!("settings.{}", prefix),
+        Some(prefix) => prefix.to_string(),
+        None => "settings.".to_string(),
+    };
+
+    let receiver = data.changes.subscribe();
+    let state = (receiver, prefix, access);
+    let events = stream::unfold(state, |(mut receiver, prefix, access)| async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(changes) => match watch_event(&changes, &prefix, &access) {
+                    Some(event) => event,
+                    // Nothing the watcher cares about changed in this commit.
+                    None => continue,
+                },
+                Err(RecvError::Lagged(missed)) => {
+                    debug!("Settings watcher missed {} commits", missed);
+                    Bytes::from_static(b"event: lagged\ndata: []\n\n")
+                }
+                // The server is shutting down.
+                Err(RecvError::Closed) => return None,
+            };
+            return Some((Ok::<_, Error>(event), (receiver, prefix, access)));
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .streaming(events))
+}
+
 async fn get_transaction_list(data: web::Data<SharedData>) -> Result<TransactionListResponse> {
     let datastore = data.ds.read().ok().context(error::DataStoreLockSnafu)?;
     let data = controller::list_transactions(&*datastore)?;
@@ -288,11 +455,12 @@ async fn get_transaction_list(data: web::Data<SharedData>) -> Result<Transaction
 async fn get_transaction(
     query: web::Query<HashMap<String, String>>,
     data: web::Data<SharedData>,
+    access: Access,
 ) -> Result<SettingsResponse> {
     let transaction = transaction_name(&query);
     let datastore = data.ds.read().ok().context(error::DataStoreLockSnafu)?;
     let data = controller::get_transaction(&*datastore, transaction)?;
-    Ok(SettingsResponse(data))
+    Ok(SettingsResponse(access.filter(&["settings"], &data)))
 }
 
 /// Delete the given transaction, or the "default" transaction if unspecified.
@@ -321,6 +489,59 @@ async fn commit_transaction(
         return error::CommitWithNoPendingSnafu.fail();
     }
 
+    notify_watchers(&data, &changes);
+
+    Ok(ChangedKeysResponse(changes))
+}
+
+/// Returns the history of committed transactions, oldest first.  Changes to sensitive settings are
+/// left out unless 'redact' is false.
+async fn get_transaction_history(
+    query: web::Query<HashMap<String, String>>,
+    data: web::Data<SharedData>,
+    access: Access,
+) -> Result<TransactionHistoryResponse> {
+    // The history holds old values, which are still secret, so unlike settings it's redacted
+    // unless the client asks otherwise.
+    let redact = query.get("redact").is_none() || bool_param(&query, "redact")?;
+    let datastore = data.ds.read().ok().context(error::DataStoreLockSnafu)?;
+    let mut history = controller::get_transaction_history(&*datastore)?;
+    // Leave out changes to keys the client isn't allowed to see.
+    for committed in &mut history {
+        committed.changes.retain(|name, _| {
+            Key::new(KeyType::Data, name)
+                .map(|key| access.allows(&key))
+                .unwrap_or(false)
+        });
+    }
+    if redact {
+        redact_history(&*datastore, &mut history)?;
+    }
+    Ok(TransactionHistoryResponse(history))
+}
+
+/// Reverts the committed transaction with the given 'id' from the history, restoring the previous
+/// values in the live data store.  Returns the list of changed keys; like a commit, the changes
+/// aren't applied to the system until a call to /tx/apply.
+async fn revert_transaction(
+    query: web::Query<HashMap<String, String>>,
+    access: Access,
+    data: web::Data<SharedData>,
+) -> Result<ChangedKeysResponse> {
+    let id_str = query
+        .get("id")
+        .context(error::MissingInputSnafu { input: "id" })?;
+    let id = id_str.parse().ok().context(error::InvalidInputSnafu {
+        input: "id",
+        expected: "transaction ID",
+        given: id_str,
+    })?;
+
+    let mut datastore = data.ds.write().ok().context(error::DataStoreLockSnafu)?;
+    let changes = controller::revert_transaction(&mut *datastore, id, &access)?;
+
+    notify_watchers(&data, &changes);
+
     Ok(ChangedKeysResponse(changes))
 }
 
@@ -329,14 +550,36 @@ async fn commit_transaction(
 async fn apply_changes(query: web::Query<HashMap<String, String>>) -> Result<HttpResponse> {
     if let Some(keys_str) = query.get("keys") {
         let keys = comma_separated("keys", keys_str)?;
-        controller::apply_changes(Some(&keys))?;
+        controller::apply_changes(Some(&keys), None)?;
     } else {
-        controller::apply_changes(None as Option<&HashSet<&str>>)?;
+        controller::apply_changes(None as Option<&HashSet<&str>>, None)?;
     }
 
     Ok(HttpResponse::NoContent().json(()))
 }
 
+/// Reports the changes that committing and applying the given transaction, or the "default"
+/// transaction if unspecified, would make to config files, and the services that would be
+/// restarted.  Nothing is committed, written, or restarted.
+async fn dry_run_transaction(
+    query: web::Query<HashMap<String, String>>,
+    access: Access,
+) -> Result<HttpResponse> {
+    // Rendered config files can contain any key, so check here too, in case the request reached
+    // us by a path the access check didn't recognize.
+    access.check_request(&Method::GET, "/tx/dry-run")?;
+    let transaction = transaction_name(&query).to_string();
+    // The config applier calls back into the API to render templates, so it has to run off of
+    // the server's worker thread.  We also don't hold a data store lock for the same reason.
+    let report = web::block(move || controller::dry_run_transaction(&transaction))
+        .await
+        .context(error::ConfigApplierBlockingSnafu)??;
+
+    Ok(HttpResponse::Ok()
+        .content_type("application/json")
+        .body(report))
+}
+
 /// Usually you want to apply settings changes you've committed, so this is a convenience method to
 /// perform both a commit and an apply.  Commits the given transaction, or the "default"
 /// transaction if unspecified.
@@ -353,8 +596,11 @@ async fn commit_transaction_and_apply(
         return error::CommitWithNoPendingSnafu.fail();
     }
 
+    notify_watchers(&data, &changes);
+
+    let transaction_id = controller::committed_transaction_id(&*datastore, transaction, &changes);
     let key_names = changes.iter().map(|k| k.name()).collect();
-    controller::apply_changes(Some(&key_names))?;
+    controller::apply_changes(Some(&key_names), transaction_id)?;
 
     Ok(ChangedKeysResponse(changes))
 }
@@ -384,6 +630,12 @@ async fn get_os_info(
     Ok(BottlerocketReleaseResponse(os))
 }
 
+/// Returns a JSON Schema describing the user data accepted by this variant, including the
+/// constraints of modeled types, so that clients can validate settings before sending them.
+async fn get_schema() -> SchemaResponse {
+    SchemaResponse(model::schema::user_data_schema())
+}
+
 /// Get the affected services for a list of data keys
 async fn get_affected_services(
     query: web::Query<HashMap<String, String>>,
@@ -424,6 +676,31 @@ async fn get_templates(
     }
 }
 
+/// Get where each setting came from and when it was set, or if 'keys' is specified, where those
+/// keys came from
+async fn get_sources(
+    query: web::Query<HashMap<String, String>>,
+    data: web::Data<SharedData>,
+    access: Access,
+) -> Result<SourcesResponse> {
+    let datastore = data.ds.read().ok().context(error::DataStoreLockSnafu)?;
+    let mut resp = match query.get("keys") {
+        Some(keys_str) => {
+            let data_keys = comma_separated("keys", keys_str)?;
+            controller::get_sources(&*datastore, Some(&data_keys))?
+        }
+        None => controller::get_sources(&*datastore, None)?,
+    };
+    // Leave out keys the client isn't allowed to see.
+    resp.retain(|name, _| {
+        Key::new(KeyType::Data, name)
+            .map(|key| access.allows(&key))
+            .unwrap_or(false)
+    });
+
+    Ok(SourcesResponse(resp))
+}
+
 /// Get all services, or if 'names' is specified, services with those names.  If you pass a
 /// 'prefix' query string, only services starting with that prefix will be included.
 async fn get_services(
@@ -487,9 +764,9 @@ async fn get_configuration_files(
 /// Get the update status from 'thar-be-updates'
 async fn get_update_status() -> Result<UpdateStatusResponse> {
     let lockfile = File::create(UPDATE_LOCKFILE).context(error::UpdateLockOpenSnafu)?;
-    lockfile
-        .try_lock_shared()
-        .context(error::UpdateShareLockSnafu)?;
+    // Call through the trait explicitly; newer standard libraries have an inherent
+    // `File::try_lock_shared` with a different error type
+    FileExt::try_lock_shared(&lockfile).context(error::UpdateShareLockSnafu)?;
     let result = thar_be_updates::status::get_update_status(&lockfile);
     match result {
         Ok(update_status) => Ok(UpdateStatusResponse(update_status)),
@@ -502,6 +779,42 @@ async fn get_update_status() -> Result<UpdateStatusResponse> {
     }
 }
 
+/// Returns the events recorded by the config applier as it applied settings, oldest first.  Given
+/// a 'run_id', returns only the events from that run of the config applier; given a
+/// 'transaction_id', returns only the events from applying that commit from the history.
+async fn get_events(
+    query: web::Query<HashMap<String, String>>,
+    access: Access,
+) -> Result<EventsResponse> {
+    let mut events = thar_be_settings::events::read_events(EVENT_LOG_FILE)
+        .context(error::ConfigApplierEventsSnafu)?;
+    if let Some(run_id) = query.get("run_id") {
+        events.retain(|event| &event.run_id == run_id);
+    }
+    if let Some(id_str) = query.get("transaction_id") {
+        let id = id_str.parse().ok().context(error::InvalidInputSnafu {
+            input: "transaction_id",
+            expected: "transaction ID",
+            given: id_str,
+        })?;
+        events.retain(|event| event.transaction_id == Some(id));
+    }
+    // Leave out changed keys the client isn't allowed to see.
+    for event in &mut events {
+        if let EventKind::ApplyStarted {
+            changed_settings: Some(changed_settings),
+        } = &mut event.kind
+        {
+            changed_settings.retain(|name| {
+                Key::new(KeyType::Data, name)
+                    .map(|key| access.allows(&key))
+                    .unwrap_or(false)
+            });
+        }
+    }
+    Ok(EventsResponse(events))
+}
+
 /// Refreshes the list of updates and checks if an update is available matching the configured version lock
 async fn refresh_updates() -> Result<HttpResponse> {
     controller::dispatch_update_command(&["refresh"])
@@ -554,6 +867,125 @@ fn comma_separated<'a>(key_name: &'static str, input: &'a str) -> Result<HashSet
     Ok(input.split(',').collect())
 }
 
+/// Reads a boolean query parameter, which is false if it's not given.
+fn bool_param(query: &web::Query<HashMap<String, String>>, name: &str) -> Result<bool> {
+    match query.get(name).map(String::as_str) {
+        None | Some("false") => Ok(false),
+        Some("true") => Ok(true),
+        Some(given) => error::InvalidInputSnafu {
+            input: name,
+            expected: "boolean",
+            given,
+        }
+        .fail(),
+    }
+}
+
+/// Removes sensitive settings from the given serialized settings.  Settings are sensitive if
+/// they're marked in the model, or if they have the metadata 'sensitive' set to true, for example
+/// in defaults.d.
+fn redact_settings<D: DataStore>(datastore: &D, settings: &mut Value) -> Result<()> {
+    model::schema::redact_settings(settings);
+
+    let sensitive = controller::get_metadata_for_all_data_keys(datastore, "sensitive")?
+        .into_iter()
+        .filter(|(_, value)| value == &Value::Bool(true))
+        .map(|(name, _)| {
+            Key::new(KeyType::Data, &name).context(error::NewKeySnafu {
+                key_type: "data",
+                name,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    access::remove_keys(&["settings"], &sensitive, settings);
+    Ok(())
+}
+
+/// Removes sensitive settings from the given serialized model, if it includes settings.
+fn redact_model<D: DataStore>(datastore: &D, model: &mut Value) -> Result<()> {
+    match model.get_mut("settings") {
+        Some(settings) => redact_settings(datastore, settings),
+        None => Ok(()),
+    }
+}
+
+/// Removes changes to sensitive settings from the given history.  A change is sensitive if
+/// redact_settings would remove its key from the settings.
+fn redact_history<D: DataStore>(datastore: &D, history: &mut [CommittedTransaction]) -> Result<()> {
+    // Build settings holding every changed key so they can all be redacted at once.
+    let mut settings = Value::Object(serde_json::Map::new());
+    for committed in history.iter() {
+        for name in committed.changes.keys() {
+            let key = Key::new(KeyType::Data, name).context(error::NewKeySnafu {
+                key_type: "data",
+                name,
+            })?;
+            if let Some((first, rest)) = key.segments().split_first() {
+                if first == "settings" {
+                    insert_path(&mut settings, rest);
+                }
+            }
+        }
+    }
+    redact_settings(datastore, &mut settings)?;
+
+    for committed in history {
+        committed.changes.retain(|name, _| {
+            let key = match Key::new(KeyType::Data, name) {
+                Ok(key) => key,
+                Err(_) => return false,
+            };
+            match key.segments().split_first() {
+                Some((first, rest)) if first == "settings" => rest
+                    .iter()
+                    .try_fold(&settings, |value, segment| value.get(segment))
+                    .is_some(),
+                _ => true,
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Adds objects to the given value along the given path, so it's there for redact_settings.
+fn insert_path(mut value: &mut Value, path: &[String]) {
+    for segment in path {
+        if !value.is_object() {
+            *value = Value::Object(serde_json::Map::new());
+        }
+        value = value
+            .as_object_mut()
+            .expect("value was just made an object")
+            .entry(segment.as_str())
+            .or_insert(Value::Null);
+    }
+}
+
+/// Sends the keys changed by a commit to anyone watching settings.
+fn notify_watchers(data: &SharedData, changes: &HashSet<Key>) {
+    // Sending only fails if nobody is watching, which is fine.
+    let _ = data.changes.send(changes.clone());
+}
+
+/// Builds the server-sent event for a commit's changed keys, including only the keys that start
+/// with the given prefix and that the client may see.  Returns None if no keys match.
+fn watch_event(changes: &HashSet<Key>, prefix: &str, access: &Access) -> Option<Bytes> {
+    let mut matched: Vec<&str> = changes
+        .iter()
+        .filter(|key| access.allows(key))
+        .map(|key| key.name().as_str())
+        .filter(|name| name.starts_with(prefix))
+        .collect();
+    if matched.is_empty() {
+        return None;
+    }
+    // Sort so that clients see a stable order.
+    matched.sort_unstable();
+
+    let data = serde_json::to_string(&matched).expect("list of strings to JSON can't fail");
+    Some(Bytes::from(format!("event: commit\ndata: {}\n\n", data)))
+}
+
 fn transaction_name(query: &web::Query<HashMap<String, String>>) -> &str {
     if let Some(name_str) = query.get("tx") {
         name_str
@@ -568,9 +1000,13 @@ impl ResponseError for error::Error {
     fn error_response(&self) -> HttpResponse {
         use error::Error::*;
         let status_code = match self {
+            // 403 Forbidden
+            AccessDenied { .. } => StatusCode::FORBIDDEN,
+
             // 400 Bad Request
             MissingInput { .. } => StatusCode::BAD_REQUEST,
             EmptyInput { .. } => StatusCode::BAD_REQUEST,
+            InvalidInput { .. } => StatusCode::BAD_REQUEST,
             NewKey { .. } => StatusCode::BAD_REQUEST,
 
             // 404 Not Found
@@ -579,6 +1015,7 @@ impl ResponseError for error::Error {
             UpdateDoesNotExist { .. } => StatusCode::NOT_FOUND,
             NoStagedImage { .. } => StatusCode::NOT_FOUND,
             UninitializedUpdateStatus { .. } => StatusCode::NOT_FOUND,
+            UnknownCommittedTransaction { .. } => StatusCode::NOT_FOUND,
 
             // 422 Unprocessable Entity
             CommitWithNoPending => StatusCode::UNPROCESSABLE_ENTITY,
@@ -589,10 +1026,13 @@ impl ResponseError for error::Error {
 
             // 409 Conflict
             DisallowCommand { .. } => StatusCode::CONFLICT,
+            RevertConflict { .. } => StatusCode::CONFLICT,
 
             // 500 Internal Server Error
             DataStoreLock => StatusCode::INTERNAL_SERVER_ERROR,
             ResponseSerialization { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            AccessPolicyRead { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            AccessPolicyParse { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             BindSocket { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             ServerStart { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             ListedKeyNotPresent { .. } => StatusCode::INTERNAL_SERVER_ERROR,
@@ -606,6 +1046,10 @@ impl ResponseError for error::Error {
             ConfigApplierStdin {} => StatusCode::INTERNAL_SERVER_ERROR,
             ConfigApplierWait { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             ConfigApplierWrite { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            ConfigApplierBlocking { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            ConfigApplierDryRun { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            ConfigApplierOutput { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            ConfigApplierEvents { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             SystemdNotify { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             SystemdNotifyStatus {} => StatusCode::INTERNAL_SERVER_ERROR,
             SetPermissions { .. } => StatusCode::INTERNAL_SERVER_ERROR,
@@ -629,6 +1073,10 @@ impl ResponseError for error::Error {
 pub(crate) struct SharedData {
     ds: sync::RwLock<FilesystemDataStore>,
     exec_socket_path: PathBuf,
+    /// Keys changed by each commit are sent here for anyone watching settings.
+    changes: broadcast::Sender<HashSet<Key>>,
+    /// If set, requests are only allowed if this policy gives the client access.
+    access_policy: Option<AccessPolicy>,
 }
 
 /// Helper macro for implementing the actix-web Responder trait for a type.
@@ -664,8 +1112,9 @@ macro_rules! impl_responder_for {
 struct ModelResponse(serde_json::Value);
 impl_responder_for!(ModelResponse, self, self.0);
 
-/// This lets us respond from our handler methods with a Settings (or Result<Settings>)
-struct SettingsResponse(Settings);
+/// This lets us respond from our handler methods with a Settings (or Result<Settings>), where the
+/// Settings are given as a serde_json::Value so that keys the client can't see are left out.
+struct SettingsResponse(serde_json::Value);
 impl_responder_for!(SettingsResponse, self, self.0);
 
 /// This lets us respond from our handler methods with a release (or Result<release>), where
@@ -680,6 +1129,10 @@ impl_responder_for!(SettingsResponse, self, self.0);
 struct BottlerocketReleaseResponse(serde_json::Value);
 impl_responder_for!(BottlerocketReleaseResponse, self, self.0);
 
+/// This lets us respond from our handler methods with a JSON Schema document.
+struct SchemaResponse(serde_json::Value);
+impl_responder_for!(SchemaResponse, self, self.0);
+
 /// This lets us respond from our handler methods with a HashMap (or Result<HashMap>) for metadata
 struct MetadataResponse(HashMap<String, Value>);
 impl_responder_for!(MetadataResponse, self, self.0);
@@ -702,3 +1155,149 @@ impl_responder_for!(ChangedKeysResponse, self, self.0);
 
 struct TransactionListResponse(HashSet<String>);
 impl_responder_for!(TransactionListResponse, self, self.0);
+
+struct TransactionHistoryResponse(Vec<CommittedTransaction>);
+impl_responder_for!(TransactionHistoryResponse, self, self.0);
+
+struct SourcesResponse(HashMap<String, Source>);
+impl_responder_for!(SourcesResponse, self, self.0);
+
+struct EventsResponse(Vec<Event>);
+impl_responder_for!(EventsResponse, self, self.0);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn changes(names: &[&str]) -> HashSet<Key> {
+        names
+            .iter()
+            .map(|name| Key::new(KeyType::Data, name).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn watch_event_filters_and_sorts() {
+        let changes = changes(&[
+            "settings.motd",
+            "settings.ntp.time-servers",
+            "settings.ntp.x",
+        ]);
+        let event = watch_event(&changes, "settings.ntp", &Access::full()).unwrap();
+        assert_eq!(
+            event,
+            Bytes::from(
+                "event: commit\ndata: [\"settings.ntp.time-servers\",\"settings.ntp.x\"]\n\n"
+            )
+        );
+    }
+
+    #[test]
+    fn watch_event_no_match() {
+        let changes = changes(&["settings.motd"]);
+        assert!(watch_event(&changes, "settings.ntp", &Access::full()).is_none());
+    }
+
+    #[test]
+    fn watch_event_hides_denied_keys() {
+        let access = Access::new(access::AccessLevel::ReadOnly, &["settings.aws"]).unwrap();
+        let mixed = changes(&["settings.aws.region", "settings.motd"]);
+        let event = watch_event(&mixed, "settings.", &access).unwrap();
+        assert_eq!(
+            event,
+            Bytes::from("event: commit\ndata: [\"settings.motd\"]\n\n")
+        );
+
+        // A commit that only changed denied keys isn't sent at all
+        let denied = changes(&["settings.aws.region"]);
+        assert!(watch_event(&denied, "settings.", &access).is_none());
+    }
+
+    fn read_only_access() -> Access {
+        Access::new(access::AccessLevel::ReadOnly, &["settings.aws"]).unwrap()
+    }
+
+    #[test]
+    fn access_check_decodes_path() {
+        let access = read_only_access();
+        for path in &["/e%78ec", "/tx/dry%2Drun", "/tx/%64ry-run"] {
+            let req = actix_web::test::TestRequest::get()
+                .uri(path)
+                .to_srv_request();
+            assert!(
+                access
+                    .check_request(req.method(), req.match_info().as_str())
+                    .is_err(),
+                "{} was allowed",
+                path
+            );
+        }
+    }
+
+    #[actix_rt::test]
+    async fn handlers_check_encoded_paths() {
+        let (changes, _) = broadcast::channel(1);
+        let data = web::Data::new(SharedData {
+            ds: sync::RwLock::new(FilesystemDataStore::new("/nonexistent")),
+            exec_socket_path: PathBuf::from("/nonexistent"),
+            changes,
+            access_policy: None,
+        });
+        // Give every request access that can't use these routes, like the access check would, so
+        // we can see the handlers refuse them even if the access check were bypassed.
+        let app = actix_web::test::init_service(
+            App::new()
+                .app_data(data)
+                .wrap_fn(|req, srv| {
+                    req.extensions_mut().insert(read_only_access());
+                    srv.call(req)
+                })
+                .service(web::scope("/tx").route("/dry-run", web::get().to(dry_run_transaction)))
+                .service(web::resource("/exec").route(web::get().to(exec::ws_exec))),
+        )
+        .await;
+
+        for path in &["/e%78ec", "/tx/dry%2Drun"] {
+            let req = actix_web::test::TestRequest::get().uri(path).to_request();
+            let resp = actix_web::test::call_service(&app, req).await;
+            assert_eq!(resp.status(), StatusCode::FORBIDDEN, "{}", path);
+        }
+    }
+
+    #[test]
+    fn history_redacts_sensitive_changes() {
+        let mut ds = datastore::memory::MemoryDataStore::new();
+        // The motd isn't sensitive in the model, but can be marked sensitive in metadata
+        ds.set_metadata(
+            &Key::new(KeyType::Meta, "sensitive").unwrap(),
+            &Key::new(KeyType::Data, "settings.motd").unwrap(),
+            "true",
+        )
+        .unwrap();
+
+        let pending = Committed::Pending {
+            tx: "default".to_string(),
+        };
+        for name in &[
+            "settings.aws.config",
+            "settings.aws.region",
+            "settings.host-containers.admin.user-data",
+            "settings.host-containers.admin.enabled",
+            "settings.motd",
+        ] {
+            ds.set_key(&Key::new(KeyType::Data, name).unwrap(), "\"x\"", &pending)
+                .unwrap();
+        }
+        ds.commit_transaction("default").unwrap();
+
+        let mut history = ds.transaction_history().unwrap();
+        redact_history(&ds, &mut history).unwrap();
+        assert_eq!(
+            history[0].changes.keys().collect::<Vec<_>>(),
+            vec![
+                "settings.aws.region",
+                "settings.host-containers.admin.enabled"
+            ]
+        );
+    }
+}
@@ -17,6 +17,9 @@ pub enum Error {
     #[snafu(display("Error serializing scalar {}: {} ", given, source))]
     SerializeScalar { given: String, source: ScalarError },
 
+    #[snafu(display("Error deserializing scalar {}: {} ", given, source))]
+    DeserializeScalar { given: String, source: ScalarError },
+
     #[snafu(display("Key would traverse outside data store: {}", name))]
     PathTraversal { name: String },
 
@@ -59,6 +62,25 @@ pub enum Error {
 
     #[snafu(display("Key name beyond maximum length {}: {}", name, max))]
     KeyTooLong { name: String, max: usize },
+
+    #[snafu(display("Unable to parse transaction history at '{}': {}", path.display(), source))]
+    HistoryParse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[snafu(display("Unable to serialize transaction history: {}", source))]
+    HistorySerialize { source: serde_json::Error },
+
+    #[snafu(display("No committed transaction with ID {} in history", id))]
+    UnknownTransaction { id: u64 },
+
+    #[snafu(display(
+        "Can't revert transaction {}: key '{}' has been changed since it was committed",
+        id,
+        key
+    ))]
+    RevertConflict { id: u64, key: String },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
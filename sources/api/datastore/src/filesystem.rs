@@ -3,20 +3,28 @@
 //!
 //! Data is kept in files with paths resembling the keys, e.g. a/b/c for a.b.c, and metadata is
 //! kept in a suffixed file next to the data, e.g. a/b/c.meta for metadata "meta" about a.b.c
+//!
+//! The history of committed transactions is kept as a JSON file next to the live and pending
+//! directories.  Migrations don't know about it, so the migrator copies it into each new data
+//! store version.
 
-use log::{debug, error, trace};
+use log::{debug, error, trace, warn};
 use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use snafu::{ensure, OptionExt, ResultExt};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io;
 use std::path::{self, Path, PathBuf};
 use walkdir::{DirEntry, WalkDir};
 
+use super::history::{self, Change, CommittedTransaction};
 use super::key::{Key, KeyType};
+use super::source;
 use super::{error, Committed, DataStore, Result};
 
 const METADATA_KEY_PREFIX: &str = ".";
+/// The name of the file, in the data store directory, that holds the history of commits.
+pub const HISTORY_FILE: &str = "history.json";
 
 // This describes the set of characters we encode when making the filesystem path for a given key.
 // Any non-ASCII characters, plus these ones, will be encoded.
@@ -29,6 +37,7 @@ const ENCODE_CHARACTERS: &AsciiSet = &NON_ALPHANUMERIC.remove(b'_').remove(b'-')
 pub struct FilesystemDataStore {
     live_path: PathBuf,
     pending_base_path: PathBuf,
+    history_path: PathBuf,
 }
 
 impl FilesystemDataStore {
@@ -36,6 +45,7 @@ impl FilesystemDataStore {
         FilesystemDataStore {
             live_path: base_path.as_ref().join("live"),
             pending_base_path: base_path.as_ref().join("pending"),
+            history_path: base_path.as_ref().join(HISTORY_FILE),
         }
     }
 
@@ -486,8 +496,9 @@ impl DataStore for FilesystemDataStore {
     where
         S: Into<String> + AsRef<str>,
     {
+        let transaction = transaction.into();
         let pending = Committed::Pending {
-            tx: transaction.into(),
+            tx: transaction.clone(),
         };
         // Get data for changed keys
         let pending_data = self.get_prefix("settings.", &pending)?;
@@ -500,15 +511,28 @@ impl DataStore for FilesystemDataStore {
         // Save Keys for return value
         let pending_keys: HashSet<Key> = pending_data.keys().cloned().collect();
 
+        // Note the old values before they're overwritten so the commit can be reverted
+        let changes = history::live_changes(self, &pending_data)?;
+
         // Apply changes to live
         debug!("Writing pending keys to live");
         self.set_keys(&pending_data, &Committed::Live)?;
+        source::commit(self, &transaction)?;
 
         // Remove pending
         debug!("Removing old pending keys");
         let path = self.base_path(&pending);
         fs::remove_dir_all(&path).context(error::IoSnafu { path })?;
 
+        // The commit has already happened, so failing to record it only costs the ability to
+        // revert it; don't fail the commit and leave the caller thinking it didn't happen.
+        if let Err(e) = self.record_transaction(&transaction, changes) {
+            warn!(
+                "Committed transaction '{}' but failed to record it in the history: {}",
+                transaction, e
+            );
+        }
+
         Ok(pending_keys)
     }
 
@@ -581,6 +605,42 @@ impl DataStore for FilesystemDataStore {
 
         Ok(transactions)
     }
+
+    fn transaction_history(&self) -> Result<Vec<CommittedTransaction>> {
+        let data = match fs::read_to_string(&self.history_path) {
+            Ok(data) => data,
+            // No commits have been made yet.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e).context(error::IoSnafu {
+                    path: &self.history_path,
+                })
+            }
+        };
+        serde_json::from_str(&data).context(error::HistoryParseSnafu {
+            path: &self.history_path,
+        })
+    }
+
+    /// We write the full history to a temporary file and rename it into place so that readers
+    /// never see a partially written history.
+    fn record_transaction(
+        &mut self,
+        transaction: &str,
+        changes: BTreeMap<String, Change>,
+    ) -> Result<u64> {
+        let mut history = self.transaction_history()?;
+        let id = history::push_record(&mut history, transaction, changes);
+
+        let data = serde_json::to_string(&history).context(error::HistorySerializeSnafu)?;
+        let tmp_path = self.history_path.with_extension("tmp");
+        fs::write(&tmp_path, data).context(error::IoSnafu { path: &tmp_path })?;
+        fs::rename(&tmp_path, &self.history_path).context(error::IoSnafu {
+            path: &self.history_path,
+        })?;
+
+        Ok(id)
+    }
 }
 
 #[cfg(test)]
@@ -647,4 +707,25 @@ mod test {
         // Invalid UTF-8
         decode_path_component("%C3%28", "").unwrap_err();
     }
+
+    #[test]
+    fn commit_with_bad_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut f = FilesystemDataStore::new(dir.path());
+        fs::write(dir.path().join(HISTORY_FILE), "not json").unwrap();
+
+        let key = Key::new(KeyType::Data, "settings.a").unwrap();
+        let tx = "test transaction";
+        let pending = Committed::Pending { tx: tx.into() };
+        f.set_key(&key, "\"1\"", &pending).unwrap();
+
+        // The commit goes through and cleans up even though it can't be recorded
+        let committed = f.commit_transaction(tx).unwrap();
+        assert!(committed.contains(&key));
+        assert_eq!(
+            f.get_key(&key, &Committed::Live).unwrap(),
+            Some("\"1\"".to_string())
+        );
+        assert!(f.list_transactions().unwrap().is_empty());
+    }
 }
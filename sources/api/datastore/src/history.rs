@@ -0,0 +1,174 @@
+//! The history module describes the journal of committed transactions that data stores keep so
+//! that changes can be reviewed, and reverted if they turn out to be bad.
+//!
+//! Each record holds the old and new value of every key changed by a commit.  Only the most recent
+//! HISTORY_LIMIT records are kept, so the journal can't grow without bound.
+
+use chrono::{DateTime, Utc};
+use log::warn;
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, OptionExt};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::{error, Committed, DataStore, Key, KeyType, Result};
+
+/// The maximum number of committed transactions kept in the history.
+pub const HISTORY_LIMIT: usize = 100;
+
+/// A record of a committed transaction.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommittedTransaction {
+    /// Identifies the record for later reference, for example to revert it.  IDs increase with
+    /// each commit.
+    pub id: u64,
+    /// The name of the transaction that was committed.
+    pub transaction: String,
+    /// When the transaction was committed.
+    pub timestamp: DateTime<Utc>,
+    /// The changes made by the commit, keyed by data key name.
+    pub changes: BTreeMap<String, Change>,
+}
+
+/// The change made to a single key by a commit.  Values are in the data store's serialized form,
+/// and a value of None means the key was not set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change {
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// Builds the list of changes that will be made by writing the given data to the live data store.
+/// This must be called before the data is written so that we can see the old values.
+pub(crate) fn live_changes<D>(
+    datastore: &D,
+    new_data: &HashMap<Key, String>,
+) -> Result<BTreeMap<String, Change>>
+where
+    D: DataStore + ?Sized,
+{
+    let mut changes = BTreeMap::new();
+    for (key, new) in new_data {
+        let change = Change {
+            old: datastore.get_key(key, &Committed::Live)?,
+            new: Some(new.clone()),
+        };
+        changes.insert(key.name().clone(), change);
+    }
+    Ok(changes)
+}
+
+/// Adds a record of the given changes to the end of the history, dropping the oldest records if
+/// we've reached HISTORY_LIMIT.  Returns the new record's ID.
+pub(crate) fn push_record<S>(
+    history: &mut Vec<CommittedTransaction>,
+    transaction: S,
+    changes: BTreeMap<String, Change>,
+) -> u64
+where
+    S: Into<String>,
+{
+    let id = history.last().map(|record| record.id + 1).unwrap_or(1);
+    history.push(CommittedTransaction {
+        id,
+        transaction: transaction.into(),
+        timestamp: Utc::now(),
+        changes,
+    });
+
+    if history.len() > HISTORY_LIMIT {
+        let excess = history.len() - HISTORY_LIMIT;
+        history.drain(..excess);
+    }
+    id
+}
+
+/// Restores the value each key had before the committed transaction with the given ID, and
+/// records the revert in the history.  Returns the list of changed keys.
+pub(crate) fn revert<D>(datastore: &mut D, id: u64) -> Result<HashSet<Key>>
+where
+    D: DataStore + ?Sized,
+{
+    let history = datastore.transaction_history()?;
+    let record = history
+        .iter()
+        .find(|record| record.id == id)
+        .context(error::UnknownTransactionSnafu { id })?;
+
+    let mut to_set = HashMap::new();
+    let mut to_unset = HashSet::new();
+    let mut changes = BTreeMap::new();
+    for (name, change) in &record.changes {
+        let key = Key::new(KeyType::Data, name)?;
+
+        // If the key was changed again later, reverting would silently discard that change.
+        let current = datastore.get_key(&key, &Committed::Live)?;
+        ensure!(
+            current == change.new,
+            error::RevertConflictSnafu { id, key: name }
+        );
+
+        match &change.old {
+            Some(old) => {
+                to_set.insert(key, old.clone());
+            }
+            None => {
+                to_unset.insert(key);
+            }
+        }
+        changes.insert(
+            name.clone(),
+            Change {
+                old: change.new.clone(),
+                new: change.old.clone(),
+            },
+        );
+    }
+
+    datastore.set_keys(&to_set, &Committed::Live)?;
+    datastore.unset_keys(&to_unset, &Committed::Live)?;
+    let source = format!("revert of transaction {}", id);
+    datastore.set_source(to_set.keys(), &source, &Committed::Live)?;
+    datastore.unset_source(&to_unset)?;
+    // As with a commit, the revert has happened by now even if we can't record it.
+    let transaction = format!("revert-{}", id);
+    if let Err(e) = datastore.record_transaction(&transaction, changes) {
+        warn!(
+            "Reverted transaction {} but failed to record the revert in the history: {}",
+            id, e
+        );
+    }
+
+    Ok(to_set.into_keys().chain(to_unset).collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use maplit::btreemap;
+
+    fn changes(new: &str) -> BTreeMap<String, Change> {
+        btreemap!(
+            "settings.a".to_string() => Change { old: None, new: Some(new.to_string()) }
+        )
+    }
+
+    #[test]
+    fn ids_increase() {
+        let mut history = Vec::new();
+        assert_eq!(push_record(&mut history, "tx", changes("1")), 1);
+        assert_eq!(push_record(&mut history, "tx", changes("2")), 2);
+        assert_eq!(history.len(), 2);
+    }
+
+    #[test]
+    fn history_is_bounded() {
+        let mut history = Vec::new();
+        for i in 0..HISTORY_LIMIT + 5 {
+            push_record(&mut history, "tx", changes(&i.to_string()));
+        }
+        assert_eq!(history.len(), HISTORY_LIMIT);
+        // The oldest records were dropped, and IDs keep increasing.
+        assert_eq!(history[0].id, 6);
+        assert_eq!(history.last().unwrap().id, (HISTORY_LIMIT + 5) as u64);
+    }
+}
@@ -14,6 +14,17 @@ There's also a common error type and some methods that implementations of DataSt
 We represent scalars -- the actual values stored under a datastore key -- using JSON, just to have a convenient human-readable form.
 (TOML doesn't allow raw scalars.  The JSON spec doesn't seem to either, but this works, and the format is so simple for scalars that it could be easily swapped out if needed.)
 
+# History
+
+Data stores keep a bounded history of committed transactions, recording the old and new value of each changed key.
+The `history` module describes the records.
+A committed transaction can be reverted by ID, which restores the old values and is itself recorded in the history.
+
+# Sources
+
+Data stores can record where the value of each key came from, like defaults, user data, a setting generator, or a person using the API, along with when it was set.
+The `source` module describes the records, which are kept in `source` and `source-timestamp` metadata.
+
 # Serialization and deserialization
 
 The `serialization` module provides code to serialize Rust types into a mapping of datastore-acceptable keys (a.b.c) and values.
@@ -23,25 +34,29 @@ The `deserialization` module provides code to deserialize datastore-acceptable k
 # Current limitations
 
 * The user (e.g. apiserver) needs to handle locking.
-* There's no support for rolling back transactions.
+* Only the most recent committed transactions are kept in the history, and only those can be reverted.
 * The `serialization` module can't handle complex types under lists; it assumes lists can be serialized as scalars.
 */
 
 pub mod deserialization;
 pub mod error;
 pub mod filesystem;
+pub mod history;
 pub mod key;
 pub mod memory;
 pub mod serialization;
+pub mod source;
 
 pub use error::{Error, Result};
 pub use filesystem::FilesystemDataStore;
+pub use history::{Change, CommittedTransaction};
 pub use key::{Key, KeyType, KEY_SEPARATOR, KEY_SEPARATOR_STR};
+pub use source::Source;
 
 use log::trace;
 use serde::{Deserialize, Serialize};
 use snafu::OptionExt;
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 /// Committed represents whether we want to look at pending (uncommitted) or live (committed) data
 /// in the datastore.
@@ -138,6 +153,50 @@ pub trait DataStore {
     /// Returns a list of the names of any pending transactions in the data store.
     fn list_transactions(&self) -> Result<HashSet<String>>;
 
+    /// Returns the history of committed transactions, oldest first.
+    fn transaction_history(&self) -> Result<Vec<CommittedTransaction>>;
+
+    /// Adds a record of the given changes to the history of committed transactions, dropping the
+    /// oldest records if the history is full.  Returns the ID of the new record.
+    fn record_transaction(
+        &mut self,
+        transaction: &str,
+        changes: BTreeMap<String, Change>,
+    ) -> Result<u64>;
+
+    /// Reverts the committed transaction with the given ID, restoring each key it changed to its
+    /// previous value in the live data store, or removing the key if it wasn't previously set.
+    /// The revert is itself recorded in the history.  Returns the list of changed keys.
+    ///
+    /// Fails if any of the keys were changed again after the transaction was committed, so that
+    /// we don't silently discard later changes.
+    fn revert_transaction(&mut self, id: u64) -> Result<HashSet<Key>> {
+        history::revert(self, id)
+    }
+
+    /// Records where the values of the given data keys came from, so clients can explain them.
+    /// `source` describes the writer, like "storewolf".  For values written to a pending
+    /// transaction, the source is recorded, with the time, when the transaction is committed.
+    fn set_source<'a, I>(&mut self, data_keys: I, source: &str, committed: &Committed) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a Key>,
+    {
+        source::set(self, data_keys, source, committed)
+    }
+
+    /// Removes any recorded source of the given data keys, for example because they were unset.
+    fn unset_source<'a, I>(&mut self, data_keys: I) -> Result<()>
+    where
+        I: IntoIterator<Item = &'a Key>,
+    {
+        source::unset(self, data_keys)
+    }
+
+    /// Returns where the value of the given data key came from, if that was recorded.
+    fn get_source(&self, data_key: &Key) -> Result<Option<Source>> {
+        source::get(self, data_key)
+    }
+
     /// Set multiple data keys at once in the data store.
     ///
     /// Implementers can replace the default implementation if there's a faster way than setting
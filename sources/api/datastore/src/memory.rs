@@ -3,8 +3,10 @@
 //! Mimics some of the decisions made for FilesystemDataStore, e.g. metadata being committed
 //! immediately.
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use super::history::{self, Change, CommittedTransaction};
+use super::source;
 use super::{Committed, DataStore, Key, Result};
 
 #[derive(Debug, Default)]
@@ -16,6 +18,8 @@ pub struct MemoryDataStore {
     // Map of data keys to their metadata, which in turn is a mapping of metadata keys to
     // arbitrary (string/serialized) values.
     metadata: HashMap<Key, HashMap<Key, String>>,
+    // Records of committed transactions, oldest first.
+    history: Vec<CommittedTransaction>,
 }
 
 impl MemoryDataStore {
@@ -150,15 +154,26 @@ impl DataStore for MemoryDataStore {
     where
         S: Into<String> + AsRef<str>,
     {
+        let pending_data: HashMap<Key, String> = match self.pending.get(transaction.as_ref()) {
+            // The transaction may also hold the sources of its values, which aren't data
+            Some(pending) => pending
+                .iter()
+                .filter(|(key, _)| !source::is_pending_source(key))
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            None => return Ok(HashSet::new()),
+        };
+
+        // Note the old values so the commit can be reverted
+        let changes = history::live_changes(self, &pending_data)?;
+        // Apply pending changes to live
+        self.set_keys(&pending_data, &Committed::Live)?;
+        source::commit(self, transaction.as_ref())?;
         // Remove anything pending for this transaction
-        if let Some(pending) = self.pending.remove(transaction.as_ref()) {
-            // Apply pending changes to live
-            self.set_keys(&pending, &Committed::Live)?;
-            // Return keys that were committed
-            Ok(pending.keys().cloned().collect())
-        } else {
-            Ok(HashSet::new())
-        }
+        self.pending.remove(transaction.as_ref());
+        self.record_transaction(transaction.as_ref(), changes)?;
+        // Return keys that were committed
+        Ok(pending_data.into_keys().collect())
     }
 
     fn delete_transaction<S>(&mut self, transaction: S) -> Result<HashSet<Key>>
@@ -167,8 +182,11 @@ impl DataStore for MemoryDataStore {
     {
         // Remove anything pending for this transaction
         if let Some(pending) = self.pending.remove(transaction.as_ref()) {
-            // Return the old pending keys
-            Ok(pending.keys().cloned().collect())
+            // Return the old pending keys, leaving out their sources
+            Ok(pending
+                .into_keys()
+                .filter(|key| !source::is_pending_source(key))
+                .collect())
         } else {
             Ok(HashSet::new())
         }
@@ -177,11 +195,27 @@ impl DataStore for MemoryDataStore {
     fn list_transactions(&self) -> Result<HashSet<String>> {
         Ok(self.pending.keys().cloned().collect())
     }
+
+    fn transaction_history(&self) -> Result<Vec<CommittedTransaction>> {
+        Ok(self.history.clone())
+    }
+
+    fn record_transaction(
+        &mut self,
+        transaction: &str,
+        changes: BTreeMap<String, Change>,
+    ) -> Result<u64> {
+        Ok(history::push_record(
+            &mut self.history,
+            transaction,
+            changes,
+        ))
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::super::{Committed, DataStore, Key, KeyType};
+    use super::super::{Change, Committed, DataStore, Key, KeyType};
     use super::MemoryDataStore;
     use maplit::hashset;
 
@@ -272,4 +306,65 @@ mod test {
         // Assure other transactions were not deleted
         assert!(m.key_populated(&k2, &pending2).unwrap());
     }
+
+    #[test]
+    fn history_and_revert() {
+        let mut m = MemoryDataStore::new();
+        let k1 = Key::new(KeyType::Data, "settings.a").unwrap();
+        let k2 = Key::new(KeyType::Data, "settings.b").unwrap();
+        m.set_key(&k1, "old", &Committed::Live).unwrap();
+
+        let tx = "test transaction";
+        let pending = Committed::Pending { tx: tx.into() };
+        m.set_key(&k1, "new", &pending).unwrap();
+        m.set_key(&k2, "added", &pending).unwrap();
+        m.commit_transaction(tx).unwrap();
+
+        let history = m.transaction_history().unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].transaction, tx);
+        assert_eq!(
+            history[0].changes["settings.a"],
+            Change {
+                old: Some("old".to_string()),
+                new: Some("new".to_string())
+            }
+        );
+
+        // Reverting restores the old value and removes the added key
+        let reverted = m.revert_transaction(history[0].id).unwrap();
+        assert_eq!(reverted, hashset!(k1.clone(), k2.clone()));
+        assert_eq!(
+            m.get_key(&k1, &Committed::Live).unwrap(),
+            Some("old".to_string())
+        );
+        assert!(!m.key_populated(&k2, &Committed::Live).unwrap());
+
+        // The revert is recorded too
+        let history = m.transaction_history().unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].transaction, format!("revert-{}", history[0].id));
+    }
+
+    #[test]
+    fn revert_conflict() {
+        let mut m = MemoryDataStore::new();
+        let k = Key::new(KeyType::Data, "settings.a").unwrap();
+        for (tx, value) in &[("first", "1"), ("second", "2")] {
+            let pending = Committed::Pending { tx: tx.to_string() };
+            m.set_key(&k, value, &pending).unwrap();
+            m.commit_transaction(*tx).unwrap();
+        }
+
+        // The first commit can't be reverted because the second changed the same key
+        let first = m.transaction_history().unwrap()[0].id;
+        m.revert_transaction(first).unwrap_err();
+        assert_eq!(
+            m.get_key(&k, &Committed::Live).unwrap(),
+            Some("2".to_string())
+        );
+
+        // Unknown IDs are an error
+        m.revert_transaction(42).unwrap_err();
+    }
 }
@@ -0,0 +1,223 @@
+//! The source module records where the value of each data key came from -- defaults, user data, a
+//! setting generator, or someone using the API -- and when, so that a value can be explained.
+//!
+//! Sources are stored in metadata of each data key, as a `source` string and a `source-timestamp`.
+//! Metadata only exists in the live data store, so a source given for a pending value is kept in
+//! the transaction, under the `sources` prefix, and only recorded in metadata when the transaction
+//! is committed.  A transaction that's deleted takes its sources with it.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+
+use std::collections::HashMap;
+
+use super::{
+    deserialize_scalar, error, serialize_scalar, Committed, DataStore, Key, KeyType, Result,
+    ScalarError,
+};
+
+/// The metadata key holding a description of where a value came from, like "storewolf".
+pub const SOURCE_METADATA_KEY: &str = "source";
+/// The metadata key holding the time a value's source was recorded.
+pub const SOURCE_TIMESTAMP_METADATA_KEY: &str = "source-timestamp";
+/// The prefix of the data keys that hold sources in a pending transaction; the rest of the key is
+/// the data key the source describes.
+const PENDING_SOURCE_PREFIX: &str = "sources";
+
+/// Where the value of a data key came from, and when it was set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Source {
+    pub source: String,
+    /// Sources recorded by hand, for example in defaults, may not have a timestamp.
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+fn metadata_keys() -> Result<(Key, Key)> {
+    Ok((
+        Key::new(KeyType::Meta, SOURCE_METADATA_KEY)?,
+        Key::new(KeyType::Meta, SOURCE_TIMESTAMP_METADATA_KEY)?,
+    ))
+}
+
+/// Returns whether the given key holds the source of a pending value, rather than data.
+pub(crate) fn is_pending_source(key: &Key) -> bool {
+    key.segments()
+        .first()
+        .map(|segment| segment == PENDING_SOURCE_PREFIX)
+        .unwrap_or(false)
+}
+
+/// Returns the key that holds the pending source of the given data key.
+fn pending_source_key(data_key: &Key) -> Result<Key> {
+    let mut segments = vec![PENDING_SOURCE_PREFIX.to_string()];
+    segments.extend(data_key.segments().iter().cloned());
+    Key::from_segments(KeyType::Data, &segments)
+}
+
+/// Records the given source for each of the given data keys.  Sources of live values are recorded
+/// with the current time; sources of pending values are kept until the transaction is committed.
+pub(crate) fn set<'a, D, I>(
+    datastore: &mut D,
+    data_keys: I,
+    source: &str,
+    committed: &Committed,
+) -> Result<()>
+where
+    D: DataStore + ?Sized,
+    I: IntoIterator<Item = &'a Key>,
+{
+    let source_value = serialize_scalar::<_, ScalarError>(&source)
+        .context(error::SerializeScalarSnafu { given: source })?;
+
+    if let Committed::Pending { .. } = committed {
+        for data_key in data_keys {
+            datastore.set_key(&pending_source_key(data_key)?, &source_value, committed)?;
+        }
+        return Ok(());
+    }
+
+    let (source_key, timestamp_key) = metadata_keys()?;
+    let timestamp = Utc::now();
+    let timestamp_value =
+        serialize_scalar::<_, ScalarError>(&timestamp).context(error::SerializeScalarSnafu {
+            given: timestamp.to_string(),
+        })?;
+
+    for data_key in data_keys {
+        datastore.set_metadata(&source_key, data_key, &source_value)?;
+        datastore.set_metadata(&timestamp_key, data_key, &timestamp_value)?;
+    }
+    Ok(())
+}
+
+/// Records the sources kept in the given pending transaction for the live values it's committing.
+/// Call this after the values are written to live, and before the transaction is removed.
+pub(crate) fn commit<D>(datastore: &mut D, transaction: &str) -> Result<()>
+where
+    D: DataStore + ?Sized,
+{
+    let pending = Committed::Pending {
+        tx: transaction.to_string(),
+    };
+    let pending_sources = datastore.get_prefix(format!("{}.", PENDING_SOURCE_PREFIX), &pending)?;
+
+    // Group the keys by source so each source is only serialized once.
+    let mut by_source: HashMap<String, Vec<Key>> = HashMap::new();
+    for (key, source_value) in pending_sources {
+        let source: String = deserialize_scalar::<_, ScalarError>(&source_value).context(
+            error::DeserializeScalarSnafu {
+                given: source_value,
+            },
+        )?;
+        let data_key = Key::from_segments(KeyType::Data, &key.segments()[1..])?;
+        by_source.entry(source).or_default().push(data_key);
+    }
+    for (source, data_keys) in by_source {
+        set(datastore, &data_keys, &source, &Committed::Live)?;
+    }
+    Ok(())
+}
+
+/// Removes any recorded source of each of the given data keys.
+pub(crate) fn unset<'a, D, I>(datastore: &mut D, data_keys: I) -> Result<()>
+where
+    D: DataStore + ?Sized,
+    I: IntoIterator<Item = &'a Key>,
+{
+    let (source_key, timestamp_key) = metadata_keys()?;
+    for data_key in data_keys {
+        datastore.unset_metadata(&source_key, data_key)?;
+        datastore.unset_metadata(&timestamp_key, data_key)?;
+    }
+    Ok(())
+}
+
+/// Returns the recorded source of the given data key, if any.  Sources aren't inherited from
+/// earlier in the tree, since a source describes a single value.
+pub(crate) fn get<D>(datastore: &D, data_key: &Key) -> Result<Option<Source>>
+where
+    D: DataStore + ?Sized,
+{
+    let (source_key, timestamp_key) = metadata_keys()?;
+    let source = match datastore.get_metadata_raw(&source_key, data_key)? {
+        Some(source) => deserialize_scalar::<_, ScalarError>(&source)
+            .context(error::DeserializeScalarSnafu { given: source })?,
+        None => return Ok(None),
+    };
+    let timestamp = match datastore.get_metadata_raw(&timestamp_key, data_key)? {
+        Some(timestamp) => Some(
+            deserialize_scalar::<_, ScalarError>(&timestamp)
+                .context(error::DeserializeScalarSnafu { given: timestamp })?,
+        ),
+        None => None,
+    };
+    Ok(Some(Source { source, timestamp }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::memory::MemoryDataStore;
+    use super::*;
+
+    #[test]
+    fn set_get_unset() {
+        let mut m = MemoryDataStore::new();
+        let a = Key::new(KeyType::Data, "settings.a").unwrap();
+        let b = Key::new(KeyType::Data, "settings.b").unwrap();
+        assert_eq!(m.get_source(&a).unwrap(), None);
+
+        let before = Utc::now();
+        m.set_source(&[a.clone(), b.clone()], "storewolf", &Committed::Live)
+            .unwrap();
+        let source = m.get_source(&a).unwrap().unwrap();
+        assert_eq!(source.source, "storewolf");
+        assert!(source.timestamp.unwrap() >= before);
+        assert_eq!(m.get_source(&b).unwrap().unwrap().source, "storewolf");
+
+        // A later source replaces the earlier one.
+        m.set_source(std::slice::from_ref(&a), "apiclient set", &Committed::Live)
+            .unwrap();
+        assert_eq!(m.get_source(&a).unwrap().unwrap().source, "apiclient set");
+
+        m.unset_source(std::slice::from_ref(&a)).unwrap();
+        assert_eq!(m.get_source(&a).unwrap(), None);
+        assert!(m.get_source(&b).unwrap().is_some());
+    }
+
+    #[test]
+    fn not_inherited() {
+        let mut m = MemoryDataStore::new();
+        let parent = Key::new(KeyType::Data, "settings.a").unwrap();
+        let child = Key::new(KeyType::Data, "settings.a.b").unwrap();
+        m.set_source(&[parent], "storewolf", &Committed::Live)
+            .unwrap();
+        assert_eq!(m.get_source(&child).unwrap(), None);
+    }
+
+    #[test]
+    fn pending_until_commit() {
+        let mut m = MemoryDataStore::new();
+        let a = Key::new(KeyType::Data, "settings.a").unwrap();
+        let pending = Committed::Pending { tx: "tx".into() };
+        m.set_key(&a, "\"x\"", &pending).unwrap();
+        m.set_source(std::slice::from_ref(&a), "apiclient set", &pending)
+            .unwrap();
+        assert_eq!(m.get_source(&a).unwrap(), None);
+
+        // Deleting the transaction drops its sources
+        m.delete_transaction("tx").unwrap();
+        m.commit_transaction("tx").unwrap();
+        assert_eq!(m.get_source(&a).unwrap(), None);
+
+        m.set_key(&a, "\"x\"", &pending).unwrap();
+        m.set_source(std::slice::from_ref(&a), "apiclient set", &pending)
+            .unwrap();
+        let committed = m.commit_transaction("tx").unwrap();
+        assert_eq!(committed.into_iter().collect::<Vec<_>>(), vec![a.clone()]);
+        assert_eq!(m.get_source(&a).unwrap().unwrap().source, "apiclient set");
+        // The pending source isn't committed as data
+        let sources = Key::new(KeyType::Data, "sources.settings.a").unwrap();
+        assert_eq!(m.get_key(&sources, &Committed::Live).unwrap(), None);
+    }
+}
@@ -21,7 +21,6 @@ const GZ_MAGIC: [u8; 2] = [0x1f, 0x8b];
 
 /// This helper takes a slice of bytes representing UTF-8 text, which can optionally be
 /// compressed, and returns an uncompressed string.
-#[allow(dead_code)]
 pub fn expand_slice_maybe(input: &[u8]) -> Result<String> {
     let mut output = String::new();
     let mut reader = OptionalCompressionReader::new(Cursor::new(input));
@@ -0,0 +1,459 @@
+//! The include module lets user data pull in more user data from URLs, listed in
+//! `settings.userdata.include`.  The list isn't a setting the API knows about, so it's removed
+//! from the user data before the user data is sent.
+//!
+//! Included user data can be signed, so that user data can be a small stub pointing at a larger
+//! document hosted elsewhere without trusting the host.  Signatures are detached Ed25519
+//! signatures of the document as served, encoded in base64.
+
+use crate::settings::SettingsJson;
+use early_boot_config::compression::expand_slice_maybe;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::Deserialize;
+use snafu::{ensure, ResultExt};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
+
+/// The table in user data that holds options for early-boot-config itself.
+const USERDATA_TABLE: &str = "userdata";
+
+/// Public keys baked into the variant, one per line in base64.  If there are any, all included
+/// user data must be signed.
+pub(crate) const TRUSTED_KEYS_FILE: &str = "/usr/share/bottlerocket/user-data-keys";
+
+// Fail rather than hang at boot if a server stops responding.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Options for early-boot-config, from the `settings.userdata` table of user data.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UserDataOptions {
+    /// User data to fetch and apply after the user data that lists it.
+    #[serde(default)]
+    include: Vec<IncludeEntry>,
+}
+
+/// User data to include is given as a URL, or as a table when it's signed.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum IncludeEntry {
+    Url(String),
+    Signed(SignedEntry),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct SignedEntry {
+    url: String,
+    /// Defaults to the URL of the user data with ".sig" added to the path.
+    signature_url: Option<String>,
+    /// A key the user data may be signed with, in addition to any baked into the variant.
+    public_key: Option<String>,
+}
+
+/// An Ed25519 public key.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PublicKey(Vec<u8>);
+
+impl FromStr for PublicKey {
+    type Err = error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let bytes = base64::decode(s.trim()).context(error::KeyDecodeSnafu { key: s })?;
+        ensure!(bytes.len() == 32, error::KeyLengthSnafu { key: s });
+        Ok(Self(bytes))
+    }
+}
+
+/// Reads the public keys trusted to sign included user data.  Blank lines and lines starting with
+/// '#' are ignored.  It's fine for the file not to exist.
+pub(crate) fn trusted_keys<P: AsRef<Path>>(path: P) -> Result<Vec<PublicKey>> {
+    let path = path.as_ref();
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context(error::TrustedKeysReadSnafu { path }),
+    };
+    data.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(PublicKey::from_str)
+        .collect()
+}
+
+/// User data to fetch, and how to check it.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Include {
+    pub(crate) url: Url,
+    signature: Option<Signature>,
+}
+
+/// Where to find the signature of included user data, and the keys it may be from.
+#[derive(Debug, PartialEq)]
+struct Signature {
+    url: Url,
+    keys: Vec<PublicKey>,
+}
+
+/// Removes the `userdata` table from the given settings, returning the user data it includes, in
+/// order.  Included user data must be signed if it's given as a table, or if there are any
+/// trusted keys.
+pub(crate) fn take_includes(
+    settings: &mut SettingsJson,
+    trusted_keys: &[PublicKey],
+) -> Result<Vec<Include>> {
+    let desc = &settings.desc;
+    let mut val: serde_json::Value =
+        serde_json::from_str(&settings.json).context(error::SettingsParseSnafu { desc })?;
+    let options = match val
+        .as_object_mut()
+        .and_then(|table| table.remove(USERDATA_TABLE))
+    {
+        Some(options) => options,
+        None => return Ok(Vec::new()),
+    };
+    let options: UserDataOptions =
+        serde_json::from_value(options).context(error::OptionsParseSnafu { desc })?;
+
+    let mut includes = Vec::new();
+    for entry in options.include {
+        let (url, signed) = match entry {
+            IncludeEntry::Url(url) => (url, None),
+            IncludeEntry::Signed(signed) => (signed.url.clone(), Some(signed)),
+        };
+        let url = parse_url(&url)?;
+
+        let signature = if signed.is_some() || !trusted_keys.is_empty() {
+            let mut keys = trusted_keys.to_vec();
+            let mut signature_url = None;
+            if let Some(signed) = signed {
+                if let Some(key) = signed.public_key {
+                    keys.push(key.parse()?);
+                }
+                if let Some(sig_url) = signed.signature_url {
+                    signature_url = Some(parse_url(&sig_url)?);
+                }
+            }
+            ensure!(
+                !keys.is_empty(),
+                error::NoPublicKeySnafu { url: url.as_str() }
+            );
+            let signature_url = signature_url.unwrap_or_else(|| {
+                let mut signature_url = url.clone();
+                signature_url.set_path(&format!("{}.sig", url.path()));
+                signature_url
+            });
+            Some(Signature {
+                url: signature_url,
+                keys,
+            })
+        } else {
+            None
+        };
+
+        includes.push(Include { url, signature });
+    }
+
+    settings.json = serde_json::to_string(&val).context(error::SettingsSerializeSnafu { desc })?;
+    Ok(includes)
+}
+
+/// Parses a URL of user data or a signature.
+fn parse_url(url: &str) -> Result<Url> {
+    let parsed = Url::parse(url).context(error::UrlParseSnafu { url })?;
+    // User data is trusted with the whole system, so don't fetch it over an insecure channel.
+    ensure!(parsed.scheme() == "https", error::InsecureUrlSnafu { url });
+    Ok(parsed)
+}
+
+/// Fetches the included user data, checking its signature if it must be signed, and returns a
+/// SettingsJson representing its `[settings]` section.  Like other user data, it may be compressed.
+pub(crate) async fn fetch(include: &Include) -> Result<SettingsJson> {
+    let url = &include.url;
+    info!("Fetching user data from {}", url);
+    let client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .context(error::HttpClientSnafu)?;
+    let body = get(&client, url).await?;
+
+    if let Some(signature) = &include.signature {
+        info!("Fetching signature from {}", signature.url);
+        let signature_body = get(&client, &signature.url).await?;
+        verify(&body, &signature_body, &signature.keys)
+            .context(error::VerifySnafu { url: url.as_str() })?;
+        info!("Verified signature of user data from {}", url);
+    }
+
+    let user_data_str =
+        expand_slice_maybe(&body).context(error::DecompressionSnafu { url: url.as_str() })?;
+    trace!("Received user data: {}", user_data_str);
+
+    SettingsJson::from_toml_str(&user_data_str, format!("user data included from {}", url))
+        .context(error::SettingsToJsonSnafu { url: url.as_str() })
+}
+
+async fn get(client: &reqwest::Client, url: &Url) -> Result<Vec<u8>> {
+    let response = client
+        .get(url.clone())
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .context(error::FetchSnafu { url: url.as_str() })?;
+    let body = response
+        .bytes()
+        .await
+        .context(error::FetchSnafu { url: url.as_str() })?;
+    Ok(body.to_vec())
+}
+
+/// Checks that the base64-encoded signature is a signature of the data by one of the keys.
+fn verify(
+    data: &[u8],
+    signature: &[u8],
+    keys: &[PublicKey],
+) -> std::result::Result<(), error::VerifyError> {
+    let signature_str = String::from_utf8_lossy(signature);
+    let signature = base64::decode(signature_str.trim()).context(error::SignatureDecodeSnafu)?;
+    ensure!(
+        keys.iter()
+            .any(|key| UnparsedPublicKey::new(&ED25519, &key.0)
+                .verify(data, &signature)
+                .is_ok()),
+        error::SignatureMismatchSnafu { keys: keys.len() }
+    );
+    Ok(())
+}
+
+mod error {
+    use snafu::Snafu;
+    use std::io;
+    use std::path::PathBuf;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(super)))]
+    pub(crate) enum Error {
+        #[snafu(display("Failed to decompress user data from '{}': {}", url, source))]
+        Decompression { url: String, source: io::Error },
+
+        #[snafu(display("Failed to fetch '{}': {}", url, source))]
+        Fetch { url: String, source: reqwest::Error },
+
+        #[snafu(display("Failed to build HTTP client: {}", source))]
+        HttpClient { source: reqwest::Error },
+
+        #[snafu(display("Refusing to fetch '{}', only HTTPS is allowed", url))]
+        InsecureUrl { url: String },
+
+        #[snafu(display("Invalid public key '{}': {}", key, source))]
+        KeyDecode {
+            key: String,
+            source: base64::DecodeError,
+        },
+
+        #[snafu(display("Invalid public key '{}': expected a 32-byte Ed25519 key", key))]
+        KeyLength { key: String },
+
+        #[snafu(display(
+            "User data from '{}' must be signed, but no public key was given or trusted",
+            url
+        ))]
+        NoPublicKey { url: String },
+
+        #[snafu(display("Invalid settings.userdata in {}: {}", desc, source))]
+        OptionsParse {
+            desc: String,
+            source: serde_json::Error,
+        },
+
+        #[snafu(display("Failed to parse settings from {}: {}", desc, source))]
+        SettingsParse {
+            desc: String,
+            source: serde_json::Error,
+        },
+
+        #[snafu(display("Failed to serialize settings from {}: {}", desc, source))]
+        SettingsSerialize {
+            desc: String,
+            source: serde_json::Error,
+        },
+
+        #[snafu(display("Unable to serialize settings from '{}': {}", url, source))]
+        SettingsToJson {
+            url: String,
+            source: crate::settings::Error,
+        },
+
+        #[snafu(display("Failed to read trusted keys from '{}': {}", path.display(), source))]
+        TrustedKeysRead { path: PathBuf, source: io::Error },
+
+        #[snafu(display("Invalid URL '{}' in settings.userdata.include: {}", url, source))]
+        UrlParse {
+            url: String,
+            source: url::ParseError,
+        },
+
+        #[snafu(display(
+            "Signature verification failed for user data from '{}': {}",
+            url,
+            source
+        ))]
+        Verify { url: String, source: VerifyError },
+    }
+
+    /// Why a signature didn't check out.
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(super)))]
+    pub(crate) enum VerifyError {
+        #[snafu(display("signature is not valid base64: {}", source))]
+        SignatureDecode { source: base64::DecodeError },
+
+        #[snafu(display("signature doesn't match any of the {} allowed keys", keys))]
+        SignatureMismatch { keys: usize },
+    }
+}
+
+pub(crate) use error::Error;
+type Result<T> = std::result::Result<T, error::Error>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+    use serde_json::json;
+
+    fn key_pair() -> (Ed25519KeyPair, PublicKey) {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let public = PublicKey(pair.public_key().as_ref().to_vec());
+        (pair, public)
+    }
+
+    fn user_data(toml: &str) -> SettingsJson {
+        SettingsJson::from_toml_str(toml, "user data").unwrap()
+    }
+
+    #[test]
+    fn take_includes_removes_options() {
+        let mut settings = user_data(
+            r#"
+            [settings.motd]
+            motd = "hi"
+            [settings.userdata]
+            include = ["https://example.com/a.toml", "https://example.com/b.toml"]
+            "#,
+        );
+
+        let includes = take_includes(&mut settings, &[]).unwrap();
+        let urls: Vec<_> = includes
+            .iter()
+            .map(|include| include.url.as_str())
+            .collect();
+        assert_eq!(
+            urls,
+            vec!["https://example.com/a.toml", "https://example.com/b.toml"]
+        );
+        assert!(includes.iter().all(|include| include.signature.is_none()));
+        assert_eq!(settings.json, json!({"motd": {"motd": "hi"}}).to_string());
+
+        // Nothing left to take.
+        assert!(take_includes(&mut settings, &[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn take_includes_rejects_http() {
+        let mut settings =
+            user_data("[settings.userdata]\ninclude = [\"http://example.com/a.toml\"]\n");
+        assert!(matches!(
+            take_includes(&mut settings, &[]),
+            Err(Error::InsecureUrl { .. })
+        ));
+    }
+
+    #[test]
+    fn take_includes_signed() {
+        let (_, stub_key) = key_pair();
+        let (_, trusted_key) = key_pair();
+        let toml = format!(
+            r#"
+            [[settings.userdata.include]]
+            url = "https://example.com/a.toml?v=1"
+            public-key = "{}"
+            [[settings.userdata.include]]
+            url = "https://example.com/b.toml"
+            signature-url = "https://example.com/b.sig"
+            "#,
+            base64::encode(&stub_key.0)
+        );
+
+        // A table asks for a signature, which needs a key.
+        assert!(matches!(
+            take_includes(&mut user_data(&toml), &[]),
+            Err(Error::NoPublicKey { .. })
+        ));
+
+        let includes =
+            take_includes(&mut user_data(&toml), std::slice::from_ref(&trusted_key)).unwrap();
+        let a = includes[0].signature.as_ref().unwrap();
+        assert_eq!(a.url.as_str(), "https://example.com/a.toml.sig?v=1");
+        assert_eq!(a.keys, vec![trusted_key.clone(), stub_key]);
+        let b = includes[1].signature.as_ref().unwrap();
+        assert_eq!(b.url.as_str(), "https://example.com/b.sig");
+        assert_eq!(b.keys, vec![trusted_key.clone()]);
+
+        // Trusted keys require signatures even for plain URLs.
+        let mut settings =
+            user_data("[settings.userdata]\ninclude = [\"https://example.com/c.toml\"]\n");
+        let includes = take_includes(&mut settings, &[trusted_key]).unwrap();
+        assert!(includes[0].signature.is_some());
+    }
+
+    #[test]
+    fn verify_signatures() {
+        let (pair, public) = key_pair();
+        let (_, other) = key_pair();
+        let data = b"[settings]\nmotd = \"hi\"\n";
+        let signature = base64::encode(pair.sign(data).as_ref());
+
+        verify(data, signature.as_bytes(), &[other.clone(), public.clone()]).unwrap();
+        assert!(matches!(
+            verify(data, signature.as_bytes(), &[other]),
+            Err(error::VerifyError::SignatureMismatch { .. })
+        ));
+        assert!(matches!(
+            verify(
+                b"tampered",
+                signature.as_bytes(),
+                std::slice::from_ref(&public)
+            ),
+            Err(error::VerifyError::SignatureMismatch { .. })
+        ));
+        assert!(matches!(
+            verify(data, b"not base64!", &[public]),
+            Err(error::VerifyError::SignatureDecode { .. })
+        ));
+    }
+
+    #[test]
+    fn read_trusted_keys() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("keys");
+        assert!(trusted_keys(&path).unwrap().is_empty());
+
+        let (_, public) = key_pair();
+        fs::write(
+            &path,
+            format!("# Release key\n\n{}\n", base64::encode(&public.0)),
+        )
+        .unwrap();
+        assert_eq!(trusted_keys(&path).unwrap(), vec![public]);
+
+        fs::write(&path, "c2hvcnQ=\n").unwrap();
+        assert!(matches!(trusted_keys(&path), Err(Error::KeyLength { .. })));
+    }
+}
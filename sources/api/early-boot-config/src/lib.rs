@@ -0,0 +1,8 @@
+/*!
+This library holds the parts of early-boot-config that are useful to other tools handling user
+data, like reading user data that may be compressed.
+*/
+
+#![deny(rust_2018_idioms)]
+
+pub mod compression;
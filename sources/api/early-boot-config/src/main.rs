@@ -6,8 +6,80 @@ early-boot-config sends provider-specific platform data to the Bottlerocket API.
 For most providers this means configuration from user data and platform metadata, taken from
 something like an instance metadata service.
 
-Currently, Amazon EC2 is supported through the IMDSv1 HTTP API.  Data will be taken from files in
-/etc/early-boot-config instead, if available, for testing purposes.
+Each variant includes the provider for the platform it was built for:
+
+* On Amazon EC2, data is taken from the instance metadata service.
+* On VMware, user data is taken from a mounted CD-ROM or from guestinfo.
+* On bare metal, user data is only taken from a local file.
+
+Variants can also read data sources used by several clouds and hypervisors, so one image can boot
+on more than one of them:
+
+* `openstack`: an OpenStack config drive, with TOML user data in `openstack/latest/user_data`.
+* `nocloud`: a NoCloud seed, with a `meta-data` file and TOML user data in `user-data`.
+
+These are read from disks attached as a CD-ROM, which are mounted at `/media/cdrom`, or from disks
+labeled `config-2` or `cidata`, which are mounted at `/media/seed`.
+
+The `--providers` argument gives a comma-separated list of providers to probe, in order; the first
+one whose data source is present is used, and `platform` means the variant's own provider.  The
+same list can be given on the kernel command line as `early-boot-config.providers=...`, for example
+in the image's boot configuration; the argument wins if both are given.  The default is `platform`,
+except on bare metal, where it's `openstack,nocloud,platform`.  If none are found, only user data
+from the local file at `/var/lib/bottlerocket/user-data.toml` is used.
+
+# Precedence
+
+Settings from each source are sent to the API in turn, so when a setting is given by more than one
+source, the value from the later source wins.  The order is:
+
+1. Default user data baked into the image at `/usr/share/bottlerocket/user-data.toml`, if any.
+2. User data from the local file at `/var/lib/bottlerocket/user-data.toml`, if any.
+3. The provider's sources, in the order it reads them.  For example, on EC2, the region from the
+   instance identity document, then user data from IMDS.
+
+User data can include more user data by listing HTTPS URLs in `settings.userdata.include`:
+
+```toml
+[settings.userdata]
+include = ["https://example.com/common.toml", "https://example.com/cluster.toml"]
+```
+
+Included user data is fetched and sent right after the user data that lists it, in the order
+listed, so it overrides that user data but not later sources.  Included user data can't include
+more user data.  If any of it can't be fetched, early-boot-config fails rather than booting with
+partial settings.
+
+## Signed user data
+
+This lets user data be a small stub pointing at a larger document hosted elsewhere, without
+trusting whoever hosts it.  Give the included user data as a table to require a signature:
+
+```toml
+[[settings.userdata.include]]
+url = "https://example.com/cluster.toml"
+# Optional; defaults to the URL with ".sig" added to the path.
+signature-url = "https://example.com/cluster.toml.sig"
+# Optional if the variant trusts keys of its own.
+public-key = "base64-encoded Ed25519 public key"
+```
+
+The signature is a detached Ed25519 signature of the document as served, before any
+decompression, encoded in base64.  It's accepted if it's from the key given in the stub, or from
+one of the keys baked into the variant in `/usr/share/bottlerocket/user-data-keys`, one per line in
+base64.  If a variant has any keys there, all included user data must be signed, even when it's
+given as a plain URL.
+
+That file comes from the `os` package.  By default it trusts no keys; a variant trusts its own by
+adding a `user-data-keys-VARIANT` file, like `user-data-keys-aws-dev`, next to `user-data-keys` in
+`packages/os`.  Lines starting with `#` are ignored.
+
+If a signature is missing or doesn't match, early-boot-config logs which user data failed to
+verify and why, and fails, rather than booting without the settings.
+
+Each source is described in the request's `source` parameter, like "early-boot-config: user data
+from IMDS", and the API server records it in `source` metadata for each key that source set.  You
+can see where a setting came from with `apiclient get --explain`.
 */
 
 #![deny(rust_2018_idioms)]
@@ -21,10 +93,11 @@ use std::fs;
 use std::str::FromStr;
 use std::{env, process};
 
-mod compression;
+mod include;
 mod provider;
 mod settings;
-use crate::provider::{Platform, PlatformDataProvider};
+use crate::provider::{ProviderKind, DEFAULT_PROBE_ORDER};
+use crate::settings::SettingsJson;
 
 // TODO
 // Tests!
@@ -34,11 +107,16 @@ use crate::provider::{Platform, PlatformDataProvider};
 // We create it after running successfully.
 const MARKER_FILE: &str = "/var/lib/bottlerocket/early-boot-config.ran";
 
+/// The kernel command line, which can give the providers to probe.
+const KERNEL_CMDLINE: &str = "/proc/cmdline";
+
 /// Store the args we receive on the command line
 #[derive(Debug)]
 struct Args {
     log_level: LevelFilter,
     socket_path: String,
+    /// The providers given on our command line, if any.
+    providers: Option<Vec<ProviderKind>>,
 }
 
 /// Print a usage message in the event a bad arg is passed
@@ -48,10 +126,17 @@ fn usage() -> ! {
         r"Usage: {}
             [ --socket-path PATH ]
             [ --log-level trace|debug|info|warn|error ]
+            [ --providers platform|openstack|nocloud[,...] ]
 
-    Socket path defaults to {}",
+    Socket path defaults to {}
+    Providers default to early-boot-config.providers on the kernel command line, or {}",
         program_name,
         constants::API_SOCKET,
+        DEFAULT_PROBE_ORDER
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(","),
     );
     process::exit(2);
 }
@@ -66,6 +151,7 @@ fn usage_msg<S: AsRef<str>>(msg: S) -> ! {
 fn parse_args(args: env::Args) -> Args {
     let mut log_level = None;
     let mut socket_path = None;
+    let mut providers = None;
 
     let mut iter = args.skip(1);
     while let Some(arg) = iter.next() {
@@ -86,6 +172,15 @@ fn parse_args(args: env::Args) -> Args {
                 }));
             }
 
+            "--providers" => {
+                let providers_str = iter
+                    .next()
+                    .unwrap_or_else(|| usage_msg("Did not give argument to --providers"));
+                let kinds =
+                    provider::parse_probe_order(&providers_str).unwrap_or_else(|e| usage_msg(e));
+                providers = Some(kinds);
+            }
+
             _ => usage(),
         }
     }
@@ -93,6 +188,7 @@ fn parse_args(args: env::Args) -> Args {
     Args {
         log_level: log_level.unwrap_or(LevelFilter::Info),
         socket_path: socket_path.unwrap_or_else(|| constants::API_SOCKET.to_string()),
+        providers,
     }
 }
 
@@ -105,39 +201,56 @@ async fn run() -> Result<()> {
 
     info!("early-boot-config started");
 
+    let providers = match args.providers {
+        Some(providers) => providers,
+        None => {
+            let cmdline =
+                fs::read_to_string(KERNEL_CMDLINE).context(error::KernelCmdlineSnafu {
+                    path: KERNEL_CMDLINE,
+                })?;
+            provider::cmdline_probe_order(&cmdline)
+                .map_err(|reason| error::Error::CmdlineProviders { reason })?
+                .unwrap_or_else(|| DEFAULT_PROBE_ORDER.to_vec())
+        }
+    };
+
     info!("Retrieving platform-specific data");
-    let uri = &format!(
-        "{}?tx={}",
-        constants::API_SETTINGS_URI,
-        constants::LAUNCH_TRANSACTION
+    let provider = provider::select(&providers);
+    // Sources are sent in order, so values from later sources override earlier ones.
+    let mut sources = Vec::new();
+    if let Some(defaults) = provider::default_user_data().context(error::ProviderSnafu)? {
+        sources.push(defaults);
+    }
+    sources.extend(
+        provider
+            .platform_data()
+            .await
+            .context(error::ProviderSnafu)?,
     );
-    let method = "PATCH";
-    for settings_json in Platform
-        .platform_data()
-        .await
-        .context(error::ProviderSnafu)?
-    {
-        // Don't send an empty request to the API
-        if settings_json.json.is_empty() {
-            warn!("{} was empty", settings_json.desc);
-            continue;
-        }
 
-        info!("Sending {} to API", settings_json.desc);
-        trace!("Request body: {}", settings_json.json);
-        let (code, response_body) =
-            apiclient::raw_request(&args.socket_path, uri, method, Some(settings_json.json))
+    let trusted_keys =
+        include::trusted_keys(include::TRUSTED_KEYS_FILE).context(error::IncludeSnafu)?;
+    for mut settings_json in sources {
+        let includes = include::take_includes(&mut settings_json, &trusted_keys)
+            .context(error::IncludeSnafu)?;
+        send_settings(&args.socket_path, settings_json).await?;
+
+        // Included user data is sent right after the user data that includes it, so it overrides
+        // that user data, but not later sources.
+        for include in includes {
+            let mut included = include::fetch(&include)
                 .await
-                .context(error::APIRequestSnafu { method, uri })?;
-        ensure!(
-            code.is_success(),
-            error::ResponseSnafu {
-                method,
-                uri,
-                code,
-                response_body,
-            }
-        );
+                .context(error::IncludeSnafu)?;
+            let nested = include::take_includes(&mut included, &trusted_keys)
+                .context(error::IncludeSnafu)?;
+            ensure!(
+                nested.is_empty(),
+                error::NestedIncludeSnafu {
+                    url: include.url.to_string()
+                }
+            );
+            send_settings(&args.socket_path, included).await?;
+        }
     }
 
     fs::write(MARKER_FILE, "").unwrap_or_else(|e| {
@@ -150,6 +263,43 @@ async fn run() -> Result<()> {
     Ok(())
 }
 
+/// Sends settings to the API in the launch transaction, recording their description as the source
+/// of each value.
+async fn send_settings(socket_path: &str, settings_json: SettingsJson) -> Result<()> {
+    // Don't send an empty request to the API
+    if settings_json.json.is_empty() {
+        warn!("{} was empty", settings_json.desc);
+        return Ok(());
+    }
+
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("tx", constants::LAUNCH_TRANSACTION)
+        .append_pair(
+            "source",
+            &format!("early-boot-config: {}", settings_json.desc),
+        )
+        .finish();
+    let uri = &format!("{}?{}", constants::API_SETTINGS_URI, query);
+    let method = "PATCH";
+
+    info!("Sending {} to API", settings_json.desc);
+    trace!("Request body: {}", settings_json.json);
+    let (code, response_body) =
+        apiclient::raw_request(socket_path, uri, method, Some(settings_json.json))
+            .await
+            .context(error::APIRequestSnafu { method, uri })?;
+    ensure!(
+        code.is_success(),
+        error::ResponseSnafu {
+            method,
+            uri,
+            code,
+            response_body,
+        }
+    );
+    Ok(())
+}
+
 // Returning a Result from main makes it print a Debug representation of the error, but with Snafu
 // we have nice Display representations of the error, so we wrap "main" (run) and print any error.
 // https://github.com/shepmaster/snafu/issues/110
@@ -176,6 +326,24 @@ mod error {
             source: Box<apiclient::Error>,
         },
 
+        #[snafu(display("Invalid providers on kernel command line: {}", reason))]
+        CmdlineProviders { reason: String },
+
+        #[snafu(display("Failed to include user data: {}", source))]
+        Include { source: crate::include::Error },
+
+        #[snafu(display("Failed to read kernel command line '{}': {}", path, source))]
+        KernelCmdline {
+            path: String,
+            source: std::io::Error,
+        },
+
+        #[snafu(display("Logger setup error: {}", source))]
+        Logger { source: log::SetLoggerError },
+
+        #[snafu(display("User data included from '{}' can't include more user data", url))]
+        NestedInclude { url: String },
+
         #[snafu(display("Provider error: {}", source))]
         Provider { source: Box<dyn std::error::Error> },
 
@@ -186,9 +354,6 @@ mod error {
             code: StatusCode,
             response_body: String,
         },
-
-        #[snafu(display("Logger setup error: {}", source))]
-        Logger { source: log::SetLoggerError },
     }
 }
 
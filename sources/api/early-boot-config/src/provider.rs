@@ -1,9 +1,15 @@
-//! The provider module owns the `PlatformDataProvider` trait
+//! The provider module owns the `PlatformDataProvider` trait, and chooses the provider to use at
+//! runtime from a list of providers to probe.
 
 use crate::settings::SettingsJson;
 use async_trait::async_trait;
+use std::fmt;
+use std::str::FromStr;
 
 mod local_file;
+pub(crate) use local_file::default_user_data;
+mod nocloud;
+mod openstack;
 
 #[cfg(variant_platform = "aws")]
 mod aws;
@@ -20,9 +26,20 @@ mod metal;
 #[cfg(variant_platform = "metal")]
 pub(crate) use metal::MetalDataProvider as Platform;
 
+/// Where disks carrying configuration are mounted.  Disks attached as CD-ROMs are mounted at
+/// /media/cdrom, and other disks with a config drive or NoCloud label at /media/seed.
+pub(crate) const SEED_MOUNT_POINTS: &[&str] = &["/media/cdrom", "/media/seed"];
+
 /// Support for new platforms can be added by implementing this trait.
 #[async_trait]
 pub(crate) trait PlatformDataProvider {
+    /// Returns whether this provider's data source is present.  Providers listed in the probe
+    /// order are tried in turn, and the first one present is used.  The default is for providers
+    /// that are always present on their platform.
+    fn probe(&self) -> bool {
+        true
+    }
+
     /// You should return a list of SettingsJson, representing the settings changes you want to
     /// send to the API.
     ///
@@ -32,3 +49,140 @@ pub(crate) trait PlatformDataProvider {
         &self,
     ) -> std::result::Result<Vec<SettingsJson>, Box<dyn std::error::Error>>;
 }
+
+/// The providers that can be selected at runtime.  `Platform` is the provider for the platform the
+/// variant was built for; the others read data sources that can show up on many platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProviderKind {
+    Platform,
+    OpenStack,
+    NoCloud,
+}
+
+/// The providers probed when none are given on the command line.  Bare metal hosts may be
+/// virtualized after all, so look for a hypervisor's configuration disk before falling back to
+/// the local file.
+#[cfg(not(variant_platform = "metal"))]
+pub(crate) const DEFAULT_PROBE_ORDER: &[ProviderKind] = &[ProviderKind::Platform];
+#[cfg(variant_platform = "metal")]
+pub(crate) const DEFAULT_PROBE_ORDER: &[ProviderKind] = &[
+    ProviderKind::OpenStack,
+    ProviderKind::NoCloud,
+    ProviderKind::Platform,
+];
+
+/// The kernel command line parameter that gives the providers to probe, like
+/// `early-boot-config.providers=openstack,nocloud,platform`, so a single image can be pointed at
+/// the data sources of the environment it boots in.
+const PROBE_ORDER_CMDLINE_PREFIX: &str = "early-boot-config.providers=";
+
+/// Parses a comma-separated list of providers to probe, in order.
+pub(crate) fn parse_probe_order(s: &str) -> std::result::Result<Vec<ProviderKind>, String> {
+    s.split(',')
+        .map(|s| ProviderKind::from_str(s.trim()))
+        .collect()
+}
+
+/// Returns the providers to probe given on the kernel command line `cmdline`, if any.  If the
+/// parameter is given more than once, the last one wins, as with other kernel parameters.
+pub(crate) fn cmdline_probe_order(
+    cmdline: &str,
+) -> std::result::Result<Option<Vec<ProviderKind>>, String> {
+    match cmdline
+        .split_whitespace()
+        .rev()
+        .find_map(|param| param.strip_prefix(PROBE_ORDER_CMDLINE_PREFIX))
+    {
+        Some(order) => parse_probe_order(order).map(Some),
+        None => Ok(None),
+    }
+}
+
+impl ProviderKind {
+    fn provider(self) -> Box<dyn PlatformDataProvider + Send + Sync> {
+        match self {
+            ProviderKind::Platform => Box::new(Platform),
+            ProviderKind::OpenStack => Box::new(openstack::OpenStackDataProvider),
+            ProviderKind::NoCloud => Box::new(nocloud::NoCloudDataProvider),
+        }
+    }
+}
+
+impl FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "platform" => Ok(ProviderKind::Platform),
+            "openstack" => Ok(ProviderKind::OpenStack),
+            "nocloud" => Ok(ProviderKind::NoCloud),
+            _ => Err(format!(
+                "Unknown provider '{}', expected platform, openstack, or nocloud",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ProviderKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ProviderKind::Platform => "platform",
+            ProviderKind::OpenStack => "openstack",
+            ProviderKind::NoCloud => "nocloud",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Returns the first provider in `probe_order` whose data source is present.  If none are, we can
+/// still use the local user data file.
+pub(crate) fn select(probe_order: &[ProviderKind]) -> Box<dyn PlatformDataProvider + Send + Sync> {
+    for kind in probe_order {
+        let provider = kind.provider();
+        if provider.probe() {
+            info!("Using {} provider", kind);
+            return provider;
+        }
+        debug!("Data source for {} provider not found", kind);
+    }
+    warn!(
+        "No data source found for providers {:?}, only using local file",
+        probe_order
+    );
+    Box::new(local_file::LocalFileDataProvider)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn provider_kind_round_trip() {
+        for kind in &[
+            ProviderKind::Platform,
+            ProviderKind::OpenStack,
+            ProviderKind::NoCloud,
+        ] {
+            assert_eq!(kind.to_string().parse::<ProviderKind>().unwrap(), *kind);
+        }
+        assert!("azure".parse::<ProviderKind>().is_err());
+    }
+
+    #[test]
+    fn probe_order_from_cmdline() {
+        assert_eq!(cmdline_probe_order("quiet console=ttyS0\n").unwrap(), None);
+        assert_eq!(
+            cmdline_probe_order("quiet early-boot-config.providers=openstack,platform\n").unwrap(),
+            Some(vec![ProviderKind::OpenStack, ProviderKind::Platform])
+        );
+        assert_eq!(
+            cmdline_probe_order(
+                "early-boot-config.providers=openstack early-boot-config.providers=nocloud"
+            )
+            .unwrap(),
+            Some(vec![ProviderKind::NoCloud])
+        );
+        assert!(cmdline_probe_order("early-boot-config.providers=openstack,azure").is_err());
+    }
+}
@@ -1,8 +1,8 @@
 //! The aws module implements the `PlatformDataProvider` trait for gathering userdata on AWS.
 
 use super::{PlatformDataProvider, SettingsJson};
-use crate::compression::expand_slice_maybe;
 use async_trait::async_trait;
+use early_boot_config::compression::expand_slice_maybe;
 use imdsclient::ImdsClient;
 use serde_json::json;
 use snafu::{OptionExt, ResultExt};
@@ -33,7 +33,7 @@ impl AwsDataProvider {
             .context(error::DecompressionSnafu { what: "user data" })?;
         trace!("Received user data: {}", user_data_str);
 
-        let json = SettingsJson::from_toml_str(&user_data_str, "user data").context(
+        let json = SettingsJson::from_toml_str(&user_data_str, "user data from IMDS").context(
             error::SettingsToJSONSnafu {
                 from: "instance user data",
             },
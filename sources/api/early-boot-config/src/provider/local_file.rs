@@ -1,37 +1,77 @@
-//! The local_file module provides a method for gathering userdata from local file
+//! The local_file module provides methods for gathering userdata from local files
 
-use super::SettingsJson;
-use crate::compression::expand_file_maybe;
+use super::{PlatformDataProvider, SettingsJson};
+use async_trait::async_trait;
+use early_boot_config::compression::expand_file_maybe;
 use snafu::ResultExt;
 use std::path::Path;
 
 pub(crate) const USER_DATA_FILE: &str = "/var/lib/bottlerocket/user-data.toml";
 
+/// User data baked into the image by the variant, applied before any other source so that every
+/// other source can override it.
+pub(crate) const DEFAULT_USER_DATA_FILE: &str = "/usr/share/bottlerocket/user-data.toml";
+
 pub(crate) fn local_file_user_data(
 ) -> std::result::Result<Option<SettingsJson>, Box<dyn std::error::Error>> {
-    if !Path::new(USER_DATA_FILE).exists() {
+    user_data_from_file(USER_DATA_FILE)
+}
+
+pub(crate) fn default_user_data(
+) -> std::result::Result<Option<SettingsJson>, Box<dyn std::error::Error>> {
+    user_data_from_file(DEFAULT_USER_DATA_FILE)
+}
+
+/// Reads TOML user data from the given file, decompressing it if compressed.  Returns None if the
+/// file doesn't exist or is empty.
+pub(crate) fn user_data_from_file<P>(
+    path: P,
+) -> std::result::Result<Option<SettingsJson>, Box<dyn std::error::Error>>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if !path.exists() {
         return Ok(None);
     }
-    info!("'{}' exists, using it", USER_DATA_FILE);
+    info!("'{}' exists, using it", path.display());
 
     // Read the file, decompressing it if compressed.
-    let user_data_str = expand_file_maybe(USER_DATA_FILE).context(error::InputFileReadSnafu {
-        path: USER_DATA_FILE,
-    })?;
+    let user_data_str = expand_file_maybe(path).context(error::InputFileReadSnafu { path })?;
 
     if user_data_str.is_empty() {
         return Ok(None);
     }
 
-    let json = SettingsJson::from_toml_str(&user_data_str, "user data").context(
-        error::SettingsToJSONSnafu {
-            from: USER_DATA_FILE,
-        },
-    )?;
+    let desc = format!("user data from {}", path.display());
+    let json =
+        SettingsJson::from_toml_str(&user_data_str, desc).context(error::SettingsToJSONSnafu {
+            from: path.display().to_string(),
+        })?;
 
     Ok(Some(json))
 }
 
+/// Gathers user data only from the local file.  Used when none of the providers we were asked to
+/// probe found their data source.
+pub(crate) struct LocalFileDataProvider;
+
+#[async_trait]
+impl PlatformDataProvider for LocalFileDataProvider {
+    async fn platform_data(
+        &self,
+    ) -> std::result::Result<Vec<SettingsJson>, Box<dyn std::error::Error>> {
+        let mut output = Vec::new();
+
+        match local_file_user_data()? {
+            Some(s) => output.push(s),
+            None => warn!("No user data found via local file: {}", USER_DATA_FILE),
+        }
+
+        Ok(output)
+    }
+}
+
 mod error {
     use snafu::Snafu;
     use std::io;
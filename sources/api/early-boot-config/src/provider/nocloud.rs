@@ -0,0 +1,101 @@
+//! The nocloud module implements the `PlatformDataProvider` trait for gathering userdata from a
+//! NoCloud seed, a disk with `meta-data` and `user-data` files at its root, as used by many
+//! hypervisors and local testing tools.  It can be selected at runtime on any platform.
+
+use super::{PlatformDataProvider, SettingsJson, SEED_MOUNT_POINTS};
+use crate::provider::local_file::{local_file_user_data, user_data_from_file};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+pub(crate) struct NoCloudDataProvider;
+
+impl NoCloudDataProvider {
+    const META_DATA_FILENAME: &'static str = "meta-data";
+    const USER_DATA_FILENAME: &'static str = "user-data";
+
+    /// Returns the first mounted seed, if any.  A seed always has a `meta-data` file, which tells
+    /// it apart from other disks that only carry user data, like VMware's.
+    fn find_seed<P: AsRef<Path>>(mount_points: &[P]) -> Option<PathBuf> {
+        mount_points
+            .iter()
+            .map(|mount_point| mount_point.as_ref().to_path_buf())
+            .find(|dir| dir.join(Self::META_DATA_FILENAME).is_file())
+    }
+
+    /// Read and return the user data from the seed, if it has any.
+    fn user_data(
+        seed: &Path,
+    ) -> std::result::Result<Option<SettingsJson>, Box<dyn std::error::Error>> {
+        user_data_from_file(seed.join(Self::USER_DATA_FILENAME))
+    }
+}
+
+#[async_trait]
+impl PlatformDataProvider for NoCloudDataProvider {
+    fn probe(&self) -> bool {
+        Self::find_seed(SEED_MOUNT_POINTS).is_some()
+    }
+
+    async fn platform_data(
+        &self,
+    ) -> std::result::Result<Vec<SettingsJson>, Box<dyn std::error::Error>> {
+        let mut output = Vec::new();
+
+        // Attempt to read from local file first on the `dev` build
+        match local_file_user_data()? {
+            None => warn!("No user data found via local file"),
+            Some(s) => output.push(s),
+        }
+
+        match Self::find_seed(SEED_MOUNT_POINTS) {
+            None => warn!("No NoCloud seed found"),
+            Some(seed) => match Self::user_data(&seed)? {
+                None => warn!("No user data found in NoCloud seed"),
+                Some(s) => output.push(s),
+            },
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn seed_user_data() {
+        let cdrom = tempfile::tempdir().unwrap();
+        let seed = tempfile::tempdir().unwrap();
+        let mount_points = [cdrom.path(), seed.path()];
+
+        // User data alone, as VMware provides, isn't a NoCloud seed.
+        fs::write(
+            cdrom.path().join("user-data"),
+            "[settings.motd]\nmotd = \"vmware\"\n",
+        )
+        .unwrap();
+        assert_eq!(NoCloudDataProvider::find_seed(&mount_points), None);
+
+        fs::write(seed.path().join("meta-data"), "instance-id: i-1\n").unwrap();
+        assert_eq!(
+            NoCloudDataProvider::find_seed(&mount_points),
+            Some(seed.path().to_path_buf())
+        );
+        assert!(NoCloudDataProvider::user_data(seed.path())
+            .unwrap()
+            .is_none());
+
+        fs::write(
+            seed.path().join("user-data"),
+            "[settings.motd]\nmotd = \"hi\"\n",
+        )
+        .unwrap();
+        let user_data = NoCloudDataProvider::user_data(seed.path())
+            .unwrap()
+            .unwrap();
+        assert_eq!(user_data.json, json!({"motd": {"motd": "hi"}}).to_string());
+    }
+}
@@ -0,0 +1,103 @@
+//! The openstack module implements the `PlatformDataProvider` trait for gathering userdata from an
+//! OpenStack config drive.  Other clouds and hypervisors use the same format, so this provider can
+//! be selected at runtime on any platform.
+
+use super::{PlatformDataProvider, SettingsJson, SEED_MOUNT_POINTS};
+use crate::provider::local_file::{local_file_user_data, user_data_from_file};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+pub(crate) struct OpenStackDataProvider;
+
+impl OpenStackDataProvider {
+    // Every config drive includes the "latest" version of the metadata, so we don't need to pick a
+    // dated version.
+    const METADATA_DIR: &'static str = "openstack/latest";
+    const META_DATA_FILENAME: &'static str = "meta_data.json";
+    const USER_DATA_FILENAME: &'static str = "user_data";
+
+    /// Returns the metadata directory of the first mounted config drive, if any.  Drives are
+    /// identified by their metadata file, since user data is optional.
+    fn find_metadata_dir<P: AsRef<Path>>(mount_points: &[P]) -> Option<PathBuf> {
+        mount_points
+            .iter()
+            .map(|mount_point| mount_point.as_ref().join(Self::METADATA_DIR))
+            .find(|dir| dir.join(Self::META_DATA_FILENAME).is_file())
+    }
+
+    /// Read and return the user data from the config drive's metadata directory, if it has any.
+    fn user_data(
+        metadata_dir: &Path,
+    ) -> std::result::Result<Option<SettingsJson>, Box<dyn std::error::Error>> {
+        user_data_from_file(metadata_dir.join(Self::USER_DATA_FILENAME))
+    }
+}
+
+#[async_trait]
+impl PlatformDataProvider for OpenStackDataProvider {
+    fn probe(&self) -> bool {
+        Self::find_metadata_dir(SEED_MOUNT_POINTS).is_some()
+    }
+
+    async fn platform_data(
+        &self,
+    ) -> std::result::Result<Vec<SettingsJson>, Box<dyn std::error::Error>> {
+        let mut output = Vec::new();
+
+        // Attempt to read from local file first on the `dev` build
+        match local_file_user_data()? {
+            None => warn!("No user data found via local file"),
+            Some(s) => output.push(s),
+        }
+
+        match Self::find_metadata_dir(SEED_MOUNT_POINTS) {
+            None => warn!("No config drive found"),
+            Some(metadata_dir) => match Self::user_data(&metadata_dir)? {
+                None => warn!("No user data found on config drive"),
+                Some(s) => output.push(s),
+            },
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn config_drive_user_data() {
+        let cdrom = tempfile::tempdir().unwrap();
+        let seed = tempfile::tempdir().unwrap();
+        let mount_points = [cdrom.path(), seed.path()];
+        assert_eq!(
+            OpenStackDataProvider::find_metadata_dir(&mount_points),
+            None
+        );
+
+        // The drive is found by its metadata, even without user data.
+        let metadata_dir = seed.path().join("openstack/latest");
+        fs::create_dir_all(&metadata_dir).unwrap();
+        fs::write(metadata_dir.join("meta_data.json"), "{}").unwrap();
+        assert_eq!(
+            OpenStackDataProvider::find_metadata_dir(&mount_points),
+            Some(metadata_dir.clone())
+        );
+        assert!(OpenStackDataProvider::user_data(&metadata_dir)
+            .unwrap()
+            .is_none());
+
+        fs::write(
+            metadata_dir.join("user_data"),
+            "[settings.motd]\nmotd = \"hi\"\n",
+        )
+        .unwrap();
+        let user_data = OpenStackDataProvider::user_data(&metadata_dir)
+            .unwrap()
+            .unwrap();
+        assert_eq!(user_data.json, json!({"motd": {"motd": "hi"}}).to_string());
+    }
+}
@@ -2,8 +2,10 @@
 //! via mounted CDRom or the guestinfo interface
 
 use super::{PlatformDataProvider, SettingsJson};
-use crate::compression::{expand_file_maybe, expand_slice_maybe, OptionalCompressionReader};
 use async_trait::async_trait;
+use early_boot_config::compression::{
+    expand_file_maybe, expand_slice_maybe, OptionalCompressionReader,
+};
 use serde::Deserialize;
 use snafu::{ensure, ResultExt};
 use std::ffi::OsStr;
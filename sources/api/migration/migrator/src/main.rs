@@ -24,6 +24,7 @@
 extern crate log;
 
 use args::Args;
+use datastore::filesystem::HISTORY_FILE;
 use direction::Direction;
 use error::Result;
 use nix::{dir::Dir, fcntl::OFlag, sys::stat::Mode, unistd::fsync};
@@ -176,6 +177,7 @@ pub(crate) fn run(args: &Args) -> Result<()> {
             &args.datastore_path,
             &args.migrate_to_version,
         )?;
+        copy_history(&args.datastore_path, &copy_path);
         flip_to_new_version(&args.migrate_to_version, copy_path)?;
     }
     Ok(())
@@ -323,6 +325,31 @@ where
     Ok(target_datastore)
 }
 
+/// Copies the history of committed transactions into the migrated data store, so commits made
+/// before the update can still be listed and reverted.  Migrations only copy settings, so the
+/// history would otherwise be lost.  Reverts check that keys still have the values recorded in
+/// the history, so entries for keys that a migration changed or removed can't be misapplied.
+fn copy_history<P1, P2>(from: P1, to: P2)
+where
+    P1: AsRef<Path>,
+    P2: AsRef<Path>,
+{
+    let from = from.as_ref().join(HISTORY_FILE);
+    let to = to.as_ref().join(HISTORY_FILE);
+    if !from.exists() {
+        return;
+    }
+    // The history isn't needed to run the new version, so we don't fail the upgrade over it.
+    if let Err(e) = fs::copy(&from, &to) {
+        warn!(
+            "Failed to copy transaction history from {} to {}: {}",
+            from.display(),
+            to.display(),
+            e
+        );
+    }
+}
+
 // Try to delete an intermediate datastore if it exists. If it fails to delete, print an error.
 fn delete_intermediate_datastore(path: &PathBuf) {
     // Even if we fail to remove an intermediate data store, we don't want to fail the upgrade -
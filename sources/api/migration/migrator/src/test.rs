@@ -3,6 +3,7 @@
 use crate::args::Args;
 use crate::run;
 use chrono::{DateTime, Utc};
+use datastore::filesystem::HISTORY_FILE;
 use semver::Version;
 use std::fs;
 use std::fs::{DirEntry, File};
@@ -342,6 +343,8 @@ fn migrate_forward() {
     let from_version = Version::parse("0.99.0").unwrap();
     let to_version = Version::parse("0.99.1").unwrap();
     let test_datastore = TestDatastore::new(from_version);
+    let history = "[]";
+    fs::write(test_datastore.datastore.join(HISTORY_FILE), history).unwrap();
     let test_repo = create_test_repo(TestType::Success);
     let args = Args {
         datastore_path: test_datastore.datastore.clone(),
@@ -381,6 +384,12 @@ fn migrate_forward() {
         .to_str()
         .unwrap()
         .starts_with("v0.99.1"));
+
+    // The transaction history should have been carried to the new data store.
+    assert_eq!(
+        fs::read_to_string(current.join(HISTORY_FILE)).unwrap(),
+        history
+    );
 }
 
 /// This test ensures that migrations run when migrating from a newer to an older version.
@@ -1,4 +1,8 @@
 use bottlerocket_variant::{Variant, VARIANT_ENV};
+use std::env;
+
+// buildsys sets this for packages built for a variant with the `systemd-networkd` image feature
+const SYSTEMD_NETWORKD_ENV: &str = "SYSTEMD_NETWORKD";
 
 fn main() {
     let variant = match Variant::from_env() {
@@ -15,5 +19,11 @@ fn main() {
     };
     variant.emit_cfgs();
 
+    // Generate config for systemd-networkd instead of wicked if the variant uses it
+    println!("cargo:rerun-if-env-changed={}", SYSTEMD_NETWORKD_ENV);
+    if matches!(env::var_os(SYSTEMD_NETWORKD_ENV), Some(v) if !v.is_empty()) {
+        println!("cargo:rustc-cfg=net_backend=\"systemd-networkd\"");
+    }
+
     generate_readme::from_main().unwrap();
 }
@@ -0,0 +1,169 @@
+//! The backend module selects the network backend that `generate-net-config` writes interface
+//! configuration for, and that `reload-net-config` applies runtime interface configuration to.
+//!
+//! Variants use wicked by default.  Variants with the `systemd-networkd` image feature build
+//! netdog with `SYSTEMD_NETWORKD` set in the environment, which makes the build script set the
+//! `net_backend = "systemd-networkd"` cfg, and netdog writes systemd-networkd config files instead.
+
+use crate::net_config::Interfaces;
+use crate::wicked::{self, ConfigChanges, WickedInterface};
+use crate::WICKED;
+use snafu::{ensure, ResultExt};
+use std::fmt::{self, Display};
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum NetworkBackend {
+    Wicked,
+    SystemdNetworkd,
+}
+
+impl NetworkBackend {
+    /// Returns the network backend of the variant netdog was built for
+    pub(crate) fn current() -> Self {
+        if cfg!(net_backend = "systemd-networkd") {
+            NetworkBackend::SystemdNetworkd
+        } else {
+            NetworkBackend::Wicked
+        }
+    }
+
+    /// Write the backend's configuration files for the given network config, with any interfaces
+    /// configured at runtime layered on top.  Only wicked supports runtime configuration.
+    pub(crate) fn write_config(
+        &self,
+        net_config: &dyn Interfaces,
+        runtime_config: Option<&dyn Interfaces>,
+    ) -> Result<()> {
+        match self {
+            NetworkBackend::Wicked => {
+                for interface in wicked_interfaces(Some(net_config), runtime_config)? {
+                    interface
+                        .write_config_file()
+                        .context(error::WickedConfigSnafu)?;
+                }
+            }
+            NetworkBackend::SystemdNetworkd => {
+                ensure!(
+                    runtime_config.is_none(),
+                    error::RuntimeConfigUnsupportedSnafu { backend: *self }
+                );
+                net_config
+                    .as_networkd_config()
+                    .write_config_files()
+                    .context(error::NetworkDConfigSnafu)?
+            }
+        }
+        Ok(())
+    }
+
+    /// Update the backend's configuration files for interfaces configured at runtime, layered on
+    /// the network config from boot.  Interfaces in the previous runtime config that are no longer
+    /// configured get their boot configuration back, or have their configuration removed if they
+    /// weren't configured at boot.
+    pub(crate) fn update_config(
+        &self,
+        net_config: Option<&dyn Interfaces>,
+        previous_runtime_config: Option<&dyn Interfaces>,
+        runtime_config: Option<&dyn Interfaces>,
+    ) -> Result<ConfigChanges> {
+        ensure!(
+            *self == NetworkBackend::Wicked,
+            error::RuntimeConfigUnsupportedSnafu { backend: *self }
+        );
+
+        let interfaces = wicked_interfaces(net_config, runtime_config)?;
+        let previous = previous_runtime_config
+            .map(|c| c.as_wicked_interfaces())
+            .unwrap_or_default();
+        wicked::update_config_files(&interfaces, &previous).context(error::WickedConfigSnafu)
+    }
+
+    /// Apply the configuration of the given interfaces to the running system
+    pub(crate) fn reload_interfaces(&self, interfaces: &[String]) -> Result<()> {
+        self.wicked("ifreload", interfaces)
+    }
+
+    /// Bring down the given interfaces, whose configuration was removed
+    pub(crate) fn bring_down_interfaces(&self, interfaces: &[String]) -> Result<()> {
+        self.wicked("ifdown", interfaces)
+    }
+
+    fn wicked(&self, subcommand: &'static str, interfaces: &[String]) -> Result<()> {
+        ensure!(
+            *self == NetworkBackend::Wicked,
+            error::RuntimeConfigUnsupportedSnafu { backend: *self }
+        );
+
+        let result = Command::new(WICKED)
+            .arg(subcommand)
+            .args(interfaces)
+            .output()
+            .context(error::WickedExecutionSnafu)?;
+        ensure!(
+            result.status.success(),
+            error::WickedCommandSnafu {
+                subcommand,
+                stderr: String::from_utf8_lossy(&result.stderr)
+            }
+        );
+        Ok(())
+    }
+}
+
+impl Display for NetworkBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkBackend::Wicked => write!(f, "wicked"),
+            NetworkBackend::SystemdNetworkd => write!(f, "systemd-networkd"),
+        }
+    }
+}
+
+fn wicked_interfaces(
+    net_config: Option<&dyn Interfaces>,
+    runtime_config: Option<&dyn Interfaces>,
+) -> Result<Vec<WickedInterface>> {
+    let boot = net_config
+        .map(|c| c.as_wicked_interfaces())
+        .unwrap_or_default();
+    match runtime_config {
+        Some(runtime_config) => {
+            wicked::layer_interfaces(boot, runtime_config.as_wicked_interfaces())
+                .context(error::WickedConfigSnafu)
+        }
+        None => Ok(boot),
+    }
+}
+
+mod error {
+    use super::NetworkBackend;
+    use crate::{networkd, wicked};
+    use snafu::Snafu;
+    use std::io;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(crate)))]
+    pub(crate) enum Error {
+        #[snafu(display("{}", source))]
+        WickedConfig { source: wicked::Error },
+
+        #[snafu(display("{}", source))]
+        NetworkDConfig { source: networkd::Error },
+
+        #[snafu(display("Network interfaces can't be configured at runtime with {}", backend))]
+        RuntimeConfigUnsupported { backend: NetworkBackend },
+
+        #[snafu(display("Failed to run 'wicked': {}", source))]
+        WickedExecution { source: io::Error },
+
+        #[snafu(display("'wicked {}' failed: {}", subcommand, stderr))]
+        WickedCommand {
+            subcommand: &'static str,
+            stderr: String,
+        },
+    }
+}
+
+pub(crate) use error::Error;
+type Result<T> = std::result::Result<T, error::Error>;
@@ -0,0 +1,14 @@
+use super::{error, Result};
+use crate::dns::stub;
+use crate::DNS_STUB_CONFIG;
+use argh::FromArgs;
+use snafu::ResultExt;
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "dns-stub")]
+/// Runs the local stub resolver for per-domain name servers and DNS-over-TLS
+pub(crate) struct DnsStubArgs {}
+
+pub(crate) fn run() -> Result<()> {
+    stub::serve(DNS_STUB_CONFIG).context(error::DnsStubSnafu)
+}
@@ -1,9 +1,10 @@
-use super::{error, Result};
+use super::{error, reload_net_config, Result};
+use crate::backend::NetworkBackend;
 use crate::interface_id::InterfaceId;
-use crate::net_config;
+use crate::net_config::{self, Interfaces};
 use crate::{
-    DEFAULT_NET_CONFIG_FILE, KERNEL_CMDLINE, OVERRIDE_NET_CONFIG_FILE, PRIMARY_INTERFACE,
-    PRIMARY_MAC_ADDRESS,
+    DEFAULT_NET_CONFIG_FILE, KERNEL_CMDLINE, OVERRIDE_NET_CONFIG_FILE,
+    PERSISTED_RUNTIME_NET_CONFIG_FILE, PRIMARY_INTERFACE, PRIMARY_MAC_ADDRESS,
 };
 use argh::FromArgs;
 use snafu::{OptionExt, ResultExt};
@@ -12,27 +13,13 @@ use std::path::Path;
 
 #[derive(FromArgs, PartialEq, Debug)]
 #[argh(subcommand, name = "generate-net-config")]
-/// Generate wicked network configuration
+/// Generate network configuration for the variant's network backend
 pub(crate) struct GenerateNetConfigArgs {}
 
 /// Generate configuration for network interfaces.
 pub(crate) fn run() -> Result<()> {
-    let maybe_net_config = if Path::exists(Path::new(OVERRIDE_NET_CONFIG_FILE)) {
-        net_config::from_path(OVERRIDE_NET_CONFIG_FILE).context(error::NetConfigParseSnafu {
-            path: OVERRIDE_NET_CONFIG_FILE,
-        })?
-    } else if Path::exists(Path::new(DEFAULT_NET_CONFIG_FILE)) {
-        net_config::from_path(DEFAULT_NET_CONFIG_FILE).context(error::NetConfigParseSnafu {
-            path: DEFAULT_NET_CONFIG_FILE,
-        })?
-    } else {
-        net_config::from_command_line(KERNEL_CMDLINE).context(error::NetConfigParseSnafu {
-            path: KERNEL_CMDLINE,
-        })?
-    };
-
-    // `maybe_net_config` could be `None` if no interfaces were defined
-    let net_config = match maybe_net_config {
+    // `boot_net_config` could return `None` if no interfaces were defined
+    let net_config = match boot_net_config()? {
         Some(net_config) => net_config,
         None => {
             eprintln!("No network interfaces were configured");
@@ -48,15 +35,34 @@ pub(crate) fn run() -> Result<()> {
     remove_old_primary_interface()?;
     write_primary_interface(&primary_interface)?;
 
-    let wicked_interfaces = net_config.as_wicked_interfaces();
-    for interface in wicked_interfaces {
-        interface
-            .write_config_file()
-            .context(error::InterfaceConfigWriteSnafu)?;
-    }
+    // Interfaces configured through the API are layered on top, so they persist across reboots
+    let runtime_net_config =
+        reload_net_config::runtime_net_config(PERSISTED_RUNTIME_NET_CONFIG_FILE)?;
+    NetworkBackend::current()
+        .write_config(&*net_config, runtime_net_config.as_deref())
+        .context(error::InterfaceConfigWriteSnafu)?;
     Ok(())
 }
 
+/// Read the network config given at boot, from the override or default net.toml, or else from the
+/// kernel command line
+pub(super) fn boot_net_config() -> Result<Option<Box<dyn Interfaces>>> {
+    match boot_net_config_path() {
+        Some(path) => net_config::from_path(path).context(error::NetConfigParseSnafu { path }),
+        None => net_config::from_command_line(KERNEL_CMDLINE).context(error::NetConfigParseSnafu {
+            path: KERNEL_CMDLINE,
+        }),
+    }
+}
+
+/// Return the path of the net.toml used at boot, favoring the override, if either exists
+pub(super) fn boot_net_config_path() -> Option<&'static str> {
+    [OVERRIDE_NET_CONFIG_FILE, DEFAULT_NET_CONFIG_FILE]
+        .iter()
+        .find(|path| Path::exists(Path::new(path)))
+        .copied()
+}
+
 /// Remove primary interface and mac address files
 fn remove_old_primary_interface() -> Result<()> {
     for file in &[PRIMARY_INTERFACE, PRIMARY_MAC_ADDRESS] {
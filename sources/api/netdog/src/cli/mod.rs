@@ -1,20 +1,26 @@
+pub(crate) mod dns_stub;
 pub(crate) mod generate_hostname;
 pub(crate) mod generate_net_config;
 pub(crate) mod install;
 pub(crate) mod node_ip;
+pub(crate) mod reload_net_config;
 pub(crate) mod remove;
 pub(crate) mod set_hostname;
+pub(crate) mod status;
 pub(crate) mod write_resolv_conf;
 
 use crate::{PRIMARY_INTERFACE, PRIMARY_MAC_ADDRESS, SYS_CLASS_NET};
+pub(crate) use dns_stub::DnsStubArgs;
 pub(crate) use generate_hostname::GenerateHostnameArgs;
 pub(crate) use generate_net_config::GenerateNetConfigArgs;
 pub(crate) use install::InstallArgs;
 pub(crate) use node_ip::NodeIpArgs;
+pub(crate) use reload_net_config::ReloadNetConfigArgs;
 pub(crate) use remove::RemoveArgs;
 use serde::{Deserialize, Serialize};
 pub(crate) use set_hostname::SetHostnameArgs;
 use snafu::{OptionExt, ResultExt};
+pub(crate) use status::StatusArgs;
 use std::fs;
 pub(crate) use write_resolv_conf::WriteResolvConfArgs;
 
@@ -65,7 +71,16 @@ fn primary_interface_name() -> Result<String> {
         },
     )?);
 
-    // There should be directories for each of the interfaces, i.e /sys/class/net/eth0
+    interface_name_for_mac(&primary_mac)?.context(error::NonExistentMacSnafu { mac: primary_mac })
+}
+
+/// Return the name of the interface with the given MAC address, if any
+// There should be directories for each of the interfaces, i.e /sys/class/net/eth0, so we crawl
+// sysfs to find which interface has the MAC.
+fn interface_name_for_mac(mac: &str) -> Result<Option<String>> {
+    let clean = |s: String| s.trim().to_lowercase();
+    let mac = mac.to_lowercase();
+
     let sysfs_net = fs::read_dir(SYS_CLASS_NET)
         .context(error::PathReadSnafu {
             path: SYS_CLASS_NET,
@@ -77,22 +92,23 @@ fn primary_interface_name() -> Result<String> {
         let mac_address_path = interface.path().join("address");
 
         if let Ok(address) = fs::read_to_string(mac_address_path) {
-            if clean(address) == primary_mac {
-                return interface.file_name().into_string().ok().context(
+            if clean(address) == mac {
+                let name = interface.file_name().into_string().ok().context(
                     error::InterfaceNameUtf8Snafu {
                         name: interface.file_name(),
                     },
-                );
+                )?;
+                return Ok(Some(name));
             }
         };
     }
 
-    error::NonExistentMacSnafu { mac: primary_mac }.fail()
+    Ok(None)
 }
 
 /// Potential errors during netdog execution
 mod error {
-    use crate::{dns, interface_id, lease, net_config, wicked};
+    use crate::{backend, dns, interface_id, lease, net_config};
     use snafu::Snafu;
     use std::ffi::OsString;
     use std::io;
@@ -114,6 +130,12 @@ mod error {
         #[snafu(display("Failed to read/parse DNS settings from DHCP lease: {}", source))]
         DnsFromLease { source: dns::Error },
 
+        #[snafu(display("Failed to run DNS stub resolver: {}", source))]
+        DnsStub { source: dns::stub::Error },
+
+        #[snafu(display("'ip {}' failed: {}", args, stderr))]
+        FailedIp { args: String, stderr: String },
+
         #[snafu(display("'systemd-sysctl' failed: {}", stderr))]
         FailedSystemdSysctl { stderr: String },
 
@@ -127,7 +149,7 @@ mod error {
         HostnameWriteFailed { path: PathBuf, source: io::Error },
 
         #[snafu(display("Failed to write network interface configuration: {}", source))]
-        InterfaceConfigWrite { source: wicked::Error },
+        InterfaceConfigWrite { source: backend::Error },
 
         #[snafu(display("Unable to determine interface name: {}", source))]
         InterfaceName { source: interface_id::Error },
@@ -135,6 +157,18 @@ mod error {
         #[snafu(display("Non-UTF8 interface name '{:?}'", name.to_string_lossy()))]
         InterfaceNameUtf8 { name: OsString },
 
+        #[snafu(display("Failed to reload network interfaces: {}", source))]
+        InterfaceReload { source: backend::Error },
+
+        #[snafu(display("Failed to run 'ip': {}", source))]
+        IpExecution { source: io::Error },
+
+        #[snafu(display("Failed to parse output of 'ip {}': {}", args, source))]
+        IpOutputParse {
+            args: String,
+            source: serde_json::error::Error,
+        },
+
         #[snafu(display("Invalid IP address '{}': {}", ip, source))]
         IpFromString {
             ip: String,
@@ -162,6 +196,9 @@ mod error {
         #[snafu(display("Unable to find an interface with MAC address '{}'", mac))]
         NonExistentMac { mac: String },
 
+        #[snafu(display("Failed to persist runtime network config to '{}': {}", path.display(), source))]
+        PersistRuntimeNetConfig { path: PathBuf, source: io::Error },
+
         #[snafu(display("Unable to read '{}': {}", path.display(), source))]
         PathRead {
             path: PathBuf,
@@ -183,6 +220,9 @@ mod error {
         #[snafu(display("Failed to write resolver configuration: {}", source))]
         ResolvConfWriteFailed { source: dns::Error },
 
+        #[snafu(display("Error serializing interface status to JSON: {}", source))]
+        StatusSerialize { source: serde_json::error::Error },
+
         #[snafu(display("Failed to build sysctl config: {}", source))]
         SysctlConfBuild { source: std::fmt::Error },
 
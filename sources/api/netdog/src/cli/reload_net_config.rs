@@ -0,0 +1,107 @@
+use super::{error, generate_net_config, Result};
+use crate::backend::NetworkBackend;
+use crate::net_config::{self, Interfaces};
+use crate::{PERSISTED_RUNTIME_NET_CONFIG_FILE, RUNTIME_NET_CONFIG_FILE};
+use argh::FromArgs;
+use snafu::ResultExt;
+use std::fs;
+use std::path::Path;
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "reload-net-config")]
+/// Apply network interface settings to the running system
+pub(crate) struct ReloadNetConfigArgs {}
+
+/// Layer the interfaces configured in settings over the network config from boot, and reload the
+/// interfaces whose configuration changed.  Interfaces removed from settings since the last run
+/// get their boot configuration back, or are brought down if they weren't configured at boot.
+pub(crate) fn run() -> Result<()> {
+    let runtime_config = runtime_net_config(RUNTIME_NET_CONFIG_FILE)?;
+    let previous_runtime_config = runtime_net_config(PERSISTED_RUNTIME_NET_CONFIG_FILE)?;
+    if runtime_config.is_none() && previous_runtime_config.is_none() {
+        eprintln!("No network interfaces are configured in settings");
+        return Ok(());
+    }
+
+    let boot_net_config = generate_net_config::boot_net_config()?;
+    let backend = NetworkBackend::current();
+    let changes = backend
+        .update_config(
+            boot_net_config.as_deref(),
+            previous_runtime_config.as_deref(),
+            runtime_config.as_deref(),
+        )
+        .context(error::InterfaceConfigWriteSnafu)?;
+
+    // Keep a copy of the settings for `generate-net-config`, which applies them at boot; they
+    // were already validated above
+    if runtime_config.is_some() {
+        fs::copy(RUNTIME_NET_CONFIG_FILE, PERSISTED_RUNTIME_NET_CONFIG_FILE).context(
+            error::PersistRuntimeNetConfigSnafu {
+                path: PERSISTED_RUNTIME_NET_CONFIG_FILE,
+            },
+        )?;
+    } else {
+        fs::remove_file(PERSISTED_RUNTIME_NET_CONFIG_FILE).context(error::FileRemoveSnafu {
+            path: PERSISTED_RUNTIME_NET_CONFIG_FILE,
+        })?;
+    }
+
+    if changes.is_empty() {
+        eprintln!("No network interface configuration changed");
+        return Ok(());
+    }
+    if !changes.removed.is_empty() {
+        eprintln!(
+            "Bringing down network interfaces: {}",
+            changes.removed.join(", ")
+        );
+        backend
+            .bring_down_interfaces(&changes.removed)
+            .context(error::InterfaceReloadSnafu)?;
+    }
+    if !changes.changed.is_empty() {
+        eprintln!(
+            "Reloading network interfaces: {}",
+            changes.changed.join(", ")
+        );
+        backend
+            .reload_interfaces(&changes.changed)
+            .context(error::InterfaceReloadSnafu)?;
+    }
+    Ok(())
+}
+
+/// Read network config rendered from settings, if any interfaces are configured.  The file is
+/// empty if no interfaces are configured.
+pub(super) fn runtime_net_config<P>(path: P) -> Result<Option<Box<dyn Interfaces>>>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    if !path.exists() {
+        return Ok(None);
+    }
+    let net_config_str = fs::read_to_string(path).context(error::PathReadSnafu { path })?;
+    if net_config_str.trim().is_empty() {
+        return Ok(None);
+    }
+    net_config::from_path(path).context(error::NetConfigParseSnafu { path })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The settings template renders nothing when no interfaces are configured
+    #[test]
+    fn no_runtime_interfaces() {
+        let empty = tempfile::NamedTempFile::new().unwrap();
+        assert!(runtime_net_config(&empty).unwrap().is_none());
+        fs::write(&empty, "\n\n").unwrap();
+        assert!(runtime_net_config(&empty).unwrap().is_none());
+
+        let missing = empty.path().with_extension("missing");
+        assert!(runtime_net_config(missing).unwrap().is_none());
+    }
+}
@@ -0,0 +1,454 @@
+use super::{error, generate_net_config, interface_name_for_mac, primary_interface_name, Result};
+use crate::interface_id::MacAddress;
+use crate::lease::{dhcp_lease_paths, LeaseInfo};
+use crate::net_config;
+use crate::{IP, KERNEL_CMDLINE, PERSISTED_RUNTIME_NET_CONFIG_FILE, SYS_CLASS_NET};
+use argh::FromArgs;
+use ipnet::IpNet;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use snafu::{ensure, ResultExt};
+use std::collections::BTreeSet;
+use std::convert::TryFrom;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[derive(FromArgs, PartialEq, Debug)]
+#[argh(subcommand, name = "status")]
+/// Print the configuration and current state of each configured network interface as JSON
+pub(crate) struct StatusArgs {}
+
+/// The configuration and current state of a network interface
+#[derive(Debug, Serialize)]
+struct InterfaceStatus {
+    /// The interface name or MAC address used in the network config
+    id: String,
+    /// The name of the device with this configuration, if it exists
+    device: Option<String>,
+    primary: bool,
+    /// Where the interface was configured: the path of a net.toml, the kernel command line, or
+    /// settings
+    config_source: String,
+    /// The interface's configuration as it was written in net.toml or settings
+    config: serde_json::Value,
+    link: Option<LinkStatus>,
+    addresses: Vec<AddressStatus>,
+    routes: Vec<RouteStatus>,
+    leases: Vec<LeaseStatus>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct LinkStatus {
+    operstate: String,
+    carrier: bool,
+    mtu: u32,
+    mac_address: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct AddressStatus {
+    address: IpNet,
+    scope: String,
+    dynamic: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct RouteStatus {
+    destination: String,
+    gateway: Option<IpAddr>,
+    preferred_source: Option<IpAddr>,
+    protocol: Option<String>,
+    metric: Option<u32>,
+    table: String,
+}
+
+#[derive(Debug, Serialize)]
+struct LeaseStatus {
+    path: PathBuf,
+    ip_address: IpNet,
+    dns_servers: Option<BTreeSet<IpAddr>>,
+    dns_domain: Option<String>,
+    dns_search: Option<Vec<String>>,
+}
+
+impl LeaseStatus {
+    fn new(path: PathBuf, lease: LeaseInfo) -> Self {
+        Self {
+            path,
+            ip_address: lease.ip_address,
+            dns_servers: lease.dns_servers,
+            dns_domain: lease.dns_domain,
+            dns_search: lease.dns_search,
+        }
+    }
+}
+
+/// A device from the output of `ip -json address show`
+#[derive(Debug, Deserialize)]
+struct IpLink {
+    operstate: String,
+    mtu: u32,
+    address: Option<String>,
+    #[serde(default)]
+    flags: Vec<String>,
+    #[serde(default)]
+    addr_info: Vec<IpAddrInfo>,
+}
+
+// `ip` may print empty objects for addresses that were filtered out, so every field is optional
+#[derive(Debug, Deserialize)]
+struct IpAddrInfo {
+    local: Option<IpAddr>,
+    prefixlen: Option<u8>,
+    scope: Option<String>,
+    #[serde(default)]
+    dynamic: bool,
+}
+
+/// A route from the output of `ip -json route show`
+#[derive(Debug, Deserialize)]
+struct IpRoute {
+    #[serde(rename = "type")]
+    route_type: Option<String>,
+    dst: String,
+    gateway: Option<IpAddr>,
+    prefsrc: Option<IpAddr>,
+    protocol: Option<String>,
+    metric: Option<u32>,
+    // Routes in the main table don't list their table
+    table: Option<String>,
+}
+
+/// An interface from the network config, and where it was configured
+#[derive(Debug, PartialEq)]
+struct ConfiguredInterface {
+    id: String,
+    source: String,
+    config: serde_json::Value,
+}
+
+/// Print the configuration and current state of each configured interface.
+pub(crate) fn run() -> Result<()> {
+    // There's no primary interface if the network config hasn't been generated
+    let primary_interface = primary_interface_name().ok();
+
+    let mut statuses = Vec::new();
+    for interface in configured_interfaces()? {
+        statuses.push(interface_status(interface, primary_interface.as_deref())?);
+    }
+
+    let output = serde_json::to_string_pretty(&statuses).context(error::StatusSerializeSnafu)?;
+    println!("{}", output);
+    Ok(())
+}
+
+/// Return the interfaces configured at boot, with any interfaces configured in settings layered on
+/// top the same way `generate-net-config` and `reload-net-config` layer them
+fn configured_interfaces() -> Result<Vec<ConfiguredInterface>> {
+    let mut interfaces = match generate_net_config::boot_net_config_path() {
+        Some(path) => interfaces_from_path(path, path)?,
+        None => {
+            // The kernel command line can only configure a single interface, which is primary
+            let net_config = net_config::from_command_line(KERNEL_CMDLINE).context(
+                error::NetConfigParseSnafu {
+                    path: KERNEL_CMDLINE,
+                },
+            )?;
+            net_config
+                .and_then(|net_config| net_config.primary_interface())
+                .map(|id| ConfiguredInterface {
+                    id: id.to_string(),
+                    source: "kernel command line".to_string(),
+                    config: serde_json::Value::Null,
+                })
+                .into_iter()
+                .collect()
+        }
+    };
+
+    if Path::exists(Path::new(PERSISTED_RUNTIME_NET_CONFIG_FILE)) {
+        let runtime_interfaces = interfaces_from_path(
+            PERSISTED_RUNTIME_NET_CONFIG_FILE,
+            "settings.network.interfaces",
+        )?;
+        layer_interfaces(&mut interfaces, runtime_interfaces);
+    }
+
+    Ok(interfaces)
+}
+
+fn interfaces_from_path<P>(path: P, source: &str) -> Result<Vec<ConfiguredInterface>>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let configs =
+        net_config::raw_interface_configs(path).context(error::NetConfigParseSnafu { path })?;
+
+    let mut interfaces = Vec::new();
+    for (id, config) in configs {
+        interfaces.push(ConfiguredInterface {
+            id,
+            source: source.to_string(),
+            config: serde_json::to_value(config).context(error::StatusSerializeSnafu)?,
+        });
+    }
+    Ok(interfaces)
+}
+
+/// An interface configured at runtime replaces the boot configuration of the interface with the
+/// same name
+fn layer_interfaces(interfaces: &mut Vec<ConfiguredInterface>, runtime: Vec<ConfiguredInterface>) {
+    for interface in runtime {
+        match interfaces.iter_mut().find(|i| i.id == interface.id) {
+            Some(existing) => *existing = interface,
+            None => interfaces.push(interface),
+        }
+    }
+}
+
+fn interface_status(
+    interface: ConfiguredInterface,
+    primary_interface: Option<&str>,
+) -> Result<InterfaceStatus> {
+    // Interfaces configured by MAC address are reported with the name of the matching device
+    let device = match MacAddress::try_from(interface.id.as_str()) {
+        Ok(mac) => interface_name_for_mac(&mac)?,
+        Err(_) => Some(interface.id.clone()),
+    };
+    // Devices that are configured but don't exist, like a VLAN that couldn't be created, have no
+    // state to report
+    let device = device.filter(|name| Path::new(SYS_CLASS_NET).join(name).exists());
+
+    let mut status = InterfaceStatus {
+        id: interface.id,
+        device: None,
+        primary: device.is_some() && device.as_deref() == primary_interface,
+        config_source: interface.source,
+        config: interface.config,
+        link: None,
+        addresses: Vec::new(),
+        routes: Vec::new(),
+        leases: Vec::new(),
+    };
+
+    let device = match device {
+        Some(device) => device,
+        None => return Ok(status),
+    };
+
+    let links: Vec<IpLink> = ip_json(&["address", "show", "dev", &device])?;
+    let (link, addresses) = link_status(links);
+    status.link = link;
+    status.addresses = addresses;
+
+    for family in &["-4", "-6"] {
+        let routes: Vec<IpRoute> =
+            ip_json(&[family, "route", "show", "table", "all", "dev", &device])?;
+        status.routes.extend(route_statuses(routes));
+    }
+
+    for path in dhcp_lease_paths(&device) {
+        let lease = LeaseInfo::from_lease(&path).context(error::LeaseParseFailedSnafu)?;
+        status.leases.push(LeaseStatus::new(path, lease));
+    }
+
+    status.device = Some(device);
+    Ok(status)
+}
+
+/// Run `ip` with the given arguments and parse its JSON output
+fn ip_json<T>(args: &[&str]) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let output = Command::new(IP)
+        .arg("-json")
+        .args(args)
+        .output()
+        .context(error::IpExecutionSnafu)?;
+    ensure!(
+        output.status.success(),
+        error::FailedIpSnafu {
+            args: args.join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr)
+        }
+    );
+    serde_json::from_slice(&output.stdout).context(error::IpOutputParseSnafu {
+        args: args.join(" "),
+    })
+}
+
+/// Return the link state and addresses of a device from `ip address show dev`, which lists the
+/// single device
+fn link_status(links: Vec<IpLink>) -> (Option<LinkStatus>, Vec<AddressStatus>) {
+    let link = match links.into_iter().next() {
+        Some(link) => link,
+        None => return (None, Vec::new()),
+    };
+
+    let addresses = link
+        .addr_info
+        .into_iter()
+        .filter_map(|info| match (info.local, info.prefixlen) {
+            (Some(local), Some(prefixlen)) => {
+                IpNet::new(local, prefixlen)
+                    .ok()
+                    .map(|address| AddressStatus {
+                        address,
+                        scope: info.scope.unwrap_or_default(),
+                        dynamic: info.dynamic,
+                    })
+            }
+            _ => None,
+        })
+        .collect();
+
+    let status = LinkStatus {
+        operstate: link.operstate,
+        carrier: link.flags.iter().any(|flag| flag == "LOWER_UP"),
+        mtu: link.mtu,
+        mac_address: link.address,
+    };
+
+    (Some(status), addresses)
+}
+
+/// Return the unicast routes from `ip route show`; the local and broadcast routes the kernel adds
+/// for every address aren't useful here
+fn route_statuses(routes: Vec<IpRoute>) -> Vec<RouteStatus> {
+    routes
+        .into_iter()
+        .filter(|route| matches!(route.route_type.as_deref(), None | Some("unicast")))
+        .map(|route| RouteStatus {
+            destination: route.dst,
+            gateway: route.gateway,
+            preferred_source: route.prefsrc,
+            protocol: route.protocol,
+            metric: route.metric,
+            table: route.table.unwrap_or_else(|| "main".to_string()),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn test_data() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test_data")
+            .join("status")
+    }
+
+    fn read_json<T>(file: &str) -> T
+    where
+        T: DeserializeOwned,
+    {
+        serde_json::from_str(&fs::read_to_string(test_data().join(file)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn link_and_addresses() {
+        let (link, addresses) = link_status(read_json("ip_address.json"));
+        assert_eq!(
+            link,
+            Some(LinkStatus {
+                operstate: "UP".to_string(),
+                carrier: true,
+                mtu: 9001,
+                mac_address: Some("0a:1b:2c:3d:4e:5f".to_string()),
+            })
+        );
+        assert_eq!(
+            addresses,
+            vec![
+                AddressStatus {
+                    address: "192.168.1.10/24".parse().unwrap(),
+                    scope: "global".to_string(),
+                    dynamic: true,
+                },
+                AddressStatus {
+                    address: "2001:db8::10/64".parse().unwrap(),
+                    scope: "global".to_string(),
+                    dynamic: false,
+                },
+                AddressStatus {
+                    address: "fe80::81b:2cff:fe3d:4e5f/64".parse().unwrap(),
+                    scope: "link".to_string(),
+                    dynamic: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_link() {
+        let (link, addresses) = link_status(Vec::new());
+        assert!(link.is_none());
+        assert!(addresses.is_empty());
+    }
+
+    #[test]
+    fn unicast_routes() {
+        let routes = route_statuses(read_json("ip_route.json"));
+        assert_eq!(
+            routes,
+            vec![
+                RouteStatus {
+                    destination: "default".to_string(),
+                    gateway: Some("192.168.1.1".parse().unwrap()),
+                    preferred_source: Some("192.168.1.10".parse().unwrap()),
+                    protocol: Some("dhcp".to_string()),
+                    metric: Some(1024),
+                    table: "main".to_string(),
+                },
+                RouteStatus {
+                    destination: "10.0.0.0/8".to_string(),
+                    gateway: Some("192.168.1.254".parse().unwrap()),
+                    preferred_source: None,
+                    protocol: Some("static".to_string()),
+                    metric: None,
+                    table: "100".to_string(),
+                },
+                RouteStatus {
+                    destination: "192.168.1.0/24".to_string(),
+                    gateway: None,
+                    preferred_source: Some("192.168.1.10".parse().unwrap()),
+                    protocol: Some("kernel".to_string()),
+                    metric: None,
+                    table: "main".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn runtime_interfaces_replace_boot_interfaces() {
+        let mut interfaces =
+            interfaces_from_path(test_data().join("net.toml"), "net.toml").unwrap();
+        let runtime =
+            interfaces_from_path(test_data().join("runtime-net.toml"), "settings").unwrap();
+        layer_interfaces(&mut interfaces, runtime);
+
+        let summary: Vec<(&str, &str)> = interfaces
+            .iter()
+            .map(|i| (i.id.as_str(), i.source.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("0a:1b:2c:3d:4e:5f", "net.toml"),
+                ("eno1", "net.toml"),
+                ("eno2", "settings"),
+                ("myvlan", "settings"),
+            ]
+        );
+        assert_eq!(
+            interfaces[2].config,
+            serde_json::json!({"static4": {"addresses": ["10.0.0.5/24"]}, "mtu": 9000})
+        );
+    }
+}
@@ -1,13 +1,16 @@
 //! The dns module contains the code necessary to gather DNS settings from config file,
 //! supplementing with DHCP lease if it exists.  It also contains the code necessary to write a
-//! properly formatted `resolv.conf`.
+//! properly formatted `resolv.conf`, and the local stub resolver that serves per-domain name
+//! servers and DNS-over-TLS upstreams, which libc's resolver can't do itself.
+pub(crate) mod stub;
+
 use crate::lease::LeaseInfo;
-use crate::RESOLV_CONF;
+use crate::{DNS_STUB_CONFIG, RESOLV_CONF};
 use rand::prelude::SliceRandom;
 use rand::thread_rng;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use snafu::ResultExt;
-use std::collections::BTreeSet;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Write;
 use std::fs;
 use std::net::IpAddr;
@@ -15,12 +18,41 @@ use std::path::Path;
 
 static DNS_CONFIG: &str = "/etc/netdog.toml";
 
-#[derive(Default, Debug, Deserialize, PartialEq)]
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq)]
 pub(crate) struct DnsSettings {
     #[serde(rename = "name-servers")]
     nameservers: Option<BTreeSet<IpAddr>>,
     #[serde(rename = "search-list")]
     search: Option<Vec<String>>,
+    options: Option<ResolverOptions>,
+    domains: Option<BTreeMap<String, DomainSettings>>,
+    #[serde(rename = "tls-upstreams")]
+    tls_upstreams: Option<Vec<TlsUpstream>>,
+}
+
+/// Options for libc's resolver, written to the `options` line of `resolv.conf`
+#[derive(Default, Debug, Deserialize, Serialize, PartialEq)]
+pub(crate) struct ResolverOptions {
+    ndots: Option<u8>,
+    timeout: Option<u8>,
+    attempts: Option<u8>,
+    rotate: Option<bool>,
+}
+
+/// Name servers for queries under a single domain
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub(crate) struct DomainSettings {
+    #[serde(rename = "name-servers")]
+    nameservers: BTreeSet<IpAddr>,
+}
+
+/// A DNS-over-TLS server; its certificate must be valid for `server-name`
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub(crate) struct TlsUpstream {
+    address: IpAddr,
+    port: Option<u16>,
+    #[serde(rename = "server-name")]
+    server_name: String,
 }
 
 impl DnsSettings {
@@ -77,13 +109,23 @@ impl DnsSettings {
 
             Ok(dns_config)
         } else {
-            eprintln!("No DNS configuration exists in {}", DNS_CONFIG);
+            eprintln!("No DNS configuration exists in {}", path.display());
             Ok(DnsSettings::default())
         }
     }
 
-    /// Write resolver configuration for libc.
+    /// Whether queries need to go through the local stub resolver, because some domains have
+    /// their own name servers or queries must be sent over TLS
+    fn uses_stub(&self) -> bool {
+        matches!(&self.domains, Some(d) if !d.is_empty())
+            || matches!(&self.tls_upstreams, Some(t) if !t.is_empty())
+    }
+
+    /// Write resolver configuration for libc, and the configuration of the stub resolver if it's
+    /// needed.  The stub's configuration is written first so it's ready for queries as soon as
+    /// libc starts sending them there.
     pub(crate) fn write_resolv_conf(&self) -> Result<()> {
+        Self::write_stub_config_impl(self, DNS_STUB_CONFIG)?;
         Self::write_resolv_conf_impl(self, RESOLV_CONF)
     }
 
@@ -99,7 +141,10 @@ impl DnsSettings {
                 .context(error::ResolvConfBuildFailedSnafu)?;
         }
 
-        if let Some(nameservers) = &self.nameservers {
+        if self.uses_stub() {
+            writeln!(output, "nameserver {}", stub::STUB_ADDRESS)
+                .context(error::ResolvConfBuildFailedSnafu)?;
+        } else if let Some(nameservers) = &self.nameservers {
             // Randomize name server order, for libc implementations like musl that send
             // queries to the first N servers.
             let mut dns_servers: Vec<IpAddr> = nameservers.clone().into_iter().collect();
@@ -109,8 +154,46 @@ impl DnsSettings {
             }
         }
 
+        if let Some(options) = &self.options {
+            let mut resolver_options = Vec::new();
+            if let Some(ndots) = options.ndots {
+                resolver_options.push(format!("ndots:{}", ndots));
+            }
+            if let Some(timeout) = options.timeout {
+                resolver_options.push(format!("timeout:{}", timeout));
+            }
+            if let Some(attempts) = options.attempts {
+                resolver_options.push(format!("attempts:{}", attempts));
+            }
+            if options.rotate == Some(true) {
+                resolver_options.push("rotate".to_string());
+            }
+            if !resolver_options.is_empty() {
+                writeln!(output, "options {}", resolver_options.join(" "))
+                    .context(error::ResolvConfBuildFailedSnafu)?;
+            }
+        }
+
         fs::write(path, output).context(error::ResolvConfWriteFailedSnafu { path })
     }
+
+    /// Write the settings the stub resolver forwards queries with, or remove them if the stub
+    /// isn't needed.  The stub reloads the file when it changes.
+    fn write_stub_config_impl<P>(&self, path: P) -> Result<()>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        if !self.uses_stub() {
+            if path.exists() {
+                fs::remove_file(path).context(error::StubConfigRemoveSnafu { path })?;
+            }
+            return Ok(());
+        }
+
+        let config = toml::to_string(self).context(error::StubConfigSerializeSnafu)?;
+        fs::write(path, config).context(error::StubConfigWriteSnafu { path })
+    }
 }
 
 mod error {
@@ -141,6 +224,15 @@ mod error {
 
         #[snafu(display("Failed to write resolver configuration to '{}': {}", path.display(), source))]
         ResolvConfWriteFailed { path: PathBuf, source: io::Error },
+
+        #[snafu(display("Failed to remove stub resolver configuration '{}': {}", path.display(), source))]
+        StubConfigRemove { path: PathBuf, source: io::Error },
+
+        #[snafu(display("Failed to serialize stub resolver configuration: {}", source))]
+        StubConfigSerialize { source: toml::ser::Error },
+
+        #[snafu(display("Failed to write stub resolver configuration to '{}': {}", path.display(), source))]
+        StubConfigWrite { path: PathBuf, source: io::Error },
     }
 }
 
@@ -195,6 +287,7 @@ mod tests {
         let expected = DnsSettings {
             nameservers: Some(nameservers),
             search,
+            ..Default::default()
         };
 
         assert_eq!(got, expected)
@@ -252,4 +345,66 @@ mod tests {
         let resolv_conf = std::fs::read_to_string(&fake_file).unwrap();
         assert_ne!(resolv_conf == format1, resolv_conf == format2)
     }
+
+    #[test]
+    fn dns_from_config_with_resolver_settings() {
+        let config = test_data().join("netdog-split.toml");
+        let dns_settings = DnsSettings::from_config_impl(&config).unwrap();
+        assert!(dns_settings.uses_stub());
+        assert_eq!(
+            dns_settings.options,
+            Some(ResolverOptions {
+                ndots: Some(2),
+                timeout: Some(1),
+                attempts: Some(3),
+                rotate: Some(true),
+            })
+        );
+        let domains = dns_settings.domains.unwrap();
+        assert_eq!(domains["corp.example.com"].nameservers.len(), 2);
+        let tls_upstreams = dns_settings.tls_upstreams.unwrap();
+        assert_eq!(tls_upstreams[1].port, Some(8853));
+        assert_eq!(tls_upstreams[1].server_name, "dns.quad9.net");
+    }
+
+    #[test]
+    fn write_resolv_conf_with_stub() {
+        let fake_file = tempfile::NamedTempFile::new().unwrap();
+        let config = test_data().join("netdog-split.toml");
+        let settings = DnsSettings::from_config_impl(config).unwrap();
+        settings.write_resolv_conf_impl(&fake_file).unwrap();
+
+        let expected = "search us-west-2.compute.internal\nnameserver 127.0.0.53\noptions ndots:2 timeout:1 attempts:3 rotate\n";
+        assert_eq!(std::fs::read_to_string(&fake_file).unwrap(), expected);
+    }
+
+    #[test]
+    fn write_resolv_conf_with_options() {
+        let fake_file = tempfile::NamedTempFile::new().unwrap();
+        let config = test_data().join("netdog-options.toml");
+        let settings = DnsSettings::from_config_impl(config).unwrap();
+        assert!(!settings.uses_stub());
+        settings.write_resolv_conf_impl(&fake_file).unwrap();
+
+        let expected = "nameserver 1.2.3.4\noptions ndots:0\n";
+        assert_eq!(std::fs::read_to_string(&fake_file).unwrap(), expected);
+    }
+
+    #[test]
+    fn stub_config_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let stub_config = dir.path().join("dns-stub.toml");
+        let config = test_data().join("netdog-split.toml");
+        let settings = DnsSettings::from_config_impl(config).unwrap();
+        settings.write_stub_config_impl(&stub_config).unwrap();
+        assert_eq!(
+            DnsSettings::from_config_impl(&stub_config).unwrap(),
+            settings
+        );
+
+        // The stub's config is removed once it isn't needed
+        let settings = DnsSettings::from_config_impl(test_data().join("netdog.toml")).unwrap();
+        settings.write_stub_config_impl(&stub_config).unwrap();
+        assert!(!stub_config.exists());
+    }
 }
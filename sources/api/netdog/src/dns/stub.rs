@@ -0,0 +1,1029 @@
+//! The stub module contains a small DNS forwarder that listens on a loopback address, so libc's
+//! resolver can use it as its only name server.  Queries for names under a domain with its own
+//! name servers are sent to those servers; other queries are sent to the DNS-over-TLS upstreams if
+//! there are any, or to the usual name servers otherwise.  Messages are forwarded unchanged; only
+//! the question is parsed, to pick the upstream.  Responses too large for a UDP client are cut
+//! down to their question with the TC flag set, so the client retries over TCP.
+//!
+//! This is deliberately a forwarder rather than a resolver: it doesn't cache, recurse, or validate
+//! DNSSEC, so it stays small enough to ship in netdog.  If the stub needs to do more than pick an
+//! upstream, an existing resolver like unbound should replace it rather than growing this one.
+use super::{DnsSettings, TlsUpstream};
+use rustls::client::{ClientConfig, ClientConnection, ServerName};
+use rustls::{RootCertStore, StreamOwned};
+use snafu::{ensure, OptionExt, ResultExt};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+pub(crate) const STUB_ADDRESS: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 53);
+const DNS_PORT: u16 = 53;
+const DNS_TLS_PORT: u16 = 853;
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+// The largest message that fits in the length prefix used over TCP; EDNS allows UDP messages
+// this large as well
+const MAX_MESSAGE_SIZE: usize = 65535;
+const HEADER_LEN: usize = 12;
+/// The largest UDP response a client accepts if its query doesn't advertise a size with EDNS
+const MIN_UDP_PAYLOAD_SIZE: usize = 512;
+const OPT_RECORD_TYPE: u16 = 41;
+// Queries are answered by a fixed number of threads; when they're all busy and the queue is full,
+// new UDP queries are dropped and the client retries, and new TCP clients are disconnected
+const UDP_WORKERS: usize = 16;
+const UDP_QUEUE_LEN: usize = 64;
+const TCP_WORKERS: usize = 8;
+const TCP_QUEUE_LEN: usize = 8;
+// Connections to DNS-over-TLS upstreams are kept open between queries, up to this many per
+// upstream; servers close idle connections, so older ones aren't reused
+const MAX_IDLE_CONNECTIONS: usize = 4;
+const CONNECTION_IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Answer queries on the stub address over UDP and TCP, forwarding them according to the
+/// settings in `config_path`.  This doesn't return unless a socket can't be set up.
+pub(crate) fn serve<P>(config_path: P) -> Result<()>
+where
+    P: AsRef<Path>,
+{
+    let address = SocketAddr::from((STUB_ADDRESS, DNS_PORT));
+    let forwarders = Arc::new(ForwarderCache::new(config_path));
+    let udp = UdpSocket::bind(address).context(error::BindSnafu { address })?;
+    let tcp = TcpListener::bind(address).context(error::BindSnafu { address })?;
+
+    let tcp_forwarders = Arc::clone(&forwarders);
+    thread::spawn(move || serve_tcp(tcp, tcp_forwarders));
+    serve_udp(udp, forwarders);
+    Ok(())
+}
+
+fn serve_udp(socket: UdpSocket, forwarders: Arc<ForwarderCache>) {
+    let socket = Arc::new(socket);
+    let workers = WorkerPool::new(UDP_WORKERS, UDP_QUEUE_LEN);
+    let mut buf = vec![0; MAX_MESSAGE_SIZE];
+    loop {
+        let (len, client) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) => {
+                eprintln!("Failed to receive query: {}", e);
+                continue;
+            }
+        };
+
+        // Upstreams can be slow, so queries are answered by the workers rather than holding up
+        // the ones behind them
+        let query = buf[..len].to_vec();
+        let socket = Arc::clone(&socket);
+        let forwarders = Arc::clone(&forwarders);
+        let queued = workers.try_execute(move || {
+            if let Some(response) = answer(&forwarders, &query, Protocol::Udp) {
+                if let Err(e) = socket.send_to(&response, client) {
+                    eprintln!("Failed to send response to {}: {}", client, e);
+                }
+            }
+        });
+        if !queued {
+            eprintln!(
+                "Too many queries in progress, dropping query from {}",
+                client
+            );
+        }
+    }
+}
+
+fn serve_tcp(listener: TcpListener, forwarders: Arc<ForwarderCache>) {
+    let workers = WorkerPool::new(TCP_WORKERS, TCP_QUEUE_LEN);
+    for stream in listener.incoming() {
+        match stream {
+            Ok(stream) => {
+                let forwarders = Arc::clone(&forwarders);
+                // The stream is dropped, closing the connection, if the job isn't queued
+                let queued = workers.try_execute(move || {
+                    if let Err(e) = serve_tcp_client(stream, &forwarders) {
+                        eprintln!("Failed to answer TCP client: {}", e);
+                    }
+                });
+                if !queued {
+                    eprintln!("Too many TCP clients, closing new connection");
+                }
+            }
+            Err(e) => eprintln!("Failed to accept TCP connection: {}", e),
+        }
+    }
+}
+
+/// Answer queries from a TCP client until it closes the connection
+fn serve_tcp_client(mut stream: TcpStream, forwarders: &ForwarderCache) -> io::Result<()> {
+    stream.set_read_timeout(Some(CLIENT_TIMEOUT))?;
+    while let Some(query) = read_message(&mut stream)? {
+        if let Some(response) = answer(forwarders, &query, Protocol::Tcp) {
+            write_message(&mut stream, &response)?;
+        }
+    }
+    Ok(())
+}
+
+/// The transport a client sent its query over
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Protocol {
+    Udp,
+    Tcp,
+}
+
+/// Forward a query, or build a SERVFAIL response if it can't be forwarded.  Returns nothing if
+/// the query is too short to respond to.
+fn answer(forwarders: &ForwarderCache, query: &[u8], protocol: Protocol) -> Option<Vec<u8>> {
+    let response = forwarders.current().and_then(|forwarder| {
+        forwarder
+            .context(error::NotConfiguredSnafu)?
+            .forward(query, protocol)
+    });
+    let response = match response {
+        Ok(response) => response,
+        Err(e) => {
+            eprintln!("{}", e);
+            servfail(query)?
+        }
+    };
+    match protocol {
+        Protocol::Udp => Some(fit_udp_response(query, response)),
+        Protocol::Tcp => Some(response),
+    }
+}
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// A fixed set of threads that run jobs from a bounded queue, so a flood of queries or clients
+/// can't start an unbounded number of threads
+struct WorkerPool {
+    sender: SyncSender<Job>,
+}
+
+impl WorkerPool {
+    fn new(workers: usize, queue_len: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel::<Job>(queue_len);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..workers {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || loop {
+                // The lock is only held while waiting for a job, not while running it
+                let job = match receiver.lock().unwrap_or_else(|e| e.into_inner()).recv() {
+                    Ok(job) => job,
+                    // The pool was dropped
+                    Err(_) => return,
+                };
+                job();
+            });
+        }
+        Self { sender }
+    }
+
+    /// Queue a job for the next free worker.  Returns false, without running the job, if the
+    /// queue is full.
+    fn try_execute<F>(&self, job: F) -> bool
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        self.sender.try_send(Box::new(job)).is_ok()
+    }
+}
+
+/// Holds the forwarder for the stub configuration file, rebuilding it when the file changes
+struct ForwarderCache {
+    path: PathBuf,
+    current: Mutex<Option<(SystemTime, Arc<Forwarder>)>>,
+}
+
+impl ForwarderCache {
+    fn new<P>(path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            current: Mutex::new(None),
+        }
+    }
+
+    /// The forwarder for the current configuration, if the stub is configured
+    fn current(&self) -> Result<Option<Arc<Forwarder>>> {
+        let path = &self.path;
+        let modified = match fs::metadata(path) {
+            Ok(metadata) => metadata
+                .modified()
+                .context(error::ConfigMetadataSnafu { path })?,
+            // The config is removed when the stub isn't needed
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context(error::ConfigMetadataSnafu { path }),
+        };
+
+        // A panic while holding the lock can't leave the cached forwarder half-updated
+        let mut current = self.current.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((loaded, forwarder)) = &*current {
+            if *loaded == modified {
+                return Ok(Some(Arc::clone(forwarder)));
+            }
+        }
+
+        let settings = DnsSettings::from_config_impl(path).context(error::ConfigLoadSnafu)?;
+        let forwarder = Arc::new(Forwarder::new(settings)?);
+        *current = Some((modified, Arc::clone(&forwarder)));
+        Ok(Some(forwarder))
+    }
+}
+
+/// A name server that queries can be forwarded to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Upstream {
+    Plain(SocketAddr),
+    Tls {
+        address: SocketAddr,
+        server_name: String,
+    },
+}
+
+impl From<&TlsUpstream> for Upstream {
+    fn from(upstream: &TlsUpstream) -> Self {
+        Upstream::Tls {
+            address: SocketAddr::new(upstream.address, upstream.port.unwrap_or(DNS_TLS_PORT)),
+            server_name: upstream.server_name.clone(),
+        }
+    }
+}
+
+impl Display for Upstream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Upstream::Plain(address) => write!(f, "{}", address),
+            Upstream::Tls {
+                address,
+                server_name,
+            } => write!(f, "{} ({}, TLS)", address, server_name),
+        }
+    }
+}
+
+type TlsStream = StreamOwned<ClientConnection, TcpStream>;
+
+/// Picks the upstreams for queries and sends the queries to them
+struct Forwarder {
+    settings: DnsSettings,
+    tls_config: Option<Arc<ClientConfig>>,
+    tls_connections: ConnectionPool<TlsStream>,
+}
+
+impl Forwarder {
+    fn new(settings: DnsSettings) -> Result<Self> {
+        let tls_config = match &settings.tls_upstreams {
+            Some(upstreams) if !upstreams.is_empty() => Some(Arc::new(tls_client_config()?)),
+            _ => None,
+        };
+        Ok(Self {
+            settings,
+            tls_config,
+            tls_connections: ConnectionPool::default(),
+        })
+    }
+
+    /// The upstreams for a name, in the order they should be tried.  If the name is under more
+    /// than one configured domain, the most specific domain wins.
+    fn upstreams(&self, name: &str) -> Vec<Upstream> {
+        let name = name.trim_end_matches('.').to_lowercase();
+        let domain_servers = self
+            .settings
+            .domains
+            .iter()
+            .flatten()
+            .map(|(domain, settings)| (domain.trim_end_matches('.').to_lowercase(), settings))
+            .filter(|(domain, _)| in_domain(&name, domain))
+            .max_by_key(|(domain, _)| domain.len())
+            .map(|(_, settings)| &settings.nameservers);
+        if let Some(servers) = domain_servers {
+            return plain_upstreams(servers);
+        }
+
+        match &self.settings.tls_upstreams {
+            Some(upstreams) if !upstreams.is_empty() => {
+                upstreams.iter().map(Upstream::from).collect()
+            }
+            _ => plain_upstreams(self.settings.nameservers.iter().flatten()),
+        }
+    }
+
+    /// Send a query to each of its upstreams in turn, returning the first response
+    fn forward(&self, query: &[u8], protocol: Protocol) -> Result<Vec<u8>> {
+        let (name, _) = parse_question(query).context(error::MalformedQuerySnafu)?;
+        let upstreams = self.upstreams(&name);
+        ensure!(!upstreams.is_empty(), error::NoUpstreamsSnafu { name });
+
+        for upstream in &upstreams {
+            match self.exchange(upstream, query, protocol) {
+                Ok(response) => return Ok(response),
+                Err(e) => eprintln!("Failed to forward query for '{}': {}", name, e),
+            }
+        }
+        error::UpstreamsFailedSnafu { name }.fail()
+    }
+
+    fn exchange(&self, upstream: &Upstream, query: &[u8], protocol: Protocol) -> Result<Vec<u8>> {
+        match upstream {
+            Upstream::Plain(address) => {
+                let response = exchange_udp(*address, query).context(error::UpstreamIoSnafu {
+                    upstream: upstream.to_string(),
+                })?;
+                // The full answer doesn't fit in a UDP response; ask again over TCP if the client
+                // can take it.  A UDP client gets the truncated response, and retries over TCP
+                // itself.
+                if is_truncated(&response) && protocol == Protocol::Tcp {
+                    exchange_tcp(*address, query).context(error::UpstreamIoSnafu {
+                        upstream: upstream.to_string(),
+                    })
+                } else {
+                    Ok(response)
+                }
+            }
+            Upstream::Tls {
+                address,
+                server_name,
+            } => {
+                let tls_config = self
+                    .tls_config
+                    .as_ref()
+                    .context(error::TlsNotConfiguredSnafu)?;
+                self.tls_connections.exchange(upstream, query, || {
+                    connect_tls(tls_config, *address, server_name)
+                })
+            }
+        }
+    }
+}
+
+fn plain_upstreams<'a, I>(servers: I) -> Vec<Upstream>
+where
+    I: IntoIterator<Item = &'a std::net::IpAddr>,
+{
+    servers
+        .into_iter()
+        .map(|ip| Upstream::Plain(SocketAddr::new(*ip, DNS_PORT)))
+        .collect()
+}
+
+/// Whether `name` is `domain` or a name under it; both must be lowercase without a trailing dot
+fn in_domain(name: &str, domain: &str) -> bool {
+    match name.strip_suffix(domain) {
+        Some(prefix) => prefix.is_empty() || prefix.ends_with('.'),
+        None => false,
+    }
+}
+
+/// Build the TLS client config for DNS-over-TLS upstreams, trusting the system's CA certificates
+fn tls_client_config() -> Result<ClientConfig> {
+    let certs = rustls_native_certs::load_native_certs().context(error::LoadCertificatesSnafu)?;
+    let certs: Vec<Vec<u8>> = certs.into_iter().map(|cert| cert.0).collect();
+    let mut roots = RootCertStore::empty();
+    let (added, _ignored) = roots.add_parsable_certificates(&certs);
+    ensure!(added > 0, error::NoCertificatesSnafu);
+
+    Ok(ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth())
+}
+
+fn exchange_udp(address: SocketAddr, query: &[u8]) -> io::Result<Vec<u8>> {
+    let local = match address {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let socket = UdpSocket::bind(local)?;
+    socket.connect(address)?;
+    socket.set_read_timeout(Some(UPSTREAM_TIMEOUT))?;
+    socket.send(query)?;
+
+    let mut buf = vec![0; MAX_MESSAGE_SIZE];
+    loop {
+        let len = socket.recv(&mut buf)?;
+        // Skip anything that isn't a response to this query, going by the message ID
+        if len >= HEADER_LEN && buf[..2] == query[..2] {
+            buf.truncate(len);
+            return Ok(buf);
+        }
+    }
+}
+
+fn exchange_tcp(address: SocketAddr, query: &[u8]) -> io::Result<Vec<u8>> {
+    let mut stream = connect_tcp(address)?;
+    exchange_stream(&mut stream, query)
+}
+
+/// Idle connections to upstreams, so queries don't each need a new connection and handshake
+struct ConnectionPool<S> {
+    idle: Mutex<HashMap<Upstream, Vec<(Instant, S)>>>,
+}
+
+impl<S> Default for ConnectionPool<S> {
+    fn default() -> Self {
+        Self {
+            idle: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl<S> ConnectionPool<S>
+where
+    S: Read + Write,
+{
+    /// Send a query over an idle connection to the upstream, or a new one from `connect` if there
+    /// isn't one, and read the response.  The connection is kept for later queries if the
+    /// exchange succeeds.  If an idle connection fails, e.g. because the upstream closed it, the
+    /// query is retried once over a new connection.
+    fn exchange<F>(&self, upstream: &Upstream, query: &[u8], connect: F) -> Result<Vec<u8>>
+    where
+        F: Fn() -> Result<S>,
+    {
+        if let Some(mut stream) = self.take(upstream) {
+            if let Ok(response) = exchange_stream(&mut stream, query) {
+                self.put(upstream, stream);
+                return Ok(response);
+            }
+        }
+
+        let mut stream = connect()?;
+        let response = exchange_stream(&mut stream, query).context(error::UpstreamIoSnafu {
+            upstream: upstream.to_string(),
+        })?;
+        self.put(upstream, stream);
+        Ok(response)
+    }
+
+    /// The most recently used connection to the upstream that hasn't been idle too long
+    fn take(&self, upstream: &Upstream) -> Option<S> {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let connections = idle.get_mut(upstream)?;
+        connections.retain(|(used, _)| used.elapsed() < CONNECTION_IDLE_TIMEOUT);
+        connections.pop().map(|(_, stream)| stream)
+    }
+
+    fn put(&self, upstream: &Upstream, stream: S) {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let connections = idle.entry(upstream.clone()).or_default();
+        if connections.len() < MAX_IDLE_CONNECTIONS {
+            connections.push((Instant::now(), stream));
+        }
+    }
+}
+
+fn connect_tls(
+    tls_config: &Arc<ClientConfig>,
+    address: SocketAddr,
+    server_name: &str,
+) -> Result<TlsStream> {
+    let upstream = Upstream::Tls {
+        address,
+        server_name: server_name.to_string(),
+    }
+    .to_string();
+    let name =
+        ServerName::try_from(server_name).context(error::InvalidServerNameSnafu { server_name })?;
+    let connection =
+        ClientConnection::new(Arc::clone(tls_config), name).context(error::TlsSnafu {
+            upstream: upstream.clone(),
+        })?;
+    let stream = connect_tcp(address).context(error::UpstreamIoSnafu { upstream })?;
+
+    Ok(StreamOwned::new(connection, stream))
+}
+
+fn connect_tcp(address: SocketAddr) -> io::Result<TcpStream> {
+    let stream = TcpStream::connect_timeout(&address, UPSTREAM_TIMEOUT)?;
+    stream.set_read_timeout(Some(UPSTREAM_TIMEOUT))?;
+    stream.set_write_timeout(Some(UPSTREAM_TIMEOUT))?;
+    Ok(stream)
+}
+
+/// Send a query over a stream and read the response
+fn exchange_stream<S>(stream: &mut S, query: &[u8]) -> io::Result<Vec<u8>>
+where
+    S: Read + Write,
+{
+    write_message(stream, query)?;
+    read_message(stream)?.ok_or_else(|| {
+        io::Error::new(
+            ErrorKind::UnexpectedEof,
+            "connection closed before response",
+        )
+    })
+}
+
+/// Read a message with the two-byte length prefix used over TCP.  Returns nothing if the stream
+/// closes before a new message starts.
+fn read_message<S>(stream: &mut S) -> io::Result<Option<Vec<u8>>>
+where
+    S: Read,
+{
+    let mut len = [0; 2];
+    match stream.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e),
+    }
+
+    let mut message = vec![0; u16::from_be_bytes(len) as usize];
+    stream.read_exact(&mut message)?;
+    Ok(Some(message))
+}
+
+/// Write a message with the two-byte length prefix used over TCP
+fn write_message<S>(stream: &mut S, message: &[u8]) -> io::Result<()>
+where
+    S: Write,
+{
+    let len = u16::try_from(message.len())
+        .map_err(|_| io::Error::new(ErrorKind::InvalidInput, "message too large"))?;
+    let mut framed = len.to_be_bytes().to_vec();
+    framed.extend_from_slice(message);
+    stream.write_all(&framed)?;
+    stream.flush()
+}
+
+/// The name in a message's first question, and the offset just past that question
+fn parse_question(message: &[u8]) -> Option<(String, usize)> {
+    if message.len() < HEADER_LEN || u16::from_be_bytes([message[4], message[5]]) == 0 {
+        return None;
+    }
+
+    let mut labels = Vec::new();
+    let mut offset = HEADER_LEN;
+    loop {
+        let len = *message.get(offset)? as usize;
+        offset += 1;
+        if len == 0 {
+            break;
+        }
+        // Names in the question can't be compressed, since there's nothing before them to point
+        // to, so anything but a plain label is malformed
+        if len & 0xc0 != 0 {
+            return None;
+        }
+        let label = message.get(offset..offset + len)?;
+        labels.push(String::from_utf8_lossy(label).to_lowercase());
+        offset += len;
+    }
+
+    // The type and class follow the name
+    let end = offset + 4;
+    if message.len() < end {
+        return None;
+    }
+    Some((labels.join("."), end))
+}
+
+/// Whether the TC (truncated) flag is set in a message's header
+fn is_truncated(message: &[u8]) -> bool {
+    message.len() >= HEADER_LEN && message[2] & 0x02 != 0
+}
+
+/// The largest UDP response the client that sent `query` accepts: the payload size from the
+/// query's EDNS OPT record, or 512 bytes without one
+fn max_udp_response_len(query: &[u8]) -> usize {
+    let count = |index: usize| u16::from_be_bytes([query[index], query[index + 1]]) as usize;
+    let mut offset = match parse_question(query) {
+        Some((_, end)) => end,
+        None => return MIN_UDP_PAYLOAD_SIZE,
+    };
+    // The OPT record is in the additional section, after any answer and authority records
+    for index in 0..count(6) + count(8) + count(10) {
+        offset = match skip_name(query, offset) {
+            Some(offset) => offset,
+            None => break,
+        };
+        // The type, class, TTL, and data length follow the name, then the data
+        let fixed = match query.get(offset..offset + 10) {
+            Some(fixed) => fixed,
+            None => break,
+        };
+        let record_type = u16::from_be_bytes([fixed[0], fixed[1]]);
+        if record_type == OPT_RECORD_TYPE && index >= count(6) + count(8) {
+            // The OPT record's class holds the payload size
+            let size = u16::from_be_bytes([fixed[2], fixed[3]]) as usize;
+            return size.max(MIN_UDP_PAYLOAD_SIZE);
+        }
+        offset += 10 + u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+    }
+    MIN_UDP_PAYLOAD_SIZE
+}
+
+/// The offset just past the name starting at `offset`, which may end in a compression pointer
+fn skip_name(message: &[u8], mut offset: usize) -> Option<usize> {
+    loop {
+        let len = *message.get(offset)? as usize;
+        match len & 0xc0 {
+            0 if len == 0 => return Some(offset + 1),
+            0 => offset += 1 + len,
+            0xc0 => return Some(offset + 2),
+            _ => return None,
+        }
+    }
+}
+
+/// Fit a response in what the UDP client that sent `query` accepts.  A response that's too large
+/// is cut down to its header and question with the TC (truncated) flag set, so the client retries
+/// over TCP.
+fn fit_udp_response(query: &[u8], response: Vec<u8>) -> Vec<u8> {
+    if response.len() <= max_udp_response_len(query) {
+        return response;
+    }
+
+    let question_end = parse_question(&response).map(|(_, end)| end);
+    let mut truncated = response[..question_end.unwrap_or(HEADER_LEN)].to_vec();
+    truncated[2] |= 0x02;
+    let question_count: u16 = if question_end.is_some() { 1 } else { 0 };
+    truncated[4..6].copy_from_slice(&question_count.to_be_bytes());
+    for count in &mut truncated[6..HEADER_LEN] {
+        *count = 0;
+    }
+    truncated
+}
+
+/// Build a SERVFAIL response to a query, echoing its question if it could be parsed
+fn servfail(query: &[u8]) -> Option<Vec<u8>> {
+    if query.len() < HEADER_LEN {
+        return None;
+    }
+
+    let question_end = parse_question(query).map(|(_, end)| end);
+    let mut response = query[..question_end.unwrap_or(HEADER_LEN)].to_vec();
+    // Set QR, keeping the opcode and RD from the query; set RA and the SERVFAIL response code
+    response[2] = 0x80 | (query[2] & 0x79);
+    response[3] = 0x80 | 0x02;
+    let question_count: u16 = if question_end.is_some() { 1 } else { 0 };
+    response[4..6].copy_from_slice(&question_count.to_be_bytes());
+    for count in &mut response[6..HEADER_LEN] {
+        *count = 0;
+    }
+    Some(response)
+}
+
+mod error {
+    use snafu::Snafu;
+    use std::io;
+    use std::net::SocketAddr;
+    use std::path::PathBuf;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(crate)))]
+    pub(crate) enum Error {
+        #[snafu(display("Failed to listen on '{}': {}", address, source))]
+        Bind {
+            address: SocketAddr,
+            source: io::Error,
+        },
+
+        #[snafu(display("Failed to load stub resolver configuration: {}", source))]
+        ConfigLoad { source: crate::dns::Error },
+
+        #[snafu(display("Failed to read file metadata from '{}': {}", path.display(), source))]
+        ConfigMetadata { path: PathBuf, source: io::Error },
+
+        #[snafu(display("Invalid TLS server name '{}': {}", server_name, source))]
+        InvalidServerName {
+            server_name: String,
+            source: rustls::client::InvalidDnsNameError,
+        },
+
+        #[snafu(display("Failed to load CA certificates: {}", source))]
+        LoadCertificates { source: io::Error },
+
+        #[snafu(display("Received malformed query"))]
+        MalformedQuery,
+
+        #[snafu(display("No CA certificates found for DNS-over-TLS"))]
+        NoCertificates,
+
+        #[snafu(display("Received query, but the stub resolver isn't configured"))]
+        NotConfigured,
+
+        #[snafu(display("No name servers configured for '{}'", name))]
+        NoUpstreams { name: String },
+
+        #[snafu(display("TLS error with '{}': {}", upstream, source))]
+        Tls {
+            upstream: String,
+            source: rustls::Error,
+        },
+
+        #[snafu(display("DNS-over-TLS upstream given without TLS configuration"))]
+        TlsNotConfigured,
+
+        #[snafu(display("Failed to exchange messages with '{}': {}", upstream, source))]
+        UpstreamIo { upstream: String, source: io::Error },
+
+        #[snafu(display("No name server answered the query for '{}'", name))]
+        UpstreamsFailed { name: String },
+    }
+}
+
+pub(crate) use error::Error;
+type Result<T> = std::result::Result<T, error::Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dns::DomainSettings;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::net::IpAddr;
+
+    // A query for "www.example.com" with ID 0x1234, type A, class IN
+    fn query() -> Vec<u8> {
+        let mut query = vec![
+            0x12, 0x34, 0x01, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        ];
+        for label in &["www", "Example", "com"] {
+            query.push(label.len() as u8);
+            query.extend_from_slice(label.as_bytes());
+        }
+        query.extend_from_slice(&[0x00, 0x00, 0x01, 0x00, 0x01]);
+        query
+    }
+
+    fn ips(ips: &[&str]) -> BTreeSet<IpAddr> {
+        ips.iter().map(|ip| ip.parse().unwrap()).collect()
+    }
+
+    fn forwarder(settings: DnsSettings) -> Forwarder {
+        // Building a real TLS config needs the system's certificates, which choosing upstreams
+        // doesn't
+        Forwarder {
+            settings,
+            tls_config: None,
+            tls_connections: ConnectionPool::default(),
+        }
+    }
+
+    fn split_settings() -> DnsSettings {
+        let mut domains = BTreeMap::new();
+        domains.insert(
+            "example.com".to_string(),
+            DomainSettings {
+                nameservers: ips(&["10.0.0.2"]),
+            },
+        );
+        domains.insert(
+            "Internal.Example.com.".to_string(),
+            DomainSettings {
+                nameservers: ips(&["10.1.0.2", "10.1.0.3"]),
+            },
+        );
+        DnsSettings {
+            nameservers: Some(ips(&["192.168.0.2"])),
+            domains: Some(domains),
+            ..Default::default()
+        }
+    }
+
+    fn plain(ip: &str) -> Upstream {
+        Upstream::Plain(SocketAddr::new(ip.parse().unwrap(), DNS_PORT))
+    }
+
+    #[test]
+    fn question_name() {
+        let query = query();
+        let (name, end) = parse_question(&query).unwrap();
+        assert_eq!(name, "www.example.com");
+        assert_eq!(end, query.len());
+    }
+
+    #[test]
+    fn question_malformed() {
+        let query = query();
+        assert!(parse_question(&query[..HEADER_LEN]).is_none());
+        assert!(parse_question(&query[..query.len() - 1]).is_none());
+
+        let mut compressed = query.clone();
+        compressed[HEADER_LEN] = 0xc0;
+        assert!(parse_question(&compressed).is_none());
+
+        let mut no_questions = query;
+        no_questions[5] = 0;
+        assert!(parse_question(&no_questions).is_none());
+    }
+
+    #[test]
+    fn servfail_response() {
+        let query = query();
+        let response = servfail(&query).unwrap();
+        assert_eq!(&response[..2], &[0x12, 0x34]);
+        assert_eq!(response[2], 0x81);
+        assert_eq!(response[3], 0x82);
+        assert_eq!(&response[4..HEADER_LEN], &[0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&response[HEADER_LEN..], &query[HEADER_LEN..]);
+
+        assert!(servfail(&query[..4]).is_none());
+    }
+
+    #[test]
+    fn domain_matching() {
+        assert!(in_domain("example.com", "example.com"));
+        assert!(in_domain("www.example.com", "example.com"));
+        assert!(!in_domain("badexample.com", "example.com"));
+        assert!(!in_domain("example.org", "example.com"));
+    }
+
+    #[test]
+    fn domain_upstreams() {
+        let forwarder = forwarder(split_settings());
+        assert_eq!(
+            forwarder.upstreams("www.example.com."),
+            vec![plain("10.0.0.2")]
+        );
+        assert_eq!(
+            forwarder.upstreams("db.internal.example.com"),
+            vec![plain("10.1.0.2"), plain("10.1.0.3")]
+        );
+        assert_eq!(
+            forwarder.upstreams("example.org"),
+            vec![plain("192.168.0.2")]
+        );
+    }
+
+    #[test]
+    fn tls_upstreams() {
+        let mut settings = split_settings();
+        settings.tls_upstreams = Some(vec![
+            TlsUpstream {
+                address: "1.1.1.1".parse().unwrap(),
+                port: None,
+                server_name: "cloudflare-dns.com".to_string(),
+            },
+            TlsUpstream {
+                address: "9.9.9.9".parse().unwrap(),
+                port: Some(8853),
+                server_name: "dns.quad9.net".to_string(),
+            },
+        ]);
+        let forwarder = forwarder(settings);
+
+        // Domains with their own name servers still use them
+        assert_eq!(
+            forwarder.upstreams("www.example.com"),
+            vec![plain("10.0.0.2")]
+        );
+        assert_eq!(
+            forwarder.upstreams("example.org"),
+            vec![
+                Upstream::Tls {
+                    address: "1.1.1.1:853".parse().unwrap(),
+                    server_name: "cloudflare-dns.com".to_string(),
+                },
+                Upstream::Tls {
+                    address: "9.9.9.9:8853".parse().unwrap(),
+                    server_name: "dns.quad9.net".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn worker_pool_bounded() {
+        let pool = WorkerPool::new(1, 1);
+        let (started_tx, started_rx) = mpsc::channel();
+        let (release_tx, release_rx) = mpsc::channel::<()>();
+        assert!(pool.try_execute(move || {
+            started_tx.send(()).unwrap();
+            let _ = release_rx.recv();
+        }));
+        // Once the only worker is busy, one job fits in the queue and the next is refused
+        started_rx.recv().unwrap();
+        let (done_tx, done_rx) = mpsc::channel();
+        assert!(pool.try_execute(move || done_tx.send(()).unwrap()));
+        assert!(!pool.try_execute(|| {}));
+
+        drop(release_tx);
+        done_rx.recv().unwrap();
+    }
+
+    #[test]
+    fn connections_reused() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let upstream = Upstream::Plain(address);
+
+        // The fake upstream answers two queries on its first connection, then closes it, and
+        // answers one more on a second connection
+        let server = thread::spawn(move || {
+            for queries in &[2, 1] {
+                let (mut stream, _) = listener.accept().unwrap();
+                for _ in 0..*queries {
+                    let mut response = read_message(&mut stream).unwrap().unwrap();
+                    response[2] |= 0x80;
+                    write_message(&mut stream, &response).unwrap();
+                }
+            }
+        });
+
+        let pool = ConnectionPool::default();
+        let connects = Mutex::new(0);
+        let connect = || {
+            *connects.lock().unwrap() += 1;
+            connect_tcp(address).context(error::UpstreamIoSnafu {
+                upstream: upstream.to_string(),
+            })
+        };
+        let query = query();
+        for _ in 0..3 {
+            let response = pool.exchange(&upstream, &query, connect).unwrap();
+            assert_eq!(response[2], 0x81);
+        }
+        server.join().unwrap();
+        assert_eq!(*connects.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn truncated_response_retried_over_tcp() {
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = udp.local_addr().unwrap();
+        let tcp = TcpListener::bind(address).unwrap();
+
+        // The fake upstream echoes the query as its response, truncated over UDP
+        let server = thread::spawn(move || {
+            let mut buf = vec![0; MAX_MESSAGE_SIZE];
+            let (len, client) = udp.recv_from(&mut buf).unwrap();
+            let mut truncated = buf[..len].to_vec();
+            truncated[2] |= 0x82;
+            udp.send_to(&truncated, client).unwrap();
+
+            let (mut stream, _) = tcp.accept().unwrap();
+            let mut response = read_message(&mut stream).unwrap().unwrap();
+            response[2] |= 0x80;
+            write_message(&mut stream, &response).unwrap();
+        });
+
+        let forwarder = forwarder(DnsSettings::default());
+        let query = query();
+        let response = forwarder
+            .exchange(&Upstream::Plain(address), &query, Protocol::Tcp)
+            .unwrap();
+        server.join().unwrap();
+
+        assert!(!is_truncated(&response));
+        assert_eq!(response[2], 0x81);
+        assert_eq!(&response[3..], &query[3..]);
+    }
+
+    // UDP clients get the truncated response, and retry over TCP themselves
+    #[test]
+    fn truncated_response_passed_to_udp_clients() {
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = udp.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let mut buf = vec![0; MAX_MESSAGE_SIZE];
+            let (len, client) = udp.recv_from(&mut buf).unwrap();
+            let mut truncated = buf[..len].to_vec();
+            truncated[2] |= 0x82;
+            udp.send_to(&truncated, client).unwrap();
+        });
+
+        let forwarder = forwarder(DnsSettings::default());
+        let response = forwarder
+            .exchange(&Upstream::Plain(address), &query(), Protocol::Udp)
+            .unwrap();
+        server.join().unwrap();
+        assert!(is_truncated(&response));
+    }
+
+    // Answers larger than the UDP client accepts are cut down to the question, with TC set
+    #[test]
+    fn oversized_udp_response() {
+        let query = query();
+        let mut response = query.clone();
+        response[2] |= 0x80;
+        response[7] = 40;
+        for _ in 0..40 {
+            // A compressed pointer to the question's name, then an A record
+            response.extend_from_slice(&[0xc0, 0x0c, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x01]);
+            response.extend_from_slice(&[0x00, 0x00, 0x04, 0x0a, 0x00, 0x00, 0x01]);
+        }
+        assert!(response.len() > MIN_UDP_PAYLOAD_SIZE);
+
+        let truncated = fit_udp_response(&query, response.clone());
+        assert!(is_truncated(&truncated));
+        assert_eq!(&truncated[..2], &query[..2]);
+        assert_eq!(&truncated[4..HEADER_LEN], &[0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&truncated[HEADER_LEN..], &query[HEADER_LEN..]);
+
+        // A client advertising a larger payload size with EDNS gets the whole answer
+        let mut edns_query = query;
+        edns_query[11] = 1;
+        edns_query.extend_from_slice(&[0x00, 0x00, 0x29, 0x04, 0xd0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(max_udp_response_len(&edns_query), 1232);
+        assert_eq!(fit_udp_response(&edns_query, response.clone()), response);
+    }
+}
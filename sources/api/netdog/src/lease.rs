@@ -84,6 +84,18 @@ where
     get_lease_path("static", interface)
 }
 
+/// Return the paths to a given interface's DHCP ipv4 and ipv6 leases, for those that exist
+pub(crate) fn dhcp_lease_paths<S>(interface: S) -> Vec<PathBuf>
+where
+    S: AsRef<str>,
+{
+    let (ipv4, ipv6) = lease_paths("dhcp", interface);
+    vec![ipv4, ipv6]
+        .into_iter()
+        .filter(|path| Path::exists(path))
+        .collect()
+}
+
 /// Given a lease type and interface, return the path to the ipv4/6 lease file if it exists,
 /// favoring ipv4 if both ipv4 and ipv6 exist
 fn get_lease_path<S1, S2>(lease_type: S1, interface: S2) -> Option<PathBuf>
@@ -91,11 +103,7 @@ where
     S1: AsRef<str>,
     S2: AsRef<str>,
 {
-    let lease_type = lease_type.as_ref();
-    let interface = interface.as_ref();
-
-    let ipv4 = Path::new(LEASE_DIR).join(format!("leaseinfo.{}.{}.ipv4", interface, lease_type));
-    let ipv6 = Path::new(LEASE_DIR).join(format!("leaseinfo.{}.{}.ipv6", interface, lease_type));
+    let (ipv4, ipv6) = lease_paths(lease_type, interface);
 
     // If both ipv4 and ipv6 leases exist, use the ipv4 lease for DNS settings
     let ipv4_exists = Path::exists(&ipv4);
@@ -108,6 +116,20 @@ where
     }
 }
 
+/// Given a lease type and interface, return the paths where wicked writes its ipv4 and ipv6 leases
+fn lease_paths<S1, S2>(lease_type: S1, interface: S2) -> (PathBuf, PathBuf)
+where
+    S1: AsRef<str>,
+    S2: AsRef<str>,
+{
+    let lease_type = lease_type.as_ref();
+    let interface = interface.as_ref();
+
+    let ipv4 = Path::new(LEASE_DIR).join(format!("leaseinfo.{}.{}.ipv4", interface, lease_type));
+    let ipv6 = Path::new(LEASE_DIR).join(format!("leaseinfo.{}.{}.ipv6", interface, lease_type));
+    (ipv4, ipv6)
+}
+
 mod error {
     use snafu::Snafu;
     use std::io;
@@ -21,9 +21,40 @@ name of the interface, and valid options are "dhcp4" and "dhcp6".  A "?" may be
 to signify that the lease for the protocol is optional and the system shouldn't wait for it.  A
 valid example: `netdog.default-interface=eno1:dhcp4,dhcp6?`.
 
+The configuration is written for the variant's network backend: wicked XML files in
+`/etc/wicked/ifconfig` by default, or `.network`, `.netdev`, and `.link` files in
+`/etc/systemd/network` for variants with the `systemd-networkd` image feature.
+
+The subcommand `reload-net-config` applies the `settings.network.interfaces` API settings to the
+running system.  It is meant to be used as a restart command for those settings.  The interfaces
+in settings, rendered to `/etc/netdog-interfaces.toml` in the `net.toml` format, are layered on the
+configuration from boot; an interface in settings replaces the boot configuration of the interface
+with the same name.  Only the interfaces whose configuration changed are reloaded with `wicked
+ifreload`.  An interface removed from settings gets its boot configuration back, or is brought
+down with `wicked ifdown` if it wasn't configured at boot.  The settings are persisted in `/var/lib/netdog`, and `generate-net-config` layers them
+on the boot configuration the same way, so they survive a reboot.  Bonds, bridges, and their
+members can't be configured in settings, and the `systemd-networkd` backend doesn't support
+runtime configuration.
+
+The subcommand `status` prints JSON describing each configured interface, for checking the
+state of the network with `apiclient exec`.  For each interface, it includes the configuration as
+written in `net.toml` or settings and where it came from, whether it's the primary interface, the
+device's link state, addresses, and routes as reported by `ip`, and the contents of its DHCP leases.
+
 The subcommand `write-resolv-conf` writes the resolv.conf, favoring DNS API settings and
 supplementing any missing settings with DNS settings from the primary interface's DHCP lease.  It
-is meant to be used as a restart command for DNS API settings.
+is meant to be used as a restart command for DNS API settings.  Resolver options like `ndots` are
+written to the `options` line.  If some domains have their own name servers, or DNS-over-TLS
+upstreams are configured, libc's resolver can't handle the queries itself; instead, resolv.conf
+points at a stub resolver on `127.0.0.53`, and the settings it needs are written to
+`/run/netdog/dns-stub.toml`.
+
+The subcommand `dns-stub` runs that stub resolver, as a long-running service.  It answers queries
+over UDP and TCP, sending queries for names under a configured domain to that domain's name
+servers, and other queries to the DNS-over-TLS upstreams if there are any, or to the usual name
+servers otherwise.  It reloads its settings when they change, and answers with SERVFAIL while it
+isn't configured.  Queries are answered by a fixed number of worker threads, and connections to
+DNS-over-TLS upstreams are kept open for reuse between queries.
 */
 
 #![deny(rust_2018_idioms)]
@@ -31,17 +62,20 @@ is meant to be used as a restart command for DNS API settings.
 #[macro_use]
 extern crate serde_plain;
 
+mod backend;
 mod cli;
 mod dns;
 mod interface_id;
 mod lease;
 mod net_config;
+mod networkd;
 mod wicked;
 
 use argh::FromArgs;
 use std::process;
 
 static RESOLV_CONF: &str = "/etc/resolv.conf";
+static DNS_STUB_CONFIG: &str = "/run/netdog/dns-stub.toml";
 static KERNEL_HOSTNAME: &str = "/proc/sys/kernel/hostname";
 static CURRENT_IP: &str = "/var/lib/netdog/current_ip";
 static KERNEL_CMDLINE: &str = "/proc/cmdline";
@@ -49,11 +83,15 @@ static PRIMARY_INTERFACE: &str = "/var/lib/netdog/primary_interface";
 static PRIMARY_MAC_ADDRESS: &str = "/var/lib/netdog/primary_mac_address";
 static DEFAULT_NET_CONFIG_FILE: &str = "/var/lib/bottlerocket/net.toml";
 static OVERRIDE_NET_CONFIG_FILE: &str = "/var/lib/netdog/net.toml";
+static RUNTIME_NET_CONFIG_FILE: &str = "/etc/netdog-interfaces.toml";
+static PERSISTED_RUNTIME_NET_CONFIG_FILE: &str = "/var/lib/netdog/runtime-net.toml";
 static PRIMARY_SYSCTL_CONF: &str = "/etc/sysctl.d/90-primary_interface.conf";
 static SYSCTL_MARKER_FILE: &str = "/run/netdog/primary_sysctls_set";
 static SYSTEMD_SYSCTL: &str = "/usr/lib/systemd/systemd-sysctl";
+static WICKED: &str = "/usr/sbin/wicked";
 static LEASE_DIR: &str = "/run/wicked";
 static SYS_CLASS_NET: &str = "/sys/class/net";
+static IP: &str = "/usr/sbin/ip";
 
 /// Stores user-supplied arguments.
 #[derive(FromArgs, PartialEq, Debug)]
@@ -70,8 +108,11 @@ enum SubCommand {
     NodeIp(cli::NodeIpArgs),
     GenerateHostname(cli::GenerateHostnameArgs),
     GenerateNetConfig(cli::GenerateNetConfigArgs),
+    ReloadNetConfig(cli::ReloadNetConfigArgs),
+    DnsStub(cli::DnsStubArgs),
     SetHostname(cli::SetHostnameArgs),
     WriteResolvConf(cli::WriteResolvConfArgs),
+    Status(cli::StatusArgs),
 }
 
 async fn run() -> cli::Result<()> {
@@ -82,8 +123,11 @@ async fn run() -> cli::Result<()> {
         SubCommand::NodeIp(_) => cli::node_ip::run()?,
         SubCommand::GenerateHostname(_) => cli::generate_hostname::run().await?,
         SubCommand::GenerateNetConfig(_) => cli::generate_net_config::run()?,
+        SubCommand::ReloadNetConfig(_) => cli::reload_net_config::run()?,
+        SubCommand::DnsStub(_) => cli::dns_stub::run()?,
         SubCommand::SetHostname(args) => cli::set_hostname::run(args)?,
         SubCommand::WriteResolvConf(_) => cli::write_resolv_conf::run()?,
+        SubCommand::Status(_) => cli::status::run()?,
     }
     Ok(())
 }
@@ -1,7 +1,9 @@
-use super::validate_addressing;
-use super::{error, Dhcp4ConfigV1, Dhcp6ConfigV1, Result, RouteV1, StaticConfigV1, Validate};
+use super::{
+    error, Dhcp4ConfigV1, Dhcp6ConfigV1, Result, RouteV1, RouteV2, RuleV1, StaticConfigV1, Validate,
+};
+use super::{validate_addressing, validate_link_options};
 use crate::interface_id::InterfaceName;
-use crate::net_config::devices::generate_addressing_validation;
+use crate::net_config::devices::{generate_addressing_validation, generate_link_validation};
 use serde::de::Error;
 use serde::{Deserialize, Deserializer};
 use snafu::ensure;
@@ -33,12 +35,7 @@ impl<'de> Deserialize<'de> for NetBondV1 {
         D: Deserializer<'de>,
     {
         let this = Self::deserialize(deserializer)?;
-        if this.kind.to_lowercase().as_str() != "bond" {
-            return Err(D::Error::custom(format!(
-                "kind of '{}' does not match 'bond'",
-                this.kind.as_str()
-            )));
-        }
+        check_kind::<D::Error>(&this.kind)?;
 
         Ok(this)
     }
@@ -49,32 +46,99 @@ generate_addressing_validation!(&NetBondV1);
 impl Validate for NetBondV1 {
     fn validate(&self) -> Result<()> {
         validate_addressing(self)?;
+        validate_bond_options(&self.interfaces, self.min_links, &self.monitoring_config)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(remote = "Self")]
+pub(crate) struct NetBondV2 {
+    pub(crate) primary: Option<bool>,
+    pub(crate) dhcp4: Option<Dhcp4ConfigV1>,
+    pub(crate) dhcp6: Option<Dhcp6ConfigV1>,
+    pub(crate) static4: Option<StaticConfigV1>,
+    pub(crate) static6: Option<StaticConfigV1>,
+    #[serde(rename = "route")]
+    pub(crate) routes: Option<Vec<RouteV2>>,
+    #[serde(rename = "rule")]
+    pub(crate) rules: Option<Vec<RuleV1>>,
+    pub(crate) mtu: Option<u32>,
+    kind: String,
+    pub(crate) mode: BondMode,
+    #[serde(rename = "min-links")]
+    pub(crate) min_links: Option<usize>,
+    #[serde(rename = "monitoring")]
+    pub(crate) monitoring_config: BondMonitoringConfig,
+    pub(crate) interfaces: Vec<InterfaceName>,
+}
+
+impl<'de> Deserialize<'de> for NetBondV2 {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let this = Self::deserialize(deserializer)?;
+        check_kind::<D::Error>(&this.kind)?;
+
+        Ok(this)
+    }
+}
+
+generate_addressing_validation!(&NetBondV2);
+generate_link_validation!(&NetBondV2);
+
+impl Validate for NetBondV2 {
+    fn validate(&self) -> Result<()> {
+        validate_addressing(self)?;
+        validate_link_options(self)?;
+        validate_bond_options(&self.interfaces, self.min_links, &self.monitoring_config)
+    }
+}
+
+fn check_kind<E>(kind: &str) -> std::result::Result<(), E>
+where
+    E: Error,
+{
+    if kind.to_lowercase().as_str() != "bond" {
+        return Err(E::custom(format!(
+            "kind of '{}' does not match 'bond'",
+            kind
+        )));
+    }
+    Ok(())
+}
 
-        // TODO: We should move this and other validation logic into Deserialize when messaging
-        // is better for enum failures https://github.com/serde-rs/serde/issues/2157
-        let interfaces_count = self.interfaces.len();
+/// Validation shared by all versions of bonds
+fn validate_bond_options(
+    interfaces: &[InterfaceName],
+    min_links: Option<usize>,
+    monitoring_config: &BondMonitoringConfig,
+) -> Result<()> {
+    // TODO: We should move this and other validation logic into Deserialize when messaging
+    // is better for enum failures https://github.com/serde-rs/serde/issues/2157
+    let interfaces_count = interfaces.len();
+    ensure!(
+        interfaces_count > 0,
+        error::InvalidNetConfigSnafu {
+            reason: "bonds must have 1 or more interfaces specified"
+        }
+    );
+    if let Some(min_links) = min_links {
         ensure!(
-            interfaces_count > 0,
+            min_links <= interfaces_count,
             error::InvalidNetConfigSnafu {
-                reason: "bonds must have 1 or more interfaces specified"
+                reason: "min-links is greater than number of interfaces configured"
             }
-        );
-        if let Some(min_links) = self.min_links {
-            ensure!(
-                min_links <= interfaces_count,
-                error::InvalidNetConfigSnafu {
-                    reason: "min-links is greater than number of interfaces configured"
-                }
-            )
-        }
-        // Validate monitoring configuration
-        match &self.monitoring_config {
-            BondMonitoringConfig::MiiMon(config) => config.validate()?,
-            BondMonitoringConfig::ArpMon(config) => config.validate()?,
-        }
-
-        Ok(())
+        )
+    }
+    // Validate monitoring configuration
+    match monitoring_config {
+        BondMonitoringConfig::MiiMon(config) => config.validate()?,
+        BondMonitoringConfig::ArpMon(config) => config.validate()?,
     }
+
+    Ok(())
 }
 
 // Currently only mode 1 (active-backup) is supported but eventually 0-6 could be added
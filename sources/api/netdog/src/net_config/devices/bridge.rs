@@ -0,0 +1,64 @@
+use super::{error, Dhcp4ConfigV1, Dhcp6ConfigV1, Result, Validate};
+use super::{validate_addressing, validate_link_options};
+use crate::interface_id::InterfaceName;
+use crate::net_config::devices::{generate_addressing_validation, generate_link_validation};
+use crate::net_config::{RouteV2, RuleV1, StaticConfigV1};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer};
+use snafu::ensure;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(remote = "Self")]
+pub(crate) struct NetBridgeV1 {
+    pub(crate) primary: Option<bool>,
+    pub(crate) dhcp4: Option<Dhcp4ConfigV1>,
+    pub(crate) dhcp6: Option<Dhcp6ConfigV1>,
+    pub(crate) static4: Option<StaticConfigV1>,
+    pub(crate) static6: Option<StaticConfigV1>,
+    #[serde(rename = "route")]
+    pub(crate) routes: Option<Vec<RouteV2>>,
+    #[serde(rename = "rule")]
+    pub(crate) rules: Option<Vec<RuleV1>>,
+    pub(crate) mtu: Option<u32>,
+    kind: String,
+    pub(crate) interfaces: Vec<InterfaceName>,
+    // Spanning tree protocol is off unless requested, since most hosts bridge to a single switch
+    pub(crate) stp: Option<bool>,
+}
+
+impl<'de> Deserialize<'de> for NetBridgeV1 {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let this = Self::deserialize(deserializer)?;
+        if this.kind.to_lowercase().as_str() != "bridge" {
+            return Err(D::Error::custom(format!(
+                "kind of '{}' does not match 'bridge'",
+                this.kind.as_str()
+            )));
+        }
+
+        Ok(this)
+    }
+}
+
+generate_addressing_validation!(&NetBridgeV1);
+generate_link_validation!(&NetBridgeV1);
+
+impl Validate for NetBridgeV1 {
+    fn validate(&self) -> Result<()> {
+        validate_addressing(self)?;
+        validate_link_options(self)?;
+
+        ensure!(
+            !self.interfaces.is_empty(),
+            error::InvalidNetConfigSnafu {
+                reason: "bridges must have 1 or more interfaces specified"
+            }
+        );
+
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
-use super::validate_addressing;
+use super::{validate_addressing, validate_link_options};
 use super::{Dhcp4ConfigV1, Dhcp6ConfigV1, Result, Validate};
-use crate::net_config::devices::generate_addressing_validation;
-use crate::net_config::{RouteV1, StaticConfigV1};
+use crate::net_config::devices::{generate_addressing_validation, generate_link_validation};
+use crate::net_config::{RouteV1, RouteV2, RuleV1, StaticConfigV1};
 use serde::Deserialize;
 
 #[derive(Debug, Deserialize)]
@@ -25,3 +25,30 @@ impl Validate for NetInterfaceV2 {
 
 // Generate the traits for IP Address validation
 generate_addressing_validation!(&NetInterfaceV2);
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct NetInterfaceV3 {
+    // Use this interface as the primary interface for the system
+    pub(crate) primary: Option<bool>,
+    pub(crate) dhcp4: Option<Dhcp4ConfigV1>,
+    pub(crate) dhcp6: Option<Dhcp6ConfigV1>,
+    pub(crate) static4: Option<StaticConfigV1>,
+    pub(crate) static6: Option<StaticConfigV1>,
+    #[serde(rename = "route")]
+    pub(crate) routes: Option<Vec<RouteV2>>,
+    #[serde(rename = "rule")]
+    pub(crate) rules: Option<Vec<RuleV1>>,
+    pub(crate) mtu: Option<u32>,
+}
+
+impl Validate for NetInterfaceV3 {
+    fn validate(&self) -> Result<()> {
+        validate_addressing(self)?;
+        validate_link_options(self)
+    }
+}
+
+// Generate the traits for IP Address and link option validation
+generate_addressing_validation!(&NetInterfaceV3);
+generate_link_validation!(&NetInterfaceV3);
@@ -3,15 +3,18 @@
 //! each device.
 
 pub(crate) mod bonding;
+pub(crate) mod bridge;
 pub(crate) mod interface;
 pub(crate) mod vlan;
 
 use super::{error, Result, Validate};
-use crate::net_config::{Dhcp4ConfigV1, Dhcp6ConfigV1, RouteV1, StaticConfigV1};
-use bonding::NetBondV1;
-use interface::NetInterfaceV2;
+use crate::net_config::{Dhcp4ConfigV1, Dhcp6ConfigV1, RouteV1, RouteV2, RuleV1, StaticConfigV1};
+use bonding::{NetBondV1, NetBondV2};
+use bridge::NetBridgeV1;
+use interface::{NetInterfaceV2, NetInterfaceV3};
 use serde::Deserialize;
-use vlan::NetVlanV1;
+use snafu::ensure;
+use vlan::{NetVlanV1, NetVlanV2};
 
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
@@ -42,6 +45,47 @@ impl Validate for NetworkDeviceV1 {
     }
 }
 
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub(crate) enum NetworkDeviceV2 {
+    Interface(NetInterfaceV3),
+    BondDevice(NetBondV2),
+    VlanDevice(NetVlanV2),
+    BridgeDevice(NetBridgeV1),
+}
+
+impl NetworkDeviceV2 {
+    pub(crate) fn primary(&self) -> Option<bool> {
+        match self {
+            Self::Interface(i) => i.primary,
+            Self::BondDevice(i) => i.primary,
+            Self::VlanDevice(i) => i.primary,
+            Self::BridgeDevice(i) => i.primary,
+        }
+    }
+
+    pub(crate) fn mtu(&self) -> Option<u32> {
+        match self {
+            Self::Interface(i) => i.mtu,
+            Self::BondDevice(i) => i.mtu,
+            Self::VlanDevice(i) => i.mtu,
+            Self::BridgeDevice(i) => i.mtu,
+        }
+    }
+}
+
+impl Validate for NetworkDeviceV2 {
+    fn validate(&self) -> Result<()> {
+        match self {
+            Self::Interface(config) => config.validate()?,
+            Self::BondDevice(config) => config.validate()?,
+            Self::VlanDevice(config) => config.validate()?,
+            Self::BridgeDevice(config) => config.validate()?,
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(untagged)]
 pub(crate) enum DeviceType {
@@ -97,8 +141,7 @@ where
 // For all devices that have IP Addressing available, generate the trait implementation
 macro_rules! generate_addressing_validation {
     ($name:ty) => {
-        use crate::net_config::devices::HasIpAddressing;
-        impl HasIpAddressing for $name {
+        impl crate::net_config::devices::HasIpAddressing for $name {
             fn has_static(&self) -> bool {
                 self.static4.is_some() || self.static6.is_some()
             }
@@ -126,3 +169,102 @@ macro_rules! generate_addressing_validation {
     };
 }
 pub(crate) use generate_addressing_validation;
+
+// The smallest MTU allowed for IPv4 and IPv6, respectively, and the largest MTU the kernel allows
+const MIN_MTU_IPV4: u32 = 68;
+const MIN_MTU_IPV6: u32 = 1280;
+const MAX_MTU: u32 = 65535;
+
+/// Devices starting with net config v4 may set an MTU, add routes to other routing tables, and
+/// add routing policy rules
+pub(crate) trait HasLinkOptions {
+    fn mtu(&self) -> Option<u32>;
+    fn routes(&self) -> &[RouteV2];
+    fn rules(&self) -> &[RuleV1];
+
+    fn has_static4(&self) -> bool;
+    fn has_static6(&self) -> bool;
+    fn has_ipv6(&self) -> bool;
+}
+
+pub(crate) fn validate_link_options<D>(device: D) -> Result<()>
+where
+    D: HasLinkOptions,
+{
+    if let Some(mtu) = device.mtu() {
+        ensure!(
+            (MIN_MTU_IPV4..=MAX_MTU).contains(&mtu),
+            error::InvalidNetConfigSnafu {
+                reason: format!("mtu must be between {} and {}", MIN_MTU_IPV4, MAX_MTU),
+            }
+        );
+        ensure!(
+            !device.has_ipv6() || mtu >= MIN_MTU_IPV6,
+            error::InvalidNetConfigSnafu {
+                reason: format!("mtu must be at least {} to use IPv6", MIN_MTU_IPV6),
+            }
+        );
+    }
+
+    for route in device.routes() {
+        route.validate()?;
+    }
+
+    // Like routes, rules are only supported alongside static addresses, and the rule must match
+    // traffic of a family that has them
+    for rule in device.rules() {
+        rule.validate()?;
+        let has_static = if rule.is_ipv6() {
+            device.has_static6()
+        } else {
+            device.has_static4()
+        };
+        ensure!(
+            has_static,
+            error::InvalidNetConfigSnafu {
+                reason:
+                    "interfaces must set static addresses of the same family in order to use rules",
+            }
+        );
+    }
+
+    Ok(())
+}
+
+// For all devices that have link options available, generate the trait implementation
+macro_rules! generate_link_validation {
+    ($name:ty) => {
+        impl crate::net_config::devices::HasLinkOptions for $name {
+            fn mtu(&self) -> Option<u32> {
+                self.mtu
+            }
+
+            fn routes(&self) -> &[crate::net_config::RouteV2] {
+                self.routes.as_deref().unwrap_or_default()
+            }
+
+            fn rules(&self) -> &[crate::net_config::RuleV1] {
+                self.rules.as_deref().unwrap_or_default()
+            }
+
+            fn has_static4(&self) -> bool {
+                self.static4.is_some()
+            }
+
+            fn has_static6(&self) -> bool {
+                self.static6.is_some()
+            }
+
+            fn has_ipv6(&self) -> bool {
+                use crate::net_config::Dhcp6ConfigV1;
+                let dhcp6 = match &self.dhcp6 {
+                    Some(Dhcp6ConfigV1::DhcpEnabled(enabled)) => *enabled,
+                    Some(Dhcp6ConfigV1::WithOptions(options)) => options.enabled,
+                    None => false,
+                };
+                dhcp6 || self.static6.is_some()
+            }
+        }
+    };
+}
+pub(crate) use generate_link_validation;
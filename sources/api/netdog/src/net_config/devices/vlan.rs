@@ -1,8 +1,8 @@
-use super::validate_addressing;
+use super::{validate_addressing, validate_link_options};
 use super::{Dhcp4ConfigV1, Dhcp6ConfigV1, Result, Validate};
 use crate::interface_id::InterfaceName;
-use crate::net_config::devices::generate_addressing_validation;
-use crate::net_config::{RouteV1, StaticConfigV1};
+use crate::net_config::devices::{generate_addressing_validation, generate_link_validation};
+use crate::net_config::{RouteV1, RouteV2, RuleV1, StaticConfigV1};
 use serde::de::Error;
 use serde::{Deserialize, Deserializer};
 
@@ -28,20 +28,7 @@ impl<'de> Deserialize<'de> for NetVlanV1 {
         D: Deserializer<'de>,
     {
         let this = Self::deserialize(deserializer)?;
-
-        if this.kind.to_lowercase().as_str() != "vlan" {
-            return Err(D::Error::custom(format!(
-                "kind of '{}' does not match 'vlan'",
-                this.kind.as_str()
-            )));
-        }
-
-        // Validate its a valid vlan id - 0-4095
-        if this.id > 4095 {
-            return Err(D::Error::custom(
-                "invalid vlan ID specified, must be between 0-4095",
-            ));
-        }
+        check_kind_and_id::<D::Error>(&this.kind, this.id)?;
 
         Ok(this)
     }
@@ -56,3 +43,67 @@ impl Validate for NetVlanV1 {
 
 // Generate the traits for IP Address validation
 generate_addressing_validation!(&NetVlanV1);
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+#[serde(remote = "Self")]
+pub(crate) struct NetVlanV2 {
+    pub(crate) primary: Option<bool>,
+    pub(crate) dhcp4: Option<Dhcp4ConfigV1>,
+    pub(crate) dhcp6: Option<Dhcp6ConfigV1>,
+    pub(crate) static4: Option<StaticConfigV1>,
+    pub(crate) static6: Option<StaticConfigV1>,
+    #[serde(rename = "route")]
+    pub(crate) routes: Option<Vec<RouteV2>>,
+    #[serde(rename = "rule")]
+    pub(crate) rules: Option<Vec<RuleV1>>,
+    pub(crate) mtu: Option<u32>,
+    kind: String,
+    pub(crate) device: InterfaceName,
+    pub(crate) id: u16,
+}
+
+impl<'de> Deserialize<'de> for NetVlanV2 {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let this = Self::deserialize(deserializer)?;
+        check_kind_and_id::<D::Error>(&this.kind, this.id)?;
+
+        Ok(this)
+    }
+}
+
+impl Validate for NetVlanV2 {
+    fn validate(&self) -> Result<()> {
+        validate_addressing(self)?;
+        validate_link_options(self)?;
+        Ok(())
+    }
+}
+
+// Generate the traits for IP Address and link option validation
+generate_addressing_validation!(&NetVlanV2);
+generate_link_validation!(&NetVlanV2);
+
+fn check_kind_and_id<E>(kind: &str, id: u16) -> std::result::Result<(), E>
+where
+    E: Error,
+{
+    if kind.to_lowercase().as_str() != "vlan" {
+        return Err(E::custom(format!(
+            "kind of '{}' does not match 'vlan'",
+            kind
+        )));
+    }
+
+    // Validate its a valid vlan id - 0-4095
+    if id > 4095 {
+        return Err(E::custom(
+            "invalid vlan ID specified, must be between 0-4095",
+        ));
+    }
+
+    Ok(())
+}
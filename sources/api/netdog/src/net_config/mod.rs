@@ -7,18 +7,22 @@
 pub(crate) mod devices;
 mod dhcp;
 mod error;
+mod rule;
 mod static_address;
 mod v1;
 mod v2;
 mod v3;
+mod v4;
 
 use crate::interface_id::InterfaceId;
+use crate::networkd::NetworkDConfig;
 use crate::wicked::WickedInterface;
 pub(crate) use dhcp::{Dhcp4ConfigV1, Dhcp4OptionsV1, Dhcp6ConfigV1, Dhcp6OptionsV1};
 pub(crate) use error::{Error, Result};
+pub(crate) use rule::RuleV1;
 use serde::Deserialize;
 use snafu::{ensure, ResultExt};
-pub(crate) use static_address::{RouteTo, RouteV1, StaticConfigV1};
+pub(crate) use static_address::{RouteTo, RouteV1, RouteV2, StaticConfigV1};
 use std::fs;
 use std::path::Path;
 use std::str::FromStr;
@@ -37,6 +41,10 @@ pub(crate) trait Interfaces {
     /// Converts the network config into a list of `WickedInterface` structs, suitable for writing
     /// to file
     fn as_wicked_interfaces(&self) -> Vec<WickedInterface>;
+
+    /// Converts the network config into a `NetworkDConfig`, holding the systemd-networkd config
+    /// files to write
+    fn as_networkd_config(&self) -> NetworkDConfig;
 }
 
 impl<I: Interfaces> Interfaces for Box<I> {
@@ -51,6 +59,10 @@ impl<I: Interfaces> Interfaces for Box<I> {
     fn as_wicked_interfaces(&self) -> Vec<WickedInterface> {
         (**self).as_wicked_interfaces()
     }
+
+    fn as_networkd_config(&self) -> NetworkDConfig {
+        (**self).as_networkd_config()
+    }
 }
 
 /// This private trait must also be implemented by each new version of network config.  It is used
@@ -84,6 +96,22 @@ where
     Ok(Some(net_config))
 }
 
+/// Read the configuration of each interface in a network config file as it was written, without
+/// interpreting it, for reporting
+pub(crate) fn raw_interface_configs<P>(path: P) -> Result<Vec<(String, toml::Value)>>
+where
+    P: AsRef<Path>,
+{
+    let path = path.as_ref();
+    let net_config_str =
+        fs::read_to_string(path).context(error::NetConfigReadFailedSnafu { path })?;
+    let mut net_config: toml::value::Table =
+        toml::from_str(&net_config_str).context(error::NetConfigParseSnafu)?;
+    net_config.remove("version");
+
+    Ok(net_config.into_iter().collect())
+}
+
 /// Deserialize the network config, using the version key to determine which config struct to
 /// deserialize into
 fn deserialize_config(config_str: &str) -> Result<Box<dyn Interfaces>> {
@@ -103,6 +131,7 @@ fn deserialize_config(config_str: &str) -> Result<Box<dyn Interfaces>> {
         1 => validate_config::<v1::NetConfigV1>(interface_config)?,
         2 => validate_config::<v2::NetConfigV2>(interface_config)?,
         3 => validate_config::<v3::NetConfigV3>(interface_config)?,
+        4 => validate_config::<v4::NetConfigV4>(interface_config)?,
         _ => {
             return error::InvalidNetConfigSnafu {
                 reason: format!("Unknown network config version: {}", version),
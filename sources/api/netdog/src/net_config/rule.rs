@@ -0,0 +1,54 @@
+use super::error::{InvalidNetConfigSnafu, Result};
+use crate::net_config::Validate;
+use ipnet::IpNet;
+use serde::Deserialize;
+use snafu::ensure;
+
+/// A routing policy rule, which looks up routes for matching traffic in the given table.  Rules
+/// were added in net config v4.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RuleV1 {
+    pub(crate) from: Option<IpNet>,
+    pub(crate) to: Option<IpNet>,
+    pub(crate) table: u32,
+    pub(crate) priority: Option<u32>,
+}
+
+impl RuleV1 {
+    /// Returns true if the rule matches IPv6 traffic.  Validation ensures `from` and `to` are of
+    /// the same family, and that at least one of them is set.
+    pub(crate) fn is_ipv6(&self) -> bool {
+        matches!(self.from.or(self.to), Some(IpNet::V6(_)))
+    }
+}
+
+impl Validate for RuleV1 {
+    fn validate(&self) -> Result<()> {
+        ensure!(
+            self.from.is_some() || self.to.is_some(),
+            InvalidNetConfigSnafu {
+                reason: "rules must match traffic using 'from' and/or 'to'"
+            }
+        );
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            ensure!(
+                matches!(
+                    (from, to),
+                    (IpNet::V4(_), IpNet::V4(_)) | (IpNet::V6(_), IpNet::V6(_))
+                ),
+                InvalidNetConfigSnafu {
+                    reason: "rule 'from' and 'to' must both be IPv4 or both be IPv6"
+                }
+            );
+        }
+        // Table 0 is reserved by the kernel to mean "unspecified"
+        ensure!(
+            self.table > 0,
+            InvalidNetConfigSnafu {
+                reason: "rule table must be between 1 and 4294967295"
+            }
+        );
+        Ok(())
+    }
+}
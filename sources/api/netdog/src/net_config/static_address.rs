@@ -23,6 +23,31 @@ pub(crate) struct RouteV1 {
     pub(crate) route_metric: Option<u32>,
 }
 
+/// Starting with net config v4, a route may be added to a routing table other than `main`, for
+/// use with policy routing rules.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RouteV2 {
+    pub(crate) to: RouteTo,
+    pub(crate) from: Option<IpAddr>,
+    pub(crate) via: Option<IpAddr>,
+    #[serde(rename = "route-metric")]
+    pub(crate) route_metric: Option<u32>,
+    pub(crate) table: Option<u32>,
+}
+
+impl From<RouteV1> for RouteV2 {
+    fn from(route: RouteV1) -> Self {
+        RouteV2 {
+            to: route.to,
+            from: route.from,
+            via: route.via,
+            route_metric: route.route_metric,
+            table: None,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(try_from = "String")]
 pub(crate) enum RouteTo {
@@ -63,6 +88,19 @@ impl Validate for StaticConfigV1 {
     }
 }
 
+impl Validate for RouteV2 {
+    fn validate(&self) -> ValidateResult<()> {
+        // Table 0 is reserved by the kernel to mean "unspecified"
+        ensure!(
+            self.table != Some(0),
+            InvalidNetConfigSnafu {
+                reason: "route table must be between 1 and 4294967295"
+            }
+        );
+        Ok(())
+    }
+}
+
 mod error {
     use snafu::Snafu;
 
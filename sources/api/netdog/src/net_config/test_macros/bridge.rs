@@ -0,0 +1,60 @@
+macro_rules! bridge_tests {
+    ($version:expr) => {
+        mod bridge {
+            use $crate::net_config::deserialize_config;
+            use $crate::net_config::test_macros::gen_boilerplate;
+
+            gen_boilerplate!($version, "bridge");
+
+            #[test]
+            fn ok_config() {
+                let ok = net_config().join("net_config.toml");
+                let rendered = render_config_template(ok);
+                assert!(deserialize_config(&rendered).is_ok())
+            }
+
+            #[test]
+            fn missing_kind() {
+                let bad = net_config().join("missing_kind.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn no_interfaces() {
+                let bad = net_config().join("no_interfaces.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn mac_as_identifier() {
+                let bad = net_config().join("mac_as_identifier.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn mac_in_interfaces_list() {
+                let bad = net_config().join("mac_in_interfaces.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn port_manually_configured() {
+                let bad = net_config().join("port_configured.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn port_in_bond() {
+                let bad = net_config().join("port_in_bond.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+        }
+    };
+}
+pub(crate) use bridge_tests;
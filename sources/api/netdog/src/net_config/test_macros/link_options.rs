@@ -0,0 +1,97 @@
+//! The link_options_tests macro contains tests for the MTU, routing table and routing policy rule
+//! options available to devices starting with net config v4.
+macro_rules! link_options_tests {
+    ($version:expr) => {
+        mod link_options {
+            use $crate::net_config::deserialize_config;
+            use $crate::net_config::test_macros::gen_boilerplate;
+
+            gen_boilerplate!($version, "link_options");
+
+            #[test]
+            fn ok_config() {
+                let ok = net_config().join("net_config.toml");
+                let rendered = render_config_template(ok);
+                assert!(deserialize_config(&rendered).is_ok())
+            }
+
+            #[test]
+            fn mtu_too_small() {
+                let bad = net_config().join("mtu_too_small.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn mtu_too_large() {
+                let bad = net_config().join("mtu_too_large.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn ipv6_small_mtu() {
+                let bad = net_config().join("ipv6_small_mtu.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn vlan_mtu_larger_than_device() {
+                let bad = net_config().join("vlan_mtu_larger_than_device.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn route_zero_table() {
+                let bad = net_config().join("route_zero_table.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn rule_zero_table() {
+                let bad = net_config().join("rule_zero_table.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn rule_no_table() {
+                let bad = net_config().join("rule_no_table.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn rule_no_match() {
+                let bad = net_config().join("rule_no_match.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn rule_mixed_family() {
+                let bad = net_config().join("rule_mixed_family.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn rule_no_static() {
+                let bad = net_config().join("rule_no_static.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+
+            #[test]
+            fn rule_wrong_family() {
+                let bad = net_config().join("rule_wrong_family.toml");
+                let rendered = render_config_template(bad);
+                assert!(deserialize_config(&rendered).is_err())
+            }
+        }
+    };
+}
+pub(crate) use link_options_tests;
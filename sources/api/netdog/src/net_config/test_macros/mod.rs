@@ -16,15 +16,21 @@ pub(super) mod basic;
 #[cfg(test)]
 pub(super) mod bonding;
 #[cfg(test)]
+pub(super) mod bridge;
+#[cfg(test)]
 pub(super) mod dhcp;
 #[cfg(test)]
+pub(super) mod link_options;
+#[cfg(test)]
 pub(super) mod static_address;
 #[cfg(test)]
 pub(super) mod vlan;
 
 pub(super) use basic::basic_tests;
 pub(super) use bonding::bonding_tests;
+pub(super) use bridge::bridge_tests;
 pub(super) use dhcp::dhcp_tests;
+pub(super) use link_options::link_options_tests;
 pub(super) use static_address::static_address_tests;
 pub(super) use vlan::vlan_tests;
 
@@ -5,6 +5,7 @@ use super::{error, Dhcp4ConfigV1, Dhcp6ConfigV1, Error, Interfaces, Result, Vali
 use crate::{
     interface_id::{InterfaceId, InterfaceName},
     net_config::{Dhcp4OptionsV1, Dhcp6OptionsV1},
+    networkd::{NetworkDConfig, NetworkDDhcp4, NetworkDDhcp6},
     wicked::{WickedDhcp4, WickedDhcp6, WickedInterface},
 };
 use indexmap::indexmap;
@@ -64,6 +65,17 @@ impl Interfaces for NetConfigV1 {
 
         wicked_interfaces
     }
+
+    fn as_networkd_config(&self) -> NetworkDConfig {
+        let mut networkd_config = NetworkDConfig::new();
+        for (name, config) in &self.interfaces {
+            let network = networkd_config.network_mut(name.clone());
+            network.dhcp4 = config.dhcp4.clone().map(NetworkDDhcp4::from);
+            network.dhcp6 = config.dhcp6.clone().map(NetworkDDhcp6::from);
+        }
+
+        networkd_config
+    }
 }
 
 impl Validate for NetConfigV1 {
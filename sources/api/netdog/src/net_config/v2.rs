@@ -4,6 +4,7 @@
 use super::{error, Interfaces, Result, Validate};
 use crate::interface_id::{InterfaceId, InterfaceName};
 use crate::net_config::devices::interface::NetInterfaceV2;
+use crate::networkd::NetworkDConfig;
 use crate::wicked::{
     wicked_from, WickedDhcp4, WickedDhcp6, WickedInterface, WickedRoutes, WickedStaticAddress,
 };
@@ -40,6 +41,15 @@ impl Interfaces for NetConfigV2 {
 
         wicked_interfaces
     }
+
+    fn as_networkd_config(&self) -> NetworkDConfig {
+        let mut networkd_config = NetworkDConfig::new();
+        for (name, config) in &self.interfaces {
+            networkd_config.add_interface(name.clone(), config);
+        }
+
+        networkd_config
+    }
 }
 
 impl Validate for NetConfigV2 {
@@ -4,6 +4,7 @@
 use super::devices::NetworkDeviceV1;
 use super::{error, Interfaces, Result, Validate};
 use crate::interface_id::{InterfaceId, InterfaceName};
+use crate::networkd::NetworkDConfig;
 use crate::wicked::{WickedInterface, WickedLinkConfig};
 use indexmap::IndexMap;
 use serde::Deserialize;
@@ -44,7 +45,8 @@ impl Interfaces for NetConfigV3 {
                 for device in &b.interfaces {
                     let mut wicked_sub_interface = WickedInterface::new(device.clone());
                     wicked_sub_interface.link = Some(WickedLinkConfig {
-                        master: name.clone(),
+                        master: Some(name.clone()),
+                        mtu: None,
                     });
 
                     wicked_interfaces.push(wicked_sub_interface)
@@ -56,6 +58,15 @@ impl Interfaces for NetConfigV3 {
 
         wicked_interfaces
     }
+
+    fn as_networkd_config(&self) -> NetworkDConfig {
+        let mut networkd_config = NetworkDConfig::new();
+        for (name, config) in &self.net_devices {
+            networkd_config.add_device(name, config);
+        }
+
+        networkd_config
+    }
 }
 
 #[allow(clippy::to_string_in_format_args)]
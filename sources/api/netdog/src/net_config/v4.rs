@@ -0,0 +1,186 @@
+//! The `v4` module contains the fourth version of the network configuration and implements the
+//! appropriate traits.
+
+use super::devices::NetworkDeviceV2;
+use super::{error, Interfaces, Result, Validate};
+use crate::interface_id::{InterfaceId, InterfaceName};
+use crate::networkd::NetworkDConfig;
+use crate::wicked::{WickedInterface, WickedLinkConfig};
+use indexmap::IndexMap;
+use serde::Deserialize;
+use snafu::ensure;
+use std::collections::HashSet;
+
+#[derive(Debug, Deserialize)]
+pub(crate) struct NetConfigV4 {
+    #[serde(flatten)]
+    pub(crate) net_devices: IndexMap<InterfaceId, NetworkDeviceV2>,
+}
+
+impl Interfaces for NetConfigV4 {
+    fn primary_interface(&self) -> Option<InterfaceId> {
+        self.net_devices
+            .iter()
+            .find(|(_, v)| v.primary() == Some(true))
+            .or_else(|| self.net_devices.first())
+            .map(|(n, _)| n.clone())
+    }
+
+    fn has_interfaces(&self) -> bool {
+        !self.net_devices.is_empty()
+    }
+
+    fn as_wicked_interfaces(&self) -> Vec<WickedInterface> {
+        let mut wicked_interfaces = Vec::new();
+        for (name, config) in &self.net_devices {
+            let interface = WickedInterface::from((name, config));
+
+            // Bonds and bridges consume their member interfaces, so we generate the configuration
+            // for those interfaces here.  Members take the MTU of the device they belong to; a
+            // bridge can't use a larger MTU than its ports.
+            // At this point we can be sure that bonds and bridges are being configured with a name
+            // rather than a MAC address since that validation happens during
+            // deserialize/validation.
+            let members = match config {
+                NetworkDeviceV2::BondDevice(b) => b.interfaces.as_slice(),
+                NetworkDeviceV2::BridgeDevice(b) => b.interfaces.as_slice(),
+                _ => &[],
+            };
+            if let InterfaceId::Name(name) = name {
+                for device in members {
+                    let mut wicked_sub_interface = WickedInterface::new(device.clone());
+                    wicked_sub_interface.link = Some(WickedLinkConfig {
+                        master: Some(name.clone()),
+                        mtu: config.mtu(),
+                    });
+
+                    wicked_interfaces.push(wicked_sub_interface)
+                }
+            }
+
+            wicked_interfaces.push(interface)
+        }
+
+        wicked_interfaces
+    }
+
+    fn as_networkd_config(&self) -> NetworkDConfig {
+        let mut networkd_config = NetworkDConfig::new();
+        for (name, config) in &self.net_devices {
+            networkd_config.add_device_v2(name, config);
+        }
+
+        networkd_config
+    }
+}
+
+#[allow(clippy::to_string_in_format_args)]
+impl Validate for NetConfigV4 {
+    fn validate(&self) -> Result<()> {
+        // Create HashSet of known device names for checking duplicates
+        let mut interface_names: HashSet<&InterfaceName> = self
+            .net_devices
+            .keys()
+            .filter_map(|i| match i {
+                InterfaceId::Name(name) => Some(name),
+                _ => None,
+            })
+            .collect();
+        for (_name, device) in &self.net_devices {
+            if let NetworkDeviceV2::VlanDevice(vlan) = device {
+                // It is valid to stack more than one vlan on a single device, but we need them all
+                // for checking bonds and bridges which can't share devices.
+                interface_names.insert(&vlan.device);
+            }
+        }
+
+        for (name, device) in &self.net_devices {
+            // Bonds / vlans / bridges cannot be configured via MAC address as it is unsupported
+            // in wicked
+            if let NetworkDeviceV2::BondDevice(_)
+            | NetworkDeviceV2::VlanDevice(_)
+            | NetworkDeviceV2::BridgeDevice(_) = device
+            {
+                ensure!(
+                    !matches!(name, InterfaceId::MacAddress(_)),
+                    error::InvalidNetConfigSnafu {
+                        reason: "bonds, vlans and bridges may not be configured using MAC address"
+                    }
+                )
+            };
+
+            // Bonds and bridges create the interfaces automatically, specifying those interfaces
+            // would cause a collision so this emits an error for any that are found
+            let (kind, members) = match device {
+                NetworkDeviceV2::BondDevice(config) => ("bond", config.interfaces.as_slice()),
+                NetworkDeviceV2::BridgeDevice(config) => ("bridge", config.interfaces.as_slice()),
+                _ => ("", &[][..]),
+            };
+            for interface in members {
+                if !interface_names.insert(interface) {
+                    return error::InvalidNetConfigSnafu {
+                        reason: format!(
+                            "{} in {} {} cannot be manually configured",
+                            interface.to_string(),
+                            kind,
+                            name.to_string()
+                        ),
+                    }
+                    .fail();
+                }
+            }
+
+            // A VLAN can't send larger frames than the device it sits on
+            if let NetworkDeviceV2::VlanDevice(vlan) = device {
+                let parent_mtu = self
+                    .net_devices
+                    .get(&InterfaceId::from(vlan.device.clone()))
+                    .and_then(|parent| parent.mtu());
+                if let (Some(mtu), Some(parent_mtu)) = (vlan.mtu, parent_mtu) {
+                    ensure!(
+                        mtu <= parent_mtu,
+                        error::InvalidNetConfigSnafu {
+                            reason: format!(
+                                "mtu of vlan {} is larger than the mtu of {}",
+                                name.to_string(),
+                                vlan.device.to_string()
+                            )
+                        }
+                    );
+                }
+            }
+
+            device.validate()?;
+        }
+
+        let primary_count = self
+            .net_devices
+            .values()
+            .filter(|v| v.primary() == Some(true))
+            .count();
+        ensure!(
+            primary_count <= 1,
+            error::InvalidNetConfigSnafu {
+                reason: "multiple primary interfaces defined, expected 1"
+            }
+        );
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::net_config::test_macros::{
+        basic_tests, bonding_tests, bridge_tests, dhcp_tests, link_options_tests,
+        static_address_tests, vlan_tests,
+    };
+
+    basic_tests!(4);
+    dhcp_tests!(4);
+    static_address_tests!(4);
+    vlan_tests!(4);
+    bonding_tests!(4);
+    bridge_tests!(4);
+    link_options_tests!(4);
+}
@@ -0,0 +1,39 @@
+use super::{write_sections, NetworkDConfigFile, Section, NETWORKD_FILE_PREFIX};
+use crate::interface_id::InterfaceName;
+use std::fmt::{self, Display};
+
+/// A `.link` file for a virtual device.  systemd's default link policy gives bonds, VLANs and
+/// bridges a generated MAC address; like wicked, we want them to use the MAC address of the device
+/// under them, so that DHCP reservations and ARP tables keyed on that address still work.
+#[derive(Debug, PartialEq)]
+pub(crate) struct NetworkDLink {
+    name: InterfaceName,
+}
+
+impl NetworkDLink {
+    pub(crate) fn new(name: InterfaceName) -> Self {
+        Self { name }
+    }
+
+    fn sections(&self) -> Vec<Section> {
+        let mut match_section = Section::new("Match");
+        match_section.add("OriginalName", &*self.name);
+
+        let mut link = Section::new("Link");
+        link.add("MACAddressPolicy", "none");
+
+        vec![match_section, link]
+    }
+}
+
+impl NetworkDConfigFile for NetworkDLink {
+    fn file_name(&self) -> String {
+        format!("{}{}.link", NETWORKD_FILE_PREFIX, &*self.name)
+    }
+}
+
+impl Display for NetworkDLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_sections(f, &self.sections())
+    }
+}
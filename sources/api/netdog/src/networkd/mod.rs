@@ -0,0 +1,416 @@
+//! The networkd module contains the data structures and functions needed to create network
+//! configuration files for systemd-networkd.
+//!
+//! The structures in this module are meant to be created from the user-facing structures in the
+//! `net_config` module.  Each interface gets a `.network` file; bonds, VLANs and bridges also get
+//! a `.netdev` file to create the device, and a `.link` file to set its properties.
+mod link;
+mod netdev;
+mod network;
+
+use crate::interface_id::{InterfaceId, InterfaceName};
+use crate::net_config::devices::bonding::{NetBondV1, NetBondV2};
+use crate::net_config::devices::bridge::NetBridgeV1;
+use crate::net_config::devices::interface::{NetInterfaceV2, NetInterfaceV3};
+use crate::net_config::devices::vlan::{NetVlanV1, NetVlanV2};
+use crate::net_config::devices::{NetworkDeviceV1, NetworkDeviceV2};
+pub(crate) use error::Error;
+use link::NetworkDLink;
+use netdev::NetworkDNetDev;
+pub(crate) use network::{NetworkDDhcp4, NetworkDDhcp6, NetworkDNetwork};
+use snafu::ResultExt;
+use std::fmt::{self, Display};
+use std::fs;
+use std::path::Path;
+
+const NETWORKD_CONFIG_DIR: &str = "/etc/systemd/network";
+// systemd-networkd and udev use the first file matching a device, in lexical order, so the prefix
+// keeps our files ahead of the defaults shipped with systemd
+const NETWORKD_FILE_PREFIX: &str = "10-";
+
+/// The full systemd-networkd configuration for a network config.
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct NetworkDConfig {
+    networks: Vec<NetworkDNetwork>,
+    netdevs: Vec<NetworkDNetDev>,
+    links: Vec<NetworkDLink>,
+}
+
+impl NetworkDConfig {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the `.network` config for the given device, creating it if needed.  A device may
+    /// be mentioned more than once, for example as an interface and as the parent of a VLAN.
+    pub(crate) fn network_mut<I>(&mut self, id: I) -> &mut NetworkDNetwork
+    where
+        I: Into<InterfaceId>,
+    {
+        let id = id.into();
+        match self.networks.iter().position(|n| *n.id() == id) {
+            Some(index) => &mut self.networks[index],
+            None => {
+                self.networks.push(NetworkDNetwork::new(id));
+                self.networks.last_mut().unwrap()
+            }
+        }
+    }
+
+    pub(crate) fn add_device(&mut self, id: &InterfaceId, device: &NetworkDeviceV1) {
+        match (id, device) {
+            (_, NetworkDeviceV1::Interface(config)) => self.add_interface(id.clone(), config),
+            (InterfaceId::Name(name), NetworkDeviceV1::BondDevice(config)) => {
+                self.add_bond(name, config)
+            }
+            (InterfaceId::Name(name), NetworkDeviceV1::VlanDevice(config)) => {
+                self.add_vlan(name, config)
+            }
+            // Bonds and VLANs can't be configured using a MAC address; validation of the network
+            // config ensures we don't get here.
+            (InterfaceId::MacAddress(_), _) => {}
+        }
+    }
+
+    pub(crate) fn add_device_v2(&mut self, id: &InterfaceId, device: &NetworkDeviceV2) {
+        match (id, device) {
+            (_, NetworkDeviceV2::Interface(config)) => self.add_interface_v3(id.clone(), config),
+            (InterfaceId::Name(name), NetworkDeviceV2::BondDevice(config)) => {
+                self.add_bond_v2(name, config)
+            }
+            (InterfaceId::Name(name), NetworkDeviceV2::VlanDevice(config)) => {
+                self.add_vlan_v2(name, config)
+            }
+            (InterfaceId::Name(name), NetworkDeviceV2::BridgeDevice(config)) => {
+                self.add_bridge(name, config)
+            }
+            // Bonds, VLANs and bridges can't be configured using a MAC address; validation of the
+            // network config ensures we don't get here.
+            (InterfaceId::MacAddress(_), _) => {}
+        }
+    }
+
+    pub(crate) fn add_interface<I>(&mut self, id: I, config: &NetInterfaceV2)
+    where
+        I: Into<InterfaceId>,
+    {
+        self.network_mut(id).set_addressing(
+            &config.dhcp4,
+            &config.dhcp6,
+            &config.static4,
+            &config.static6,
+            &config.routes,
+        );
+    }
+
+    fn add_interface_v3<I>(&mut self, id: I, config: &NetInterfaceV3)
+    where
+        I: Into<InterfaceId>,
+    {
+        let network = self.network_mut(id);
+        network.set_addressing(
+            &config.dhcp4,
+            &config.dhcp6,
+            &config.static4,
+            &config.static6,
+            &config.routes,
+        );
+        network.set_link_options(config.mtu, &config.rules);
+    }
+
+    fn add_bond(&mut self, name: &InterfaceName, config: &NetBondV1) {
+        self.network_mut(name.clone()).set_addressing(
+            &config.dhcp4,
+            &config.dhcp6,
+            &config.static4,
+            &config.static6,
+            &config.routes,
+        );
+        let netdev = NetworkDNetDev::bond(
+            name.clone(),
+            &config.mode,
+            config.min_links,
+            &config.monitoring_config,
+        );
+        self.add_bond_netdev(name, netdev, &config.interfaces, None);
+    }
+
+    fn add_bond_v2(&mut self, name: &InterfaceName, config: &NetBondV2) {
+        let network = self.network_mut(name.clone());
+        network.set_addressing(
+            &config.dhcp4,
+            &config.dhcp6,
+            &config.static4,
+            &config.static6,
+            &config.routes,
+        );
+        network.set_link_options(config.mtu, &config.rules);
+        let netdev = NetworkDNetDev::bond(
+            name.clone(),
+            &config.mode,
+            config.min_links,
+            &config.monitoring_config,
+        );
+        self.add_bond_netdev(name, netdev, &config.interfaces, config.mtu);
+    }
+
+    fn add_bond_netdev(
+        &mut self,
+        name: &InterfaceName,
+        netdev: NetworkDNetDev,
+        interfaces: &[InterfaceName],
+        mtu: Option<u32>,
+    ) {
+        self.netdevs.push(netdev);
+        self.links.push(NetworkDLink::new(name.clone()));
+
+        // The bond consumes its interfaces, so we generate their configuration here.  The first
+        // interface is the primary.
+        for (i, device) in interfaces.iter().enumerate() {
+            let member = self.network_mut(device.clone());
+            member.bond = Some(name.clone());
+            member.primary_bond_member = i == 0;
+            member.mtu = mtu;
+        }
+    }
+
+    fn add_vlan(&mut self, name: &InterfaceName, config: &NetVlanV1) {
+        self.network_mut(name.clone()).set_addressing(
+            &config.dhcp4,
+            &config.dhcp6,
+            &config.static4,
+            &config.static6,
+            &config.routes,
+        );
+        self.add_vlan_netdev(name, &config.device, config.id);
+    }
+
+    fn add_vlan_v2(&mut self, name: &InterfaceName, config: &NetVlanV2) {
+        let network = self.network_mut(name.clone());
+        network.set_addressing(
+            &config.dhcp4,
+            &config.dhcp6,
+            &config.static4,
+            &config.static6,
+            &config.routes,
+        );
+        network.set_link_options(config.mtu, &config.rules);
+        self.add_vlan_netdev(name, &config.device, config.id);
+    }
+
+    fn add_vlan_netdev(&mut self, name: &InterfaceName, device: &InterfaceName, id: u16) {
+        self.netdevs.push(NetworkDNetDev::vlan(name.clone(), id));
+        self.links.push(NetworkDLink::new(name.clone()));
+
+        // The VLAN is created by the `.network` file of the device it sits on, which may not be
+        // configured otherwise
+        self.network_mut(device.clone()).vlans.push(name.clone());
+    }
+
+    fn add_bridge(&mut self, name: &InterfaceName, config: &NetBridgeV1) {
+        let network = self.network_mut(name.clone());
+        network.set_addressing(
+            &config.dhcp4,
+            &config.dhcp6,
+            &config.static4,
+            &config.static6,
+            &config.routes,
+        );
+        network.set_link_options(config.mtu, &config.rules);
+        self.netdevs.push(NetworkDNetDev::bridge(
+            name.clone(),
+            config.stp.unwrap_or(false),
+        ));
+        self.links.push(NetworkDLink::new(name.clone()));
+
+        // Like bonds, the bridge consumes its ports.  They take the bridge's MTU, since a bridge
+        // can't use a larger MTU than its ports.
+        for device in &config.interfaces {
+            let port = self.network_mut(device.clone());
+            port.bridge = Some(name.clone());
+            port.mtu = config.mtu;
+        }
+    }
+
+    /// Returns all of the config files, in the order they were added
+    pub(crate) fn config_files(&self) -> impl Iterator<Item = &dyn NetworkDConfigFile> {
+        self.networks
+            .iter()
+            .map(|n| n as &dyn NetworkDConfigFile)
+            .chain(self.netdevs.iter().map(|n| n as &dyn NetworkDConfigFile))
+            .chain(self.links.iter().map(|l| l as &dyn NetworkDConfigFile))
+    }
+
+    /// Write all of the config files to the systemd-networkd config directory
+    pub(crate) fn write_config_files(&self) -> Result<()> {
+        for config_file in self.config_files() {
+            let cfg_path = Path::new(NETWORKD_CONFIG_DIR).join(config_file.file_name());
+            fs::write(&cfg_path, config_file.to_string())
+                .context(error::NetworkDConfigWriteSnafu { path: cfg_path })?;
+        }
+        Ok(())
+    }
+}
+
+/// Implemented by each type of systemd-networkd config file; the `Display` implementation gives
+/// the contents of the file.
+pub(crate) trait NetworkDConfigFile: Display {
+    /// The name of the file, including its extension
+    fn file_name(&self) -> String;
+}
+
+/// A section of a systemd-networkd config file, like `[Network]`, with its settings in order.
+/// Settings like `Address` may be given more than once.
+#[derive(Debug)]
+struct Section {
+    name: &'static str,
+    settings: Vec<(&'static str, String)>,
+}
+
+impl Section {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            settings: Vec::new(),
+        }
+    }
+
+    fn add<V>(&mut self, key: &'static str, value: V)
+    where
+        V: Display,
+    {
+        self.settings.push((key, value.to_string()))
+    }
+}
+
+/// Writes sections in the INI-like format used by systemd, leaving out sections with no settings
+fn write_sections(f: &mut fmt::Formatter<'_>, sections: &[Section]) -> fmt::Result {
+    let mut first = true;
+    for section in sections.iter().filter(|s| !s.settings.is_empty()) {
+        if !first {
+            writeln!(f)?;
+        }
+        first = false;
+
+        writeln!(f, "[{}]", section.name)?;
+        for (key, value) in &section.settings {
+            writeln!(f, "{}={}", key, value)?;
+        }
+    }
+    Ok(())
+}
+
+mod error {
+    use snafu::Snafu;
+    use std::io;
+    use std::path::PathBuf;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(crate)))]
+    pub(crate) enum Error {
+        #[snafu(display("Failed to write network configuration to '{}': {}", path.display(), source))]
+        NetworkDConfigWrite { path: PathBuf, source: io::Error },
+    }
+}
+
+type Result<T> = std::result::Result<T, error::Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net_config::{self, Interfaces, NetConfigV1};
+    use handlebars::Handlebars;
+    use serde::Serialize;
+    use std::path::PathBuf;
+    use std::str::FromStr;
+
+    static NET_CONFIG_VERSIONS: &[u8] = &[1, 2, 3, 4];
+
+    fn test_data() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_data")
+    }
+
+    fn networkd_test_data() -> PathBuf {
+        test_data().join("networkd")
+    }
+
+    // Test the end-to-end trip: "net config from cmdline -> networkd -> config files"
+    #[test]
+    fn interface_config_from_str() {
+        // Interface names here coincide with config files, some of which are shared with the
+        // `net_config` test below
+        let ok = &[
+            "eno1:dhcp4",
+            "eno2:dhcp6",
+            "eno9:dhcp4?",
+            "eno10:dhcp6?",
+            "eno5:dhcp4,dhcp6",
+            "eno5:dhcp6,dhcp4",
+            "eno7:dhcp4,dhcp6?",
+            "eno7:dhcp6?,dhcp4",
+            "eno8:dhcp6?,dhcp4?",
+            "eno8:dhcp4?,dhcp6?",
+        ];
+        for ok_str in ok {
+            let net_config = NetConfigV1::from_str(ok_str).unwrap();
+
+            let networkd_config = net_config.as_networkd_config();
+            for config_file in networkd_config.config_files() {
+                let path = networkd_test_data().join(config_file.file_name());
+                let expected = fs::read_to_string(path).unwrap();
+
+                assert_eq!(expected, config_file.to_string())
+            }
+        }
+    }
+
+    // Test the end to end trip: "net config -> networkd -> config files"
+    #[test]
+    fn net_config_to_interface_config() {
+        let net_config_path = networkd_test_data().join("net_config.toml");
+
+        for version in NET_CONFIG_VERSIONS {
+            let temp_config = tempfile::NamedTempFile::new().unwrap();
+
+            render_config_template(&net_config_path, &temp_config, version);
+            let net_config = net_config::from_path(&temp_config).unwrap().unwrap();
+            let networkd_config = net_config.as_networkd_config();
+            for config_file in networkd_config.config_files() {
+                let file_name = config_file.file_name();
+                let path = networkd_test_data().join(&file_name);
+                let expected = fs::read_to_string(path).unwrap();
+
+                assert_eq!(
+                    expected,
+                    config_file.to_string(),
+                    "failed test for net config version: '{}', file: '{}'",
+                    version,
+                    file_name
+                )
+            }
+        }
+    }
+
+    fn render_config_template<P1, P2>(template_path: P1, output_path: P2, version: &u8)
+    where
+        P1: AsRef<Path>,
+        P2: AsRef<Path>,
+    {
+        #[derive(Serialize)]
+        struct Context {
+            version: u8,
+        }
+
+        let output_path = output_path.as_ref();
+        let template_path = template_path.as_ref();
+        let template_str = fs::read_to_string(template_path).unwrap();
+
+        let mut hb = Handlebars::new();
+        hb.register_template_string("template", &template_str)
+            .unwrap();
+
+        let context = Context { version: *version };
+        let rendered = hb.render("template", &context).unwrap();
+        fs::write(output_path, rendered).unwrap()
+    }
+}
@@ -0,0 +1,182 @@
+use super::{write_sections, NetworkDConfigFile, Section, NETWORKD_FILE_PREFIX};
+use crate::interface_id::InterfaceName;
+use crate::net_config::devices::bonding::{
+    ArpMonitoringConfig, ArpValidate, BondMode, BondMonitoringConfig, MiiMonitoringConfig,
+};
+use std::fmt::{self, Display};
+
+/// A `.netdev` file, which creates a virtual device like a bond, VLAN or bridge
+#[derive(Debug, PartialEq)]
+pub(crate) struct NetworkDNetDev {
+    name: InterfaceName,
+    kind: NetDevKind,
+}
+
+#[derive(Debug, PartialEq)]
+enum NetDevKind {
+    Bond(NetworkDBond),
+    Vlan { id: u16 },
+    Bridge { stp: bool },
+}
+
+impl NetworkDNetDev {
+    pub(crate) fn bond(
+        name: InterfaceName,
+        mode: &BondMode,
+        min_links: Option<usize>,
+        monitoring_config: &BondMonitoringConfig,
+    ) -> Self {
+        Self {
+            name,
+            kind: NetDevKind::Bond(NetworkDBond::new(mode, min_links, monitoring_config)),
+        }
+    }
+
+    pub(crate) fn vlan(name: InterfaceName, id: u16) -> Self {
+        Self {
+            name,
+            kind: NetDevKind::Vlan { id },
+        }
+    }
+
+    pub(crate) fn bridge(name: InterfaceName, stp: bool) -> Self {
+        Self {
+            name,
+            kind: NetDevKind::Bridge { stp },
+        }
+    }
+
+    fn sections(&self) -> Vec<Section> {
+        let mut netdev = Section::new("NetDev");
+        netdev.add("Name", &*self.name);
+        match &self.kind {
+            NetDevKind::Bond(bond) => {
+                netdev.add("Kind", "bond");
+                vec![netdev, bond.section()]
+            }
+            NetDevKind::Vlan { id } => {
+                netdev.add("Kind", "vlan");
+                let mut vlan = Section::new("VLAN");
+                vlan.add("Id", id);
+                vec![netdev, vlan]
+            }
+            NetDevKind::Bridge { stp } => {
+                netdev.add("Kind", "bridge");
+                let mut bridge = Section::new("Bridge");
+                bridge.add("STP", if *stp { "yes" } else { "no" });
+                vec![netdev, bridge]
+            }
+        }
+    }
+}
+
+impl NetworkDConfigFile for NetworkDNetDev {
+    fn file_name(&self) -> String {
+        format!("{}{}.netdev", NETWORKD_FILE_PREFIX, &*self.name)
+    }
+}
+
+impl Display for NetworkDNetDev {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_sections(f, &self.sections())
+    }
+}
+
+/// The `[Bond]` section of a bond's `.netdev` file
+#[derive(Debug, PartialEq)]
+struct NetworkDBond {
+    mode: &'static str,
+    min_links: Option<usize>,
+    monitoring: NetworkDBondMonitoring,
+}
+
+#[derive(Debug, PartialEq)]
+enum NetworkDBondMonitoring {
+    MiiMon {
+        frequency: u32,
+        updelay: u32,
+        downdelay: u32,
+    },
+    ArpMon {
+        interval: u32,
+        validate: &'static str,
+        targets: Vec<String>,
+    },
+}
+
+impl NetworkDBond {
+    fn new(
+        mode: &BondMode,
+        min_links: Option<usize>,
+        monitoring_config: &BondMonitoringConfig,
+    ) -> Self {
+        let mode = match mode {
+            BondMode::ActiveBackup => "active-backup",
+        };
+        let monitoring = match monitoring_config {
+            BondMonitoringConfig::MiiMon(mii) => NetworkDBondMonitoring::from(mii),
+            BondMonitoringConfig::ArpMon(arp) => NetworkDBondMonitoring::from(arp),
+        };
+        Self {
+            mode,
+            min_links,
+            monitoring,
+        }
+    }
+
+    fn section(&self) -> Section {
+        let mut bond = Section::new("Bond");
+        bond.add("Mode", self.mode);
+        if let Some(min_links) = self.min_links {
+            bond.add("MinLinks", min_links);
+        }
+        // Intervals in networkd config are time spans, so we give the unit our config uses
+        match &self.monitoring {
+            NetworkDBondMonitoring::MiiMon {
+                frequency,
+                updelay,
+                downdelay,
+            } => {
+                bond.add("MIIMonitorSec", format!("{}ms", frequency));
+                bond.add("UpDelaySec", format!("{}ms", updelay));
+                bond.add("DownDelaySec", format!("{}ms", downdelay));
+            }
+            NetworkDBondMonitoring::ArpMon {
+                interval,
+                validate,
+                targets,
+            } => {
+                bond.add("ARPIntervalSec", format!("{}ms", interval));
+                bond.add("ARPValidate", validate);
+                bond.add("ARPIPTargets", targets.join(" "));
+            }
+        }
+        bond
+    }
+}
+
+impl From<&MiiMonitoringConfig> for NetworkDBondMonitoring {
+    fn from(config: &MiiMonitoringConfig) -> Self {
+        NetworkDBondMonitoring::MiiMon {
+            frequency: config.frequency,
+            updelay: config.updelay,
+            downdelay: config.downdelay,
+        }
+    }
+}
+
+impl From<&ArpMonitoringConfig> for NetworkDBondMonitoring {
+    fn from(config: &ArpMonitoringConfig) -> Self {
+        let validate = match config.validate {
+            ArpValidate::Active => "active",
+            ArpValidate::All => "all",
+            ArpValidate::Backup => "backup",
+            ArpValidate::None => "none",
+        };
+        NetworkDBondMonitoring::ArpMon {
+            interval: config.interval,
+            validate,
+            targets: config.targets.iter().map(|t| t.to_string()).collect(),
+        }
+    }
+}
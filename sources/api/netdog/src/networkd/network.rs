@@ -0,0 +1,336 @@
+use super::{write_sections, NetworkDConfigFile, Section, NETWORKD_FILE_PREFIX};
+use crate::interface_id::{InterfaceId, InterfaceName};
+use crate::net_config::{
+    Dhcp4ConfigV1, Dhcp4OptionsV1, Dhcp6ConfigV1, Dhcp6OptionsV1, RouteTo, RouteV2, RuleV1,
+    StaticConfigV1,
+};
+use ipnet::IpNet;
+use lazy_static::lazy_static;
+use std::fmt::{self, Display};
+use std::net::IpAddr;
+
+lazy_static! {
+    static ref DEFAULT_ROUTE_IPV4: IpNet = "0.0.0.0/0".parse().unwrap();
+    static ref DEFAULT_ROUTE_IPV6: IpNet = "::/0".parse().unwrap();
+}
+
+/// A `.network` file, which configures addressing for a single device.  Bond members, bridge ports
+/// and the parent devices of VLANs get a `.network` file too, to attach them to the bond, bridge
+/// or VLAN.
+#[derive(Debug, PartialEq)]
+pub(crate) struct NetworkDNetwork {
+    id: InterfaceId,
+    pub(crate) dhcp4: Option<NetworkDDhcp4>,
+    pub(crate) dhcp6: Option<NetworkDDhcp6>,
+    pub(crate) addresses: Vec<IpNet>,
+    pub(crate) routes: Vec<NetworkDRoute>,
+    pub(crate) rules: Vec<NetworkDRule>,
+    pub(crate) mtu: Option<u32>,
+    pub(crate) bond: Option<InterfaceName>,
+    pub(crate) primary_bond_member: bool,
+    pub(crate) bridge: Option<InterfaceName>,
+    pub(crate) vlans: Vec<InterfaceName>,
+}
+
+impl NetworkDNetwork {
+    pub(crate) fn new<I>(id: I) -> Self
+    where
+        I: Into<InterfaceId>,
+    {
+        Self {
+            id: id.into(),
+            dhcp4: None,
+            dhcp6: None,
+            addresses: Vec::new(),
+            routes: Vec::new(),
+            rules: Vec::new(),
+            mtu: None,
+            bond: None,
+            primary_bond_member: false,
+            bridge: None,
+            vlans: Vec::new(),
+        }
+    }
+
+    pub(crate) fn id(&self) -> &InterfaceId {
+        &self.id
+    }
+
+    /// Sets the DHCP and static addressing shared by all device types
+    pub(crate) fn set_addressing<R>(
+        &mut self,
+        dhcp4: &Option<Dhcp4ConfigV1>,
+        dhcp6: &Option<Dhcp6ConfigV1>,
+        static4: &Option<StaticConfigV1>,
+        static6: &Option<StaticConfigV1>,
+        routes: &Option<Vec<R>>,
+    ) where
+        R: Clone + Into<RouteV2>,
+    {
+        self.dhcp4 = dhcp4.clone().map(NetworkDDhcp4::from);
+        self.dhcp6 = dhcp6.clone().map(NetworkDDhcp6::from);
+        self.addresses = static4
+            .iter()
+            .chain(static6.iter())
+            .flat_map(|s| s.addresses.iter().cloned())
+            .collect();
+        self.routes = routes
+            .iter()
+            .flatten()
+            .cloned()
+            .map(|r| NetworkDRoute::from(r.into()))
+            .collect();
+    }
+
+    /// Sets the MTU and routing policy rules available to devices starting with net config v4
+    pub(crate) fn set_link_options(&mut self, mtu: Option<u32>, rules: &Option<Vec<RuleV1>>) {
+        self.mtu = mtu;
+        self.rules = rules
+            .iter()
+            .flatten()
+            .cloned()
+            .map(NetworkDRule::from)
+            .collect();
+    }
+
+    /// The value of the `DHCP` setting, if DHCP is enabled for either protocol
+    fn dhcp(&self) -> Option<&'static str> {
+        let dhcp4 = matches!(&self.dhcp4, Some(d) if d.enabled);
+        let dhcp6 = matches!(&self.dhcp6, Some(d) if d.enabled);
+        match (dhcp4, dhcp6) {
+            (true, true) => Some("yes"),
+            (true, false) => Some("ipv4"),
+            (false, true) => Some("ipv6"),
+            (false, false) => None,
+        }
+    }
+
+    /// Wicked doesn't wait for leases marked optional; systemd-networkd-wait-online does the same
+    /// if we tell it which address families are required for the link to be considered online.
+    fn online_settings(&self) -> Option<(&'static str, &'static str)> {
+        let optional4 = matches!(&self.dhcp4, Some(d) if d.enabled && d.optional);
+        let optional6 = matches!(&self.dhcp6, Some(d) if d.enabled && d.optional);
+        if !optional4 && !optional6 {
+            return None;
+        }
+
+        let required4 = matches!(&self.dhcp4, Some(d) if d.enabled && !d.optional)
+            || self.addresses.iter().any(|a| matches!(a, IpNet::V4(_)));
+        let required6 = matches!(&self.dhcp6, Some(d) if d.enabled && !d.optional)
+            || self.addresses.iter().any(|a| matches!(a, IpNet::V6(_)));
+        match (required4, required6) {
+            (true, true) => None,
+            (true, false) => Some(("RequiredFamilyForOnline", "ipv4")),
+            (false, true) => Some(("RequiredFamilyForOnline", "ipv6")),
+            (false, false) => Some(("RequiredForOnline", "no")),
+        }
+    }
+
+    fn sections(&self) -> Vec<Section> {
+        let mut match_section = Section::new("Match");
+        match &self.id {
+            InterfaceId::Name(name) => match_section.add("Name", &**name),
+            InterfaceId::MacAddress(mac) => match_section.add("PermanentMACAddress", &**mac),
+        }
+
+        let mut link = Section::new("Link");
+        if let Some(mtu) = self.mtu {
+            link.add("MTUBytes", mtu);
+        }
+        if let Some((key, value)) = self.online_settings() {
+            link.add(key, value);
+        }
+
+        let mut network = Section::new("Network");
+        if let Some(dhcp) = self.dhcp() {
+            network.add("DHCP", dhcp);
+        }
+        for address in &self.addresses {
+            network.add("Address", address);
+        }
+        if let Some(bond) = &self.bond {
+            network.add("Bond", &**bond);
+            if self.primary_bond_member {
+                network.add("PrimarySlave", "true");
+            }
+        }
+        if let Some(bridge) = &self.bridge {
+            network.add("Bridge", &**bridge);
+        }
+        for vlan in &self.vlans {
+            network.add("VLAN", &**vlan);
+        }
+
+        let mut dhcp4 = Section::new("DHCPv4");
+        if let Some(metric) = self.dhcp4.as_ref().and_then(|d| d.route_metric) {
+            dhcp4.add("RouteMetric", metric);
+        }
+
+        let mut sections = vec![match_section, link, network, dhcp4];
+        sections.extend(self.routes.iter().map(NetworkDRoute::section));
+        sections.extend(self.rules.iter().map(NetworkDRule::section));
+        sections
+    }
+}
+
+impl NetworkDConfigFile for NetworkDNetwork {
+    fn file_name(&self) -> String {
+        let name = match &self.id {
+            InterfaceId::Name(name) => name.to_string(),
+            InterfaceId::MacAddress(mac) => mac.replace(':', ""),
+        };
+        format!("{}{}.network", NETWORKD_FILE_PREFIX, name)
+    }
+}
+
+impl Display for NetworkDNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_sections(f, &self.sections())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NetworkDDhcp4 {
+    enabled: bool,
+    optional: bool,
+    route_metric: Option<u32>,
+}
+
+impl From<Dhcp4ConfigV1> for NetworkDDhcp4 {
+    fn from(dhcp4: Dhcp4ConfigV1) -> Self {
+        match dhcp4 {
+            Dhcp4ConfigV1::DhcpEnabled(b) => NetworkDDhcp4 {
+                enabled: b,
+                optional: false,
+                route_metric: None,
+            },
+            Dhcp4ConfigV1::WithOptions(o) => NetworkDDhcp4::from(o),
+        }
+    }
+}
+
+impl From<Dhcp4OptionsV1> for NetworkDDhcp4 {
+    fn from(options: Dhcp4OptionsV1) -> Self {
+        NetworkDDhcp4 {
+            enabled: options.enabled,
+            optional: options.optional == Some(true),
+            route_metric: options.route_metric,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NetworkDDhcp6 {
+    enabled: bool,
+    optional: bool,
+}
+
+impl From<Dhcp6ConfigV1> for NetworkDDhcp6 {
+    fn from(dhcp6: Dhcp6ConfigV1) -> Self {
+        match dhcp6 {
+            Dhcp6ConfigV1::DhcpEnabled(b) => NetworkDDhcp6 {
+                enabled: b,
+                optional: false,
+            },
+            Dhcp6ConfigV1::WithOptions(o) => NetworkDDhcp6::from(o),
+        }
+    }
+}
+
+impl From<Dhcp6OptionsV1> for NetworkDDhcp6 {
+    fn from(options: Dhcp6OptionsV1) -> Self {
+        NetworkDDhcp6 {
+            enabled: options.enabled,
+            optional: options.optional == Some(true),
+        }
+    }
+}
+
+/// A `[Route]` section of a `.network` file
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NetworkDRoute {
+    destination: IpNet,
+    gateway: Option<IpAddr>,
+    preferred_source: Option<IpAddr>,
+    metric: Option<u32>,
+    table: Option<u32>,
+}
+
+impl NetworkDRoute {
+    fn section(&self) -> Section {
+        let mut route = Section::new("Route");
+        route.add("Destination", self.destination);
+        if let Some(gateway) = self.gateway {
+            route.add("Gateway", gateway);
+        }
+        if let Some(preferred_source) = self.preferred_source {
+            route.add("PreferredSource", preferred_source);
+        }
+        if let Some(metric) = self.metric {
+            route.add("Metric", metric);
+        }
+        if let Some(table) = self.table {
+            route.add("Table", table);
+        }
+        route
+    }
+}
+
+impl From<RouteV2> for NetworkDRoute {
+    fn from(route: RouteV2) -> Self {
+        let destination = match route.to {
+            RouteTo::DefaultRoute => match route.via.or(route.from) {
+                Some(IpAddr::V4(_)) => *DEFAULT_ROUTE_IPV4,
+                Some(IpAddr::V6(_)) => *DEFAULT_ROUTE_IPV6,
+                // If no gateway or from is given, assume the ipv4 default
+                None => *DEFAULT_ROUTE_IPV4,
+            },
+            RouteTo::Ip(ip) => ip,
+        };
+
+        NetworkDRoute {
+            destination,
+            gateway: route.via,
+            preferred_source: route.from,
+            metric: route.route_metric,
+            table: route.table,
+        }
+    }
+}
+
+/// A `[RoutingPolicyRule]` section of a `.network` file
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct NetworkDRule {
+    from: Option<IpNet>,
+    to: Option<IpNet>,
+    table: u32,
+    priority: Option<u32>,
+}
+
+impl NetworkDRule {
+    fn section(&self) -> Section {
+        let mut rule = Section::new("RoutingPolicyRule");
+        if let Some(from) = self.from {
+            rule.add("From", from);
+        }
+        if let Some(to) = self.to {
+            rule.add("To", to);
+        }
+        rule.add("Table", self.table);
+        if let Some(priority) = self.priority {
+            rule.add("Priority", priority);
+        }
+        rule
+    }
+}
+
+impl From<RuleV1> for NetworkDRule {
+    fn from(rule: RuleV1) -> Self {
+        NetworkDRule {
+            from: rule.from,
+            to: rule.to,
+            table: rule.table,
+            priority: rule.priority,
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::interface_id::InterfaceName;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(crate) struct WickedBridge {
+    #[serde(rename = "$unflatten=stp")]
+    stp: bool,
+    ports: BridgePorts,
+}
+
+impl WickedBridge {
+    pub(crate) fn new(stp: bool, devices: Vec<InterfaceName>) -> Self {
+        let ports = devices
+            .into_iter()
+            .map(|device| BridgePort { device })
+            .collect();
+        Self {
+            stp,
+            ports: BridgePorts { ports },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(crate) struct BridgePorts {
+    #[serde(rename = "port")]
+    ports: Vec<BridgePort>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub(crate) struct BridgePort {
+    #[serde(rename = "$unflatten=device")]
+    device: InterfaceName,
+}
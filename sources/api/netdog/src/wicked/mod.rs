@@ -4,27 +4,32 @@
 //! The structures in this module are meant to be created from the user-facing structures in the
 //! `net_config` module.  `Default` implementations for WickedInterface exist here as well.
 mod bonding;
+mod bridge;
 mod dhcp;
 mod static_address;
 mod vlan;
 
 use crate::interface_id::{InterfaceId, InterfaceName, MacAddress};
-use crate::net_config::devices::bonding::{BondMonitoringConfig, NetBondV1};
-use crate::net_config::devices::interface::NetInterfaceV2;
-use crate::net_config::devices::vlan::NetVlanV1;
-use crate::net_config::devices::NetworkDeviceV1;
+use crate::net_config::devices::bonding::{BondMode, BondMonitoringConfig, NetBondV1, NetBondV2};
+use crate::net_config::devices::bridge::NetBridgeV1;
+use crate::net_config::devices::interface::{NetInterfaceV2, NetInterfaceV3};
+use crate::net_config::devices::vlan::{NetVlanV1, NetVlanV2};
+use crate::net_config::devices::{NetworkDeviceV1, NetworkDeviceV2};
+use crate::net_config::RuleV1;
 use crate::wicked::bonding::{
     WickedArpMonitoringConfig, WickedBondMode, WickedMiiMonitoringConfig,
 };
 use bonding::WickedBond;
+use bridge::WickedBridge;
 pub(crate) use dhcp::{WickedDhcp4, WickedDhcp6};
 pub(crate) use error::Error;
 use serde::Serialize;
-use snafu::ResultExt;
-pub(crate) use static_address::{WickedRoutes, WickedStaticAddress};
+use snafu::{ensure, ResultExt};
+pub(crate) use static_address::{WickedRoutes, WickedRule, WickedStaticAddress};
 use std::fmt::{self, Display};
 use std::fs;
-use std::path::Path;
+use std::io;
+use std::path::{Path, PathBuf};
 use vlan::WickedVlanTag;
 
 const WICKED_CONFIG_DIR: &str = "/etc/wicked/ifconfig";
@@ -83,6 +88,8 @@ pub(crate) struct WickedInterface {
     #[serde(rename = "bond")]
     pub(crate) bond: Option<WickedBond>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) bridge: Option<WickedBridge>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub(crate) link: Option<WickedLinkConfig>,
 }
 
@@ -176,20 +183,157 @@ impl WickedInterface {
             ipv6_static: None,
             vlan_tag: None,
             bond: None,
+            bridge: None,
             link: None,
         }
     }
 
+    /// Sets the MTU and routing policy rules available to devices starting with net config v4
+    fn set_link_options(&mut self, mtu: Option<u32>, rules: &Option<Vec<RuleV1>>) {
+        if mtu.is_some() {
+            self.link = Some(WickedLinkConfig { master: None, mtu });
+        }
+
+        for rule in rules.iter().flatten() {
+            let static_address = if rule.is_ipv6() {
+                &mut self.ipv6_static
+            } else {
+                &mut self.ipv4_static
+            };
+            // Validation ensures rules are only given alongside static addresses of the same
+            // family, so there's always a static address to add them to
+            if let Some(static_address) = static_address {
+                static_address.add_rule(WickedRule::from(rule.clone()));
+            }
+        }
+    }
+
     /// Serialize the interface's configuration file
     pub(crate) fn write_config_file(&self) -> Result<()> {
-        let mut cfg_path = Path::new(WICKED_CONFIG_DIR).join(self.name.to_string());
+        let cfg_path = self.config_path(WICKED_CONFIG_DIR);
+        let xml = self.to_xml()?;
+        fs::write(&cfg_path, xml).context(error::WickedConfigWriteSnafu { path: cfg_path })
+    }
+
+    /// Serialize the interface's configuration file unless the existing file is identical.
+    /// Returns whether the file changed, meaning wicked needs to reload the interface.
+    fn update_config_file_in<P>(&self, dir: P) -> Result<bool>
+    where
+        P: AsRef<Path>,
+    {
+        let cfg_path = self.config_path(dir);
+        let xml = self.to_xml()?;
+        if fs::read_to_string(&cfg_path).ok().as_deref() == Some(xml.as_str()) {
+            return Ok(false);
+        }
+        fs::write(&cfg_path, xml).context(error::WickedConfigWriteSnafu { path: cfg_path })?;
+        Ok(true)
+    }
+
+    /// Bonds, bridges, and their member interfaces are configured together, so none of them can
+    /// be replaced on its own
+    fn is_linked(&self) -> bool {
+        self.bond.is_some()
+            || self.bridge.is_some()
+            || matches!(&self.link, Some(link) if link.master.is_some())
+    }
+
+    fn config_path<P>(&self, dir: P) -> PathBuf
+    where
+        P: AsRef<Path>,
+    {
+        let mut cfg_path = dir.as_ref().join(self.name.to_string());
         cfg_path.set_extension(WICKED_FILE_EXT);
+        cfg_path
+    }
 
-        let xml = quick_xml::se::to_string(&self).context(error::XmlSerializeSnafu {
+    fn to_xml(&self) -> Result<String> {
+        quick_xml::se::to_string(&self).context(error::XmlSerializeSnafu {
             interface: self.name.to_string(),
-        })?;
-        fs::write(&cfg_path, xml).context(error::WickedConfigWriteSnafu { path: cfg_path })
+        })
+    }
+}
+
+/// Layers interfaces configured at runtime over the interfaces configured at boot.  A runtime
+/// interface replaces the boot configuration of the interface with the same name; bonds, bridges,
+/// and their members can only be configured at boot.
+pub(crate) fn layer_interfaces(
+    boot: Vec<WickedInterface>,
+    runtime: Vec<WickedInterface>,
+) -> Result<Vec<WickedInterface>> {
+    let mut interfaces = boot;
+    for interface in runtime {
+        match interfaces.iter().position(|i| i.name == interface.name) {
+            Some(index) => {
+                ensure!(
+                    !interfaces[index].is_linked(),
+                    error::RuntimeLinkedInterfaceSnafu {
+                        interface: interface.name.to_string()
+                    }
+                );
+                interfaces[index] = interface;
+            }
+            None => interfaces.push(interface),
+        }
+    }
+    Ok(interfaces)
+}
+
+/// The configuration files changed by `update_config_files`
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct ConfigChanges {
+    /// Interfaces whose configuration file was written; wicked needs to reload them
+    pub(crate) changed: Vec<String>,
+    /// Interfaces whose configuration file was removed; wicked needs to bring them down
+    pub(crate) removed: Vec<String>,
+}
+
+impl ConfigChanges {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Update the configuration files of `interfaces`, and remove the files of any `previous`
+/// interfaces that are no longer configured.  Interfaces from boot that a previous runtime
+/// interface replaced are part of `interfaces` again, so their boot configuration is restored.
+pub(crate) fn update_config_files(
+    interfaces: &[WickedInterface],
+    previous: &[WickedInterface],
+) -> Result<ConfigChanges> {
+    update_config_files_in(WICKED_CONFIG_DIR, interfaces, previous)
+}
+
+fn update_config_files_in<P>(
+    dir: P,
+    interfaces: &[WickedInterface],
+    previous: &[WickedInterface],
+) -> Result<ConfigChanges>
+where
+    P: AsRef<Path>,
+{
+    let dir = dir.as_ref();
+    let mut changes = ConfigChanges::default();
+    for interface in interfaces {
+        if interface.update_config_file_in(dir)? {
+            changes.changed.push(interface.name.to_string());
+        }
+    }
+
+    for interface in previous {
+        if interfaces.iter().any(|i| i.name == interface.name) {
+            continue;
+        }
+        let cfg_path = interface.config_path(dir);
+        match fs::remove_file(&cfg_path) {
+            Ok(()) => changes.removed.push(interface.name.to_string()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(e).context(error::WickedConfigRemoveSnafu { path: cfg_path });
+            }
+        }
     }
+    Ok(changes)
 }
 
 impl<T> From<(&T, &NetworkDeviceV1)> for WickedInterface
@@ -225,27 +369,38 @@ where
         let config = device_tup.1;
         let mut wicked_interface = wicked_from!(name, config);
 
-        // Here is where bonding specific things begin
-        let mut wicked_bond = WickedBond::new(
-            WickedBondMode::from(config.mode.clone()),
-            config.interfaces.clone(),
-        );
+        wicked_interface.bond = Some(wicked_bond(
+            &config.mode,
+            &config.interfaces,
+            config.min_links,
+            &config.monitoring_config,
+        ));
 
-        wicked_bond.min_links = config.min_links;
+        wicked_interface
+    }
+}
 
-        match &config.monitoring_config {
-            BondMonitoringConfig::MiiMon(config) => {
-                wicked_bond.mii_monitoring = Some(WickedMiiMonitoringConfig::from(config.clone()))
-            }
-            BondMonitoringConfig::ArpMon(config) => {
-                wicked_bond.arp_monitoring = Some(WickedArpMonitoringConfig::from(config.clone()))
-            }
-        }
+/// Builds the bonding specific part of a bond's config, which is shared by all versions of bonds
+fn wicked_bond(
+    mode: &BondMode,
+    interfaces: &[InterfaceName],
+    min_links: Option<usize>,
+    monitoring_config: &BondMonitoringConfig,
+) -> WickedBond {
+    let mut wicked_bond = WickedBond::new(WickedBondMode::from(mode.clone()), interfaces.to_vec());
 
-        wicked_interface.bond = Some(wicked_bond);
+    wicked_bond.min_links = min_links;
 
-        wicked_interface
+    match monitoring_config {
+        BondMonitoringConfig::MiiMon(config) => {
+            wicked_bond.mii_monitoring = Some(WickedMiiMonitoringConfig::from(config.clone()))
+        }
+        BondMonitoringConfig::ArpMon(config) => {
+            wicked_bond.arp_monitoring = Some(WickedArpMonitoringConfig::from(config.clone()))
+        }
     }
+
+    wicked_bond
 }
 
 impl<T> From<(&T, &NetVlanV1)> for WickedInterface
@@ -263,10 +418,98 @@ where
     }
 }
 
+impl<T> From<(&T, &NetworkDeviceV2)> for WickedInterface
+where
+    T: Into<InterfaceId> + Clone,
+{
+    fn from(device_tup: (&T, &NetworkDeviceV2)) -> Self {
+        match device_tup.1 {
+            NetworkDeviceV2::Interface(i) => WickedInterface::from((device_tup.0, i)),
+            NetworkDeviceV2::BondDevice(b) => WickedInterface::from((device_tup.0, b)),
+            NetworkDeviceV2::VlanDevice(v) => WickedInterface::from((device_tup.0, v)),
+            NetworkDeviceV2::BridgeDevice(b) => WickedInterface::from((device_tup.0, b)),
+        }
+    }
+}
+
+impl<T> From<(&T, &NetInterfaceV3)> for WickedInterface
+where
+    T: Into<InterfaceId> + Clone,
+{
+    fn from(device_tup: (&T, &NetInterfaceV3)) -> Self {
+        let name = device_tup.0;
+        let config = device_tup.1;
+        let mut wicked_interface = wicked_from!(name, config);
+        wicked_interface.set_link_options(config.mtu, &config.rules);
+
+        wicked_interface
+    }
+}
+
+impl<T> From<(&T, &NetBondV2)> for WickedInterface
+where
+    T: Into<InterfaceId> + Clone,
+{
+    fn from(device_tup: (&T, &NetBondV2)) -> Self {
+        let name = device_tup.0;
+        let config = device_tup.1;
+        let mut wicked_interface = wicked_from!(name, config);
+        wicked_interface.set_link_options(config.mtu, &config.rules);
+
+        wicked_interface.bond = Some(wicked_bond(
+            &config.mode,
+            &config.interfaces,
+            config.min_links,
+            &config.monitoring_config,
+        ));
+
+        wicked_interface
+    }
+}
+
+impl<T> From<(&T, &NetVlanV2)> for WickedInterface
+where
+    T: Into<InterfaceId> + Clone,
+{
+    fn from(device_tup: (&T, &NetVlanV2)) -> Self {
+        let name = device_tup.0;
+        let config = device_tup.1;
+        let mut wicked_interface = wicked_from!(name, config);
+        wicked_interface.set_link_options(config.mtu, &config.rules);
+
+        wicked_interface.vlan_tag = Some(WickedVlanTag::new(config.device.clone(), config.id));
+
+        wicked_interface
+    }
+}
+
+impl<T> From<(&T, &NetBridgeV1)> for WickedInterface
+where
+    T: Into<InterfaceId> + Clone,
+{
+    fn from(device_tup: (&T, &NetBridgeV1)) -> Self {
+        let name = device_tup.0;
+        let config = device_tup.1;
+        let mut wicked_interface = wicked_from!(name, config);
+        wicked_interface.set_link_options(config.mtu, &config.rules);
+
+        wicked_interface.bridge = Some(WickedBridge::new(
+            config.stp.unwrap_or(false),
+            config.interfaces.clone(),
+        ));
+
+        wicked_interface
+    }
+}
+
 #[derive(Debug, Clone, Serialize, PartialEq)]
 pub(crate) struct WickedLinkConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "$unflatten=master")]
-    pub(crate) master: InterfaceName,
+    pub(crate) master: Option<InterfaceName>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "$unflatten=mtu")]
+    pub(crate) mtu: Option<u32>,
 }
 
 mod error {
@@ -277,9 +520,18 @@ mod error {
     #[derive(Debug, Snafu)]
     #[snafu(visibility(pub(crate)))]
     pub(crate) enum Error {
+        #[snafu(display(
+            "Interface '{}' is a bond, a bridge, or a member of one, and can only be configured at boot",
+            interface
+        ))]
+        RuntimeLinkedInterface { interface: String },
+
         #[snafu(display("Failed to write network configuration to '{}': {}", path.display(), source))]
         WickedConfigWrite { path: PathBuf, source: io::Error },
 
+        #[snafu(display("Failed to remove network configuration '{}': {}", path.display(), source))]
+        WickedConfigRemove { path: PathBuf, source: io::Error },
+
         #[snafu(display("Error serializing config for '{}' to XML: {}", interface, source))]
         XmlSerialize {
             interface: String,
@@ -299,7 +551,7 @@ mod tests {
     use std::path::PathBuf;
     use std::str::FromStr;
 
-    static NET_CONFIG_VERSIONS: &[u8] = &[1, 2, 3];
+    static NET_CONFIG_VERSIONS: &[u8] = &[1, 2, 3, 4];
 
     fn test_data() -> PathBuf {
         PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_data")
@@ -372,6 +624,123 @@ mod tests {
         }
     }
 
+    // Interfaces configured at runtime replace the boot configuration of interfaces with the same
+    // name, and are added after the interfaces from boot otherwise
+    #[test]
+    fn runtime_interfaces_layered_on_boot_interfaces() {
+        let runtime_config = wicked_config().join("runtime");
+        let boot = net_config::from_path(runtime_config.join("boot_net_config.toml"))
+            .unwrap()
+            .unwrap();
+        let runtime = net_config::from_path(runtime_config.join("runtime_net_config.toml"))
+            .unwrap()
+            .unwrap();
+
+        let interfaces =
+            layer_interfaces(boot.as_wicked_interfaces(), runtime.as_wicked_interfaces()).unwrap();
+        let names: Vec<String> = interfaces.iter().map(|i| i.name.to_string()).collect();
+        assert_eq!(names, vec!["eno1", "eno2", "bond0", "eno3", "myvlan"]);
+
+        let eno3 = &interfaces[3];
+        assert!(eno3.ipv4_dhcp.is_none());
+        assert!(eno3.ipv4_static.is_some());
+        assert_eq!(eno3.link.as_ref().and_then(|l| l.mtu), Some(9000));
+    }
+
+    #[test]
+    fn runtime_interfaces_cannot_replace_bonds() {
+        let runtime_config = wicked_config().join("runtime");
+        let boot = net_config::from_path(runtime_config.join("boot_net_config.toml"))
+            .unwrap()
+            .unwrap();
+
+        for bad in &["bond.toml", "bond_member.toml"] {
+            let runtime = net_config::from_path(runtime_config.join(bad))
+                .unwrap()
+                .unwrap();
+            assert!(
+                layer_interfaces(boot.as_wicked_interfaces(), runtime.as_wicked_interfaces())
+                    .is_err(),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    // Config files are only rewritten, and interfaces reloaded, when their configuration changes
+    #[test]
+    fn update_config_file_reports_changes() {
+        let config_dir = tempfile::TempDir::new().unwrap();
+        let runtime = net_config::from_path(
+            wicked_config()
+                .join("runtime")
+                .join("runtime_net_config.toml"),
+        )
+        .unwrap()
+        .unwrap();
+        let mut interfaces = runtime.as_wicked_interfaces();
+
+        for interface in &interfaces {
+            assert!(interface.update_config_file_in(&config_dir).unwrap());
+        }
+        for interface in &interfaces {
+            assert!(!interface.update_config_file_in(&config_dir).unwrap());
+        }
+
+        interfaces[0].ipv4_dhcp = Some(WickedDhcp4::default());
+        assert!(interfaces[0].update_config_file_in(&config_dir).unwrap());
+        assert!(!interfaces[1].update_config_file_in(&config_dir).unwrap());
+    }
+
+    // Removing a runtime interface restores the boot configuration it replaced, and removes the
+    // configuration of interfaces that weren't configured at boot
+    #[test]
+    fn removed_runtime_interfaces() {
+        let config_dir = tempfile::TempDir::new().unwrap();
+        let runtime_config = wicked_config().join("runtime");
+        let boot = net_config::from_path(runtime_config.join("boot_net_config.toml"))
+            .unwrap()
+            .unwrap();
+        let runtime = net_config::from_path(runtime_config.join("runtime_net_config.toml"))
+            .unwrap()
+            .unwrap();
+        let layered =
+            layer_interfaces(boot.as_wicked_interfaces(), runtime.as_wicked_interfaces()).unwrap();
+
+        let changes = update_config_files_in(&config_dir, &layered, &[]).unwrap();
+        assert_eq!(changes.changed.len(), layered.len());
+        assert!(changes.removed.is_empty());
+
+        let boot_interfaces = boot.as_wicked_interfaces();
+        let changes = update_config_files_in(
+            &config_dir,
+            &boot_interfaces,
+            &runtime.as_wicked_interfaces(),
+        )
+        .unwrap();
+        assert_eq!(
+            changes,
+            ConfigChanges {
+                changed: vec!["eno3".to_string()],
+                removed: vec!["myvlan".to_string()],
+            }
+        );
+
+        let eno3 = boot_interfaces
+            .iter()
+            .find(|i| i.name.to_string() == "eno3");
+        let eno3 = eno3.unwrap();
+        assert_eq!(
+            fs::read_to_string(eno3.config_path(&config_dir)).unwrap(),
+            eno3.to_xml().unwrap()
+        );
+        assert!(!config_dir.path().join("myvlan.xml").exists());
+
+        // Nothing is left to change once the removal is applied
+        let changes = update_config_files_in(&config_dir, &boot_interfaces, &[]).unwrap();
+        assert!(changes.is_empty());
+    }
+
     fn render_config_template<P1, P2>(template_path: P1, output_path: P2, version: &u8)
     where
         P1: AsRef<Path>,
@@ -1,4 +1,4 @@
-use crate::net_config::{RouteTo, RouteV1, StaticConfigV1};
+use crate::net_config::{RouteTo, RouteV2, RuleV1, StaticConfigV1};
 use ipnet::IpNet;
 use lazy_static::lazy_static;
 use serde::Serialize;
@@ -16,6 +16,9 @@ pub(crate) struct WickedStaticAddress {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "route")]
     routes: Option<Vec<WickedRoute>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "rule")]
+    rules: Option<Vec<WickedRule>>,
 }
 
 impl WickedStaticAddress {
@@ -37,8 +40,13 @@ impl WickedStaticAddress {
         Some(WickedStaticAddress {
             address: static_addresses,
             routes,
+            rules: None,
         })
     }
+
+    pub(crate) fn add_rule(&mut self, rule: WickedRule) {
+        self.rules.get_or_insert_with(Vec::new).push(rule)
+    }
 }
 
 #[derive(Debug, Serialize, PartialEq)]
@@ -68,6 +76,8 @@ pub(crate) struct WickedRoute {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(rename = "$unflatten=priority")]
     priority: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kern: Option<WickedRouteKern>,
 }
 
 impl WickedRoute {
@@ -92,8 +102,15 @@ pub(crate) struct WickedNextHop {
     gateway: Option<IpAddr>,
 }
 
-impl From<RouteV1> for WickedRoute {
-    fn from(route: RouteV1) -> Self {
+// Wicked keeps the kernel-specific properties of a route, like its table, in their own node
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub(crate) struct WickedRouteKern {
+    #[serde(rename = "$unflatten=table")]
+    table: u32,
+}
+
+impl From<RouteV2> for WickedRoute {
+    fn from(route: RouteV2) -> Self {
         let destination = match route.to {
             RouteTo::DefaultRoute => match route.via.or(route.from) {
                 Some(IpAddr::V4(_)) => *DEFAULT_ROUTE_IPV4,
@@ -111,6 +128,7 @@ impl From<RouteV1> for WickedRoute {
             nexthop: Some(nexthop),
             pref_source: route.from,
             priority: route.route_metric,
+            kern: route.table.map(|table| WickedRouteKern { table }),
         }
     }
 }
@@ -134,13 +152,55 @@ impl WickedRoutes {
     }
 }
 
-impl From<Vec<RouteV1>> for WickedRoutes {
-    fn from(routes: Vec<RouteV1>) -> Self {
+impl<R> From<Vec<R>> for WickedRoutes
+where
+    R: Into<RouteV2>,
+{
+    fn from(routes: Vec<R>) -> Self {
         let mut wicked_routes = Self::default();
         for route in routes {
-            let wicked_route = WickedRoute::from(route);
+            let wicked_route = WickedRoute::from(route.into());
             wicked_routes.add_route(wicked_route);
         }
         wicked_routes
     }
 }
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub(crate) struct WickedRule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "$unflatten=priority")]
+    priority: Option<u32>,
+    #[serde(rename = "match")]
+    rule_match: WickedRuleMatch,
+    action: WickedRuleAction,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub(crate) struct WickedRuleMatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "$unflatten=from")]
+    from: Option<IpNet>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "$unflatten=to")]
+    to: Option<IpNet>,
+}
+
+#[derive(Clone, Debug, Serialize, PartialEq)]
+pub(crate) struct WickedRuleAction {
+    #[serde(rename = "$unflatten=table")]
+    table: u32,
+}
+
+impl From<RuleV1> for WickedRule {
+    fn from(rule: RuleV1) -> Self {
+        WickedRule {
+            priority: rule.priority,
+            rule_match: WickedRuleMatch {
+                from: rule.from,
+                to: rule.to,
+            },
+            action: WickedRuleAction { table: rule.table },
+        }
+    }
+}
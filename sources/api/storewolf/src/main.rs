@@ -5,6 +5,8 @@ storewolf creates the filesystem datastore used by the API system.
 
 It creates the datastore at a provided path and populates any default settings, as given in the
 TOML files of the current variant's `defaults.d` directory, unless the datastore already exists.
+The source of each default it writes is recorded as `storewolf`, so defaults can be told apart from
+values set later.
 */
 #![deny(rust_2018_idioms)]
 
@@ -26,6 +28,9 @@ use datastore::serialization::{to_pairs, to_pairs_with_prefix};
 use datastore::{self, DataStore, FilesystemDataStore, ScalarError};
 use model::modeled_types::SingleLineString;
 
+/// Recorded as the source of the defaults we write.
+const SOURCE: &str = "storewolf";
+
 mod error {
     use std::io;
     use std::path::PathBuf;
@@ -301,6 +306,9 @@ fn populate_default_datastore<P: AsRef<Path>>(
         datastore
             .set_keys(&settings_to_write, &pending)
             .context(error::WriteKeysSnafu)?;
+        datastore
+            .set_source(settings_to_write.keys(), SOURCE, &pending)
+            .context(error::WriteMetadataSnafu)?;
     }
 
     // If we have metadata, write it out to the datastore in Live state
@@ -383,6 +391,13 @@ fn populate_default_datastore<P: AsRef<Path>>(
         datastore
             .set_keys(&other_defaults_to_write, &datastore::Committed::Live)
             .context(error::WriteKeysSnafu)?;
+        datastore
+            .set_source(
+                other_defaults_to_write.keys(),
+                SOURCE,
+                &datastore::Committed::Live,
+            )
+            .context(error::WriteMetadataSnafu)?;
     }
     Ok(())
 }
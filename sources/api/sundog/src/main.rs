@@ -4,7 +4,7 @@
 sundog is a small program to handle settings that must be generated at OS runtime.
 
 It requests settings generators from the API and runs them.
-The output is collected and sent to a known Bottlerocket API server endpoint.
+The output is collected and sent to a known Bottlerocket API server endpoint, recording `sundog` as the source of the generated settings.
 */
 
 #![deny(rust_2018_idioms)]
@@ -199,7 +199,7 @@ where
 
     // Retrieve settings by querying the settings by prefix
     let prefixes: Vec<String> = to_query.into_iter().map(|s| s.to_string()).collect();
-    let response = apiclient::get::get_prefixes(socket_path, prefixes.to_owned())
+    let response = apiclient::get::get_prefixes(socket_path, prefixes.to_owned(), false)
         .await
         .context(error::GetPrefixSnafu { prefixes })?;
     debug!("API response model: {}", response.to_string());
@@ -230,7 +230,7 @@ where
 {
     // Retrieve network proxy related settings.
     let prefixes = vec!["settings.network".to_string()];
-    let response = apiclient::get::get_prefixes(&socket_path, prefixes.to_owned())
+    let response = apiclient::get::get_prefixes(&socket_path, prefixes.to_owned(), false)
         .await
         .context(error::GetPrefixSnafu { prefixes })?;
 
@@ -266,7 +266,7 @@ where
     }
     // We potentially need to also no-proxy some K8s related domains for K8s variants
     let prefixes = vec!["settings.kubernetes".to_string()];
-    let response = apiclient::get::get_prefixes(&socket_path, prefixes.to_owned())
+    let response = apiclient::get::get_prefixes(&socket_path, prefixes.to_owned(), false)
         .await
         .context(error::GetPrefixSnafu { prefixes })?;
 
@@ -446,7 +446,7 @@ where
     let request_body = serde_json::to_string(&settings).context(error::SerializeRequestSnafu)?;
 
     let uri = &format!(
-        "{}?tx={}",
+        "{}?tx={}&source=sundog",
         constants::API_SETTINGS_URI,
         constants::LAUNCH_TRANSACTION
     );
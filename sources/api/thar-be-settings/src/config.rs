@@ -1,9 +1,13 @@
+use crate::events::{EventKind, EventLog};
 use crate::service::Services;
 use crate::{error, Result};
 use itertools::join;
+use ring::digest::{digest, SHA256};
+use similar::TextDiff;
 use snafu::{ensure, ResultExt};
 use std::collections::HashSet;
 use std::fs;
+use std::io;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
@@ -45,6 +49,28 @@ pub fn get_config_file_names(services: &Services) -> HashSet<String> {
     config_file_set
 }
 
+/// Build the template registry from config file metadata
+pub fn build_template_registry(
+    config_files: &model::ConfigurationFiles,
+) -> Result<handlebars::Handlebars<'static>> {
+    debug!("Building template registry");
+    let mut template_registry =
+        schnauzer::build_template_registry().context(error::TemplateRegistrySnafu)?;
+    for (name, metadata) in config_files {
+        debug!(
+            "Registering {} at path '{}'",
+            &name, &metadata.template_path
+        );
+        template_registry
+            .register_template_file(name, metadata.template_path.as_ref())
+            .context(error::TemplateRegisterSnafu {
+                name: name.as_str(),
+                path: metadata.template_path.as_ref(),
+            })?;
+    }
+    Ok(template_registry)
+}
+
 /// Render the configuration files
 // If strict is True, return an error if we fail to render any template.
 // If strict is False, ignore failures, always returning an Ok value
@@ -77,11 +103,18 @@ pub fn render_config_files(
     Ok(rendered_configs)
 }
 
-/// Write all the configuration files to disk
-pub fn write_config_files(rendered_configs: &[RenderedConfigFile]) -> Result<()> {
+/// Write all the configuration files to disk, emitting an event for each
+pub fn write_config_files(
+    rendered_configs: &[RenderedConfigFile],
+    events: &EventLog,
+) -> Result<()> {
     for cfg in rendered_configs {
         debug!("Writing {:?}", &cfg.path);
         cfg.write_to_disk()?;
+        events.emit(EventKind::ConfigFileWritten {
+            path: cfg.path.clone(),
+            sha256: cfg.sha256(),
+        });
     }
     Ok(())
 }
@@ -158,6 +191,44 @@ impl RenderedConfigFile {
     fn needs_reload(&self) -> bool {
         self.path.to_string_lossy().starts_with("/etc/systemd/")
     }
+
+    /// The path the rendered template would be written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the hex-encoded SHA-256 digest of the rendered template, so the written file can be
+    /// identified later.
+    pub fn sha256(&self) -> String {
+        hex::encode(digest(&SHA256, self.rendered.as_bytes()))
+    }
+
+    /// Returns a unified diff from the file currently on disk to the rendered template, or None
+    /// if writing the rendered template wouldn't change the file.  A missing file is treated as
+    /// empty.
+    pub fn diff(&self) -> Result<Option<String>> {
+        let current = match fs::read_to_string(&self.path) {
+            Ok(current) => current,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e).context(error::ConfigReadSnafu { path: &self.path }),
+        };
+        Ok(unified_diff(&self.path, &current, &self.rendered))
+    }
+}
+
+/// Returns a unified diff between the two given versions of the file at `path`, or None if they
+/// are the same.
+fn unified_diff(path: &Path, old: &str, new: &str) -> Option<String> {
+    if old == new {
+        return None;
+    }
+    let path = path.display().to_string();
+    Some(
+        TextDiff::from_lines(old, new)
+            .unified_diff()
+            .header(&path, &path)
+            .to_string(),
+    )
 }
 
 #[cfg(test)]
@@ -185,4 +256,14 @@ mod test {
 
         assert_eq!(get_config_file_names(&services), expected_output)
     }
+
+    #[test]
+    fn test_unified_diff() {
+        let path = Path::new("/etc/motd");
+        assert_eq!(unified_diff(path, "hi\n", "hi\n"), None);
+        assert_eq!(
+            unified_diff(path, "hi\nthere\n", "hi\nfriend\n").unwrap(),
+            "--- /etc/motd\n+++ /etc/motd\n@@ -1,2 +1,2 @@\n hi\n-there\n+friend\n"
+        );
+    }
 }
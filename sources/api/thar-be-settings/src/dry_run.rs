@@ -0,0 +1,90 @@
+use crate::{config, error, service, Result};
+use serde::Serialize;
+use snafu::ResultExt;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::Path;
+
+/// DryRunReport describes what applying a pending transaction would do to the system, without
+/// doing any of it.
+#[derive(Debug, Default, Serialize)]
+pub struct DryRunReport {
+    /// The settings keys set in the pending transaction.
+    pub changed_settings: BTreeSet<String>,
+    /// Unified diffs from the current configuration files to the newly rendered ones, keyed by
+    /// file path.  Files that wouldn't change aren't included.
+    pub file_diffs: BTreeMap<String, String>,
+    /// The services that would have their restart-commands run.
+    pub services: BTreeSet<String>,
+}
+
+/// Renders the configuration files affected by the given pending transaction as if it had been
+/// committed, and reports how they differ from the files currently on disk, along with the
+/// services that would be restarted.  Nothing is written and nothing is restarted.
+pub async fn dry_run<P>(socket_path: P, transaction: &str) -> Result<DryRunReport>
+where
+    P: AsRef<Path>,
+{
+    let socket_path = socket_path.as_ref();
+
+    info!(
+        "Requesting pending settings for transaction '{}'",
+        transaction
+    );
+    let uri = "/tx";
+    let pending: serde_json::Value =
+        schnauzer::get_json(socket_path, uri, Some(("tx", transaction)))
+            .await
+            .context(error::GetJsonSnafu { uri })?;
+    let changed_settings: HashSet<String> =
+        datastore::serialization::to_pairs_with_prefix("settings", &pending)
+            .context(error::PendingKeysSnafu)?
+            .into_keys()
+            .map(|key| key.name().to_string())
+            .collect();
+    trace!("Pending settings: {:?}", changed_settings);
+
+    let mut report = DryRunReport {
+        changed_settings: changed_settings.iter().cloned().collect(),
+        ..Default::default()
+    };
+    if changed_settings.is_empty() {
+        info!("Transaction '{}' has no pending settings", transaction);
+        return Ok(report);
+    }
+
+    let services = service::get_affected_services(socket_path, Some(changed_settings)).await?;
+    trace!("Found services: {:?}", services);
+    report.services = services.0.keys().cloned().collect();
+
+    let config_file_names = config::get_config_file_names(&services);
+    if config_file_names.is_empty() {
+        return Ok(report);
+    }
+
+    let config_files =
+        config::get_affected_config_files(socket_path, Some(config_file_names)).await?;
+    let template_registry = config::build_template_registry(&config_files)?;
+
+    // Render against the live settings with the pending settings layered on top, which is what
+    // the settings will look like once the transaction is committed.
+    debug!("Requesting settings values");
+    let live = schnauzer::get_settings(socket_path)
+        .await
+        .context(error::GetSettingsSnafu)?;
+    let mut merged = serde_json::to_value(live).context(error::SerializeSettingsSnafu)?;
+    apiclient::get::merge_json(&mut merged, serde_json::json!({ "settings": pending }));
+    let settings: model::Model =
+        serde_json::from_value(merged).context(error::DeserializeSettingsSnafu)?;
+
+    info!("Rendering config files...");
+    let rendered = config::render_config_files(&template_registry, config_files, settings, true)?;
+    for cfg in rendered {
+        if let Some(diff) = cfg.diff()? {
+            report
+                .file_diffs
+                .insert(cfg.path().display().to_string(), diff);
+        }
+    }
+
+    Ok(report)
+}
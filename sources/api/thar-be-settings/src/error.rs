@@ -39,6 +39,26 @@ pub enum Error {
     #[snafu(display("Restart command is invalid (empty, space prefix, etc.) - {}", command))]
     InvalidRestartCommand { command: String },
 
+    #[snafu(display("Failed to read event log {}: {}", path.display(), source))]
+    EventLogRead { path: PathBuf, source: io::Error },
+
+    #[snafu(display("Failed to write event log {}: {}", path.display(), source))]
+    EventLogWrite { path: PathBuf, source: io::Error },
+
+    #[snafu(display("Failed to build template registry: {}", source))]
+    TemplateRegistry { source: schnauzer::Error },
+
+    #[snafu(display("Failure to read template '{}' from '{}': {}", name, path.display(), source))]
+    TemplateRegister {
+        name: String,
+        path: PathBuf,
+        #[snafu(source(from(handlebars::TemplateError, Box::new)))]
+        source: Box<handlebars::TemplateError>,
+    },
+
+    #[snafu(display("Failed to read current config file {}: {}", path.display(), source))]
+    ConfigRead { path: PathBuf, source: io::Error },
+
     #[snafu(display("Configuration file '{}' failed to render: {}", template, source))]
     TemplateRender {
         template: String,
@@ -79,4 +99,36 @@ pub enum Error {
         uri: String,
         source: schnauzer::Error,
     },
+
+    #[snafu(display("Failed to get settings data: {}", source))]
+    GetSettings { source: schnauzer::Error },
+
+    #[snafu(display("Failed to serialize settings: {}", source))]
+    SerializeSettings { source: serde_json::Error },
+
+    #[snafu(display("Failed to deserialize merged settings: {}", source))]
+    DeserializeSettings { source: serde_json::Error },
+
+    #[snafu(display("Failed to list pending settings keys: {}", source))]
+    PendingKeys {
+        source: datastore::serialization::Error,
+    },
+
+    #[snafu(display("Failed to parse apply status from {}: {}", path.display(), source))]
+    StatusParse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[snafu(display("Apply status path {} has no parent directory", path.display()))]
+    StatusPath { path: PathBuf },
+
+    #[snafu(display("Failed to read apply status from {}: {}", path.display(), source))]
+    StatusRead { path: PathBuf, source: io::Error },
+
+    #[snafu(display("Failed to serialize apply status: {}", source))]
+    StatusSerialize { source: serde_json::Error },
+
+    #[snafu(display("Failed to write apply status to {}: {}", path.display(), source))]
+    StatusWrite { path: PathBuf, source: io::Error },
 }
@@ -0,0 +1,235 @@
+//! Records structured events about each run that applies settings: the settings that changed, the
+//! configuration files written, and the restart commands run, so a restart can be traced back to
+//! the settings change that caused it.
+//!
+//! Each event is logged as a line of JSON, which ends up in the journal, and appended to an event
+//! log file.  The file is rotated when it gets large, keeping one older file; `read_events` reads
+//! both, and the API server serves them at `/events`.
+
+use crate::{error, Result};
+use chrono::{DateTime, Utc};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use snafu::ResultExt;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Where events are appended.  It's under /var, so events from earlier boots are kept.
+pub const EVENT_LOG_FILE: &str = "/var/log/thar-be-settings/events.log";
+
+/// When the event log would grow past this size, it's moved aside to make room, replacing any
+/// older one.
+const MAX_EVENT_LOG_BYTES: u64 = 1024 * 1024;
+
+/// One thing that happened while applying settings.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Event {
+    /// Identifies the run of thar-be-settings that emitted the event, so the events of one run can
+    /// be grouped.
+    pub run_id: String,
+    /// The ID, in the datastore's transaction history, of the commit whose changes the run
+    /// applied.  None for runs that weren't started for a commit, like at boot.
+    pub transaction_id: Option<u64>,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum EventKind {
+    /// A run started.  `changed_settings` is None when applying all settings, like at boot.
+    ApplyStarted {
+        changed_settings: Option<Vec<String>>,
+    },
+    /// A configuration file was rendered and written.
+    ConfigFileWritten { path: PathBuf, sha256: String },
+    /// A restart command was run for a service.  `exit_code` is None if the command couldn't be
+    /// started or was killed by a signal.
+    RestartCommand {
+        service: String,
+        command: String,
+        exit_code: Option<i32>,
+        success: bool,
+    },
+    /// A run finished; `error` describes why it failed, if it did.
+    ApplyFinished {
+        success: bool,
+        error: Option<String>,
+    },
+}
+
+/// Emits the events of one run, with a run ID and transaction ID shared by all of them.
+#[derive(Debug)]
+pub struct EventLog {
+    run_id: String,
+    transaction_id: Option<u64>,
+    path: PathBuf,
+}
+
+impl EventLog {
+    /// Starts emitting events for a new run, appending them to the event log at `path`.
+    /// `transaction_id` is the history ID of the commit being applied, if the API server gave one.
+    pub fn new<P: AsRef<Path>>(path: P, transaction_id: Option<u64>) -> Self {
+        // The start time and process ID make the ID unique on the host, and it sorts by start time.
+        let now = Utc::now();
+        let run_id = format!("{}-{}", now.format("%Y%m%dT%H%M%S%.9fZ"), process::id());
+        Self {
+            run_id,
+            transaction_id,
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+
+    pub fn run_id(&self) -> &str {
+        &self.run_id
+    }
+
+    /// Logs the event and appends it to the event log.  Failing to record an event doesn't stop us
+    /// from applying settings, so errors are logged rather than returned.
+    pub fn emit(&self, kind: EventKind) {
+        let event = Event {
+            run_id: self.run_id.clone(),
+            transaction_id: self.transaction_id,
+            timestamp: Utc::now(),
+            kind,
+        };
+        let line = match serde_json::to_string(&event) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize event {:?}: {}", event, e);
+                return;
+            }
+        };
+        info!("event: {}", line);
+        if let Err(e) = self.append(&line) {
+            warn!("Failed to record event: {}", e);
+        }
+    }
+
+    fn append(&self, line: &str) -> Result<()> {
+        let path = &self.path;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).context(error::EventLogWriteSnafu { path: dir })?;
+        }
+        // Runs can overlap, so hold a lock while rotating and appending.
+        let lock_path = path.with_extension("lock");
+        let lockfile =
+            File::create(&lock_path).context(error::EventLogWriteSnafu { path: &lock_path })?;
+        FileExt::lock_exclusive(&lockfile)
+            .context(error::EventLogWriteSnafu { path: &lock_path })?;
+
+        let size = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == ErrorKind::NotFound => 0,
+            Err(e) => return Err(e).context(error::EventLogWriteSnafu { path }),
+        };
+        if size > 0 && size + line.len() as u64 + 1 > MAX_EVENT_LOG_BYTES {
+            let rotated = rotated_path(path);
+            fs::rename(path, &rotated).context(error::EventLogWriteSnafu { path: &rotated })?;
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .context(error::EventLogWriteSnafu { path })?;
+        writeln!(file, "{}", line).context(error::EventLogWriteSnafu { path })
+    }
+}
+
+/// Reads the events in the event log at `path`, including the older rotated file, oldest first.
+/// Lines that can't be parsed, like one cut short by a crash, are skipped.
+pub fn read_events<P: AsRef<Path>>(path: P) -> Result<Vec<Event>> {
+    let path = path.as_ref();
+    let mut events = Vec::new();
+    for path in &[rotated_path(path), path.to_path_buf()] {
+        let data = match fs::read_to_string(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).context(error::EventLogReadSnafu { path }),
+        };
+        for line in data.lines().filter(|line| !line.trim().is_empty()) {
+            match serde_json::from_str(line) {
+                Ok(event) => events.push(event),
+                Err(e) => debug!("Skipping unparseable event in {}: {}", path.display(), e),
+            }
+        }
+    }
+    Ok(events)
+}
+
+/// The path the event log is moved to when it's rotated, e.g. `events.log.1`.
+fn rotated_path(path: &Path) -> PathBuf {
+    let mut rotated = OsString::from(path.as_os_str());
+    rotated.push(".1");
+    rotated.into()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn emit_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log").join("events.log");
+        assert!(read_events(&path).unwrap().is_empty());
+
+        let events = EventLog::new(&path, Some(42));
+        events.emit(EventKind::ApplyStarted {
+            changed_settings: Some(vec!["settings.motd".to_string()]),
+        });
+        events.emit(EventKind::RestartCommand {
+            service: "motd".to_string(),
+            command: "/bin/true".to_string(),
+            exit_code: Some(0),
+            success: true,
+        });
+
+        let read = read_events(&path).unwrap();
+        assert_eq!(read.len(), 2);
+        assert!(read
+            .iter()
+            .all(|event| event.run_id == events.run_id() && event.transaction_id == Some(42)));
+        assert_eq!(
+            read[0].kind,
+            EventKind::ApplyStarted {
+                changed_settings: Some(vec!["settings.motd".to_string()])
+            }
+        );
+
+        // The format is read by other tools, so check the field names.
+        let line = fs::read_to_string(&path).unwrap();
+        let first: serde_json::Value = serde_json::from_str(line.lines().next().unwrap()).unwrap();
+        assert_eq!(first["event"], "apply-started");
+        assert_eq!(first["changed_settings"][0], "settings.motd");
+        assert_eq!(first["run_id"], events.run_id());
+        assert_eq!(first["transaction_id"], 42);
+    }
+
+    #[test]
+    fn rotate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.log");
+        // Fill the log so the next event doesn't fit, and include a line that can't be parsed.
+        let filler = format!("{}\n", "x".repeat(MAX_EVENT_LOG_BYTES as usize - 1));
+        fs::write(&path, filler).unwrap();
+
+        let events = EventLog::new(&path, None);
+        let finished = EventKind::ApplyFinished {
+            success: false,
+            error: Some("oops".to_string()),
+        };
+        events.emit(finished.clone());
+
+        assert!(rotated_path(&path).exists());
+        let read = read_events(&path).unwrap();
+        assert_eq!(read.len(), 1);
+        assert_eq!(read[0].kind, finished);
+        assert_eq!(read[0].transaction_id, None);
+    }
+}
@@ -12,6 +12,26 @@ It then renders the templates and rewrites the affected configuration files.
 Service data from the API includes any commands needed to restart services affected by configuration file changes, which are run here.
 
 In the standalone ("all keys") mode, it queries the API for all services and configuration files, then renders and rewrites all configuration files and restarts all services.
+
+In the "dry run" mode, it's given the name of a pending transaction.
+It determines the services and configuration files affected by the pending settings, and renders those configuration files as if the transaction had been committed.
+Rather than writing anything, it prints a JSON report to stdout with the pending settings, a unified diff for each configuration file that would change, and the services that would be restarted.
+The API server uses this mode to let users preview a change before committing it.
+
+After each run that applies settings, it records how long the run took and whether it succeeded in `/run/cache/thar-be-settings/status.json`, along with counts of runs and failures since boot.
+metricdog reports these in its Prometheus metrics.
+
+Runs that apply settings also emit structured events, so a service restart can be traced back to the settings change that caused it.
+Events are logged as JSON, which puts them in the journal, and appended to `/var/log/thar-be-settings/events.log`, which is rotated to `events.log.1` when it reaches 1 MiB.
+Each event has the ID of the run, a timestamp, and the `transaction_id` the API server passes with `--transaction-id` when it applies a commit.
+That's the commit's ID in the datastore's transaction history, as shown by `/tx/history`; it's null for runs that don't apply a commit, like at boot.
+Each event also has one of these types:
+* `apply-started`, with the changed settings, or null when applying all settings
+* `config-file-written`, with the path and SHA-256 digest of the rendered file
+* `restart-command`, with the service, the command, its exit code, and whether it succeeded
+* `apply-finished`, with whether the run succeeded, and the error if not
+
+The API server serves these events at `/events`.
 */
 
 #![deny(rust_2018_idioms)]
@@ -24,8 +44,11 @@ use std::collections::HashSet;
 use std::io::{self, Read};
 
 pub mod config;
+pub mod dry_run;
 pub mod error;
+pub mod events;
 pub mod service;
+pub mod status;
 
 pub use error::Error;
 type Result<T> = std::result::Result<T, Error>;
@@ -2,19 +2,22 @@
 extern crate log;
 
 use nix::unistd::{fork, ForkResult};
-use simplelog::{Config as LogConfig, LevelFilter, SimpleLogger};
+use simplelog::{Config as LogConfig, LevelFilter, SimpleLogger, WriteLogger};
 use snafu::ResultExt;
 use std::collections::HashSet;
 use std::env;
+use std::io;
 use std::process;
 use std::str::FromStr;
+use std::time::Instant;
 use tokio::runtime::Runtime;
 
-use thar_be_settings::{config, get_changed_settings, service};
+use thar_be_settings::events::{EventKind, EventLog, EVENT_LOG_FILE};
+use thar_be_settings::status::{ApplyStatus, APPLY_STATUS_FILE};
+use thar_be_settings::{config, dry_run, get_changed_settings, service};
 
 mod error {
     use snafu::Snafu;
-    use std::path::PathBuf;
 
     #[derive(Debug, Snafu)]
     #[snafu(visibility(pub(super)))]
@@ -22,22 +25,20 @@ mod error {
         #[snafu(display("Logger setup error: {}", source))]
         Logger { source: log::SetLoggerError },
 
-        #[snafu(display("Failure to read template '{}' from '{}': {}", name, path.display(), source))]
-        TemplateRegister {
-            name: String,
-            path: PathBuf,
-            source: handlebars::TemplateError,
-        },
+        #[snafu(display("Failed to serialize dry run report: {}", source))]
+        Report { source: serde_json::Error },
     }
 }
 
 /// RunMode represents how thar-be-settings was requested to be run, either handling all
 /// configuration files and services, or handling configuration files and services based on
-/// specific keys given by the user.
+/// specific keys given by the user, or reporting what a pending transaction would change
+/// without changing anything.
 #[derive(Debug)]
 enum RunMode {
     All,
     SpecificKeys,
+    DryRun { transaction: String },
 }
 
 /// Store the args we receive on the command line
@@ -46,6 +47,7 @@ struct Args {
     log_level: LevelFilter,
     mode: RunMode,
     socket_path: String,
+    transaction_id: Option<u64>,
 }
 
 /// Print a usage message in the event a bad arg is passed
@@ -54,6 +56,8 @@ fn usage() -> ! {
     eprintln!(
         r"Usage: {}
             [ --all ]
+            [ --dry-run [ --transaction NAME ] ]
+            [ --transaction-id ID ]
             [ --daemon ]
             [ --socket-path PATH ]
             [ --log-level trace|debug|info|warn|error ]
@@ -63,6 +67,16 @@ fn usage() -> ! {
     will be read from stdin; only files related to those keys will be written,
     and only services related to those keys will be restarted.
 
+    If --dry-run is given, the settings pending in the given transaction
+    (default 'default') are read from the API, and a JSON report is printed
+    to stdout showing the changes that committing them would make to
+    configuration files, and the services that would be restarted.  Nothing
+    is written or restarted.
+
+    If --transaction-id is given, it's recorded in the events of the run, to
+    show which commit in the datastore's transaction history is being
+    applied.  It can't be used with --dry-run.
+
     If --daemon is given, thar-be-settings will fork and do its work in a new
     process; this is useful to prevent blocking an API call.
 
@@ -84,6 +98,9 @@ fn parse_args(args: env::Args) -> Args {
     let mut daemon = false;
     let mut log_level = None;
     let mut mode = RunMode::SpecificKeys;
+    let mut dry_run = false;
+    let mut transaction = None;
+    let mut transaction_id = None;
     let mut socket_path = None;
 
     let mut iter = args.skip(1);
@@ -91,6 +108,25 @@ fn parse_args(args: env::Args) -> Args {
         match arg.as_ref() {
             "--all" => mode = RunMode::All,
 
+            "--dry-run" => dry_run = true,
+
+            "--transaction" => {
+                transaction = Some(
+                    iter.next()
+                        .unwrap_or_else(|| usage_msg("Did not give argument to --transaction")),
+                )
+            }
+
+            "--transaction-id" => {
+                let id_str = iter
+                    .next()
+                    .unwrap_or_else(|| usage_msg("Did not give argument to --transaction-id"));
+                transaction_id =
+                    Some(id_str.parse().unwrap_or_else(|_| {
+                        usage_msg(format!("Invalid transaction ID '{}'", id_str))
+                    }));
+            }
+
             "--daemon" => daemon = true,
 
             "--log-level" => {
@@ -113,11 +149,26 @@ fn parse_args(args: env::Args) -> Args {
         }
     }
 
+    if dry_run {
+        if let RunMode::All = mode {
+            usage_msg("--dry-run cannot be used with --all");
+        }
+        if transaction_id.is_some() {
+            usage_msg("--transaction-id cannot be used with --dry-run");
+        }
+        mode = RunMode::DryRun {
+            transaction: transaction.unwrap_or_else(|| "default".to_string()),
+        };
+    } else if transaction.is_some() {
+        usage_msg("--transaction can only be used with --dry-run");
+    }
+
     Args {
         daemon,
         mode,
         log_level: log_level.unwrap_or(LevelFilter::Info),
         socket_path: socket_path.unwrap_or_else(|| constants::API_SOCKET.to_string()),
+        transaction_id,
     }
 }
 
@@ -126,6 +177,7 @@ fn parse_args(args: env::Args) -> Args {
 async fn write_config_files(
     args: &Args,
     files_limit: Option<HashSet<String>>,
+    events: &EventLog,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Create a vec of ConfigFile structs from the list of changed services
     info!("Requesting configuration file data for affected services");
@@ -133,20 +185,7 @@ async fn write_config_files(
     trace!("Found config files: {:?}", config_files);
 
     // Build the template registry from config file metadata
-    debug!("Building template registry");
-    let mut template_registry = schnauzer::build_template_registry()?;
-    for (name, metadata) in &config_files {
-        debug!(
-            "Registering {} at path '{}'",
-            &name, &metadata.template_path
-        );
-        template_registry
-            .register_template_file(name, metadata.template_path.as_ref())
-            .context(error::TemplateRegisterSnafu {
-                name: name.as_str(),
-                path: metadata.template_path.as_ref(),
-            })?;
-    }
+    let template_registry = config::build_template_registry(&config_files)?;
 
     // Get all settings values for config file templates
     debug!("Requesting settings values");
@@ -155,14 +194,14 @@ async fn write_config_files(
     // Ensure all files render properly
     info!("Rendering config files...");
     let strict = match &args.mode {
-        RunMode::SpecificKeys => true,
+        RunMode::SpecificKeys | RunMode::DryRun { .. } => true,
         RunMode::All => false,
     };
     let rendered = config::render_config_files(&template_registry, config_files, settings, strict)?;
 
     // If all the config renders properly, write it to disk
     info!("Writing config files to disk...");
-    config::write_config_files(&rendered)?;
+    config::write_config_files(&rendered, events)?;
 
     // If we're done with early boot and only working with specific services,
     // then trigger a reload if necessary.
@@ -173,9 +212,15 @@ async fn write_config_files(
     Ok(())
 }
 
-async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
-    // SimpleLogger will send errors to stderr and anything less to stdout.
-    SimpleLogger::init(args.log_level, LogConfig::default()).context(error::LoggerSnafu)?;
+async fn run(args: Args, events: &EventLog) -> Result<(), Box<dyn std::error::Error>> {
+    if let RunMode::DryRun { .. } = &args.mode {
+        // The report goes to stdout, so keep it clear of log messages.
+        WriteLogger::init(args.log_level, LogConfig::default(), io::stderr())
+            .context(error::LoggerSnafu)?;
+    } else {
+        // SimpleLogger will send errors to stderr and anything less to stdout.
+        SimpleLogger::init(args.log_level, LogConfig::default()).context(error::LoggerSnafu)?;
+    }
 
     info!("thar-be-settings started");
 
@@ -184,6 +229,11 @@ async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
             // Get the settings that changed via stdin
             info!("Parsing stdin for updated settings");
             let changed_settings = get_changed_settings()?;
+            let mut changed_list: Vec<String> = changed_settings.iter().cloned().collect();
+            changed_list.sort();
+            events.emit(EventKind::ApplyStarted {
+                changed_settings: Some(changed_list),
+            });
 
             // Create a HashSet of affected services
             info!(
@@ -195,27 +245,35 @@ async fn run(args: Args) -> Result<(), Box<dyn std::error::Error>> {
             trace!("Found services: {:?}", services);
             if services.0.is_empty() {
                 info!("No services are affected, exiting...");
-                process::exit(0)
+                return Ok(());
             }
 
             // Create a HashSet of configuration file names
             let config_file_names = config::get_config_file_names(&services);
 
             if !config_file_names.is_empty() {
-                write_config_files(&args, Some(config_file_names)).await?;
+                write_config_files(&args, Some(config_file_names), events).await?;
             }
 
             // Now go bounce the affected services
             info!("Restarting affected services...");
-            service::restart_services(services)?;
+            service::restart_services(services, events)?;
         }
         RunMode::All => {
-            write_config_files(&args, None).await?;
+            events.emit(EventKind::ApplyStarted {
+                changed_settings: None,
+            });
+            write_config_files(&args, None, events).await?;
 
             info!("Restarting all services...");
             let services = service::get_affected_services(&args.socket_path, None).await?;
             trace!("Found services: {:?}", services);
-            service::restart_services(services)?;
+            service::restart_services(services, events)?;
+        }
+        RunMode::DryRun { transaction } => {
+            let report = dry_run::dry_run(&args.socket_path, &transaction).await?;
+            let output = serde_json::to_string(&report).context(error::ReportSnafu)?;
+            println!("{}", output);
         }
     }
 
@@ -249,8 +307,23 @@ fn main() {
         }
     }
 
+    // Dry runs don't apply settings, so they aren't recorded in the apply status or event log.
+    let record_status = !matches!(args.mode, RunMode::DryRun { .. });
+    let start = Instant::now();
+    let events = EventLog::new(EVENT_LOG_FILE, args.transaction_id);
+
     let rt = Runtime::new().expect("Failed to create tokio runtime");
-    if let Err(e) = rt.block_on(async { run(args).await }) {
+    let result = rt.block_on(async { run(args, &events).await });
+    if record_status {
+        events.emit(EventKind::ApplyFinished {
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        });
+        if let Err(e) = ApplyStatus::record(APPLY_STATUS_FILE, start.elapsed(), result.is_ok()) {
+            eprintln!("Failed to record apply status: {}", e);
+        }
+    }
+    if let Err(e) = result {
         eprintln!("{}", e);
         process::exit(1);
     }
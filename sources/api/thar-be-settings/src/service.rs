@@ -1,3 +1,4 @@
+use crate::events::{EventKind, EventLog};
 use crate::{error, Result};
 use itertools::join;
 use snafu::{ensure, OptionExt, ResultExt};
@@ -133,11 +134,12 @@ where
     Ok(service_map)
 }
 
-/// Call the `restart()` method on each Service in a Services object
-pub fn restart_services(services: Services) -> Result<()> {
+/// Call the `restart()` method on each Service in a Services object, emitting an event for each
+/// restart command run
+pub fn restart_services(services: Services, events: &EventLog) -> Result<()> {
     for (name, service) in services.0 {
         debug!("Checking for restart-commands for {}", name);
-        service.restart()?;
+        service.restart(&name, events)?;
     }
     Ok(())
 }
@@ -146,11 +148,11 @@ pub fn restart_services(services: Services) -> Result<()> {
 /// inside the Service struct to restart the service.
 trait ServiceRestart {
     /// Restart the service
-    fn restart(&self) -> Result<()>;
+    fn restart(&self, name: &str, events: &EventLog) -> Result<()>;
 }
 
 impl ServiceRestart for Service {
-    fn restart(&self) -> Result<()> {
+    fn restart(&self, name: &str, events: &EventLog) -> Result<()> {
         let restart_commands = &self.model.restart_commands;
         info!("restart commands {:?}", restart_commands);
         for restart_command in restart_commands {
@@ -174,11 +176,16 @@ impl ServiceRestart for Service {
                     process_command.env("CHANGED_SETTINGS", join(changed_settings, " "));
                 }
             }
-            let result = process_command
-                .output()
-                .context(error::CommandExecutionFailureSnafu {
-                    command: restart_command.as_str(),
-                })?;
+            let result = process_command.output();
+            events.emit(EventKind::RestartCommand {
+                service: name.to_string(),
+                command: restart_command.clone(),
+                exit_code: result.as_ref().ok().and_then(|output| output.status.code()),
+                success: matches!(&result, Ok(output) if output.status.success()),
+            });
+            let result = result.context(error::CommandExecutionFailureSnafu {
+                command: restart_command.as_str(),
+            })?;
 
             // If the restart command exited nonzero, call it a failure
             ensure!(
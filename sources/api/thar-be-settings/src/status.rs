@@ -0,0 +1,114 @@
+//! Records how runs that apply settings went, so other programs, like metricdog, can report on it.
+
+use crate::{error, Result};
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt, ResultExt};
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Where the status is kept.  It's under /run, so the counts start over on each boot.
+pub const APPLY_STATUS_FILE: &str = "/run/cache/thar-be-settings/status.json";
+
+/// The outcome of the latest run that applied settings, and counts of runs since boot.  Dry runs
+/// don't apply settings and aren't counted.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ApplyStatus {
+    /// When the latest run finished, in seconds since the Unix epoch.
+    pub last_run_timestamp: u64,
+    /// How long the latest run took, in seconds.
+    pub last_run_duration_seconds: f64,
+    /// Whether the latest run applied settings without errors.
+    pub last_run_success: bool,
+    pub runs: u64,
+    pub failures: u64,
+}
+
+impl ApplyStatus {
+    /// Loads the status from `path`.  Returns `None` if settings haven't been applied yet.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>> {
+        let path = path.as_ref();
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context(error::StatusReadSnafu { path }),
+        };
+        let status = serde_json::from_slice(&data).context(error::StatusParseSnafu { path })?;
+        Ok(Some(status))
+    }
+
+    /// Adds a run that took `duration` and succeeded or not to the status at `path`.
+    pub fn record<P: AsRef<Path>>(path: P, duration: Duration, success: bool) -> Result<()> {
+        let path = path.as_ref();
+        let dir = path.parent().context(error::StatusPathSnafu { path })?;
+        fs::create_dir_all(dir).context(error::StatusWriteSnafu { path: dir })?;
+
+        // Runs can overlap, so hold a lock while updating the counts, or one run's could be lost.
+        let lock_path = path.with_extension("lock");
+        let lockfile =
+            File::create(&lock_path).context(error::StatusWriteSnafu { path: &lock_path })?;
+        FileExt::lock_exclusive(&lockfile).context(error::StatusWriteSnafu { path: &lock_path })?;
+
+        let mut status = Self::load(path)?.unwrap_or_default();
+        status.last_run_timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        status.last_run_duration_seconds = duration.as_secs_f64();
+        status.last_run_success = success;
+        status.runs += 1;
+        if !success {
+            status.failures += 1;
+        }
+
+        // Write to a temporary file and rename it into place, so readers never see part of it.
+        let temp_path = path.with_extension(format!("{}.tmp", std::process::id()));
+        let data = serde_json::to_vec(&status).context(error::StatusSerializeSnafu)?;
+        fs::write(&temp_path, data).context(error::StatusWriteSnafu { path: &temp_path })?;
+        fs::rename(&temp_path, path).context(error::StatusWriteSnafu { path })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("status").join("status.json");
+        assert_eq!(ApplyStatus::load(&path).unwrap(), None);
+
+        ApplyStatus::record(&path, Duration::from_millis(1500), true).unwrap();
+        let status = ApplyStatus::load(&path).unwrap().unwrap();
+        assert_eq!(status.last_run_duration_seconds, 1.5);
+        assert!(status.last_run_success);
+        assert!(status.last_run_timestamp > 0);
+        assert_eq!((status.runs, status.failures), (1, 0));
+
+        ApplyStatus::record(&path, Duration::from_secs(2), false).unwrap();
+        let status = ApplyStatus::load(&path).unwrap().unwrap();
+        assert_eq!(status.last_run_duration_seconds, 2.0);
+        assert!(!status.last_run_success);
+        assert_eq!((status.runs, status.failures), (2, 1));
+    }
+
+    #[test]
+    fn record_concurrent_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("status.json");
+        let threads: Vec<_> = (0..8)
+            .map(|_| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    ApplyStatus::record(&path, Duration::from_secs(1), true).unwrap()
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(ApplyStatus::load(&path).unwrap().unwrap().runs, 8);
+    }
+}
@@ -152,6 +152,18 @@ pub enum Error {
 
     #[snafu(display("Unable to create a tokio runtime: {}", source))]
     Runtime { source: std::io::Error },
+
+    #[snafu(display("Invalid maintenance window: {}", reason))]
+    MaintenanceWindow { reason: String },
+
+    #[snafu(display("Failed to serialize maintenance window state: {}", source))]
+    WindowStateSerialize { source: serde_json::Error },
+
+    #[snafu(display("Failed to write maintenance window state to '{}': {}", path.display(), source))]
+    WindowStateWrite {
+        path: PathBuf,
+        source: std::io::Error,
+    },
 }
 
 /// Map errors to specific exit codes to return to caller
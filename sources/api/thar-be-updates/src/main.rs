@@ -14,8 +14,16 @@ This allows the caller to synchronously call thar-be-updates without having to w
 
 thar-be-updates uses a lockfile to control read/write access to the disks and the update status file.
 
+## Maintenance windows
+
+If `settings.updates.maintenance-window` is enabled, a timer regularly runs `thar-be-updates maintenance-window`, which applies updates on its own while the window is open.
+It refreshes the list of updates, prepares and activates the chosen update, and reboots into it, running each command in turn rather than forking.
+Outside the window it does nothing, and it reboots at most `max-reboots` times per window, counting reboots in `/var/lib/thar-be-updates` so the count survives them.
+It records what it decided, and why, as `maintenance_window` in the update status.
+
 */
 
+use chrono::{DateTime, Utc};
 use fs2::FileExt;
 use log::{debug, warn};
 use nix::unistd::{fork, ForkResult};
@@ -31,16 +39,27 @@ use std::{env, process};
 use tempfile::NamedTempFile;
 use thar_be_updates::error;
 use thar_be_updates::error::{Error, Result, TbuErrorStatus};
+use thar_be_updates::maintenance::{
+    MaintenanceDecision, MaintenanceWindow, WindowState, MAINTENANCE_STATE_FILE,
+};
 use thar_be_updates::status::{
-    get_update_status, UpdateCommand, UpdateState, UpdateStatus, UPDATE_LOCKFILE,
+    get_settings, get_update_status, UpdateCommand, UpdateState, UpdateStatus, UPDATE_LOCKFILE,
     UPDATE_STATUS_FILE,
 };
+use tokio::runtime::Runtime;
 
 const UPDATE_STATUS_DIR: &str = "/run/cache/thar-be-updates";
 
+/// The subcommands we accept: an update command, as issued by the apiserver, or a check of the
+/// maintenance window, which issues update commands itself
+enum Subcommand {
+    Update(UpdateCommand),
+    MaintenanceWindow,
+}
+
 /// Stores the command line arguments
 struct Args {
-    subcommand: UpdateCommand,
+    subcommand: Subcommand,
     log_level: LevelFilter,
     socket_path: String,
 }
@@ -57,6 +76,9 @@ fn usage() -> ! {
                             inactive partition
                 activate    Marks the inactive partition for boot
                 deactivate  Reverts update activation by marking current active partition for boot
+                maintenance-window
+                            If the maintenance window is open, apply the chosen update and
+                            reboot into it
 
             Global options:
                     [ --socket-path PATH ]    Bottlerocket API socket path (default {})
@@ -102,8 +124,12 @@ fn parse_args(args: std::env::Args) -> Args {
                 if subcommand.is_some() {
                     usage();
                 }
-                subcommand =
-                    Some(serde_plain::from_str::<UpdateCommand>(s).unwrap_or_else(|_| usage()));
+                subcommand = Some(match s {
+                    "maintenance-window" => Subcommand::MaintenanceWindow,
+                    _ => Subcommand::Update(
+                        serde_plain::from_str::<UpdateCommand>(s).unwrap_or_else(|_| usage()),
+                    ),
+                });
             }
             _ => usage(),
         }
@@ -160,19 +186,24 @@ fn write_update_status(update_status: &UpdateStatus) -> Result<()> {
 }
 
 /// This macros encapsulates the boilerplate code for dispatching the update command in a forked process
+/// If `$fork` is false, the command runs in the current process instead, and we wait for it
 macro_rules! fork_and_return {
-    ($child_process:block) => {
-        match unsafe { fork() } {
-            Ok(ForkResult::Parent { child, .. }) => {
-                debug!("forked child pid: {}", child);
-                // Exit immediately as the parent
-                // Parent's lockfile fd will close but child will still have a duplicate fd
-                exit(0);
-            }
-            Ok(ForkResult::Child) => $child_process,
-            Err(e) => {
-                eprintln!("{}", e);
-                error::ForkSnafu.fail()
+    ($fork:expr, $child_process:block) => {
+        if !$fork {
+            $child_process
+        } else {
+            match unsafe { fork() } {
+                Ok(ForkResult::Parent { child, .. }) => {
+                    debug!("forked child pid: {}", child);
+                    // Exit immediately as the parent
+                    // Parent's lockfile fd will close but child will still have a duplicate fd
+                    exit(0);
+                }
+                Ok(ForkResult::Child) => $child_process,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    error::ForkSnafu.fail()
+                }
             }
         }
     };
@@ -180,8 +211,8 @@ macro_rules! fork_and_return {
 
 /// Spawns updog process to get list of updates and check if any of them can be updated to.
 /// Returns true if there is an available update, returns false otherwise.
-fn refresh(status: &mut UpdateStatus, socket_path: &str) -> Result<bool> {
-    fork_and_return!({
+fn refresh(status: &mut UpdateStatus, socket_path: &str, fork: bool) -> Result<bool> {
+    fork_and_return!(fork, {
         debug!("Spawning 'updog whats'");
         let output = Command::new("updog")
             .args(["whats", "--all", "--json"])
@@ -199,8 +230,8 @@ fn refresh(status: &mut UpdateStatus, socket_path: &str) -> Result<bool> {
 }
 
 /// Prepares the update by downloading and writing the update to the staging partition
-fn prepare(status: &mut UpdateStatus) -> Result<()> {
-    fork_and_return!({
+fn prepare(status: &mut UpdateStatus, fork: bool) -> Result<()> {
+    fork_and_return!(fork, {
         debug!("Spawning 'updog update-image'");
         let chosen_update = status
             .chosen_update()
@@ -221,8 +252,8 @@ fn prepare(status: &mut UpdateStatus) -> Result<()> {
 }
 
 /// "Activates" the staged update by letting updog set up the appropriate boot flags
-fn activate(status: &mut UpdateStatus) -> Result<()> {
-    fork_and_return!({
+fn activate(status: &mut UpdateStatus, fork: bool) -> Result<()> {
+    fork_and_return!(fork, {
         debug!("Spawning 'updog update-apply'");
         let output = Command::new("updog")
             .arg("update-apply")
@@ -238,8 +269,8 @@ fn activate(status: &mut UpdateStatus) -> Result<()> {
 }
 
 /// "Deactivates" the staged update by rolling back actions done by `activate_update`
-fn deactivate(status: &mut UpdateStatus) -> Result<()> {
-    fork_and_return!({
+fn deactivate(status: &mut UpdateStatus, fork: bool) -> Result<()> {
+    fork_and_return!(fork, {
         debug!("Spawning 'updog update-revert'");
         let output = Command::new("updog")
             .arg("update-revert")
@@ -255,15 +286,17 @@ fn deactivate(status: &mut UpdateStatus) -> Result<()> {
 }
 
 /// Given the update command, this drives the update state machine.
+/// Commands run in a forked process if `fork` is true.
 fn drive_state_machine(
     update_status: &mut UpdateStatus,
     operation: &UpdateCommand,
     socket_path: &str,
+    fork: bool,
 ) -> Result<()> {
     let new_state = match (operation, update_status.update_state()) {
         (UpdateCommand::Refresh, UpdateState::Idle)
         | (UpdateCommand::Refresh, UpdateState::Available) => {
-            if refresh(update_status, socket_path)? {
+            if refresh(update_status, socket_path, fork)? {
                 // Transitions state to `Available` if there is an available update
                 UpdateState::Available
             } else {
@@ -273,7 +306,7 @@ fn drive_state_machine(
         }
         // Refreshing the list of updates is allowed under every update state
         (UpdateCommand::Refresh, _) => {
-            refresh(update_status, socket_path)?;
+            refresh(update_status, socket_path, fork)?;
             // No need to transition state here as we're already beyond `Available`
             update_status.update_state().to_owned()
         }
@@ -285,7 +318,7 @@ fn drive_state_machine(
                 update_status.chosen_update().is_some(),
                 error::UpdateDoesNotExistSnafu
             );
-            prepare(update_status)?;
+            prepare(update_status, fork)?;
             // If we succeed in preparing the update, we transition to `Staged`
             UpdateState::Staged
         }
@@ -296,7 +329,7 @@ fn drive_state_machine(
                 update_status.staging_partition().is_some(),
                 error::StagingPartitionSnafu
             );
-            activate(update_status)?;
+            activate(update_status, fork)?;
             // If we succeed in activating the update, we transition to `Ready`
             UpdateState::Ready
         }
@@ -307,7 +340,7 @@ fn drive_state_machine(
                 update_status.staging_partition().is_some(),
                 error::StagingPartitionSnafu
             );
-            deactivate(update_status)?;
+            deactivate(update_status, fork)?;
             // If we succeed in deactivating the update, we transition to `Staged`
             UpdateState::Staged
         }
@@ -324,6 +357,118 @@ fn drive_state_machine(
     Ok(())
 }
 
+/// Checks the maintenance window, and if it's open, applies the chosen update.  Records the
+/// decision in the update status.  Returns true if we should reboot into the update.
+fn maintenance_window(update_status: &mut UpdateStatus, socket_path: &str) -> Result<bool> {
+    let settings = get_settings(socket_path)?;
+    let now = Utc::now();
+    let window = match MaintenanceWindow::from_settings(&settings)? {
+        Some(window) => window,
+        None => {
+            let mut decision = MaintenanceDecision::new(None, None);
+            decision.set_reason("maintenance window is not enabled");
+            update_status.set_maintenance_decision(decision);
+            return Ok(false);
+        }
+    };
+    let window_start = match window.open_at(now) {
+        Some(window_start) => window_start,
+        None => {
+            let mut decision = MaintenanceDecision::new(None, window.next_open(now));
+            decision.set_reason("maintenance window is closed");
+            update_status.set_maintenance_decision(decision);
+            return Ok(false);
+        }
+    };
+
+    let mut decision = MaintenanceDecision::new(Some(window_start), None);
+    let result = apply_in_window(
+        update_status,
+        socket_path,
+        &window,
+        window_start,
+        &mut decision,
+    );
+    if let Err(e) = &result {
+        decision.set_reason(e.to_string());
+    }
+    update_status.set_maintenance_decision(decision);
+    result
+}
+
+/// Runs the update commands needed to reboot into the chosen update, stopping early if there's
+/// nothing to do or if the window doesn't allow another reboot.
+fn apply_in_window(
+    update_status: &mut UpdateStatus,
+    socket_path: &str,
+    window: &MaintenanceWindow,
+    window_start: DateTime<Utc>,
+    decision: &mut MaintenanceDecision,
+) -> Result<bool> {
+    let mut run = |update_status: &mut UpdateStatus, command: UpdateCommand| {
+        decision.add_command(command.clone());
+        drive_state_machine(update_status, &command, socket_path, false)
+    };
+
+    run(update_status, UpdateCommand::Refresh)?;
+    if let UpdateState::Idle = update_status.update_state() {
+        decision.set_reason("no update available");
+        return Ok(false);
+    }
+    if let UpdateState::Available = update_status.update_state() {
+        run(update_status, UpdateCommand::Prepare)?;
+    }
+
+    let mut window_state = WindowState::load(MAINTENANCE_STATE_FILE);
+    let reboots = window_state.reboots_in(window_start);
+    if reboots >= window.max_reboots() {
+        decision.set_reason(format!(
+            "update staged, but already rebooted {} of {} times allowed in this window",
+            reboots,
+            window.max_reboots()
+        ));
+        return Ok(false);
+    }
+    // Preparing an update can take a while, so make sure we're still in the window.
+    if window.open_at(Utc::now()) != Some(window_start) {
+        decision.set_reason("update staged, but maintenance window closed");
+        return Ok(false);
+    }
+    if let UpdateState::Staged = update_status.update_state() {
+        run(update_status, UpdateCommand::Activate)?;
+    }
+
+    window_state.add_reboot(window_start);
+    window_state.save(MAINTENANCE_STATE_FILE)?;
+    decision.set_reboot();
+    decision.set_reason("rebooting into update");
+    Ok(true)
+}
+
+/// Reboots the host through the API.
+///
+/// NOTE: like `get_settings`, this creates its own tokio runtime, and shouldn't be called while
+/// running another.
+fn reboot(socket_path: &str) -> Result<()> {
+    let uri = "/actions/reboot";
+    let method = "POST";
+
+    let rt = Runtime::new().context(error::RuntimeSnafu)?;
+    let try_response =
+        rt.block_on(async { apiclient::raw_request(&socket_path, uri, method, None).await });
+    let (code, response_body) = try_response.context(error::APIRequestSnafu { method, uri })?;
+    ensure!(
+        code.is_success(),
+        error::APIResponseSnafu {
+            method,
+            uri,
+            code,
+            response_body,
+        }
+    );
+    Ok(())
+}
+
 fn run() -> Result<()> {
     // Parse and store the args passed to the program
     let args = parse_args(env::args());
@@ -348,9 +493,21 @@ fn run() -> Result<()> {
     // The commands inside drive_state_machine update the update_status object (hence &mut) to
     // reflect success or failure, and we want to reflect that in our status file regardless of
     // success, so we store the result rather than returning early here.
-    let result = drive_state_machine(&mut update_status, &args.subcommand, &args.socket_path);
+    let mut reboot_now = false;
+    let result = match &args.subcommand {
+        Subcommand::Update(command) => {
+            drive_state_machine(&mut update_status, command, &args.socket_path, true)
+        }
+        Subcommand::MaintenanceWindow => {
+            maintenance_window(&mut update_status, &args.socket_path).map(|r| reboot_now = r)
+        }
+    };
     write_update_status(&update_status)?;
-    result
+    result?;
+    if reboot_now {
+        reboot(&args.socket_path)?;
+    }
+    Ok(())
 }
 
 fn match_error_to_exit_status(err: Error) -> i32 {
@@ -0,0 +1,377 @@
+//! Maintenance windows limit when thar-be-updates applies updates on its own, as configured in
+//! `settings.updates.maintenance-window`.
+
+use crate::error::{self, Result};
+use crate::status::UpdateCommand;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, NaiveDate, NaiveTime, TimeZone, Timelike, Utc,
+    Weekday,
+};
+use serde::{Deserialize, Serialize};
+use snafu::{OptionExt, ResultExt};
+use std::convert::TryFrom;
+use std::fs;
+use std::path::Path;
+
+/// Where we remember reboots across the reboots themselves; `/run` doesn't survive them.
+pub const MAINTENANCE_STATE_FILE: &str = "/var/lib/thar-be-updates/maintenance-window.json";
+
+/// A maintenance window, which opens at `start` on each of `days` and closes at `end`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MaintenanceWindow {
+    days: Vec<Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+    offset: FixedOffset,
+    max_reboots: u32,
+}
+
+impl MaintenanceWindow {
+    /// Reads the maintenance window from the given settings.  Returns `None` if there's no enabled
+    /// window, in which case updates are only applied through the API.
+    pub fn from_settings(settings: &serde_json::Value) -> Result<Option<Self>> {
+        let setting = "/settings/updates/maintenance-window";
+        let value = &settings["updates"]["maintenance-window"];
+        if value.is_null() {
+            return Ok(None);
+        }
+        let window: model::MaintenanceWindow =
+            serde_json::from_value(value.to_owned()).context(error::GetSettingSnafu { setting })?;
+        if window.enabled != Some(true) {
+            return Ok(None);
+        }
+
+        // Without days, the window opens every day.
+        let days = match window.days {
+            Some(days) if !days.is_empty() => days
+                .iter()
+                .map(|day| {
+                    day.parse().ok().context(error::MaintenanceWindowSnafu {
+                        reason: format!("invalid day '{}'", &**day),
+                    })
+                })
+                .collect::<Result<_>>()?,
+            _ => vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+                Weekday::Sat,
+                Weekday::Sun,
+            ],
+        };
+        let start = window.start.context(error::MaintenanceWindowSnafu {
+            reason: "'start' is required",
+        })?;
+        let end = window.end.context(error::MaintenanceWindowSnafu {
+            reason: "'end' is required",
+        })?;
+        let offset = match window.timezone {
+            Some(timezone) => parse_offset(&timezone)?,
+            None => FixedOffset::east_opt(0).context(error::MaintenanceWindowSnafu {
+                reason: "invalid timezone",
+            })?,
+        };
+
+        Ok(Some(Self {
+            days,
+            start: parse_time(&start)?,
+            end: parse_time(&end)?,
+            offset,
+            // Reboot once per window unless told otherwise.
+            max_reboots: window.max_reboots.unwrap_or(1),
+        }))
+    }
+
+    /// The number of times we may reboot into updates in each window.
+    pub fn max_reboots(&self) -> u32 {
+        self.max_reboots
+    }
+
+    /// Returns the start of the window that's open at `now`, if any.
+    pub fn open_at(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.with_timezone(&self.offset).naive_local().date();
+        // Yesterday's window may still be open if it crosses midnight.
+        [today.pred_opt()?, today]
+            .iter()
+            .filter_map(|date| self.occurrence(*date))
+            .find(|(start, end)| *start <= now && now < *end)
+            .map(|(start, _)| start)
+    }
+
+    /// Returns when the window next opens after `now`.
+    pub fn next_open(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let today = now.with_timezone(&self.offset).naive_local().date();
+        (0..=7)
+            .filter_map(|days| today.checked_add_signed(Duration::days(days)))
+            .filter_map(|date| self.occurrence(date))
+            .map(|(start, _)| start)
+            .find(|start| *start > now)
+    }
+
+    /// Returns the start and end of the window that opens on `date`, if it opens that day.  The
+    /// window ends on the next day if `end` isn't later than `start`.
+    fn occurrence(&self, date: NaiveDate) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        if !self.days.contains(&date.weekday()) {
+            return None;
+        }
+        let start = date.and_time(self.start);
+        let mut end = date.and_time(self.end);
+        if self.end <= self.start {
+            end += Duration::days(1);
+        }
+        Some((
+            self.offset
+                .from_local_datetime(&start)
+                .single()?
+                .with_timezone(&Utc),
+            self.offset
+                .from_local_datetime(&end)
+                .single()?
+                .with_timezone(&Utc),
+        ))
+    }
+}
+
+/// Parses a validated `TimeOfDay`, like '02:30'.
+fn parse_time(time: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M")
+        .ok()
+        .context(error::MaintenanceWindowSnafu {
+            reason: format!("invalid time '{}'", time),
+        })
+}
+
+/// Parses a validated `TimezoneOffset`, like 'UTC' or '-08:00'.
+fn parse_offset(timezone: &str) -> Result<FixedOffset> {
+    let invalid = || error::MaintenanceWindowSnafu {
+        reason: format!("invalid timezone '{}'", timezone),
+    };
+    if timezone == "UTC" {
+        return FixedOffset::east_opt(0).context(invalid());
+    }
+    let (sign, offset) = match timezone.split_at(1) {
+        ("+", offset) => (1, offset),
+        ("-", offset) => (-1, offset),
+        _ => return invalid().fail(),
+    };
+    let offset = NaiveTime::parse_from_str(offset, "%H:%M")
+        .ok()
+        .context(invalid())?;
+    let seconds = i32::try_from(offset.num_seconds_from_midnight())
+        .ok()
+        .context(invalid())?;
+    FixedOffset::east_opt(sign * seconds).context(invalid())
+}
+
+/// What we remember about the current window between runs, including across reboots.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct WindowState {
+    /// The start of the window that `reboots` counts reboots for.
+    window_start: Option<DateTime<Utc>>,
+    reboots: u32,
+}
+
+impl WindowState {
+    /// Loads the state from `path`.  If it's missing or unreadable, we haven't rebooted for an
+    /// update in any window we know of.
+    pub fn load<P: AsRef<Path>>(path: P) -> Self {
+        fs::read(path.as_ref())
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let data = serde_json::to_vec(self).context(error::WindowStateSerializeSnafu)?;
+        fs::write(path, data).context(error::WindowStateWriteSnafu { path })
+    }
+
+    /// Returns how many times we've rebooted into updates in the window starting at `start`.
+    pub fn reboots_in(&self, start: DateTime<Utc>) -> u32 {
+        if self.window_start == Some(start) {
+            self.reboots
+        } else {
+            0
+        }
+    }
+
+    /// Counts a reboot into an update in the window starting at `start`.
+    pub fn add_reboot(&mut self, start: DateTime<Utc>) {
+        self.reboots = self.reboots_in(start) + 1;
+        self.window_start = Some(start);
+    }
+}
+
+/// The most recent decision made by the maintenance window check, stored in the update status.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MaintenanceDecision {
+    timestamp: DateTime<Utc>,
+    /// The start of the open window, if there is one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    window_start: Option<DateTime<Utc>>,
+    /// When the window next opens, if it's closed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_window: Option<DateTime<Utc>>,
+    /// The update commands we ran, in order.
+    #[serde(default)]
+    commands: Vec<UpdateCommand>,
+    /// Whether we rebooted into the update.
+    #[serde(default)]
+    reboot: bool,
+    /// Why we stopped where we did.
+    reason: String,
+}
+
+impl MaintenanceDecision {
+    pub fn new(window_start: Option<DateTime<Utc>>, next_window: Option<DateTime<Utc>>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            window_start,
+            next_window,
+            commands: Vec::new(),
+            reboot: false,
+            reason: String::new(),
+        }
+    }
+
+    pub fn add_command(&mut self, command: UpdateCommand) {
+        self.commands.push(command);
+    }
+
+    pub fn set_reboot(&mut self) {
+        self.reboot = true;
+    }
+
+    pub fn set_reason<S: Into<String>>(&mut self, reason: S) {
+        self.reason = reason.into();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn make_window(days: &[&str], start: &str, end: &str, timezone: &str) -> MaintenanceWindow {
+        let settings = json!({"updates": {"maintenance-window": {
+            "enabled": true,
+            "days": days,
+            "start": start,
+            "end": end,
+            "timezone": timezone,
+        }}});
+        MaintenanceWindow::from_settings(&settings)
+            .unwrap()
+            .unwrap()
+    }
+
+    fn time(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn disabled_window() {
+        for settings in &[
+            json!({"updates": {}}),
+            json!({"updates": {"maintenance-window": {"start": "01:00", "end": "02:00"}}}),
+            json!({"updates": {"maintenance-window": {"enabled": false, "start": "01:00"}}}),
+        ] {
+            assert!(MaintenanceWindow::from_settings(settings)
+                .unwrap()
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn incomplete_window() {
+        let settings =
+            json!({"updates": {"maintenance-window": {"enabled": true, "start": "01:00"}}});
+        assert!(MaintenanceWindow::from_settings(&settings).is_err());
+    }
+
+    #[test]
+    fn defaults() {
+        let settings = json!({"updates": {"maintenance-window": {
+            "enabled": true, "start": "01:00", "end": "02:00",
+        }}});
+        let window = MaintenanceWindow::from_settings(&settings)
+            .unwrap()
+            .unwrap();
+        assert_eq!(window.max_reboots(), 1);
+        // Every day, in UTC.
+        let start = time("2022-10-05T01:00:00Z");
+        assert_eq!(window.open_at(time("2022-10-05T01:30:00Z")), Some(start));
+        assert_eq!(window.open_at(time("2022-10-05T02:00:00Z")), None);
+        assert_eq!(
+            window.next_open(time("2022-10-05T02:00:00Z")),
+            Some(time("2022-10-06T01:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn open_on_days() {
+        // 2022-10-08 is a Saturday.
+        let window = make_window(&["sat", "sun"], "02:00", "04:00", "UTC");
+        let start = time("2022-10-08T02:00:00Z");
+        assert_eq!(window.open_at(time("2022-10-08T01:59:59Z")), None);
+        assert_eq!(window.open_at(time("2022-10-08T02:00:00Z")), Some(start));
+        assert_eq!(window.open_at(time("2022-10-08T03:59:59Z")), Some(start));
+        assert_eq!(window.open_at(time("2022-10-08T04:00:00Z")), None);
+        assert_eq!(window.open_at(time("2022-10-10T03:00:00Z")), None);
+        assert_eq!(
+            window.next_open(time("2022-10-09T05:00:00Z")),
+            Some(time("2022-10-15T02:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn crosses_midnight() {
+        // Opens Friday 22:00, closes Saturday 02:00.
+        let window = make_window(&["fri"], "22:00", "02:00", "UTC");
+        let start = time("2022-10-07T22:00:00Z");
+        assert_eq!(window.open_at(time("2022-10-08T01:00:00Z")), Some(start));
+        assert_eq!(window.open_at(time("2022-10-08T02:00:00Z")), None);
+        // Saturday isn't a window day, so Saturday night is closed.
+        assert_eq!(window.open_at(time("2022-10-08T23:00:00Z")), None);
+    }
+
+    #[test]
+    fn timezone_offset() {
+        // 01:00-03:00 at UTC-08:00 is 09:00-11:00 UTC.
+        let window = make_window(&["mon"], "01:00", "03:00", "-08:00");
+        let start = time("2022-10-10T09:00:00Z");
+        assert_eq!(window.open_at(time("2022-10-10T10:00:00Z")), Some(start));
+        assert_eq!(window.open_at(time("2022-10-10T02:00:00Z")), None);
+
+        // Monday 01:00 at UTC+05:30 is Sunday 19:30 UTC.
+        let window = make_window(&["mon"], "01:00", "03:00", "+05:30");
+        assert_eq!(
+            window.open_at(time("2022-10-09T20:00:00Z")),
+            Some(time("2022-10-09T19:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn reboots_per_window() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("state.json");
+        let first = time("2022-10-08T02:00:00Z");
+        let second = time("2022-10-09T02:00:00Z");
+
+        let mut state = WindowState::load(&path);
+        assert_eq!(state.reboots_in(first), 0);
+        state.add_reboot(first);
+        state.save(&path).unwrap();
+
+        let mut state = WindowState::load(&path);
+        assert_eq!(state.reboots_in(first), 1);
+        // Reboots only count against the window they happened in.
+        assert_eq!(state.reboots_in(second), 0);
+        state.add_reboot(second);
+        assert_eq!(state.reboots_in(second), 1);
+        assert_eq!(state.reboots_in(first), 0);
+    }
+}
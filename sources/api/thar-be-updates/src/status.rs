@@ -1,16 +1,19 @@
 use crate::error;
 use crate::error::Result;
+use crate::maintenance::MaintenanceDecision;
 use bottlerocket_release::BottlerocketRelease;
 use chrono::{DateTime, Utc};
 use model::modeled_types::FriendlyVersion;
 use serde::{Deserialize, Serialize};
 use signpost::State;
 use snafu::{OptionExt, ResultExt};
+use std::collections::BTreeMap;
 use std::convert::TryInto;
 use std::fs::File;
 use std::os::unix::process::ExitStatusExt;
 use std::process::Output;
 use tokio::runtime::Runtime;
+use update_metadata::Channel;
 
 pub const UPDATE_LOCKFILE: &str = "/run/lock/thar-be-updates.lock";
 pub const UPDATE_STATUS_FILE: &str = "/run/cache/thar-be-updates/status.json";
@@ -29,12 +32,23 @@ pub struct UpdateImage {
     arch: String,
     version: semver::Version,
     variant: String,
+    /// The release channel of an update; we don't know it for the image we booted from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    channel: Option<Channel>,
 }
 
 impl UpdateImage {
     pub fn version(&self) -> &semver::Version {
         &self.version
     }
+
+    pub fn arch(&self) -> &str {
+        &self.arch
+    }
+
+    pub fn variant(&self) -> &str {
+        &self.variant
+    }
 }
 
 /// StagedImage represents a Bottlerocket image that is written to a partition set
@@ -46,6 +60,14 @@ pub struct StagedImage {
 }
 
 impl StagedImage {
+    pub fn image(&self) -> &UpdateImage {
+        &self.image
+    }
+
+    pub fn next_to_boot(&self) -> bool {
+        self.next_to_boot
+    }
+
     pub(crate) fn set_next_to_boot(&mut self, next_to_boot: bool) {
         self.next_to_boot = next_to_boot
     }
@@ -69,7 +91,7 @@ pub enum UpdateCommand {
 
 /// CommandResult represents the result of an issued command
 #[derive(Debug, Clone, Deserialize, Serialize)]
-struct CommandResult {
+pub struct CommandResult {
     cmd_type: UpdateCommand,
     cmd_status: CommandStatus,
     timestamp: DateTime<Utc>,
@@ -77,14 +99,34 @@ struct CommandResult {
     stderr: Option<String>,
 }
 
+impl CommandResult {
+    pub fn cmd_type(&self) -> &UpdateCommand {
+        &self.cmd_type
+    }
+
+    pub fn cmd_status(&self) -> &CommandStatus {
+        &self.cmd_status
+    }
+
+    pub fn timestamp(&self) -> &DateTime<Utc> {
+        &self.timestamp
+    }
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 pub struct UpdateStatus {
     update_state: UpdateState,
     available_updates: Vec<semver::Version>,
+    /// The release channel of each available update.
+    #[serde(default)]
+    available_update_channels: BTreeMap<semver::Version, Channel>,
     chosen_update: Option<UpdateImage>,
     active_partition: Option<StagedImage>,
     staging_partition: Option<StagedImage>,
     most_recent_command: Option<CommandResult>,
+    /// The most recent decision about applying updates in the maintenance window.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    maintenance_window: Option<MaintenanceDecision>,
 }
 
 impl Default for UpdateStatus {
@@ -111,7 +153,7 @@ pub fn get_update_status(_lockfile: &File) -> Result<UpdateStatus> {
 /// not be called if you're running another tokio runtime.  The program structure requires forking
 /// to handle long-running update actions, and the tokio runtime uses threading, which generally
 /// isn't safe over forks; instead, we create and drop one here for the short period we need it.
-fn get_settings(socket_path: &str) -> Result<serde_json::Value> {
+pub fn get_settings(socket_path: &str) -> Result<serde_json::Value> {
     let uri = "/settings";
     let method = "GET";
 
@@ -131,10 +173,12 @@ impl UpdateStatus {
         Self {
             update_state: UpdateState::Idle,
             available_updates: vec![],
+            available_update_channels: BTreeMap::new(),
             chosen_update: None,
             active_partition: None,
             staging_partition: None,
             most_recent_command: None,
+            maintenance_window: None,
         }
     }
 
@@ -153,6 +197,18 @@ impl UpdateStatus {
         }
     }
 
+    pub fn available_updates(&self) -> &[semver::Version] {
+        &self.available_updates
+    }
+
+    pub fn active_partition(&self) -> Option<&StagedImage> {
+        self.active_partition.as_ref()
+    }
+
+    pub fn most_recent_command(&self) -> Option<&CommandResult> {
+        self.most_recent_command.as_ref()
+    }
+
     pub fn staging_partition(&self) -> Option<&StagedImage> {
         match &self.staging_partition {
             Some(partition_info) => Some(partition_info),
@@ -168,6 +224,7 @@ impl UpdateStatus {
             arch: os_info.arch,
             version: os_info.version_id,
             variant: os_info.variant_id,
+            channel: None,
         };
 
         // Get partition set information. We can infer the version of the image in the active
@@ -241,6 +298,11 @@ impl UpdateStatus {
         self.most_recent_command = Some(command_result);
     }
 
+    /// Records the most recent decision about applying updates in the maintenance window
+    pub fn set_maintenance_decision(&mut self, decision: MaintenanceDecision) {
+        self.maintenance_window = Some(decision);
+    }
+
     /// Returns the update information of the 'latest' available update
     pub fn get_latest_update(
         updates: Vec<update_metadata::Update>,
@@ -267,6 +329,10 @@ impl UpdateStatus {
     ) -> Result<bool> {
         // Extract the version to store
         self.available_updates = updates.iter().map(|u| u.version.to_owned()).collect();
+        self.available_update_channels = updates
+            .iter()
+            .map(|u| (u.version.to_owned(), u.channel))
+            .collect();
         // Check if the 'version-lock'ed update is available as the 'chosen' update
         // Retrieve the 'version-lock' setting
         let settings = get_settings(socket_path)?;
@@ -284,6 +350,7 @@ impl UpdateStatus {
                     arch: latest_update.arch,
                     version: latest_update.version,
                     variant: latest_update.variant,
+                    channel: Some(latest_update.channel),
                 });
                 return Ok(true);
             }
@@ -301,6 +368,7 @@ impl UpdateStatus {
                             arch: update.arch.clone(),
                             version: chosen_version,
                             variant: update.variant.clone(),
+                            channel: Some(update.channel),
                         });
                         return Ok(true);
                     }
@@ -0,0 +1,22 @@
+use bottlerocket_variant::{Variant, VARIANT_ENV};
+
+fn main() {
+    let variant = match Variant::from_env() {
+        Ok(variant) => variant,
+        Err(e) => {
+            eprintln!(
+                "For local builds, you must set the '{}' environment variable so we know \
+                which API model to validate against. Valid values are the directories in \
+                models/src/variants/, for example 'aws-ecs-1': {}",
+                VARIANT_ENV, e,
+            );
+            std::process::exit(1);
+        }
+    };
+    Variant::rerun_if_changed();
+    // The model is chosen at build time, so record which variant it belongs to; we check that it
+    // matches the variant the user asks for.
+    println!("cargo:rustc-env=BUILT_VARIANT={}", variant);
+
+    generate_readme::from_main().unwrap();
+}
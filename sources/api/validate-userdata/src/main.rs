@@ -0,0 +1,378 @@
+/*!
+# Introduction
+
+validate-userdata checks Bottlerocket user data against the settings model of a variant, without
+booting a host.
+It reads the user data the same way early-boot-config does, deserializes the `settings` into the
+variant's model, and reports every invalid key along with the reason it was rejected, like an
+invalid hostname or an unknown setting.
+
+```shell
+validate-userdata --user-data user-data.toml
+```
+
+User data compressed with gzip is detected automatically.
+If the user data is base64 encoded, as some platforms require, pass `--base64`.
+
+The variant is fixed when the tool is built: each build can only check user data for the variant
+named in the `VARIANT` environment variable at build time, and there's no way to choose another
+variant's model when running it.
+To check user data for several variants, build the tool once for each, for example:
+
+```shell
+VARIANT=aws-k8s-1.24 cargo build -p validate-userdata
+```
+
+The usage message shows which variant a build checks.
+`--expect-variant` doesn't choose a model; it only guards against using the wrong build, for
+example in a script that keeps builds for several variants.
+If it names a different variant than the one the tool was built for, the tool exits with an error
+rather than checking against the wrong model.
+
+```shell
+validate-userdata --expect-variant aws-k8s-1.24 --user-data user-data.toml
+```
+
+The tool exits with:
+* 0 if the user data is valid
+* 1 if any settings are invalid, or for other errors, like failing to read or decode the user data
+* 2 for usage errors
+*/
+
+#![deny(rust_2018_idioms)]
+
+use early_boot_config::compression::expand_slice_maybe;
+use serde_json::Value;
+use serde_path_to_error::{Path, Segment};
+use snafu::{ensure, OptionExt, ResultExt};
+use std::fmt;
+use std::fs;
+use std::{env, process};
+
+/// The variant whose settings model this program was built against.
+const BUILT_VARIANT: &str = env!("BUILT_VARIANT");
+
+/// Store the args we receive on the command line
+struct Args {
+    expect_variant: Option<String>,
+    user_data_path: String,
+    base64: bool,
+}
+
+/// Print a usage message in the event a bad arg is passed
+fn usage() -> ! {
+    let program_name = env::args().next().unwrap_or_else(|| "program".to_string());
+    eprintln!(
+        r"Usage: {}
+            --user-data PATH
+            [ --base64 ]
+            [ --expect-variant VARIANT ]
+
+    This build validates user data for variant {} only; the variant is fixed
+    when the program is built.
+    Compressed user data is detected automatically; pass --base64 if it's base64 encoded.
+
+    --expect-variant doesn't change the variant that's checked; it makes the
+    program fail if it was built for a different variant.
+
+    Exits 0 if the user data is valid, 1 if settings are invalid or it can't be
+    read, and 2 for usage errors.",
+        program_name, BUILT_VARIANT,
+    );
+    process::exit(2);
+}
+
+/// Prints a more specific message before exiting through usage().
+fn usage_msg<S: AsRef<str>>(msg: S) -> ! {
+    eprintln!("{}\n", msg.as_ref());
+    usage();
+}
+
+/// Parse the args to the program and return an Args struct
+fn parse_args(args: env::Args) -> Args {
+    let mut expect_variant = None;
+    let mut user_data_path = None;
+    let mut base64 = false;
+
+    let mut iter = args.skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_ref() {
+            "--expect-variant" => {
+                expect_variant = Some(
+                    iter.next()
+                        .unwrap_or_else(|| usage_msg("Did not give argument to --expect-variant")),
+                )
+            }
+
+            "--user-data" => {
+                user_data_path = Some(
+                    iter.next()
+                        .unwrap_or_else(|| usage_msg("Did not give argument to --user-data")),
+                )
+            }
+
+            "--base64" => base64 = true,
+
+            _ => usage(),
+        }
+    }
+
+    Args {
+        expect_variant,
+        user_data_path: user_data_path.unwrap_or_else(|| usage_msg("Did not give --user-data")),
+        base64,
+    }
+}
+
+/// Problem represents one invalid key in the user data.
+#[derive(Debug, PartialEq)]
+struct Problem {
+    /// The dotted path to the invalid key, starting with "settings".
+    path: String,
+    /// Why the model rejected the key.
+    message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Reads the user data file, decoding and decompressing it as needed, and returns the settings
+/// from it as JSON, the form in which early-boot-config sends them to the API.
+fn read_settings(path: &str, base64: bool) -> Result<Value> {
+    let mut data = fs::read(path).context(error::ReadSnafu { path })?;
+    if base64 {
+        // Encoded data is often wrapped over several lines, which the decoder doesn't accept.
+        data.retain(|b| !b.is_ascii_whitespace());
+        data = base64::decode(&data).context(error::Base64DecodeSnafu { path })?;
+    }
+    let user_data = expand_slice_maybe(&data).context(error::DecompressionSnafu { path })?;
+
+    let mut user_data: toml::Value = toml::from_str(&user_data).context(error::TomlSnafu)?;
+    let settings = user_data
+        .as_table_mut()
+        .context(error::NotTomlTableSnafu)?
+        .remove("settings")
+        .context(error::MissingSettingsSnafu)?;
+
+    serde_json::to_value(settings).context(error::ToJsonSnafu)
+}
+
+/// Deserializes the given settings into the model and returns every problem found.  Deserializing
+/// stops at the first error, so after each one we remove the offending key and try again, until
+/// the rest of the settings are accepted.
+fn find_problems(mut settings: Value) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    loop {
+        let error = match serde_path_to_error::deserialize::<_, model::Settings>(&settings) {
+            Ok(_) => return problems,
+            Err(e) => e,
+        };
+        problems.push(Problem {
+            path: dotted_path(error.path()),
+            message: error.inner().to_string(),
+        });
+        // If we can't narrow the error down to a key, there's nothing left we can check.
+        if !remove_key(&mut settings, error.path()) {
+            return problems;
+        }
+    }
+}
+
+/// Returns the path to a setting in the dotted form users see in the API, for example
+/// `settings.kernel.sysctl."vm.max_map_count"` or `settings.network.hosts[0]`.  Like in the API,
+/// names that contain dots are quoted.
+fn dotted_path(path: &Path) -> String {
+    let mut dotted = String::from("settings");
+    for segment in path.iter() {
+        match segment {
+            Segment::Map { key } => {
+                dotted.push('.');
+                if key.contains('.') {
+                    dotted.push_str(&format!("\"{}\"", key));
+                } else {
+                    dotted.push_str(key);
+                }
+            }
+            Segment::Seq { index } => dotted.push_str(&format!("[{}]", index)),
+            Segment::Enum { .. } | Segment::Unknown => {}
+        }
+    }
+    dotted
+}
+
+/// Removes the key containing the error at the given path, so the rest of the settings can be
+/// checked.  Lists and tuples are removed as a whole, since removing one item could make the
+/// rest invalid.  Returns false if the path didn't lead to a key that could be removed.
+fn remove_key(settings: &mut Value, path: &Path) -> bool {
+    let keys: Vec<&str> = path
+        .iter()
+        .map_while(|segment| match segment {
+            Segment::Map { key } => Some(key.as_str()),
+            _ => None,
+        })
+        .collect();
+    let (last, parents) = match keys.split_last() {
+        Some(split) => split,
+        None => return false,
+    };
+
+    let mut current = settings;
+    for key in parents {
+        current = match current.get_mut(key) {
+            Some(value) => value,
+            None => return false,
+        };
+    }
+    match current.as_object_mut() {
+        Some(object) => object.remove(*last).is_some(),
+        None => false,
+    }
+}
+
+fn run() -> Result<()> {
+    let args = parse_args(env::args());
+    if let Some(expected) = args.expect_variant {
+        ensure!(
+            expected == BUILT_VARIANT,
+            error::VariantMismatchSnafu { expected }
+        );
+    }
+
+    let settings = read_settings(&args.user_data_path, args.base64)?;
+    let problems = find_problems(settings);
+    ensure!(problems.is_empty(), error::InvalidSnafu { problems });
+
+    println!(
+        "{} contains valid settings for {}",
+        args.user_data_path, BUILT_VARIANT
+    );
+    Ok(())
+}
+
+// Returning a Result from main makes it print a Debug representation of the error, but with Snafu
+// we have nice Display representations of the error, so we wrap "main" (run) and print any error.
+// https://github.com/shepmaster/snafu/issues/110
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+mod error {
+    use super::{Problem, BUILT_VARIANT};
+    use snafu::Snafu;
+    use std::io;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(super)))]
+    pub(super) enum Error {
+        #[snafu(display("Unable to decode base64 in {}: {}", path, source))]
+        Base64Decode {
+            path: String,
+            source: base64::DecodeError,
+        },
+
+        #[snafu(display("Failed to decompress {}: {}", path, source))]
+        Decompression { path: String, source: io::Error },
+
+        #[snafu(display(
+            "Found {} invalid settings:\n{}",
+            problems.len(),
+            problems.iter().map(|p| format!("  {}", p)).collect::<Vec<_>>().join("\n")
+        ))]
+        Invalid { problems: Vec<Problem> },
+
+        #[snafu(display("User data did not contain 'settings' section"))]
+        MissingSettings,
+
+        #[snafu(display("User data is not a TOML table"))]
+        NotTomlTable,
+
+        #[snafu(display("Failed to read {}: {}", path, source))]
+        Read { path: String, source: io::Error },
+
+        #[snafu(display("Failed to convert settings to JSON: {}", source))]
+        ToJson { source: serde_json::Error },
+
+        #[snafu(display("Error parsing TOML user data: {}", source))]
+        Toml { source: toml::de::Error },
+
+        #[snafu(display(
+            "This build of validate-userdata checks variant {}, not {}; rebuild with VARIANT={}",
+            BUILT_VARIANT,
+            expected,
+            expected
+        ))]
+        VariantMismatch { expected: String },
+    }
+}
+type Result<T> = std::result::Result<T, error::Error>;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    fn problem_paths(settings: Value) -> Vec<String> {
+        find_problems(settings)
+            .into_iter()
+            .map(|p| p.path)
+            .collect()
+    }
+
+    #[test]
+    fn valid_settings() {
+        let settings = json!({
+            "motd": "hi",
+            "network": {"hostname": "node-1"},
+            "kernel": {"sysctl": {"vm.max_map_count": "262144"}},
+        });
+        assert!(find_problems(settings).is_empty());
+    }
+
+    #[test]
+    fn reports_every_invalid_key() {
+        let settings = json!({
+            "motd": "hi",
+            "network": {"hostname": "-not-valid"},
+            "kernel": {"sysctl": {"vm..max_map_count": "262144"}},
+        });
+        assert_eq!(
+            problem_paths(settings),
+            vec![
+                r#"settings.kernel.sysctl."vm..max_map_count""#,
+                "settings.network.hostname"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unknown_keys() {
+        let settings = json!({
+            "motd": "hi",
+            "not-a-setting": true,
+            "network": {"hostname": "node-1", "not-a-network-setting": true},
+        });
+        let problems = find_problems(settings);
+        assert_eq!(problems.len(), 2);
+        assert_eq!(problems[0].path, "settings.network.not-a-network-setting");
+        assert!(problems[0].message.contains("unknown field"));
+        assert_eq!(problems[1].path, "settings.not-a-setting");
+    }
+
+    #[test]
+    fn wrong_types() {
+        let settings = json!({
+            "motd": ["not", "a", "string"],
+            "network": {"hosts": [["10.0.0.1", "not-a-list"]]},
+        });
+        assert_eq!(
+            problem_paths(settings),
+            vec!["settings.motd", "settings.network.hosts[0][1]"]
+        );
+    }
+}
@@ -1,7 +1,8 @@
-//! Provides a function for compressing a directory's contents into a tarball.
+//! Provides functions for compressing a directory's contents into a tarball.
 
 use crate::error::{self, Result};
 use std::fs::{self, File};
+use std::io::Write;
 use std::path::Path;
 
 use flate2::write::GzEncoder;
@@ -39,6 +40,25 @@ where
         .context(error::TarballCloseSnafu { path: indir })
 }
 
+/// Writes a tarball with all the contents of directory `indir` to `out` as it's built, for example
+/// to stdout.
+pub(crate) fn stream_tarball<P, W>(indir: P, out: W) -> Result<()>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    let encoder = GzEncoder::new(out, Compression::default());
+    let mut tarball = tar::Builder::new(encoder);
+    tarball
+        .append_dir_all(crate::TARBALL_DIRNAME, indir)
+        .context(error::TarballStreamSnafu)?;
+    let mut out = tarball
+        .into_inner()
+        .and_then(GzEncoder::finish)
+        .context(error::TarballStreamSnafu)?;
+    out.flush().context(error::TarballStreamSnafu)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -84,4 +104,27 @@ mod tests {
         let expected_path = PathBuf::from(crate::TARBALL_DIRNAME).join("hello.txt");
         assert!(actual_path == expected_path);
     }
+
+    #[test]
+    fn stream_tarball_test() {
+        let indir = TempDir::new().unwrap();
+        fs::write(indir.path().join("hello.txt"), b"Hello World!").unwrap();
+
+        let mut out = Vec::new();
+        stream_tarball(indir.path(), &mut out).unwrap();
+
+        let mut archive = Archive::new(GzDecoder::new(out.as_slice()));
+        let paths: Vec<PathBuf> = archive
+            .entries()
+            .unwrap()
+            .map(|entry| PathBuf::from(entry.unwrap().path().unwrap()))
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                PathBuf::from(crate::TARBALL_DIRNAME),
+                PathBuf::from(crate::TARBALL_DIRNAME).join("hello.txt"),
+            ]
+        );
+    }
 }
@@ -0,0 +1,143 @@
+//! Encrypts support bundles to an [age](https://age-encryption.org/v1) X25519 recipient, so only
+//! the holder of the matching identity can read them once they leave the host.  Files written here
+//! can be decrypted with any age implementation, for example `age --decrypt -i key.txt`.
+//!
+//! Recipients are age public keys, like `age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p`.
+
+use crate::error::{self, Result};
+use age::stream::StreamWriter;
+use snafu::{OptionExt, ResultExt};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// An age X25519 recipient, parsed from its public key.
+#[derive(Clone)]
+pub(crate) struct Recipient(age::x25519::Recipient);
+
+impl FromStr for Recipient {
+    type Err = error::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let recipient = input
+            .parse()
+            .ok()
+            .context(error::InvalidRecipientSnafu { recipient: input })?;
+        Ok(Recipient(recipient))
+    }
+}
+
+impl fmt::Debug for Recipient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Recipient({})", self.0)
+    }
+}
+
+/// Encrypts everything written to it to an age recipient, writing the encrypted file to `inner`.
+/// `finish` must be called to write the end of the file.
+pub(crate) struct Encryptor<W: Write>(StreamWriter<W>);
+
+impl<W: Write> Encryptor<W> {
+    /// Starts an encrypted file for `recipient`, writing its header to `inner`.
+    pub(crate) fn new(inner: W, recipient: &Recipient) -> Result<Self> {
+        let recipients: Vec<Box<dyn age::Recipient>> = vec![Box::new(recipient.0.clone())];
+        let writer = age::Encryptor::with_recipients(recipients)
+            .wrap_output(inner)
+            .context(error::EncryptSnafu)?;
+        Ok(Self(writer))
+    }
+
+    /// Encrypts the rest of the file, and returns `inner` once it's written.
+    pub(crate) fn finish(self) -> Result<W> {
+        let mut inner = self.0.finish().context(error::EncryptWriteSnafu)?;
+        inner.flush().context(error::EncryptWriteSnafu)?;
+        Ok(inner)
+    }
+}
+
+impl<W: Write> Write for Encryptor<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.0.write(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::iter;
+
+    /// The size of the plaintext in each encrypted chunk of an age payload.
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    fn decrypt(identity: &age::x25519::Identity, file: &[u8]) -> Vec<u8> {
+        let decryptor = match age::Decryptor::new(file).unwrap() {
+            age::Decryptor::Recipients(decryptor) => decryptor,
+            age::Decryptor::Passphrase(_) => panic!("file is encrypted to a passphrase"),
+        };
+        let mut reader = decryptor
+            .decrypt(iter::once(identity as &dyn age::Identity))
+            .unwrap();
+        let mut plaintext = Vec::new();
+        reader.read_to_end(&mut plaintext).unwrap();
+        plaintext
+    }
+
+    fn roundtrip(plaintext: &[u8]) {
+        let identity = age::x25519::Identity::generate();
+        let recipient: Recipient = identity.to_public().to_string().parse().unwrap();
+
+        let mut encryptor = Encryptor::new(Vec::new(), &recipient).unwrap();
+        // write in uneven pieces to cross chunk boundaries
+        for piece in plaintext.chunks(10_000) {
+            encryptor.write_all(piece).unwrap();
+        }
+        let file = encryptor.finish().unwrap();
+        assert!(file.starts_with(b"age-encryption.org/v1\n-> X25519 "));
+        assert_eq!(decrypt(&identity, &file), plaintext);
+    }
+
+    #[test]
+    fn encrypt_empty() {
+        roundtrip(b"");
+    }
+
+    #[test]
+    fn encrypt_short() {
+        roundtrip(b"hello world");
+    }
+
+    #[test]
+    fn encrypt_full_chunk() {
+        roundtrip(&[7; CHUNK_SIZE]);
+    }
+
+    #[test]
+    fn encrypt_chunks() {
+        let plaintext: Vec<u8> = (0..CHUNK_SIZE * 5 / 2).map(|i| i as u8).collect();
+        roundtrip(&plaintext);
+    }
+
+    #[test]
+    fn parse_recipient() {
+        "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p"
+            .parse::<Recipient>()
+            .unwrap();
+        for bad in &[
+            // bad checksum
+            "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8q",
+            // wrong human-readable part
+            "agf1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p",
+            // an identity isn't a recipient
+            "AGE-SECRET-KEY-1GQ9778VQXMMJVE8SK7J6VT8UJ4HDQAJUVSFCWCM02D8GEWQ72PVQ2Y5J33",
+            "age1",
+            "",
+        ] {
+            assert!(bad.parse::<Recipient>().is_err(), "{}", bad);
+        }
+    }
+}
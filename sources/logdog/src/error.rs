@@ -3,7 +3,6 @@
 use std::io;
 use std::path::PathBuf;
 
-use datastore::{deserialization, serialization};
 use reqwest::Url;
 use snafu::{Backtrace, Snafu};
 
@@ -66,8 +65,11 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
-    #[snafu(display("Error deserializing Settings: {} ", source))]
-    DeserializeSettings { source: deserialization::Error },
+    #[snafu(display("Error encrypting logs: {}", source))]
+    Encrypt { source: age::EncryptError },
+
+    #[snafu(display("Error writing encrypted logs: {}", source))]
+    EncryptWrite { source: io::Error },
 
     #[snafu(display("Error creating the error file '{}': {}", path.display(), source))]
     ErrorFile {
@@ -145,6 +147,12 @@ pub(crate) enum Error {
         source: std::io::Error,
     },
 
+    #[snafu(display(
+        "Invalid age recipient '{}', expected a public key like 'age1...'",
+        recipient
+    ))]
+    InvalidRecipient { recipient: String },
+
     #[snafu(display("Empty command."))]
     ModeMissing {},
 
@@ -157,12 +165,12 @@ pub(crate) enum Error {
     #[snafu(display("The logdog configuration has a 'glob' line with no glob instructions."))]
     PatternMissing {},
 
+    #[snafu(display("Unable to read log requests from '{}': {}", path.display(), source))]
+    RequestFileRead { source: io::Error, path: PathBuf },
+
     #[snafu(display("Cannot write to / as a file."))]
     RootAsFile { backtrace: Backtrace },
 
-    #[snafu(display("Error serializing Settings: {} ", source))]
-    SerializeSettings { source: serialization::Error },
-
     #[snafu(display("Unable to deserialize Bottlerocket settings: {}", source))]
     SettingsJson { source: serde_json::Error },
 
@@ -191,6 +199,12 @@ pub(crate) enum Error {
         backtrace: Backtrace,
     },
 
+    #[snafu(display("Error streaming the tarball: {}", source))]
+    TarballStream {
+        source: io::Error,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Error writing to the tarball '{}': {}", path.display(), source))]
     TarballWrite {
         source: io::Error,
@@ -206,6 +220,18 @@ pub(crate) enum Error {
 
     #[snafu(display("Unknown request type '{}' in '{}'", mode, request))]
     UnhandledRequest { mode: String, request: String },
+
+    #[snafu(display("Unable to create HTTP client for upload: {}", source))]
+    UploadClient { source: reqwest::Error },
+
+    #[snafu(display("Unable to open '{}' for upload: {}", path.display(), source))]
+    UploadFileOpen { path: PathBuf, source: io::Error },
+
+    #[snafu(display("Upload to '{}' failed: {}", url, source))]
+    UploadResponse { url: Url, source: reqwest::Error },
+
+    #[snafu(display("Unable to send upload to '{}': {}", url, source))]
+    UploadSend { url: Url, source: reqwest::Error },
 }
 
 pub(crate) type Result<T> = std::result::Result<T, Error>;
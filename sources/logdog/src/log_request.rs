@@ -7,18 +7,23 @@
 //! file which points to the log requests for the current variant. This file is named `logdog.conf`.
 //! We load `logdog.conf` and `logdog.common.conf` files into static strings at compile time, and
 //! these provide the list of log requests that `logdog` will run.
+//!
+//! # Log Requests From Settings
+//!
+//! More log requests can be given at runtime in `settings.logdog.requests`, a map from a name to
+//! the `mode`, `filename`, and `instructions` of a request.  `logdog` reads them from the API when
+//! it starts, and runs them after the static log requests, in order of their names.
 
 use crate::error::{self, Result};
-use datastore::deserialization::from_map;
-use datastore::serialization::to_pairs;
-use glob::{glob, Pattern};
+use glob::glob;
 use reqwest::blocking::{Client, Response};
 use snafu::{ensure, OptionExt, ResultExt};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::fs;
 use std::fs::File;
 use std::path::Path;
 use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
 use url::Url;
 use walkdir::WalkDir;
 
@@ -27,26 +32,83 @@ const COMMON_REQUESTS: &str = include_str!("../conf/logdog.common.conf");
 /// The `logdog` log requests that are specific to the current variant.
 const VARIANT_REQUESTS: &str = include_str!("../conf/current/logdog.conf");
 
-/// Patterns to filter from settings output.  These follow the Unix shell style pattern outlined
-/// here: https://docs.rs/glob/0.3.0/glob/struct.Pattern.html.
-const SENSITIVE_SETTINGS_PATTERNS: &[&str] = &[
-    "*.user-data",
-    "settings.kubernetes.bootstrap-token",
-    // Can contain a username:password component
-    "settings.network.https-proxy",
-];
-
 /// Returns the list of log requests to run by combining `VARIANT_REQUESTS` and `COMMON_REQUESTS`.
 /// These are read at compile time from files named `logdog.conf` and `logdog.common.conf`
 /// respectively.
 pub(crate) fn log_requests() -> Vec<&'static str> {
-    COMMON_REQUESTS
+    request_lines(COMMON_REQUESTS)
+        .chain(request_lines(VARIANT_REQUESTS))
+        .collect()
+}
+
+/// Reads additional log requests from the file at `path`, which has the same format as the files
+/// the static log requests are read from.
+pub(crate) fn read_log_requests<P: AsRef<Path>>(path: P) -> Result<Vec<String>> {
+    let path = path.as_ref();
+    let requests = fs::read_to_string(path).context(error::RequestFileReadSnafu { path })?;
+    Ok(request_lines(&requests).map(String::from).collect())
+}
+
+/// Returns the log requests in `requests`, skipping empty lines and comments.
+fn request_lines(requests: &str) -> impl Iterator<Item = &str> {
+    requests
         .lines()
-        .chain(VARIANT_REQUESTS.lines())
         .filter(|&command| !command.is_empty() && !command.trim_start().starts_with('#'))
+}
+
+/// Reads the log requests defined in `settings.logdog.requests` from the API.
+pub(crate) async fn settings_log_requests() -> Result<Vec<String>> {
+    let settings = get_settings().await?;
+    Ok(settings_requests(&settings))
+}
+
+/// Returns the log requests defined in `settings.logdog.requests`, sorted by name, in the same
+/// format as the static log requests.  Requests that are missing fields are still returned, so
+/// the problem is noted in the error file when they're run.
+fn settings_requests(settings: &model::Settings) -> Vec<String> {
+    let requests = match settings.logdog.as_ref().and_then(|l| l.requests.as_ref()) {
+        Some(requests) => requests,
+        None => return Vec::new(),
+    };
+    let requests: BTreeMap<&str, _> = requests
+        .iter()
+        .map(|(name, request)| (name.as_ref(), request))
+        .collect();
+    requests
+        .into_values()
+        .map(|request| {
+            let mode = request.mode.as_ref().map_or("", |mode| mode.as_ref());
+            // glob requests name their files after the files they copy.
+            let filename = if mode == "glob" {
+                None
+            } else {
+                request.filename.as_ref().map(|filename| filename.as_ref())
+            };
+            let instructions = request.instructions.as_ref().map(|i| i.as_ref());
+            std::iter::once(mode)
+                .chain(filename)
+                .chain(instructions)
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
         .collect()
 }
 
+/// Returns whether the log request is selected by any of the names in `only`.  A name selects a
+/// request if it's the request's mode, like `settings` or `exec`, or if it starts the request's
+/// output filename, like `journal` for `journalctl.log`.  For `glob` requests, which keep the
+/// names of the files they copy, a name can start any directory or file name in the pattern, like
+/// `kdump` for `/var/log/kdump/*`.  If `only` is empty, every request is selected.
+pub(crate) fn request_selected(request: &str, only: &[String]) -> bool {
+    if only.is_empty() {
+        return true;
+    }
+    match LogRequest::parse(request) {
+        Ok(req) => only.iter().any(|name| req.selected_by(name)),
+        Err(_) => false,
+    }
+}
+
 /// A logdog `LogRequest` represents a line from the config file. It starts with a "mode" that
 /// specifies what type of request it is, e.g. `exec ` for a command or `http` for an HTTP get
 /// request. Some modes then require a `filename` that determines where the data will be saved in
@@ -95,6 +157,46 @@ struct LogRequest<'a> {
     instructions: &'a str,
 }
 
+impl<'a> LogRequest<'a> {
+    /// Splits a line from the config file into a `LogRequest`.
+    fn parse(request: &'a str) -> Result<Self> {
+        let mut iter = request.splitn(3, ' ');
+        let mode = iter.next().context(error::ModeMissingSnafu)?;
+        if mode == "glob" {
+            // for glob request format is "glob <pattern>"
+            Ok(LogRequest {
+                mode,
+                filename: "",
+                instructions: iter.next().context(error::PatternMissingSnafu)?,
+            })
+        } else {
+            // Get the second token (output filename) and put the remainder of the
+            // log request into the instructions field (or default to an empty string).
+            Ok(LogRequest {
+                mode,
+                filename: iter
+                    .next()
+                    .context(error::FilenameMissingSnafu { request })?,
+                instructions: iter.next().unwrap_or(""),
+            })
+        }
+    }
+
+    /// Returns whether `name` selects this request; see `request_selected`.
+    fn selected_by(&self, name: &str) -> bool {
+        if self.mode == name {
+            return true;
+        }
+        if self.mode == "glob" {
+            self.instructions
+                .split('/')
+                .any(|component| component.starts_with(name))
+        } else {
+            self.filename.starts_with(name)
+        }
+    }
+}
+
 /// This is used in error construction.
 impl ToString for LogRequest<'_> {
     fn to_string(&self) -> String {
@@ -106,40 +208,26 @@ impl ToString for LogRequest<'_> {
     }
 }
 
-/// Runs a `LogRequest` and writes its output to a file in `tempdir`.
-pub(crate) async fn handle_log_request<S, P>(request: S, tempdir: P) -> Result<()>
+/// Runs a `LogRequest` and writes its output to a file in `tempdir`.  If `since` is given, only
+/// journal entries and files matched by `glob` requests from after that time are collected.
+pub(crate) async fn handle_log_request<S, P>(
+    request: S,
+    tempdir: P,
+    since: Option<SystemTime>,
+) -> Result<()>
 where
     S: AsRef<str>,
     P: AsRef<Path>,
 {
     let request = request.as_ref();
-    let mut iter = request.splitn(3, ' ');
-    let mode = iter.next().context(error::ModeMissingSnafu)?;
-    let req = if mode == "glob" {
-        // for glob request format is "glob <pattern>"
-        LogRequest {
-            mode,
-            filename: "",
-            instructions: iter.next().context(error::PatternMissingSnafu)?,
-        }
-    } else {
-        // Get the second token (output filename) and put the remainder of the
-        // log request into the instructions field (or default to an empty string).
-        LogRequest {
-            mode,
-            filename: iter
-                .next()
-                .context(error::FilenameMissingSnafu { request })?,
-            instructions: iter.next().unwrap_or(""),
-        }
-    };
+    let req = LogRequest::parse(request)?;
     // execute the log request with the correct handler based on the mode field.
     match req.mode {
         "settings" => handle_settings_request(&req, tempdir).await?,
-        "exec" => handle_exec_request(&req, tempdir)?,
+        "exec" => handle_exec_request(&req, tempdir, since)?,
         "http" | "https" => handle_http_request(&req, tempdir)?,
         "file" => handle_file_request(&req, tempdir)?,
-        "glob" => handle_glob_request(&req, tempdir)?,
+        "glob" => handle_glob_request(&req, tempdir, since)?,
         unmatched => {
             return Err(error::Error::UnhandledRequest {
                 mode: unmatched.into(),
@@ -150,24 +238,12 @@ where
     Ok(())
 }
 
-/// Requests settings from the API, filters them, and writes the output to `tempdir`
+/// Requests settings from the API, without sensitive settings, and writes the output to `tempdir`
 async fn handle_settings_request<P>(request: &LogRequest<'_>, tempdir: P) -> Result<()>
 where
     P: AsRef<Path>,
 {
     let settings = get_settings().await?;
-    let mut settings_map = to_pairs(&settings).context(error::SerializeSettingsSnafu)?;
-
-    // Filter all settings that match any of the "sensitive" patterns
-    for pattern in SENSITIVE_SETTINGS_PATTERNS {
-        let pattern =
-            Pattern::new(pattern).context(error::ParseGlobPatternSnafu { pattern: *pattern })?;
-        settings_map.retain(|k, _| !pattern.matches(k.name().as_str()))
-    }
-
-    // Serialize the map back to a `Settings` to remove the escaping so it writes nicely to file
-    let settings: model::Settings =
-        from_map(&settings_map).context(error::DeserializeSettingsSnafu)?;
     let outpath = tempdir.as_ref().join(request.filename);
     let outfile = File::create(&outpath).context(error::FileCreateSnafu { path: &outpath })?;
     serde_json::to_writer_pretty(&outfile, &settings)
@@ -175,18 +251,24 @@ where
     Ok(())
 }
 
-/// Uses `apiclient` to request all settings from the apiserver and deserializes into a `Settings`
+/// Uses `apiclient` to request all settings from the apiserver and deserializes into a `Settings`.
+/// The apiserver redacts settings that are marked sensitive, like credentials and user data.
 async fn get_settings() -> Result<model::Settings> {
-    let uri = constants::API_SETTINGS_URI;
-    let (_status, response_body) = apiclient::raw_request(constants::API_SOCKET, uri, "GET", None)
+    let uri = format!("{}?redact=true", constants::API_SETTINGS_URI);
+    let (_status, response_body) = apiclient::raw_request(constants::API_SOCKET, &uri, "GET", None)
         .await
         .context(error::ApiClientSnafu { uri })?;
 
     serde_json::from_str(&response_body).context(error::SettingsJsonSnafu)
 }
 
-/// Runs an `exec` `LogRequest`'s `instructions` and writes its output to to `tempdir`.
-fn handle_exec_request<P>(request: &LogRequest<'_>, tempdir: P) -> Result<()>
+/// Runs an `exec` `LogRequest`'s `instructions` and writes its output to to `tempdir`.  If `since`
+/// is given, `journalctl` commands only show entries from after that time.
+fn handle_exec_request<P>(
+    request: &LogRequest<'_>,
+    tempdir: P,
+    since: Option<SystemTime>,
+) -> Result<()>
 where
     P: AsRef<Path>,
 {
@@ -199,6 +281,13 @@ where
         .with_context(|| error::CommandMissingSnafu {
             request: request.to_string(),
         })?;
+    let mut args = args.to_vec();
+    if let Some(since) = since {
+        // Listing boots doesn't take a time range.
+        if command == "journalctl" && !args.iter().any(|arg| arg == "--list-boots") {
+            args.push(format!("--since=@{}", unix_seconds(since)));
+        }
+    }
     let outpath = tempdir.as_ref().join(request.filename);
     let ofile = File::create(&outpath).context(error::CommandOutputFileSnafu { path: &outpath })?;
     let stderr_file = ofile
@@ -282,8 +371,12 @@ where
 }
 
 /// Copies all files matching the glob pattern given by `request.instructions` to the tempdir with filename and path
-/// same as source file.
-fn handle_glob_request<P>(request: &LogRequest<'_>, tempdir: P) -> Result<()>
+/// same as source file.  If `since` is given, files last modified before then are skipped.
+fn handle_glob_request<P>(
+    request: &LogRequest<'_>,
+    tempdir: P,
+    since: Option<SystemTime>,
+) -> Result<()>
 where
     P: AsRef<Path>,
 {
@@ -303,6 +396,9 @@ where
             files.insert(path);
         }
     }
+    if let Some(since) = since {
+        files.retain(|path| modified_since(path, since));
+    }
     for src_filepath in &files {
         // with glob pattern there are chances of multiple targets with same name, therefore
         // we maintain source file path and name in destination directory.
@@ -325,12 +421,27 @@ where
     Ok(())
 }
 
+/// Returns whether the file at `path` was modified at or after `since`.  Files whose modification
+/// time can't be read are kept, so we don't lose logs we aren't sure about.
+fn modified_since(path: &Path, since: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .map_or(true, |modified| modified >= since)
+}
+
+/// Returns `time` as seconds since the Unix epoch, or 0 for times before it.
+fn unix_seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
 #[cfg(test)]
 mod test {
-    use crate::log_request::handle_log_request;
+    use crate::log_request::{handle_log_request, request_selected, settings_requests};
     use std::fs;
     use std::fs::write;
     use std::path::PathBuf;
+    use std::time::{Duration, SystemTime};
     use tempfile::TempDir;
 
     // adds a sub directory and some files to temp directory for file request tests
@@ -385,7 +496,9 @@ mod test {
         write(&source_filepath, want).unwrap();
         let request = format!("file foo-bar {}", source_filepath.display());
         let outdir = TempDir::new().unwrap();
-        handle_log_request(&request, outdir.path()).await.unwrap();
+        handle_log_request(&request, outdir.path(), None)
+            .await
+            .unwrap();
         let outfile = outdir.path().join("foo-bar");
         let got = std::fs::read_to_string(outfile).unwrap();
         assert_eq!(got, want);
@@ -396,7 +509,9 @@ mod test {
         let want = "hello world! \"quoted\"\n";
         let request = r#"exec output-file.txt echo 'hello' "world!" "\"quoted\"""#;
         let outdir = TempDir::new().unwrap();
-        handle_log_request(&request, outdir.path()).await.unwrap();
+        handle_log_request(&request, outdir.path(), None)
+            .await
+            .unwrap();
         let outfile = outdir.path().join("output-file.txt");
         let got = std::fs::read_to_string(outfile).unwrap();
         assert_eq!(got, want);
@@ -409,7 +524,9 @@ mod test {
         create_source_dir(&source_dir);
         let outdir = TempDir::new().unwrap();
         let request = format!("glob {}/foo.source", source_dir.path().display());
-        handle_log_request(&request, outdir.path()).await.unwrap();
+        handle_log_request(&request, outdir.path(), None)
+            .await
+            .unwrap();
         assert_file_match(&outdir, get_dest_filepath(&source_dir, "foo.source"), "1");
     }
 
@@ -420,7 +537,9 @@ mod test {
         create_source_dir(&source_dir);
         let outdir = TempDir::new().unwrap();
         let request = format!("glob {}/*.source", source_dir.path().display());
-        handle_log_request(&request, outdir.path()).await.unwrap();
+        handle_log_request(&request, outdir.path(), None)
+            .await
+            .unwrap();
         assert_file_match(&outdir, get_dest_filepath(&source_dir, "foo.source"), "1");
         assert_file_match(&outdir, get_dest_filepath(&source_dir, "bar.source"), "2");
     }
@@ -432,7 +551,9 @@ mod test {
         create_source_dir(&source_dir);
         let outdir = TempDir::new().unwrap();
         let request = format!("glob {}/**/*.source", source_dir.path().display());
-        handle_log_request(&request, outdir.path()).await.unwrap();
+        handle_log_request(&request, outdir.path(), None)
+            .await
+            .unwrap();
         assert_file_match(&outdir, get_dest_filepath(&source_dir, "foo.source"), "1");
         assert_file_match(&outdir, get_dest_filepath(&source_dir, "bar.source"), "2");
         assert_file_match(
@@ -464,7 +585,9 @@ mod test {
         create_source_dir(&source_dir);
         let outdir = TempDir::new().unwrap();
         let request = format!("glob {}/**/", source_dir.path().display());
-        handle_log_request(&request, outdir.path()).await.unwrap();
+        handle_log_request(&request, outdir.path(), None)
+            .await
+            .unwrap();
         assert_file_match(
             &outdir,
             get_dest_filepath(&source_dir, "depth1/foo.source"),
@@ -502,9 +625,79 @@ mod test {
     async fn glob_empty_pattern_request() {
         let outdir = TempDir::new().unwrap();
         let request = "glob";
-        let err = handle_log_request(&request, outdir.path())
+        let err = handle_log_request(&request, outdir.path(), None)
             .await
             .unwrap_err();
         assert!(matches!(err, crate::error::Error::PatternMissing {}));
     }
+
+    #[tokio::test]
+    // ensures files modified before the given time are skipped
+    async fn glob_since_request() {
+        let source_dir = TempDir::new().unwrap();
+        create_source_dir(&source_dir);
+        let outdir = TempDir::new().unwrap();
+        let request = format!("glob {}/*.source", source_dir.path().display());
+        let since = SystemTime::now() + Duration::from_secs(60 * 60);
+        handle_log_request(&request, outdir.path(), Some(since))
+            .await
+            .unwrap();
+        assert_eq!(fs::read_dir(outdir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn select_requests() {
+        let only = |names: &[&str]| -> Vec<String> {
+            names.iter().map(|&name| name.to_string()).collect()
+        };
+        let journal = "exec journalctl.log journalctl -a --no-pager";
+        let settings = "settings settings.json";
+        let kdump = "glob /var/log/kdump/*";
+
+        for request in &[journal, settings, kdump] {
+            assert!(request_selected(request, &[]));
+        }
+        assert!(request_selected(journal, &only(&["journal"])));
+        assert!(request_selected(journal, &only(&["exec"])));
+        assert!(!request_selected(journal, &only(&["settings"])));
+        assert!(request_selected(settings, &only(&["journal", "settings"])));
+        assert!(request_selected(kdump, &only(&["kdump"])));
+        assert!(request_selected(kdump, &only(&["glob"])));
+        assert!(!request_selected(kdump, &only(&["journal"])));
+        assert!(!request_selected("glob", &only(&["glob"])));
+    }
+
+    #[test]
+    fn requests_from_settings() {
+        let settings: model::Settings = serde_json::from_value(serde_json::json!({
+            "logdog": {
+                "requests": {
+                    "my-app": {
+                        "mode": "file",
+                        "filename": "my-app.log",
+                        "instructions": "/local/my-app/app.log"
+                    },
+                    "app-logs": {
+                        "mode": "glob",
+                        "filename": "ignored",
+                        "instructions": "/local/my-app/logs/*"
+                    },
+                    "no-instructions": {
+                        "mode": "settings",
+                        "filename": "settings.json"
+                    }
+                }
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            settings_requests(&settings),
+            vec![
+                "glob /local/my-app/logs/*",
+                "file my-app.log /local/my-app/app.log",
+                "settings settings.json",
+            ]
+        );
+        assert!(settings_requests(&model::Settings::default()).is_empty());
+    }
 }
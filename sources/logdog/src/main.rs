@@ -11,6 +11,44 @@ $ logdog
 logs are at: /var/log/support/bottlerocket-logs.tar.gz
 ```
 
+# Options
+
+* `--output PATH` writes the tarball to `PATH` instead.
+* `--stdout` writes the tarball to stdout as it's built, so it can be piped elsewhere without being stored on the host first.
+* `--only NAME[,NAME...]` only runs some of the log requests, like `--only journal,settings`.
+* `--since DURATION` only collects journal entries, and files matched by `glob` requests, from the given time until now, like `30m`, `2h`, or `1d`; other requests, like copies of config files, are collected in full.
+* `--requests PATH` also runs the log requests listed in the file at `PATH`, in the same format as the files below, and can be given more than once.
+* `--upload URL` sends the tarball to `URL` in an HTTP PUT request instead, like a pre-signed S3 URL, without storing it on the host.
+* `--encrypt-to RECIPIENT` encrypts the tarball to an [age](https://age-encryption.org) public key, like `age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p`, wherever it's written; by default, the file is then named `bottlerocket-logs.tar.gz.age`.
+
+A name given to `--only` selects a request if it's the request's mode, like `settings` or `exec`, or if it starts the request's output filename, like `journal` for `journalctl.log`.
+For `glob` requests, a name can start any directory or file name in the pattern, like `kdump`.
+Requests from `--requests` files are selected the same way.
+
+Progress messages are written to stderr, so they don't mix with the tarball.
+
+For example, to collect the last two hours of the journal, plus the settings, and list what was collected:
+
+```shell
+$ logdog --stdout --only journal,settings --since 2h | tar -tzf -
+Running: exec journalctl-boots journalctl --list-boots --no-pager
+...
+bottlerocket-logs/
+bottlerocket-logs/journalctl.log
+...
+```
+
+To hand logs to someone else without them being readable on the way, encrypt them to their key and upload them to a URL they've pre-signed:
+
+```shell
+$ logdog --encrypt-to age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p \
+    --upload 'https://my-bucket.s3.amazonaws.com/bottlerocket-logs.tar.gz.age?X-Amz-Signature=...'
+...
+logs uploaded to: https://my-bucket.s3.amazonaws.com/bottlerocket-logs.tar.gz.age
+```
+
+The recipient can then decrypt them with their identity, for example with `age --decrypt -i key.txt bottlerocket-logs.tar.gz.age > bottlerocket-logs.tar.gz`.
+
 # Logs
 
 For the log requests used to gather logs, please see the following:
@@ -20,35 +58,82 @@ For the log requests used to gather logs, please see the following:
 * And the variant-specific files in [conf](conf/), one of which is selected by [build.rs](build.rs)
 based on the value of the `VARIANT` environment variable at build time.
 
+More log requests can be added at runtime through `settings.logdog.requests`.
+Each one is named, and has the `mode`, `filename`, and `instructions` described in [log_request](src/log_request.rs).
+For example, to collect an application's log file from a host container's persistent storage:
+
+```toml
+[settings.logdog.requests.my-app]
+mode = "file"
+filename = "my-app.log"
+instructions = "/local/host-containers/my-app/app.log"
+```
+
+These requests run after the ones built into the image, in order of their names, and can be selected with `--only` like any other request.
+
 */
 
 #![deny(rust_2018_idioms)]
 
 mod create_tarball;
+mod encrypt;
 mod error;
 mod log_request;
+mod upload;
 
-use create_tarball::create_tarball;
+use create_tarball::{create_tarball, stream_tarball};
+use encrypt::{Encryptor, Recipient};
 use error::Result;
-use log_request::{handle_log_request, log_requests};
+use log_request::{
+    handle_log_request, log_requests, read_log_requests, request_selected, settings_log_requests,
+};
+use reqwest::Url;
 use snafu::{ErrorCompat, ResultExt};
-use std::fs::File;
-use std::io::Write;
+use std::fs::{self, File};
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
 use std::{env, process};
 use tempfile::TempDir;
 
 const ERROR_FILENAME: &str = "logdog.errors";
 const OUTPUT_FILENAME: &str = "bottlerocket-logs.tar.gz";
+const ENCRYPTED_EXTENSION: &str = "age";
 const OUTPUT_DIRNAME: &str = "/var/log/support";
 const TARBALL_DIRNAME: &str = "bottlerocket-logs";
 
+/// Where to write the tarball of logs.
+#[derive(Debug, PartialEq)]
+enum Output {
+    File(PathBuf),
+    Stdout,
+    Upload(Url),
+}
+
+/// Stores user-supplied arguments.
+#[derive(Debug)]
+struct Args {
+    output: Output,
+    only: Vec<String>,
+    since: Option<SystemTime>,
+    request_files: Vec<PathBuf>,
+    encrypt_to: Option<Recipient>,
+}
+
 /// Prints a usage message in the event a bad arg is passed.
 fn usage() -> ! {
     let program_name = env::args().next().unwrap_or_else(|| "program".to_string());
     eprintln!(
         r"Usage: {}
-            [ --output PATH ]       where to write archived logs
+            [ --output PATH | --stdout | --upload URL ]
+                                            where to write archived logs, or write them to stdout,
+                                            or PUT them to an HTTP URL
+            [ --only NAME[,NAME...] ]       only run log requests with these modes or output names,
+                                            like 'journal,settings'
+            [ --since DURATION ]            only collect journal entries and globbed files from
+                                            this long ago until now, like '30m', '2h', or '1d'
+            [ --requests PATH ]...          also run the log requests in this file
+            [ --encrypt-to RECIPIENT ]      encrypt archived logs to this age public key
 ",
         program_name,
     );
@@ -62,9 +147,18 @@ fn usage_msg(msg: &str) -> ! {
 }
 
 /// Parses the command line arguments.
-fn parse_args(args: env::Args) -> PathBuf {
+fn parse_args<I>(args: I) -> Args
+where
+    I: IntoIterator<Item = String>,
+{
     let mut output_arg = None;
-    let mut iter = args.skip(1);
+    let mut stdout = false;
+    let mut upload = None;
+    let mut encrypt_to = None;
+    let mut only = Vec::new();
+    let mut since = None;
+    let mut request_files = Vec::new();
+    let mut iter = args.into_iter().skip(1);
     while let Some(arg) = iter.next() {
         match arg.as_ref() {
             "--output" => {
@@ -73,21 +167,113 @@ fn parse_args(args: env::Args) -> PathBuf {
                         .unwrap_or_else(|| usage_msg("Did not give argument to --output")),
                 )
             }
+            "--stdout" => stdout = true,
+            "--only" => {
+                let names = iter
+                    .next()
+                    .unwrap_or_else(|| usage_msg("Did not give argument to --only"));
+                only.extend(
+                    names
+                        .split(',')
+                        .filter(|name| !name.is_empty())
+                        .map(String::from),
+                );
+            }
+            "--since" => {
+                let duration = iter
+                    .next()
+                    .unwrap_or_else(|| usage_msg("Did not give argument to --since"));
+                let duration = parse_duration(&duration).unwrap_or_else(|| {
+                    usage_msg(&format!(
+                        "Invalid duration '{}' given to --since, expected a number and a unit \
+                        like '30m', '2h', or '1d'",
+                        duration
+                    ))
+                });
+                since = Some(
+                    SystemTime::now()
+                        .checked_sub(duration)
+                        .unwrap_or(SystemTime::UNIX_EPOCH),
+                );
+            }
+            "--requests" => request_files
+                .push(PathBuf::from(iter.next().unwrap_or_else(|| {
+                    usage_msg("Did not give argument to --requests")
+                }))),
+            "--upload" => {
+                let url = iter
+                    .next()
+                    .unwrap_or_else(|| usage_msg("Did not give argument to --upload"));
+                upload = Some(Url::parse(&url).unwrap_or_else(|e| {
+                    usage_msg(&format!("Invalid URL given to --upload: {}", e))
+                }));
+            }
+            "--encrypt-to" => {
+                let recipient = iter
+                    .next()
+                    .unwrap_or_else(|| usage_msg("Did not give argument to --encrypt-to"));
+                encrypt_to = Some(
+                    recipient
+                        .parse()
+                        .unwrap_or_else(|e: error::Error| usage_msg(&e.to_string())),
+                );
+            }
             _ => usage(),
         }
     }
 
-    match output_arg {
-        Some(path) => PathBuf::from(path),
-        None => PathBuf::from(OUTPUT_DIRNAME).join(OUTPUT_FILENAME),
+    let output = match (output_arg, stdout, upload) {
+        (Some(path), false, None) => Output::File(PathBuf::from(path)),
+        (None, true, None) => Output::Stdout,
+        (None, false, Some(url)) => Output::Upload(url),
+        (None, false, None) => {
+            let mut filename = OUTPUT_FILENAME.to_string();
+            if encrypt_to.is_some() {
+                filename = format!("{}.{}", filename, ENCRYPTED_EXTENSION);
+            }
+            Output::File(PathBuf::from(OUTPUT_DIRNAME).join(filename))
+        }
+        _ => usage_msg("Only one of --output, --stdout, and --upload may be given"),
+    };
+
+    Args {
+        output,
+        only,
+        since,
+        request_files,
+        encrypt_to,
     }
 }
 
+/// Parses a duration given as a number followed by a unit: `s` for seconds, `m` for minutes, `h`
+/// for hours, or `d` for days.
+fn parse_duration(input: &str) -> Option<Duration> {
+    let unit_start = input.find(|c: char| !c.is_ascii_digit())?;
+    let (count, unit) = input.split_at(unit_start);
+    let count: u64 = count.parse().ok()?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => return None,
+    };
+    count.checked_mul(unit_seconds).map(Duration::from_secs)
+}
+
 /// Runs a list of log requests and writes their output into files in `outdir`. Any failures are
 /// noted in the file named by `ERROR_FILENAME`. Note: In the case of `exec` log requests, non-zero
 /// exit codes are not considered errors and the command's stdout and stderr will be still be
 /// written.
-pub(crate) async fn collect_logs<P: AsRef<Path>>(log_requests: &[&str], outdir: P) -> Result<()> {
+pub(crate) async fn collect_logs<S, P>(
+    log_requests: &[S],
+    outdir: P,
+    since: Option<SystemTime>,
+) -> Result<()>
+where
+    S: AsRef<str>,
+    P: AsRef<Path>,
+{
     // if a command fails, we will pipe its error here and continue.
     let outdir = outdir.as_ref();
     let error_path = outdir.join(crate::ERROR_FILENAME);
@@ -95,10 +281,12 @@ pub(crate) async fn collect_logs<P: AsRef<Path>>(log_requests: &[&str], outdir:
         path: error_path.clone(),
     })?;
 
-    for &log_request in log_requests {
-        // show the user what command we are running
-        println!("Running: {}", log_request);
-        if let Err(e) = handle_log_request(log_request, &outdir).await {
+    for log_request in log_requests {
+        let log_request = log_request.as_ref();
+        // show the user what command we are running; this goes to stderr so it doesn't mix with
+        // the tarball when streaming it to stdout.
+        eprintln!("Running: {}", log_request);
+        if let Err(e) = handle_log_request(log_request, &outdir, since).await {
             // ignore the error, but make note of it in the error file.
             writeln!(
                 &mut error_file,
@@ -114,19 +302,83 @@ pub(crate) async fn collect_logs<P: AsRef<Path>>(log_requests: &[&str], outdir:
 }
 
 /// Runs the bulk of the program's logic, main wraps this.
-async fn run(outfile: &Path, commands: &[&str]) -> Result<()> {
+async fn run<S>(args: &Args, log_requests: &[S]) -> Result<()>
+where
+    S: AsRef<str>,
+{
+    let mut requests: Vec<String> = log_requests
+        .iter()
+        .map(|r| r.as_ref().to_string())
+        .collect();
+    for path in &args.request_files {
+        requests.extend(read_log_requests(path)?);
+    }
+    requests.retain(|request| request_selected(request, &args.only));
+
     let temp_dir = TempDir::new().context(error::TempDirCreateSnafu)?;
-    collect_logs(commands, &temp_dir.path().to_path_buf()).await?;
-    create_tarball(temp_dir.path(), outfile)?;
-    println!("logs are at: {}", outfile.display());
+    collect_logs(&requests, &temp_dir.path().to_path_buf(), args.since).await?;
+    match (&args.output, &args.encrypt_to) {
+        (Output::File(outfile), None) => {
+            create_tarball(temp_dir.path(), outfile)?;
+            println!("logs are at: {}", outfile.display());
+        }
+        (Output::File(outfile), Some(recipient)) => {
+            if let Some(outdir) = outfile.parent() {
+                fs::create_dir_all(outdir)
+                    .context(error::CreateOutputDirectorySnafu { path: outdir })?;
+            }
+            let file =
+                File::create(outfile).context(error::TarballFileCreateSnafu { path: outfile })?;
+            write_archive(temp_dir.path(), file, Some(recipient))?;
+            println!("logs are at: {}", outfile.display());
+        }
+        (Output::Stdout, recipient) => {
+            write_archive(temp_dir.path(), io::stdout().lock(), recipient.as_ref())?;
+        }
+        (Output::Upload(url), recipient) => {
+            // the archive can't be written into the directory it's built from.
+            let archive_dir = TempDir::new().context(error::TempDirCreateSnafu)?;
+            let archive_path = archive_dir.path().join(OUTPUT_FILENAME);
+            let file = File::create(&archive_path).context(error::TarballFileCreateSnafu {
+                path: &archive_path,
+            })?;
+            write_archive(temp_dir.path(), file, recipient.as_ref())?;
+            // the HTTP client blocks, so let the runtime move other tasks off this thread.
+            tokio::task::block_in_place(|| upload::upload(&archive_path, url))?;
+            println!("logs uploaded to: {}", upload::redact_url(url));
+        }
+    }
     Ok(())
 }
 
+/// Writes a tarball with all the contents of directory `indir` to `out`, encrypting it if given a
+/// `recipient`.
+fn write_archive<P, W>(indir: P, out: W, recipient: Option<&Recipient>) -> Result<()>
+where
+    P: AsRef<Path>,
+    W: Write,
+{
+    match recipient {
+        None => stream_tarball(indir, out),
+        Some(recipient) => {
+            let mut encryptor = Encryptor::new(out, recipient)?;
+            stream_tarball(indir, &mut encryptor)?;
+            encryptor.finish()?;
+            Ok(())
+        }
+    }
+}
+
 #[tokio::main]
 async fn main() -> ! {
-    let outpath = parse_args(env::args());
-    let log_requests = log_requests();
-    process::exit(match run(&outpath, &log_requests).await {
+    let args = parse_args(env::args());
+    let mut log_requests: Vec<String> = log_requests().into_iter().map(String::from).collect();
+    match settings_log_requests().await {
+        Ok(requests) => log_requests.extend(requests),
+        // the static requests are still worth collecting, especially if the API is what's broken.
+        Err(e) => eprintln!("Unable to read log requests from settings: {}", e),
+    }
+    process::exit(match run(&args, &log_requests).await {
         Ok(()) => 0,
         Err(err) => {
             eprintln!("{}", err);
@@ -149,14 +401,130 @@ mod tests {
     use std::fs::File;
     use tar::Archive;
 
+    fn args(args: &[&str]) -> Args {
+        parse_args(
+            std::iter::once("logdog")
+                .chain(args.iter().copied())
+                .map(String::from),
+        )
+    }
+
+    #[test]
+    fn default_args() {
+        let args = args(&[]);
+        assert_eq!(
+            args.output,
+            Output::File(PathBuf::from(OUTPUT_DIRNAME).join(OUTPUT_FILENAME))
+        );
+        assert!(args.only.is_empty());
+        assert!(args.since.is_none());
+        assert!(args.request_files.is_empty());
+        assert!(args.encrypt_to.is_none());
+    }
+
+    #[test]
+    fn upload_args() {
+        let args = args(&[
+            "--upload",
+            "https://example.com/logs?X-Amz-Signature=abc",
+            "--encrypt-to",
+            "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p",
+        ]);
+        assert_eq!(
+            args.output,
+            Output::Upload(
+                "https://example.com/logs?X-Amz-Signature=abc"
+                    .parse()
+                    .unwrap()
+            )
+        );
+        assert!(args.encrypt_to.is_some());
+    }
+
+    #[test]
+    fn encrypted_default_output() {
+        let args = args(&[
+            "--encrypt-to",
+            "age1ql3z7hjy54pw3hyww5ayyfg7zqgvc7w3j2elw8zmrj2kg5sfn9aqmcac8p",
+        ]);
+        assert_eq!(
+            args.output,
+            Output::File(PathBuf::from(OUTPUT_DIRNAME).join("bottlerocket-logs.tar.gz.age"))
+        );
+    }
+
+    #[test]
+    fn filter_args() {
+        let args = args(&[
+            "--stdout",
+            "--only",
+            "journal,settings",
+            "--since",
+            "2h",
+            "--requests",
+            "/a",
+            "--requests",
+            "/b",
+        ]);
+        assert_eq!(args.output, Output::Stdout);
+        assert_eq!(args.only, vec!["journal", "settings"]);
+        let since = SystemTime::now()
+            .duration_since(args.since.unwrap())
+            .unwrap();
+        assert!(since >= Duration::from_secs(2 * 60 * 60));
+        assert!(since < Duration::from_secs(2 * 60 * 60 + 60));
+        assert_eq!(
+            args.request_files,
+            vec![PathBuf::from("/a"), PathBuf::from("/b")]
+        );
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("30m"), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("2h"), Some(Duration::from_secs(2 * 60 * 60)));
+        assert_eq!(
+            parse_duration("1d"),
+            Some(Duration::from_secs(24 * 60 * 60))
+        );
+        for bad in &[
+            "",
+            "2",
+            "h",
+            "2 h",
+            "-2h",
+            "2w",
+            "2hours",
+            "99999999999999999999d",
+        ] {
+            assert_eq!(parse_duration(bad), None, "{}", bad);
+        }
+    }
+
     #[tokio::test]
     async fn test_program() {
         let output_tempdir = TempDir::new().unwrap();
         let outfile = output_tempdir.path().join("logstest");
 
         // we assume that `echo` will not do something unexpected on the machine running this test.
-        let commands = vec!["exec hello.txt echo hello world"];
-        run(&outfile, &commands).await.unwrap();
+        // it's given in a requests file, and only selected requests are run, so the request we
+        // pass directly is skipped.
+        let requests_file = output_tempdir.path().join("requests");
+        std::fs::write(
+            &requests_file,
+            "# extra requests\nexec hello.txt echo hello world\n",
+        )
+        .unwrap();
+        let args = Args {
+            output: Output::File(outfile.clone()),
+            only: vec!["hello".to_string()],
+            since: None,
+            request_files: vec![requests_file],
+            encrypt_to: None,
+        };
+        let commands = vec!["exec goodbye.txt echo goodbye world"];
+        run(&args, &commands).await.unwrap();
 
         // this function will panic if the given path is not found in the tarball.
         let find = |path_to_find: &PathBuf| {
@@ -177,5 +545,12 @@ mod tests {
         // assert that the expected paths exist in the tarball
         find(&PathBuf::from(TARBALL_DIRNAME));
         find(&PathBuf::from(TARBALL_DIRNAME).join("hello.txt"));
+
+        // assert that the request that wasn't selected didn't run
+        let tar_gz = File::open(&outfile).unwrap();
+        let mut archive = Archive::new(GzDecoder::new(tar_gz));
+        assert!(!archive.entries().unwrap().any(|item| {
+            item.unwrap().path().unwrap() == PathBuf::from(TARBALL_DIRNAME).join("goodbye.txt")
+        }));
     }
 }
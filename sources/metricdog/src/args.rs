@@ -24,4 +24,6 @@ pub(crate) enum Command {
     SendBootSuccess,
     /// check services and report their health.
     SendHealthPing,
+    /// serve Prometheus metrics about host components, if enabled in the config.
+    Serve,
 }
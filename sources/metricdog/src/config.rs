@@ -2,9 +2,11 @@ use crate::error::{self, Result};
 use serde::Deserialize;
 use snafu::ResultExt;
 use std::fs;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 
 const DEFAULT_CONFIG_PATH: &str = "/etc/metricdog.toml";
+const DEFAULT_PROMETHEUS_LISTEN_ADDRESS: &str = "127.0.0.1:9101";
 
 #[derive(Debug, Deserialize)]
 pub(crate) struct Config {
@@ -15,6 +17,15 @@ pub(crate) struct Config {
     pub(crate) seed: u32,
     pub(crate) version_lock: String,
     pub(crate) ignore_waves: bool,
+    #[serde(default)]
+    pub(crate) prometheus_enabled: bool,
+    #[serde(default = "default_prometheus_listen_address")]
+    pub(crate) prometheus_listen_address: SocketAddr,
+}
+
+fn default_prometheus_listen_address() -> SocketAddr {
+    // This is a valid constant, so parsing can't fail.
+    DEFAULT_PROMETHEUS_LISTEN_ADDRESS.parse().unwrap()
 }
 
 impl Config {
@@ -44,6 +55,8 @@ mod test {
     seed = 1234
     version_lock = "v0.1.2"
     ignore_waves = false
+    prometheus_enabled = true
+    prometheus_listen_address = "0.0.0.0:9100"
     "#;
 
     // This is what a config might look like if the user opts out of metrics collection.
@@ -73,6 +86,8 @@ mod test {
         assert_eq!(1234, config.seed);
         assert_eq!("v0.1.2", config.version_lock);
         assert!(!config.ignore_waves);
+        assert!(config.prometheus_enabled);
+        assert_eq!("0.0.0.0:9100", config.prometheus_listen_address.to_string());
     }
 
     #[test]
@@ -91,5 +106,10 @@ mod test {
         assert_eq!(1234, config.seed);
         assert_eq!("v0.1.2", config.version_lock);
         assert!(!config.ignore_waves);
+        assert!(!config.prometheus_enabled);
+        assert_eq!(
+            "127.0.0.1:9101",
+            config.prometheus_listen_address.to_string()
+        );
     }
 }
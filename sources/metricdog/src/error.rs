@@ -1,6 +1,7 @@
 //! Provides the list of errors for `metricdog`.
 
 use snafu::Snafu;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 use url::Url;
 
@@ -10,13 +11,6 @@ pub(crate) enum Error {
     #[snafu(display("Unable to load Bottlerocket release info: '{}'", source))]
     BottlerocketRelease { source: bottlerocket_release::Error },
 
-    #[snafu(display("Command '{}' with args '{:?}' failed: {}", command, args, source))]
-    Command {
-        command: String,
-        args: Vec<String>,
-        source: std::io::Error,
-    },
-
     #[snafu(display("Failed to parse config file {}: {}", path.display(), source))]
     ConfigParse {
         path: PathBuf,
@@ -29,6 +23,18 @@ pub(crate) enum Error {
         source: std::io::Error,
     },
 
+    #[snafu(display("Unable to read settings apply status: {}", source))]
+    ApplyStatus { source: thar_be_settings::Error },
+
+    #[snafu(display("Unable to read datastore version link {}: {}", path.display(), source))]
+    DatastoreLink {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Datastore version link {} has no usable version", path.display()))]
+    DatastoreVersion { path: PathBuf },
+
     #[snafu(display("Error building HTTP client for {}: {}", url.as_str(), source))]
     HttpClient { url: Url, source: reqwest::Error },
 
@@ -38,6 +44,39 @@ pub(crate) enum Error {
     #[snafu(display("Error receiving HTTP response {}: {}", url.as_str(), source))]
     HttpResponse { url: Url, source: reqwest::Error },
 
+    #[snafu(display("Unable to listen for metrics requests on {}: {}", address, source))]
+    Listen {
+        address: SocketAddr,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Unable to read partition set state: {}", source))]
+    PartitionState { source: signpost::Error },
+
+    #[snafu(display("Unable to check health of service '{}': {}", service, source))]
+    ServiceCheck {
+        service: String,
+        source: service_check::Error,
+    },
+
+    #[snafu(display("Unable to lock update status with {}: {}", path.display(), source))]
+    UpdateLock {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Unable to parse update status {}: {}", path.display(), source))]
+    UpdateStatusParse {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+
+    #[snafu(display("Unable to read update status {}: {}", path.display(), source))]
+    UpdateStatusRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
     #[snafu(display("Unable to parse URL {}: {}", url, source))]
     UrlParse {
         url: String,
@@ -0,0 +1,739 @@
+//! Serves metrics about host components in the Prometheus text exposition format.
+//!
+//! Metrics are gathered when they're scraped, by a set of collectors that each read one source:
+//! the update status kept by thar-be-updates, the partition set state read by signpost, the
+//! datastore version links, the apply status kept by thar-be-settings, and the health of the
+//! configured services.  A collector that can't read its source doesn't stop the others; it's
+//! reported through `bottlerocket_exporter_collector_success` instead.
+
+use crate::error::{self, Result};
+use bottlerocket_release::BottlerocketRelease;
+use fs2::FileExt;
+use log::{debug, info, warn};
+use service_check::ServiceCheck;
+use signpost::{SetSelect, State};
+use snafu::{OptionExt, ResultExt};
+use std::fmt::Write as _;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, ErrorKind, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use thar_be_settings::status::{ApplyStatus, APPLY_STATUS_FILE};
+use thar_be_updates::status::{
+    CommandStatus, UpdateCommand, UpdateState, UpdateStatus, UPDATE_LOCKFILE, UPDATE_STATUS_FILE,
+};
+
+const DATASTORE_DIR: &str = "/var/lib/bottlerocket/datastore";
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+/// Scrapers send small requests; anything beyond this isn't read.
+const MAX_REQUEST_BYTES: u64 = 8192;
+/// How long a client has to send its request and read the response, in total.  Requests are
+/// handled one at a time, so this bounds how long a slow client can hold up other scrapers.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Adds one group of metrics, e.g. about updates, to the output.
+type Collector = fn(&Exporter, &mut Exposition) -> Result<()>;
+/// Gets a value about a partition set, for one of the `bottlerocket_partition_*` metrics.
+type PartitionValue = fn(&State, SetSelect) -> f64;
+
+/// The files the collectors read.  These are the system paths except in tests.
+pub(crate) struct Sources {
+    pub(crate) update_lockfile: PathBuf,
+    pub(crate) update_status: PathBuf,
+    pub(crate) datastore_dir: PathBuf,
+    pub(crate) apply_status: PathBuf,
+}
+
+impl Default for Sources {
+    fn default() -> Self {
+        Self {
+            update_lockfile: UPDATE_LOCKFILE.into(),
+            update_status: UPDATE_STATUS_FILE.into(),
+            datastore_dir: DATASTORE_DIR.into(),
+            apply_status: APPLY_STATUS_FILE.into(),
+        }
+    }
+}
+
+/// Gathers metrics from host components and serves them over HTTP.
+pub(crate) struct Exporter {
+    /// Information about the running Bottlerocket release, e.g. from `os-release`
+    os_release: BottlerocketRelease,
+    /// The services whose health is reported, from `service_checks` in the config
+    service_checks: Vec<String>,
+    /// Checks the health of the services in `service_checks`
+    healthcheck: Box<dyn ServiceCheck>,
+    sources: Sources,
+}
+
+impl Exporter {
+    pub(crate) fn from_parts(
+        os_release: BottlerocketRelease,
+        service_checks: Vec<String>,
+        healthcheck: Box<dyn ServiceCheck>,
+        sources: Sources,
+    ) -> Self {
+        Self {
+            os_release,
+            service_checks,
+            healthcheck,
+            sources,
+        }
+    }
+
+    /// Listens on `address` and answers `GET /metrics` with the current metrics until the process
+    /// is stopped.  Requests are handled one at a time, each within `REQUEST_TIMEOUT`; scrapers
+    /// are expected to be few.
+    pub(crate) fn serve(&self, address: SocketAddr) -> Result<()> {
+        let listener = TcpListener::bind(address).context(error::ListenSnafu { address })?;
+        info!("Serving Prometheus metrics at http://{}/metrics", address);
+        for stream in listener.incoming() {
+            let result = stream.and_then(|stream| self.handle(stream, REQUEST_TIMEOUT));
+            if let Err(e) = result {
+                warn!("Failed to handle metrics request: {}", e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Answers one request, giving up if the client hasn't sent its request and read the response
+    /// within `timeout`.
+    fn handle(&self, stream: TcpStream, timeout: Duration) -> io::Result<()> {
+        let mut stream = DeadlineStream {
+            stream,
+            deadline: Instant::now() + timeout,
+        };
+        let mut reader = BufReader::new((&mut stream).take(MAX_REQUEST_BYTES));
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        // We don't use the headers, but read them so the client sees its request was consumed.
+        let mut header = String::new();
+        while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+            header.clear();
+        }
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default();
+        let path = parts.next().unwrap_or_default();
+        debug!("Received metrics request: {} {}", method, path);
+        let (status, content_type, body) = match (method, path.split('?').next()) {
+            ("GET", Some("/metrics")) => ("200 OK", CONTENT_TYPE, self.render()),
+            ("GET", _) => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+            _ => (
+                "405 Method Not Allowed",
+                "text/plain",
+                "Method not allowed\n".to_string(),
+            ),
+        };
+        write!(
+            stream,
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        )?;
+        stream.flush()
+    }
+
+    /// Runs each collector and returns the metrics in the Prometheus text exposition format.
+    pub(crate) fn render(&self) -> String {
+        let mut out = Exposition::default();
+        out.family(
+            "bottlerocket_info",
+            "gauge",
+            "The running Bottlerocket release.",
+        );
+        out.sample(
+            "bottlerocket_info",
+            &[
+                ("version", &self.os_release.version_id.to_string()),
+                ("variant", &self.os_release.variant_id),
+                ("arch", &self.os_release.arch),
+                ("build_id", &self.os_release.build_id),
+            ],
+            1.0,
+        );
+
+        let collectors: [(&str, Collector); 5] = [
+            ("updates", Self::collect_updates),
+            ("partitions", Self::collect_partitions),
+            ("datastore", Self::collect_datastore),
+            ("settings_apply", Self::collect_settings_apply),
+            ("services", Self::collect_services),
+        ];
+        let mut results = Vec::new();
+        for (name, collector) in collectors.iter() {
+            // Collect into a scratch buffer so a collector that fails partway adds nothing.
+            let mut scratch = Exposition::default();
+            let success = match collector(self, &mut scratch) {
+                Ok(()) => {
+                    out.text.push_str(&scratch.text);
+                    true
+                }
+                Err(e) => {
+                    warn!("Failed to collect {} metrics: {}", name, e);
+                    false
+                }
+            };
+            results.push((*name, success));
+        }
+
+        out.family(
+            "bottlerocket_exporter_collector_success",
+            "gauge",
+            "Whether the collector gathered its metrics.",
+        );
+        for (name, success) in results {
+            out.sample(
+                "bottlerocket_exporter_collector_success",
+                &[("collector", name)],
+                flag(success),
+            );
+        }
+        out.text
+    }
+
+    /// Reads the update status the way the API server does, under a shared lock so we don't see
+    /// a partial write from thar-be-updates.  Returns `None` before the status is first written.
+    fn update_status(&self) -> Result<Option<UpdateStatus>> {
+        let lockfile_path = &self.sources.update_lockfile;
+        let lockfile = File::create(lockfile_path).context(error::UpdateLockSnafu {
+            path: lockfile_path,
+        })?;
+        FileExt::try_lock_shared(&lockfile).context(error::UpdateLockSnafu {
+            path: lockfile_path,
+        })?;
+        let path = &self.sources.update_status;
+        let status_file = match File::open(path) {
+            Ok(f) => f,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context(error::UpdateStatusReadSnafu { path }),
+        };
+        let status =
+            serde_json::from_reader(status_file).context(error::UpdateStatusParseSnafu { path })?;
+        Ok(Some(status))
+    }
+
+    fn collect_updates(&self, out: &mut Exposition) -> Result<()> {
+        let status = match self.update_status()? {
+            Some(status) => status,
+            None => return Ok(()),
+        };
+
+        out.family(
+            "bottlerocket_update_state",
+            "gauge",
+            "The state of the update process; 1 for the current state.",
+        );
+        for (state, name) in &[
+            (UpdateState::Idle, "idle"),
+            (UpdateState::Available, "available"),
+            (UpdateState::Staged, "staged"),
+            (UpdateState::Ready, "ready"),
+        ] {
+            let current =
+                std::mem::discriminant(state) == std::mem::discriminant(status.update_state());
+            out.sample(
+                "bottlerocket_update_state",
+                &[("state", name)],
+                flag(current),
+            );
+        }
+
+        out.family(
+            "bottlerocket_updates_available",
+            "gauge",
+            "The number of update versions available in the update repository.",
+        );
+        out.sample(
+            "bottlerocket_updates_available",
+            &[],
+            status.available_updates().len() as f64,
+        );
+
+        if let Some(command) = status.most_recent_command() {
+            let name = match command.cmd_type() {
+                UpdateCommand::Refresh => "refresh",
+                UpdateCommand::Prepare => "prepare",
+                UpdateCommand::Activate => "activate",
+                UpdateCommand::Deactivate => "deactivate",
+            };
+            out.family(
+                "bottlerocket_update_last_command_success",
+                "gauge",
+                "Whether the most recent update command succeeded.",
+            );
+            out.sample(
+                "bottlerocket_update_last_command_success",
+                &[("command", name)],
+                flag(matches!(command.cmd_status(), CommandStatus::Success)),
+            );
+            out.family(
+                "bottlerocket_update_last_command_timestamp_seconds",
+                "gauge",
+                "When the most recent update command ran, in seconds since the Unix epoch.",
+            );
+            out.sample(
+                "bottlerocket_update_last_command_timestamp_seconds",
+                &[("command", name)],
+                command.timestamp().timestamp() as f64,
+            );
+        }
+        Ok(())
+    }
+
+    fn collect_partitions(&self, out: &mut Exposition) -> Result<()> {
+        let state = State::load().context(error::PartitionStateSnafu)?;
+        // The staged version is only known if thar-be-updates wrote it this boot.
+        let staged_version = self
+            .update_status()
+            .ok()
+            .flatten()
+            .and_then(|status| {
+                status
+                    .staging_partition()
+                    .map(|staged| staged.image().version().to_string())
+            })
+            .unwrap_or_default();
+        let active_version = self.os_release.version_id.to_string();
+
+        let sets = [
+            (state.active(), "active", active_version.as_str()),
+            (state.inactive(), "inactive", staged_version.as_str()),
+        ];
+        let families: [(&str, &str, PartitionValue); 4] = [
+            (
+                "bottlerocket_partition_priority",
+                "The boot priority of the partition set; the highest that can boot is used.",
+                |state, set| state.priority(set) as f64,
+            ),
+            (
+                "bottlerocket_partition_tries_left",
+                "How many more times the partition set may be tried before it's booted successfully.",
+                |state, set| state.tries_left(set) as f64,
+            ),
+            (
+                "bottlerocket_partition_successful",
+                "Whether the partition set has been marked as successfully booted.",
+                |state, set| flag(state.successful(set)),
+            ),
+            (
+                "bottlerocket_partition_next_to_boot",
+                "Whether the partition set will be used on the next boot.",
+                |state, set| flag(state.next() == Some(set)),
+            ),
+        ];
+
+        out.family(
+            "bottlerocket_partition_info",
+            "gauge",
+            "The version in each partition set, if known.",
+        );
+        for (set, role, version) in &sets {
+            out.sample(
+                "bottlerocket_partition_info",
+                &[
+                    ("set", &set.to_string()),
+                    ("role", role),
+                    ("version", version),
+                ],
+                1.0,
+            );
+        }
+        for (name, help, value) in &families {
+            out.family(name, "gauge", help);
+            for (set, role, _) in &sets {
+                out.sample(
+                    name,
+                    &[("set", &set.to_string()), ("role", role)],
+                    value(&state, *set),
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Follows the `current` link through the major, minor, and patch version links to find the
+    /// version of the datastore, like migrator does.
+    fn collect_datastore(&self, out: &mut Exposition) -> Result<()> {
+        let dir = &self.sources.datastore_dir;
+        let mut link = dir.join("current");
+        for _ in 0..3 {
+            let target = fs::read_link(&link).context(error::DatastoreLinkSnafu { path: &link })?;
+            link = dir.join(target);
+        }
+        let version = link
+            .file_name()
+            .and_then(|name| name.to_str())
+            .context(error::DatastoreVersionSnafu { path: &link })?;
+        // Allow 'v' at the start so the links have clearer names for humans
+        let version = version.strip_prefix('v').unwrap_or(version);
+
+        out.family(
+            "bottlerocket_datastore_version_info",
+            "gauge",
+            "The version of the settings datastore.",
+        );
+        out.sample(
+            "bottlerocket_datastore_version_info",
+            &[("version", version)],
+            1.0,
+        );
+        Ok(())
+    }
+
+    fn collect_settings_apply(&self, out: &mut Exposition) -> Result<()> {
+        let status =
+            match ApplyStatus::load(&self.sources.apply_status).context(error::ApplyStatusSnafu)? {
+                Some(status) => status,
+                None => return Ok(()),
+            };
+        let samples: [(&str, &str, &str, f64); 5] = [
+            (
+                "bottlerocket_settings_apply_last_duration_seconds",
+                "gauge",
+                "How long the most recent run that applied settings took.",
+                status.last_run_duration_seconds,
+            ),
+            (
+                "bottlerocket_settings_apply_last_success",
+                "gauge",
+                "Whether the most recent run that applied settings succeeded.",
+                flag(status.last_run_success),
+            ),
+            (
+                "bottlerocket_settings_apply_last_timestamp_seconds",
+                "gauge",
+                "When the most recent run that applied settings finished, in seconds since the Unix epoch.",
+                status.last_run_timestamp as f64,
+            ),
+            (
+                "bottlerocket_settings_apply_runs_total",
+                "counter",
+                "Runs that applied settings since boot.",
+                status.runs as f64,
+            ),
+            (
+                "bottlerocket_settings_apply_failures_total",
+                "counter",
+                "Runs that failed to apply settings since boot.",
+                status.failures as f64,
+            ),
+        ];
+        for (name, kind, help, value) in &samples {
+            out.family(name, kind, help);
+            out.sample(name, &[], *value);
+        }
+        Ok(())
+    }
+
+    fn collect_services(&self, out: &mut Exposition) -> Result<()> {
+        let mut healths = Vec::new();
+        for service in &self.service_checks {
+            let health = self
+                .healthcheck
+                .check(service)
+                .context(error::ServiceCheckSnafu { service })?;
+            healths.push((service, health));
+        }
+
+        out.family(
+            "bottlerocket_service_healthy",
+            "gauge",
+            "Whether the service checked by metricdog is healthy.",
+        );
+        for (service, health) in &healths {
+            out.sample(
+                "bottlerocket_service_healthy",
+                &[("service", service)],
+                flag(health.is_healthy),
+            );
+        }
+        out.family(
+            "bottlerocket_service_exit_code",
+            "gauge",
+            "The exit code of an unhealthy service, if it exited.",
+        );
+        for (service, health) in &healths {
+            if let Some(exit_code) = health.exit_code {
+                out.sample(
+                    "bottlerocket_service_exit_code",
+                    &[("service", service)],
+                    f64::from(exit_code),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Wraps a connection so reads and writes fail once the deadline passes.  A socket timeout alone
+/// only limits each read or write, so a client sending a byte at a time could take much longer.
+struct DeadlineStream {
+    stream: TcpStream,
+    deadline: Instant,
+}
+
+impl DeadlineStream {
+    /// Limits the next read or write to the time left before the deadline.
+    fn remaining(&self) -> io::Result<Duration> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining == Duration::ZERO {
+            return Err(io::Error::new(
+                ErrorKind::TimedOut,
+                "client didn't finish its request in time",
+            ));
+        }
+        Ok(remaining)
+    }
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.remaining()?;
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for DeadlineStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let remaining = self.remaining()?;
+        self.stream.set_write_timeout(Some(remaining))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+/// Builds a document in the Prometheus text exposition format.
+#[derive(Default)]
+struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    /// Starts a metric family; its samples should follow.
+    fn family(&mut self, name: &str, kind: &str, help: &str) {
+        // Writing to a String can't fail.
+        let _ = writeln!(self.text, "# HELP {} {}", name, escape(help, false));
+        let _ = writeln!(self.text, "# TYPE {} {}", name, kind);
+    }
+
+    fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) {
+        self.text.push_str(name);
+        if !labels.is_empty() {
+            let labels = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape(value, true)))
+                .collect::<Vec<_>>()
+                .join(",");
+            let _ = write!(self.text, "{{{}}}", labels);
+        }
+        let _ = writeln!(self.text, " {}", value);
+    }
+}
+
+/// Prometheus has no boolean type; true is 1 and false is 0.
+fn flag(b: bool) -> f64 {
+    f64::from(u8::from(b))
+}
+
+/// Escapes backslashes and newlines, and double quotes in label values, as the format requires.
+fn escape(s: &str, quotes: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '"' if quotes => escaped.push_str("\\\""),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use service_check::ServiceHealth;
+    use std::os::unix::fs::symlink;
+    use tempfile::TempDir;
+
+    const OS_RELEASE: &str = r#"PRETTY_NAME=Bottlerocket
+VARIANT_ID=myvariant
+VERSION_ID=1.2.3
+BUILD_ID=abcdef0
+"#;
+
+    const UPDATE_STATUS: &str = r#"{
+        "update_state": "Available",
+        "available_updates": ["1.3.0", "1.2.3"],
+        "chosen_update": null,
+        "active_partition": null,
+        "staging_partition": null,
+        "most_recent_command": {
+            "cmd_type": "refresh",
+            "cmd_status": "Failed",
+            "timestamp": "2021-01-02T03:04:05Z",
+            "exit_status": 1,
+            "stderr": "oops"
+        }
+    }"#;
+
+    struct MockCheck {}
+
+    impl ServiceCheck for MockCheck {
+        fn check(&self, service_name: &str) -> service_check::Result<ServiceHealth> {
+            Ok(ServiceHealth {
+                is_healthy: !service_name.ends_with("failed"),
+                exit_code: service_name.strip_suffix("failed").map(|_| 3),
+            })
+        }
+    }
+
+    fn exporter(dir: &TempDir) -> Exporter {
+        let os_release_path = dir.path().join("os-release");
+        fs::write(&os_release_path, OS_RELEASE).unwrap();
+        Exporter::from_parts(
+            BottlerocketRelease::from_file(&os_release_path).unwrap(),
+            vec!["a".to_string(), "bfailed".to_string()],
+            Box::new(MockCheck {}),
+            Sources {
+                update_lockfile: dir.path().join("update.lock"),
+                update_status: dir.path().join("update-status.json"),
+                datastore_dir: dir.path().join("datastore"),
+                apply_status: dir.path().join("apply-status.json"),
+            },
+        )
+    }
+
+    #[test]
+    fn render_metrics() {
+        let dir = TempDir::new().unwrap();
+        let exporter = exporter(&dir);
+        fs::write(&exporter.sources.update_status, UPDATE_STATUS).unwrap();
+        let datastore = &exporter.sources.datastore_dir;
+        fs::create_dir(datastore).unwrap();
+        fs::create_dir(datastore.join("v1.2.3_abc")).unwrap();
+        symlink("v1.2.3_abc", datastore.join("v1.2.3")).unwrap();
+        symlink("v1.2.3", datastore.join("v1.2")).unwrap();
+        symlink("v1.2", datastore.join("v1")).unwrap();
+        symlink("v1", datastore.join("current")).unwrap();
+        ApplyStatus::record(
+            &exporter.sources.apply_status,
+            Duration::from_secs(2),
+            false,
+        )
+        .unwrap();
+
+        let text = exporter.render();
+        for line in &[
+            "bottlerocket_info{version=\"1.2.3\",variant=\"myvariant\",arch=\"",
+            "bottlerocket_update_state{state=\"idle\"} 0\n",
+            "bottlerocket_update_state{state=\"available\"} 1\n",
+            "bottlerocket_updates_available 2\n",
+            "bottlerocket_update_last_command_success{command=\"refresh\"} 0\n",
+            "bottlerocket_update_last_command_timestamp_seconds{command=\"refresh\"} 1609556645\n",
+            "bottlerocket_datastore_version_info{version=\"1.2.3\"} 1\n",
+            "bottlerocket_settings_apply_last_duration_seconds 2\n",
+            "bottlerocket_settings_apply_last_success 0\n",
+            "bottlerocket_settings_apply_runs_total 1\n",
+            "bottlerocket_settings_apply_failures_total 1\n",
+            "bottlerocket_service_healthy{service=\"a\"} 1\n",
+            "bottlerocket_service_healthy{service=\"bfailed\"} 0\n",
+            "bottlerocket_service_exit_code{service=\"bfailed\"} 3\n",
+            "bottlerocket_exporter_collector_success{collector=\"updates\"} 1\n",
+            "bottlerocket_exporter_collector_success{collector=\"datastore\"} 1\n",
+            "bottlerocket_exporter_collector_success{collector=\"settings_apply\"} 1\n",
+            "bottlerocket_exporter_collector_success{collector=\"services\"} 1\n",
+        ] {
+            assert!(text.contains(line), "missing {:?} in:\n{}", line, text);
+        }
+        assert!(!text.contains("bottlerocket_service_exit_code{service=\"a\"}"));
+    }
+
+    #[test]
+    fn render_missing_sources() {
+        // Before updates are checked or settings applied, those collectors have nothing to add.
+        let dir = TempDir::new().unwrap();
+        let text = exporter(&dir).render();
+        assert!(!text.contains("bottlerocket_update_state"));
+        assert!(!text.contains("bottlerocket_settings_apply"));
+        assert!(text.contains("bottlerocket_exporter_collector_success{collector=\"updates\"} 1\n"));
+        assert!(text
+            .contains("bottlerocket_exporter_collector_success{collector=\"settings_apply\"} 1\n"));
+        assert!(
+            text.contains("bottlerocket_exporter_collector_success{collector=\"datastore\"} 0\n")
+        );
+    }
+
+    #[test]
+    fn handle_request() {
+        let dir = TempDir::new().unwrap();
+        let exporter = exporter(&dir);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream
+                .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+                .unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        });
+        let (stream, _) = listener.accept().unwrap();
+        exporter.handle(stream, REQUEST_TIMEOUT).unwrap();
+        let response = client.join().unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("bottlerocket_info{"));
+    }
+
+    #[test]
+    fn handle_slow_client() {
+        // A client that keeps sending a byte at a time is cut off at the deadline, even though
+        // no single read waits long.
+        let dir = TempDir::new().unwrap();
+        let exporter = exporter(&dir);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            for _ in 0..50 {
+                if stream.write_all(b"G").is_err() {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        });
+        let (stream, _) = listener.accept().unwrap();
+        let start = Instant::now();
+        let err = exporter
+            .handle(stream, Duration::from_millis(300))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn exposition_format() {
+        let mut out = Exposition::default();
+        out.family("thing_total", "counter", "Things seen.\nMore \\ less");
+        out.sample("thing_total", &[], 3.0);
+        out.sample("thing_total", &[("kind", "a \"b\"\\c\n")], 1.5);
+        out.sample("thing_total", &[("kind", "x"), ("good", "y")], flag(true));
+        assert_eq!(
+            out.text,
+            "# HELP thing_total Things seen.\\nMore \\\\ less\n\
+             # TYPE thing_total counter\n\
+             thing_total 3\n\
+             thing_total{kind=\"a \\\"b\\\"\\\\c\\n\"} 1.5\n\
+             thing_total{kind=\"x\",good=\"y\"} 1\n"
+        );
+    }
+}
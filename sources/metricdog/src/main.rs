@@ -35,6 +35,28 @@ seed the environment with these variables manually.
 * `is_healthy`: true or false based on whether critical services are running.
 * `failed_services`: a list of critical services that have failed, if any.
 
+# Prometheus metrics
+
+`metricdog serve` answers `GET /metrics` on `prometheus_listen_address` with metrics about host
+components in the Prometheus text format, so a local agent can scrape them.
+It's off unless `prometheus_enabled` is true; otherwise it exits right away.
+Nothing is sent anywhere, so this doesn't depend on `send_metrics`.
+
+Metrics are gathered on each scrape:
+
+* `bottlerocket_info`: the running version, variant, architecture, and build.
+* `bottlerocket_update_*`: the update state, the number of available updates, and the result of the
+  most recent update command, from the status kept by `thar-be-updates`.
+* `bottlerocket_partition_*`: the version, priority, tries left, successful flag, and whether it
+  boots next, for the active and inactive partition sets.
+* `bottlerocket_datastore_version_info`: the version of the settings datastore.
+* `bottlerocket_settings_apply_*`: how long the latest run of `thar-be-settings` took, whether it
+  succeeded, and how many runs and failures there have been since boot.
+* `bottlerocket_service_healthy` and `bottlerocket_service_exit_code`: the health of each service in
+  `service_checks`.
+* `bottlerocket_exporter_collector_success`: whether each of the groups above could be read.
+  A group that can't be read is left out rather than failing the scrape.
+
 # Configuration
 
 Configuration is read from a TOML file, which is generated from Bottlerocket settings:
@@ -54,6 +76,10 @@ seed = 1234
 version_lock = "latest"
 # whether bottlerocket should ignore update roll-out timing
 ignore_waves = false
+# whether `metricdog serve` serves Prometheus metrics, off by default
+prometheus_enabled = false
+# where `metricdog serve` listens
+prometheus_listen_address = "127.0.0.1:9101"
 ```
 */
 
@@ -62,20 +88,21 @@ ignore_waves = false
 mod args;
 mod config;
 mod error;
+mod exporter;
 #[cfg(test)]
 mod main_test;
 mod metricdog;
 #[cfg(test)]
 mod metricdog_test;
-mod service_check;
 
 use crate::args::{Arguments, Command};
 use crate::config::Config;
 use crate::error::Result;
+use crate::exporter::{Exporter, Sources};
 use crate::metricdog::Metricdog;
-use crate::service_check::{ServiceCheck, SystemdCheck};
 use bottlerocket_release::BottlerocketRelease;
-use log::error;
+use log::{error, info};
+use service_check::{ServiceCheck, SystemdCheck};
 use simplelog::{Config as LogConfig, SimpleLogger};
 use snafu::ResultExt;
 use std::process;
@@ -101,18 +128,28 @@ pub(crate) fn main_inner(arguments: Arguments, service_check: Box<dyn ServiceChe
         Some(filepath) => Config::from_file(filepath)?,
     };
 
+    // serving Prometheus metrics is opted into separately from sending metrics
+    if let Command::Serve = arguments.command {
+        if !config.prometheus_enabled {
+            info!("Prometheus metrics are disabled, exiting");
+            return Ok(());
+        }
+        let exporter = Exporter::from_parts(
+            load_os_release(&arguments)?,
+            config.service_checks,
+            service_check,
+            Sources::default(),
+        );
+        return exporter.serve(config.prometheus_listen_address);
+    }
+
     // exit early with no error if the opt-out flag is set
     if !config.send_metrics {
         return Ok(());
     }
 
     // load bottlerocket release info
-    let os_release = if let Some(os_release_path) = &arguments.os_release {
-        BottlerocketRelease::from_file(os_release_path)
-    } else {
-        BottlerocketRelease::new()
-    }
-    .context(error::BottlerocketReleaseSnafu)?;
+    let os_release = load_os_release(&arguments)?;
 
     // instantiate the metricdog object
     let metricdog = Metricdog::from_parts(config, os_release, service_check)?;
@@ -129,6 +166,17 @@ pub(crate) fn main_inner(arguments: Arguments, service_check: Box<dyn ServiceChe
         Command::SendHealthPing => {
             metricdog.send_health_ping()?;
         }
+        // handled above, since it doesn't send anything
+        Command::Serve => {}
     }
     Ok(())
 }
+
+fn load_os_release(arguments: &Arguments) -> Result<BottlerocketRelease> {
+    if let Some(os_release_path) = &arguments.os_release {
+        BottlerocketRelease::from_file(os_release_path)
+    } else {
+        BottlerocketRelease::new()
+    }
+    .context(error::BottlerocketReleaseSnafu)
+}
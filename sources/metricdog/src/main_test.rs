@@ -1,10 +1,9 @@
 use crate::args::{Arguments, Command};
-use crate::error::Result;
 use crate::main_inner;
-use crate::service_check::{ServiceCheck, ServiceHealth};
 use httptest::responders::status_code;
 use httptest::{matchers::*, Expectation, Server};
 use log::LevelFilter;
+use service_check::{Result, ServiceCheck, ServiceHealth};
 use std::fs::write;
 use std::path::PathBuf;
 use tempfile::TempDir;
@@ -173,3 +172,16 @@ fn send_health_ping() {
     };
     main_inner(args, Box::new(MockCheck {})).unwrap();
 }
+
+#[test]
+/// assert that serve exits without error, instead of listening, when Prometheus metrics are disabled
+fn serve_disabled() {
+    let tempdir = create_test_files("http://localhost:0/metrics", &[], true);
+    let args = Arguments {
+        config: Some(config_path(&tempdir)),
+        log_level: LevelFilter::Off,
+        os_release: Some(os_release_path(&tempdir)),
+        command: Command::Serve,
+    };
+    main_inner(args, Box::new(MockCheck {})).unwrap();
+}
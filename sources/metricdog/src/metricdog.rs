@@ -1,9 +1,9 @@
 use crate::config::Config;
 use crate::error::{self, Result};
-use crate::service_check::ServiceCheck;
 use bottlerocket_release::BottlerocketRelease;
 use log::debug;
 use reqwest::blocking::Client;
+use service_check::ServiceCheck;
 use snafu::ResultExt;
 use std::collections::HashMap;
 use std::str::FromStr;
@@ -118,7 +118,10 @@ impl Metricdog {
         let mut is_healthy = true;
         let mut failed_services = Vec::new();
         for service in &self.config.service_checks {
-            let service_status = self.healthcheck.check(service)?;
+            let service_status = self
+                .healthcheck
+                .check(service)
+                .context(error::ServiceCheckSnafu { service })?;
             if !service_status.is_healthy {
                 is_healthy = false;
                 match service_status.exit_code {
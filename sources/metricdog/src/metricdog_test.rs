@@ -1,9 +1,8 @@
 use crate::config::Config;
-use crate::error::Result;
 use crate::metricdog::Metricdog;
-use crate::service_check::{ServiceCheck, ServiceHealth};
 use bottlerocket_release::BottlerocketRelease;
 use httptest::{matchers::*, responders::*, Expectation, Server};
+use service_check::{Result, ServiceCheck, ServiceHealth};
 use tempfile::TempDir;
 
 const OS_RELEASE: &str = r#"NAME=Bottlerocket
@@ -74,6 +73,8 @@ fn send_healthy_ping() {
             seed: 2041,
             version_lock: String::from("latest"),
             ignore_waves: false,
+            prometheus_enabled: false,
+            prometheus_listen_address: "127.0.0.1:9101".parse().unwrap(),
         },
         os_release(),
         Box::new(MockCheck {}),
@@ -117,6 +118,8 @@ fn send_unhealthy_ping() {
             seed: 2041,
             version_lock: String::from("latest"),
             ignore_waves: false,
+            prometheus_enabled: false,
+            prometheus_listen_address: "127.0.0.1:9101".parse().unwrap(),
         },
         os_release(),
         Box::new(MockCheck {}),
@@ -153,6 +156,8 @@ fn send_boot_success() {
             seed: 2041,
             version_lock: String::from("latest"),
             ignore_waves: false,
+            prometheus_enabled: false,
+            prometheus_listen_address: "127.0.0.1:9101".parse().unwrap(),
         },
         os_release(),
         Box::new(MockCheck {}),
@@ -35,16 +35,44 @@ This can be disabled by specifying the argument `add_option = false`.
 
 Fields are all wrapped in `Option<...>`.
 Similar to the `serde` attribute added to fields, this is because we don't want users to have to specify fields they aren't changing, and can be disabled the same way, by specifying `add_option = false`.
+
+## JSON Schema
+
+An implementation of `scalar::schema::JsonSchema` is added so that the struct can be described in a JSON Schema of the model.
+The struct is described as an object with a property for each field, named as serde would name it, including any serde `alias`es.
+Unknown properties aren't allowed, matching `deny_unknown_fields`.
+Fields are only required if `add_option = false` and the field isn't an `Option`.
+
+Every field type must implement `JsonSchema` too.
+Structs that aren't part of settings and contain types that can't be described can skip this by specifying `schema = false`.
+
+# Field arguments
+
+## Sensitive
+
+Fields that hold secrets, like credentials or tokens, can be marked with `#[model(sensitive)]`.
+The property is described as `"writeOnly": true` in the JSON Schema, and the API leaves it out of responses when the client asks for redacted settings, as logdog does.
+The attribute is removed from the output, so serde never sees it; it has no effect with `schema = false`.
+
+```ignore
+#[model]
+struct ExampleSettings {
+    name: String,
+    #[model(sensitive)]
+    token: String,
+}
+```
 */
 
 extern crate proc_macro;
 
 use darling::FromMeta;
 use proc_macro::TokenStream;
-use quote::ToTokens;
+use quote::{quote, ToTokens};
 use syn::visit_mut::{self, VisitMut};
 use syn::{
-    parse_macro_input, parse_quote, Attribute, AttributeArgs, Field, ItemStruct, Visibility,
+    parse_macro_input, parse_quote, Attribute, AttributeArgs, Field, Fields, ItemStruct, Lit, Meta,
+    NestedMeta, Type, Visibility,
 };
 
 /// Define a `#[model]` attribute that can be placed on structs to be used in an API model.
@@ -62,8 +90,17 @@ pub fn model(args: TokenStream, input: TokenStream) -> TokenStream {
     // Parse and modify source
     let mut ast: ItemStruct =
         syn::parse(input).expect("Unable to parse item `model` was placed on - is it a struct?");
+    // The schema describes the fields as the user wrote them, before we wrap them in Option.
+    let schema_impl = if helper.schema {
+        schema_impl(&ast, helper.add_option)
+    } else {
+        proc_macro2::TokenStream::new()
+    };
     helper.visit_item_struct_mut(&mut ast);
-    ast.into_token_stream().into()
+
+    let mut output = ast.into_token_stream();
+    output.extend(schema_impl);
+    output.into()
 }
 
 /// Store any args given by the user inside `#[model(...)]`.
@@ -73,6 +110,7 @@ struct ParsedArgs {
     rename: Option<String>,
     impl_default: Option<bool>,
     add_option: Option<bool>,
+    schema: Option<bool>,
 }
 
 /// Stores the user's requested options, plus any defaults for unspecified options.
@@ -81,6 +119,7 @@ struct ModelHelper {
     rename: Option<String>,
     impl_default: bool,
     add_option: bool,
+    schema: bool,
 }
 
 /// Takes the user's requested options and sets default values for anything unspecified.
@@ -91,6 +130,7 @@ impl From<ParsedArgs> for ModelHelper {
             rename: args.rename,
             impl_default: args.impl_default.unwrap_or(false),
             add_option: args.add_option.unwrap_or(true),
+            schema: args.schema.unwrap_or(true),
         }
     }
 }
@@ -143,6 +183,10 @@ impl VisitMut for ModelHelper {
             node.vis = parse_quote!(pub)
         }
 
+        // Our field arguments were read when building the schema; remove them so the compiler
+        // doesn't look for a field attribute named `model`.
+        node.attrs.retain(|attr| !attr.path.is_ident("model"));
+
         // Add our serde attribute, if the user hasn't set one
         if self.add_option {
             if !is_attr_set("serde", &node.attrs) {
@@ -173,3 +217,140 @@ fn is_attr_set(attr_name: &'static str, attrs: &[Attribute]) -> bool {
     }
     false
 }
+
+/// Checks whether a field is marked `#[model(sensitive)]`.  Panics if the field has a `model`
+/// attribute with any other arguments, so typos aren't silently ignored.
+fn is_sensitive(attrs: &[Attribute]) -> bool {
+    let mut sensitive = false;
+    for attr in attrs {
+        if !attr.path.is_ident("model") {
+            continue;
+        }
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            _ => panic!("Field arguments to `model` must be a list, like `#[model(sensitive)]`"),
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::Path(path)) if path.is_ident("sensitive") => {
+                    sensitive = true
+                }
+                _ => panic!("Unknown field argument to `model`; expected `sensitive`"),
+            }
+        }
+    }
+    sensitive
+}
+
+/// Builds an implementation of `scalar::schema::JsonSchema` describing the given struct as an
+/// object.  If `add_option` is true, all fields are optional, as they will be wrapped in `Option`.
+fn schema_impl(node: &ItemStruct, add_option: bool) -> proc_macro2::TokenStream {
+    let name = &node.ident;
+    let title = name.to_string();
+
+    let mut property_names = Vec::new();
+    let mut property_schemas = Vec::new();
+    let mut required = Vec::new();
+    if let Fields::Named(fields) = &node.fields {
+        for field in &fields.named {
+            let ident = field.ident.as_ref().expect("named field has no name");
+            let (rename, aliases) = serde_names(&field.attrs);
+            // Fields are renamed to kebab-case by the serde attribute we add to the struct.
+            let field_name = rename
+                .unwrap_or_else(|| ident.to_string().trim_start_matches("r#").replace('_', "-"));
+
+            if !add_option && !is_option(&field.ty) {
+                required.push(field_name.clone());
+            }
+            let ty = &field.ty;
+            let property_schema = if is_sensitive(&field.attrs) {
+                quote!(scalar::schema::with_keywords(
+                    <#ty as scalar::schema::JsonSchema>::json_schema(),
+                    r#"{"writeOnly": true}"#,
+                ))
+            } else {
+                quote!(<#ty as scalar::schema::JsonSchema>::json_schema())
+            };
+            for property_name in std::iter::once(field_name).chain(aliases) {
+                property_names.push(property_name);
+                property_schemas.push(property_schema.clone());
+            }
+        }
+    }
+
+    quote!(
+        impl scalar::schema::JsonSchema for #name {
+            fn json_schema() -> scalar::schema::Value {
+                let mut properties = scalar::schema::Map::new();
+                #(
+                    properties.insert(#property_names.to_string(), #property_schemas);
+                )*
+                let required: Vec<&str> = vec![#(#required),*];
+
+                let mut schema = scalar::schema::json!({
+                    "type": "object",
+                    "title": #title,
+                    "properties": properties,
+                    "additionalProperties": false,
+                });
+                if !required.is_empty() {
+                    schema["required"] = required.into();
+                }
+                schema
+            }
+        }
+    )
+}
+
+/// Finds the name serde deserializes a field from, if it's renamed with a serde attribute, and any
+/// aliases it's also deserialized from.
+fn serde_names(attrs: &[Attribute]) -> (Option<String>, Vec<String>) {
+    let mut rename = None;
+    let mut aliases = Vec::new();
+    for attr in attrs {
+        if !attr.path.is_ident("serde") {
+            continue;
+        }
+        let list = match attr.parse_meta() {
+            Ok(Meta::List(list)) => list,
+            _ => continue,
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(nv)) => {
+                    if let Lit::Str(value) = nv.lit {
+                        if nv.path.is_ident("rename") {
+                            rename = Some(value.value());
+                        } else if nv.path.is_ident("alias") {
+                            aliases.push(value.value());
+                        }
+                    }
+                }
+                // rename(serialize = "...", deserialize = "...")
+                NestedMeta::Meta(Meta::List(inner)) if inner.path.is_ident("rename") => {
+                    for nested in inner.nested {
+                        if let NestedMeta::Meta(Meta::NameValue(nv)) = nested {
+                            if let Lit::Str(value) = nv.lit {
+                                if nv.path.is_ident("deserialize") {
+                                    rename = Some(value.value());
+                                }
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    (rename, aliases)
+}
+
+/// Checks whether the given type is spelled as an `Option`.
+fn is_option(ty: &Type) -> bool {
+    if let Type::Path(path) = ty {
+        if let Some(segment) = path.path.segments.last() {
+            return segment.ident == "Option";
+        }
+    }
+    false
+}
@@ -28,8 +28,17 @@ enum.
 The macro can take the following input parameters when used with wrapper structs (in most cases you
 will not need to use these; the defaults will "just work"):
 - `as_ref_str: bool`: Set to `true` if need the macro to treat your inner type as a `String`.
-   This will happen automatically if your inner type is named `String`.
+  This will happen automatically if your inner type is named `String`.
 - `inner`: The name of the field that holds your `inner` type. Defaults to `inner`.
+- `schema`: A JSON object of [JSON Schema](https://json-schema.org/) keywords describing the
+  constraints your `Validate` implementation enforces, like `pattern` or `maxLength`.
+
+## JSON Schema
+
+The macro implements `scalar::schema::JsonSchema` so the type can be described in a schema of the
+model.  Structs use the schema of their inner type, titled with the struct name, plus any keywords
+given in the `schema` parameter.  Enums are described as a string with one of the serialized
+names of their variants.
 
 # Examples
 
@@ -43,8 +52,10 @@ use scalar::traits::{Scalar, Validate};
 use scalar::ValidationError;
 use scalar_derive::Scalar;
 
-// We create a struct with an inner type in a field named `inner`. We derive `Scalar`.
+// We create a struct with an inner type in a field named `inner`. We derive `Scalar`.  We also
+// describe our validation in the type's JSON Schema.
 #[derive(Debug, PartialEq, Scalar)]
+#[scalar(schema = r#"{"not": {"const": "pineapple"}}"#)]
 struct Pizza {
     inner: String
 }
@@ -71,6 +82,13 @@ assert!("pepperoni" == pizza);
 let err = Pizza::new("pineapple");
 // no that's gross!
 assert!(err.is_err());
+
+// Clients can learn that without trying it.
+use scalar::schema::{json, JsonSchema};
+assert_eq!(
+    Pizza::json_schema(),
+    json!({"type": "string", "title": "Pizza", "not": {"const": "pineapple"}})
+);
 ```
 
 ## Validating a Number
@@ -114,6 +132,7 @@ In this example we will show how to use the parameters `as_ref_str` and `inner`.
 contrived, but demonstrates how to pass parameters to the derive macro.
 
 ```
+use scalar::schema::{json, JsonSchema, Value};
 use scalar::traits::{Scalar, Validate};
 use scalar::ValidationError;
 use scalar_derive::Scalar;
@@ -122,6 +141,13 @@ use serde::{Serialize, Deserialize};
 #[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 struct WeirdType;
 
+// The inner type has to describe itself so the macro can describe the wrapper.
+impl JsonSchema for WeirdType {
+    fn json_schema() -> Value {
+        json!({"type": "null"})
+    }
+}
+
 // We need `AsRef<str>` to treat it like a string with `as_ref_str`.
 impl AsRef<str> for WeirdType {
     fn as_ref(&self) -> &str {
@@ -205,6 +231,12 @@ let into_string_val: String = value.into();
 assert_eq!(into_string_val, "blue");
 let try_from_value: Color = "blue".try_into().unwrap();
 assert_eq!(Color::Blue, try_from_value);
+
+use scalar::schema::{json, JsonSchema};
+assert_eq!(
+    Color::json_schema(),
+    json!({"type": "string", "title": "Color", "enum": ["red", "green", "blue"]})
+);
 ```
 
 */
@@ -237,7 +269,8 @@ pub fn scalar(input: TokenStream) -> TokenStream {
         }
         Data::Enum(data_enum) => {
             require_simple_enum(&name, data_enum);
-            write_string_impls_for_enum(&name, &mut ast2)
+            write_string_impls_for_enum(&name, &mut ast2);
+            write_schema_impl_for_enum(&name, data_enum, &mut ast2);
         }
         Data::Union(_) => panic!("A Scalar cannot be an union, it must be a struct or enum"),
     }
@@ -259,6 +292,9 @@ struct RawSettings {
     as_ref_str: Option<bool>,
     /// The name of the field that holds the inner value in the struct. Defaults to "inner".
     inner: Option<String>,
+    /// JSON Schema keywords, as the text of a JSON object, describing the constraints enforced by
+    /// the type's validation.
+    schema: Option<String>,
 }
 
 /// Once we parse the incoming AST and see what our struct is named, see what its inner type is,
@@ -274,6 +310,9 @@ struct StructInfo {
     inner_type: String,
     /// Whether or not we should treat the inner reference type as `&str`.
     as_ref_str: bool,
+    /// The JSON Schema keywords to add to the schema of the inner type, as the text of a JSON
+    /// object.
+    schema_keywords: String,
 }
 
 impl StructInfo {
@@ -288,11 +327,26 @@ impl StructInfo {
             .as_ref_str
             .unwrap_or_else(|| is_string(&inner_type));
 
+        // Check the given schema keywords now so that mistakes are build errors.
+        let mut schema_keywords = match settings.schema {
+            Some(schema) => serde_json::from_str(&schema).unwrap_or_else(|e| {
+                panic!(
+                    "The `schema` given to the Scalar macro is not a JSON object: {}",
+                    e
+                )
+            }),
+            None => serde_json::Map::new(),
+        };
+        schema_keywords
+            .entry("title")
+            .or_insert_with(|| name.into());
+
         Self {
             scalar: name.to_owned(),
             inner_field,
             inner_type,
             as_ref_str,
+            schema_keywords: serde_json::Value::Object(schema_keywords).to_string(),
         }
     }
 
@@ -314,6 +368,7 @@ impl StructInfo {
         let scalar = format_ident!("{}", &self.scalar);
         let inner_type = format_ident!("{}", &self.inner_type);
         let inner_ref_type = self.inner_ref_type();
+        let schema_keywords = &self.schema_keywords;
 
         // Create the Scalar trait implementation, which is different based on whether the inner
         // field is named or unnamed (i.e. in a tuple struct like this `MyStruct(String)`
@@ -435,6 +490,13 @@ impl StructInfo {
                     scalar.unwrap()
                 }
             }
+
+            impl scalar::schema::JsonSchema for #scalar {
+                fn json_schema() -> scalar::schema::Value {
+                    let schema = <<#scalar as scalar::traits::Scalar>::Inner as scalar::schema::JsonSchema>::json_schema();
+                    scalar::schema::with_keywords(schema, #schema_keywords)
+                }
+            }
         );
 
         stream.append_all(impls.into_iter());
@@ -597,6 +659,26 @@ fn write_string_impls_for_enum(name: &str, ast: &mut TokenStream2) {
     ast.append_all(impls.into_iter());
 }
 
+/// Enums are described in JSON Schema as a string with one of the serialized names of the variants.
+/// We let serde tell us the names at runtime so that any serde renaming is respected.
+fn write_schema_impl_for_enum(name: &str, e: &DataEnum, ast: &mut TokenStream2) {
+    let scalar = format_ident!("{}", name);
+    let variants = e.variants.iter().map(|variant| &variant.ident);
+    let impls = quote!(
+        impl scalar::schema::JsonSchema for #scalar {
+            fn json_schema() -> scalar::schema::Value {
+                let values = vec![
+                    #(serde_plain::to_string(&#scalar::#variants)
+                        .expect("Scalar enum variant could not be serialized as a string")),*
+                ];
+                scalar::schema::json!({"type": "string", "title": #name, "enum": values})
+            }
+        }
+    );
+
+    ast.append_all(impls);
+}
+
 /// Make sure all variants of the enum are empty of data. Otherwise we can't represent this enum
 /// as a simple string in the datastore.
 fn require_simple_enum(name: &str, e: &DataEnum) {
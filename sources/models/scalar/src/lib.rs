@@ -4,12 +4,17 @@ This library provides traits and an error type to be used in Bottlerocket models
 to be combined with the `scalar-derive` crate so that we can use the `Scalar` macro on simple
 types.
 
+It also provides the `JsonSchema` trait, in the `schema` module, so that model types can describe
+the values they accept as JSON Schema.
+
 */
 
 use serde::Serialize;
 use std::error::Error;
 use std::fmt::{Display, Formatter};
 
+pub mod schema;
+
 /// Because the `Scalar` trait has the same name as the `Scalar` derive macro, we place it and the
 /// `Validate` traits into a module named `traits`.
 pub mod traits {
@@ -0,0 +1,268 @@
+//! The `schema` module lets model types describe the values they accept as [JSON Schema]
+//! (draft 7), so that clients can validate settings without a running API server.
+//!
+//! Model types implement the `JsonSchema` trait.  The `Scalar` derive macro and the `model`
+//! attribute macro implement it automatically; this module implements it for the standard types
+//! that models are built from.
+//!
+//! [JSON Schema]: https://json-schema.org/
+//!
+//! # Example
+//!
+//! ```
+//! use scalar::schema::{json, with_keywords, JsonSchema, Value};
+//!
+//! struct Pizza {
+//!     inner: String,
+//! }
+//!
+//! impl JsonSchema for Pizza {
+//!     fn json_schema() -> Value {
+//!         with_keywords(String::json_schema(), r#"{"not": {"const": "pineapple"}}"#)
+//!     }
+//! }
+//!
+//! assert_eq!(
+//!     Pizza::json_schema(),
+//!     json!({"type": "string", "not": {"const": "pineapple"}})
+//! );
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::{IpAddr, SocketAddr};
+
+pub use serde_json::{json, Map, Value};
+
+/// The JSON Schema dialect used by generated schema documents.
+pub const SCHEMA_DIALECT: &str = "http://json-schema.org/draft-07/schema#";
+
+/// Types that implement `JsonSchema` can describe, as a JSON Schema, the serialized values they
+/// accept.
+pub trait JsonSchema {
+    /// Returns a schema describing the values accepted for this type.
+    fn json_schema() -> Value;
+}
+
+/// Returns a complete schema document for `T`, which includes the dialect of the schema so that
+/// validators know how to treat it.
+pub fn document<T: JsonSchema>() -> Value {
+    with_keywords(
+        T::json_schema(),
+        &json!({ "$schema": SCHEMA_DIALECT }).to_string(),
+    )
+}
+
+/// Adds the keywords in `extra`, the text of a JSON object, to the given schema, replacing any
+/// keywords already set.  This is used to describe the constraints of a validated type on top of
+/// the schema of the type it wraps.
+///
+/// Panics if `extra` is not a JSON object, since it's expected to be a literal in the source.
+pub fn with_keywords(mut schema: Value, extra: &str) -> Value {
+    let extra: serde_json::Map<String, Value> = serde_json::from_str(extra)
+        .unwrap_or_else(|e| panic!("Schema keywords '{}' are not a JSON object: {}", extra, e));
+    if let Value::Object(schema) = &mut schema {
+        schema.extend(extra);
+    }
+    schema
+}
+
+/// Removes the properties of `value` that the given schema of its type describes as `writeOnly`,
+/// like secrets that clients can set but shouldn't see again, looking through nested objects,
+/// maps, and arrays.
+pub fn remove_write_only(schema: &Value, value: &mut Value) {
+    match value {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let additional = schema.get("additionalProperties");
+            object.retain(|name, _| {
+                let property = properties.and_then(|p| p.get(name)).or(additional);
+                property.and_then(|p| p.get("writeOnly")) != Some(&Value::Bool(true))
+            });
+            for (name, inner) in object.iter_mut() {
+                if let Some(property) = properties.and_then(|p| p.get(name)).or(additional) {
+                    remove_write_only(property, inner);
+                }
+            }
+        }
+        Value::Array(array) => match schema.get("items") {
+            // A tuple has a schema for each position.
+            Some(Value::Array(items)) => {
+                for (item_schema, item) in items.iter().zip(array) {
+                    remove_write_only(item_schema, item);
+                }
+            }
+            Some(items) => {
+                for item in array {
+                    remove_write_only(items, item);
+                }
+            }
+            None => {}
+        },
+        _ => {}
+    }
+}
+
+impl JsonSchema for String {
+    fn json_schema() -> Value {
+        json!({"type": "string"})
+    }
+}
+
+impl JsonSchema for bool {
+    fn json_schema() -> Value {
+        json!({"type": "boolean"})
+    }
+}
+
+/// Integers are constrained to the range of the Rust type, since larger values can't be
+/// deserialized.
+macro_rules! integer_schema_for {
+    ($($for:ty),+) => {
+        $(
+            impl JsonSchema for $for {
+                fn json_schema() -> Value {
+                    json!({"type": "integer", "minimum": <$for>::MIN, "maximum": <$for>::MAX})
+                }
+            }
+        )+
+    };
+}
+
+integer_schema_for!(i8, i16, i32, i64, u8, u16, u32, u64);
+
+impl JsonSchema for f32 {
+    fn json_schema() -> Value {
+        json!({"type": "number"})
+    }
+}
+
+impl JsonSchema for f64 {
+    fn json_schema() -> Value {
+        json!({"type": "number"})
+    }
+}
+
+impl JsonSchema for IpAddr {
+    fn json_schema() -> Value {
+        json!({"type": "string", "anyOf": [{"format": "ipv4"}, {"format": "ipv6"}]})
+    }
+}
+
+/// JSON Schema has no format for socket addresses, so this checks the shape, like "127.0.0.1:80"
+/// or "[::1]:80"; the address itself is checked when deserializing.
+impl JsonSchema for SocketAddr {
+    fn json_schema() -> Value {
+        json!({"type": "string", "pattern": "^([0-9.]+|\\[[0-9A-Fa-f:.]+\\]):[0-9]{1,5}$"})
+    }
+}
+
+/// Whether a field is optional is up to the containing object, so an `Option` accepts the same
+/// values as the type it holds.
+impl<T: JsonSchema> JsonSchema for Option<T> {
+    fn json_schema() -> Value {
+        T::json_schema()
+    }
+}
+
+impl<T: JsonSchema> JsonSchema for Vec<T> {
+    fn json_schema() -> Value {
+        json!({"type": "array", "items": T::json_schema()})
+    }
+}
+
+impl<A: JsonSchema, B: JsonSchema> JsonSchema for (A, B) {
+    fn json_schema() -> Value {
+        json!({
+            "type": "array",
+            "items": [A::json_schema(), B::json_schema()],
+            "minItems": 2,
+            "maxItems": 2,
+        })
+    }
+}
+
+/// Maps are serialized as objects, so the schema of the key type applies to property names.
+fn map_schema<K: JsonSchema, V: JsonSchema>() -> Value {
+    json!({
+        "type": "object",
+        "propertyNames": K::json_schema(),
+        "additionalProperties": V::json_schema(),
+    })
+}
+
+impl<K: JsonSchema, V: JsonSchema, S> JsonSchema for HashMap<K, V, S> {
+    fn json_schema() -> Value {
+        map_schema::<K, V>()
+    }
+}
+
+impl<K: JsonSchema, V: JsonSchema> JsonSchema for BTreeMap<K, V> {
+    fn json_schema() -> Value {
+        map_schema::<K, V>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keywords_replace() {
+        let schema = with_keywords(u8::json_schema(), r#"{"maximum": 100}"#);
+        assert_eq!(
+            schema,
+            json!({"type": "integer", "minimum": 0, "maximum": 100})
+        );
+    }
+
+    #[test]
+    fn nested_types() {
+        assert_eq!(
+            HashMap::<String, Vec<bool>>::json_schema(),
+            json!({
+                "type": "object",
+                "propertyNames": {"type": "string"},
+                "additionalProperties": {"type": "array", "items": {"type": "boolean"}},
+            })
+        );
+    }
+
+    #[test]
+    fn write_only_removed() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string"},
+                "token": {"type": "string", "writeOnly": true},
+                "containers": {
+                    "type": "object",
+                    "additionalProperties": {
+                        "type": "object",
+                        "properties": {"user-data": {"type": "string", "writeOnly": true}},
+                    },
+                },
+                "credentials": Vec::<HashMap<String, String>>::json_schema(),
+            },
+        });
+        let mut value = json!({
+            "name": "n",
+            "token": "secret",
+            "containers": {"admin": {"user-data": "secret"}, "control": {}},
+            "credentials": [{"password": "p"}],
+        });
+        remove_write_only(&schema, &mut value);
+        assert_eq!(
+            value,
+            json!({
+                "name": "n",
+                "containers": {"admin": {}, "control": {}},
+                "credentials": [{"password": "p"}],
+            })
+        );
+    }
+
+    #[test]
+    fn document_has_dialect() {
+        assert_eq!(document::<String>()["$schema"], SCHEMA_DIALECT);
+    }
+}
@@ -5,8 +5,8 @@ use std::collections::HashMap;
 use crate::modeled_types::Identifier;
 use crate::{
     AwsSettings, BootSettings, BootstrapContainer, CloudFormationSettings, DnsSettings,
-    HostContainer, KernelSettings, MetricsSettings, NetworkSettings, NtpSettings, OciHooks,
-    PemCertificate, RegistrySettings, UpdatesSettings,
+    HostContainer, KernelSettings, LogdogSettings, MetricsSettings, NetworkSettings, NtpSettings,
+    OciHooks, PemCertificate, RegistrySettings, UpdatesSettings,
 };
 
 // Note: we have to use 'rename' here because the top-level Settings structure is the only one
@@ -23,6 +23,7 @@ struct Settings {
     boot: BootSettings,
     aws: AwsSettings,
     metrics: MetricsSettings,
+    logdog: LogdogSettings,
     pki: HashMap<Identifier, PemCertificate>,
     container_registry: RegistrySettings,
     oci_hooks: OciHooks,
@@ -5,8 +5,8 @@ use std::collections::HashMap;
 use crate::modeled_types::Identifier;
 use crate::{
     AutoScalingSettings, AwsSettings, BootstrapContainer, CloudFormationSettings, DnsSettings,
-    ECSSettings, HostContainer, KernelSettings, MetricsSettings, NetworkSettings, NtpSettings,
-    OciHooks, PemCertificate, RegistrySettings, UpdatesSettings,
+    ECSSettings, HostContainer, KernelSettings, LogdogSettings, MetricsSettings, NetworkSettings,
+    NtpSettings, OciHooks, PemCertificate, RegistrySettings, UpdatesSettings,
 };
 
 // Note: we have to use 'rename' here because the top-level Settings structure is the only one
@@ -23,6 +23,7 @@ struct Settings {
     aws: AwsSettings,
     ecs: ECSSettings,
     metrics: MetricsSettings,
+    logdog: LogdogSettings,
     pki: HashMap<Identifier, PemCertificate>,
     container_registry: RegistrySettings,
     oci_hooks: OciHooks,
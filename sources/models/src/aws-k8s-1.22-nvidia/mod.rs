@@ -1,8 +1,9 @@
 use crate::modeled_types::Identifier;
 use crate::{
     AutoScalingSettings, AwsSettings, BootstrapContainer, CloudFormationSettings, DnsSettings,
-    HostContainer, KernelSettings, KubernetesSettings, MetricsSettings, NetworkSettings,
-    NtpSettings, OciDefaults, OciHooks, PemCertificate, RegistrySettings, UpdatesSettings,
+    HostContainer, KernelSettings, KubernetesSettings, LogdogSettings, MetricsSettings,
+    NetworkSettings, NtpSettings, OciDefaults, OciHooks, PemCertificate, RegistrySettings,
+    UpdatesSettings,
 };
 
 use model_derive::model;
@@ -23,6 +24,7 @@ struct Settings {
     kernel: KernelSettings,
     aws: AwsSettings,
     metrics: MetricsSettings,
+    logdog: LogdogSettings,
     pki: HashMap<Identifier, PemCertificate>,
     container_registry: RegistrySettings,
     oci_defaults: OciDefaults,
@@ -1,9 +1,9 @@
 use crate::modeled_types::Identifier;
 use crate::{
     AutoScalingSettings, AwsSettings, BootSettings, BootstrapContainer, CloudFormationSettings,
-    DnsSettings, HostContainer, KernelSettings, KubernetesSettings, MetricsSettings,
-    NetworkSettings, NtpSettings, OciDefaults, OciHooks, PemCertificate, RegistrySettings,
-    UpdatesSettings,
+    DnsSettings, HostContainer, KernelSettings, KubernetesSettings, LogdogSettings,
+    MetricsSettings, NetworkSettings, NtpSettings, OciDefaults, OciHooks, PemCertificate,
+    RegistrySettings, UpdatesSettings,
 };
 
 use model_derive::model;
@@ -25,6 +25,7 @@ struct Settings {
     boot: BootSettings,
     aws: AwsSettings,
     metrics: MetricsSettings,
+    logdog: LogdogSettings,
     pki: HashMap<Identifier, PemCertificate>,
     container_registry: RegistrySettings,
     oci_defaults: OciDefaults,
@@ -2,8 +2,8 @@ use crate::modeled_types::Identifier;
 use crate::{
     AutoScalingSettings, AwsSettings, BootSettings, BootstrapContainer, CloudFormationSettings,
     ContainerRuntimeSettings, DnsSettings, HostContainer, KernelSettings, KubernetesSettings,
-    MetricsSettings, NetworkSettings, NtpSettings, OciDefaults, OciHooks, PemCertificate,
-    RegistrySettings, UpdatesSettings,
+    LogdogSettings, MetricsSettings, NetworkSettings, NtpSettings, OciDefaults, OciHooks,
+    PemCertificate, RegistrySettings, UpdatesSettings,
 };
 
 use model_derive::model;
@@ -25,6 +25,7 @@ struct Settings {
     boot: BootSettings,
     aws: AwsSettings,
     metrics: MetricsSettings,
+    logdog: LogdogSettings,
     pki: HashMap<Identifier, PemCertificate>,
     container_registry: RegistrySettings,
     oci_defaults: OciDefaults,
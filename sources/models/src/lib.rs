@@ -19,6 +19,18 @@ Entries are sorted by filename, and later entries take precedence.
 
 The `#[model]` attribute on Settings and its sub-structs reduces duplication and adds some required metadata; see [its docs](model-derive/) for details.
 
+The `#[model]` attribute and the `Scalar` derive also describe the model as a [JSON Schema](https://json-schema.org/), so tools can check settings for a variant without a running API server.
+Modeled types describe the checks they make, like patterns, where JSON Schema can express them.
+`schema::user_data_schema` returns the document for the current variant.
+
+Settings that hold secrets, like tokens and credentials, are marked `#[model(sensitive)]`, so they're left out when clients ask the API for redacted settings, for example with `apiclient get --redact`, and from the settings that logdog collects.
+Settings can also be marked sensitive in `defaults.d` by setting `sensitive = true` in their metadata, for example:
+
+```toml
+[metadata.settings.example.token]
+sensitive = true
+```
+
 ## aws-k8s-1.21: Kubernetes 1.21
 
 * [Model](src/aws-k8s-1.22/mod.rs)
@@ -177,6 +189,9 @@ pub use variant::*;
 // Types used to communicate between client and server for 'apiclient exec'.
 pub mod exec;
 
+// A JSON Schema describing the current variant's settings.
+pub mod schema;
+
 // Below, we define common structures used in the API surface; specific variants build a Settings
 // structure based on these, and that's what gets exposed via the API.  (Specific variants' models
 // are in subdirectories and linked into place by build.rs at variant/current.)
@@ -184,20 +199,22 @@ pub mod exec;
 use model_derive::model;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::net::IpAddr;
+use std::net::{IpAddr, SocketAddr};
 
 use crate::de::{deserialize_mirrors, deserialize_node_taints};
 use crate::modeled_types::{
-    BootConfigKey, BootConfigValue, BootstrapContainerMode, CpuManagerPolicy, CredentialProvider,
-    DNSDomain, ECSAgentImagePullBehavior, ECSAgentLogLevel, ECSAttributeKey, ECSAttributeValue,
-    ECSDurationValue, EtcHostsEntries, FriendlyVersion, Identifier, ImageGCHighThresholdPercent,
-    ImageGCLowThresholdPercent, KmodKey, KubernetesAuthenticationMode, KubernetesBootstrapToken,
-    KubernetesCloudProvider, KubernetesClusterDnsIp, KubernetesClusterName,
-    KubernetesDurationValue, KubernetesEvictionHardKey, KubernetesLabelKey, KubernetesLabelValue,
-    KubernetesQuantityValue, KubernetesReservedResourceKey, KubernetesTaintValue,
-    KubernetesThresholdValue, Lockdown, OciDefaultsCapability, OciDefaultsResourceLimitType,
-    PemCertificateString, SingleLineString, SysctlKey, TopologyManagerPolicy, TopologyManagerScope,
-    Url, ValidBase64, ValidLinuxHostname,
+    BootConfigKey, BootConfigValue, BootstrapContainerMode, CidrAddress, CpuManagerPolicy,
+    CredentialProvider, DNSDomain, DayOfWeek, ECSAgentImagePullBehavior, ECSAgentLogLevel,
+    ECSAttributeKey, ECSAttributeValue, ECSDurationValue, EtcHostsEntries, FriendlyVersion,
+    Identifier, ImageGCHighThresholdPercent, ImageGCLowThresholdPercent, KmodKey,
+    KubernetesAuthenticationMode, KubernetesBootstrapToken, KubernetesCloudProvider,
+    KubernetesClusterDnsIp, KubernetesClusterName, KubernetesDurationValue,
+    KubernetesEvictionHardKey, KubernetesLabelKey, KubernetesLabelValue, KubernetesQuantityValue,
+    KubernetesReservedResourceKey, KubernetesTaintValue, KubernetesThresholdValue, Lockdown,
+    LogdogFilename, LogdogRequestMode, NetworkInterfaceName, OciDefaultsCapability,
+    OciDefaultsResourceLimitType, PemCertificateString, SingleLineString, SysctlKey, TimeOfDay,
+    TimezoneOffset, TopologyManagerPolicy, TopologyManagerScope, UpdateChannel, Url, ValidBase64,
+    ValidLinuxHostname,
 };
 
 // Kubernetes static pod manifest settings
@@ -226,6 +243,7 @@ struct KubernetesSettings {
     node_taints: HashMap<KubernetesLabelKey, Vec<KubernetesTaintValue>>,
     static_pods: HashMap<Identifier, StaticPod>,
     authentication_mode: KubernetesAuthenticationMode,
+    #[model(sensitive)]
     bootstrap_token: KubernetesBootstrapToken,
     standalone_mode: bool,
     eviction_hard: HashMap<KubernetesEvictionHardKey, KubernetesThresholdValue>,
@@ -291,9 +309,12 @@ struct RegistryMirror {
 struct RegistryCredential {
     registry: SingleLineString,
     username: SingleLineString,
+    #[model(sensitive)]
     password: SingleLineString,
     // This is the base64 encoding of "username:password"
+    #[model(sensitive)]
     auth: ValidBase64,
+    #[model(sensitive)]
     identitytoken: SingleLineString,
 }
 
@@ -320,6 +341,33 @@ struct UpdatesSettings {
     // Version to update to when updating via the API.
     version_lock: FriendlyVersion,
     ignore_waves: bool,
+    // Release channel to follow, like 'stable' or 'canary'.
+    channel: UpdateChannel,
+    // When updates are applied automatically.
+    maintenance_window: MaintenanceWindow,
+    // Units that must be healthy after an update, or it's rolled back.
+    health_checks: UpdateHealthChecks,
+}
+
+// Window in which thar-be-updates may apply updates automatically and reboot into them.  The window
+// opens at 'start' on each of 'days' and closes at 'end', which is on the next day if it's earlier
+// than 'start'.  'max-reboots' limits reboots per window; with 0, updates are only staged.
+#[model]
+struct MaintenanceWindow {
+    enabled: bool,
+    days: Vec<DayOfWeek>,
+    start: TimeOfDay,
+    end: TimeOfDay,
+    timezone: TimezoneOffset,
+    max_reboots: u32,
+}
+
+// Systemd units that signpost waits for on the first boot after an update before marking the boot
+// successful.  If they aren't all healthy within 'timeout-seconds', the update is rolled back.
+#[model]
+struct UpdateHealthChecks {
+    units: Vec<SingleLineString>,
+    timeout_seconds: u32,
 }
 
 #[model]
@@ -327,6 +375,7 @@ struct HostContainer {
     source: Url,
     enabled: bool,
     superpowered: bool,
+    #[model(sensitive)]
     user_data: ValidBase64,
 }
 
@@ -335,9 +384,47 @@ struct HostContainer {
 struct NetworkSettings {
     hostname: ValidLinuxHostname,
     hosts: EtcHostsEntries,
+    // Can contain a username:password component
+    #[model(sensitive)]
     https_proxy: Url,
     // We allow some flexibility in NO_PROXY values because different services support different formats.
     no_proxy: Vec<SingleLineString>,
+    interfaces: HashMap<NetworkInterfaceName, NetworkInterface>,
+}
+
+// An interface that netdog configures at runtime, over the configuration it was given at boot; see
+// netdog's net.toml documentation for the meaning of each field.  Bonds and bridges can only be
+// configured at boot.
+#[model]
+struct NetworkInterface {
+    dhcp4: bool,
+    dhcp6: bool,
+    static4: NetworkStaticAddresses,
+    static6: NetworkStaticAddresses,
+    routes: Vec<NetworkRoute>,
+    mtu: u32,
+    vlan: NetworkVlan,
+}
+
+#[model]
+struct NetworkStaticAddresses {
+    addresses: Vec<CidrAddress>,
+}
+
+#[model]
+struct NetworkRoute {
+    to: CidrAddress,
+    from: IpAddr,
+    via: IpAddr,
+    route_metric: u32,
+    table: u32,
+}
+
+// Makes the interface a VLAN on top of another device
+#[model]
+struct NetworkVlan {
+    device: NetworkInterfaceName,
+    id: u16,
 }
 
 // NTP settings
@@ -351,6 +438,33 @@ struct NtpSettings {
 struct DnsSettings {
     name_servers: Vec<IpAddr>,
     search_list: Vec<ValidLinuxHostname>,
+    // Queries for names under these domains go to the domain's own name servers
+    domains: HashMap<DNSDomain, DnsDomainSettings>,
+    options: DnsResolverOptions,
+    // Other queries are sent over TLS to these servers, rather than to `name_servers`
+    tls_upstreams: Vec<DnsTlsUpstream>,
+}
+
+#[model]
+struct DnsDomainSettings {
+    name_servers: Vec<IpAddr>,
+}
+
+// Options for the libc resolver; see resolv.conf(5)
+#[model]
+struct DnsResolverOptions {
+    ndots: u8,
+    timeout: u8,
+    attempts: u8,
+    rotate: bool,
+}
+
+// A DNS-over-TLS server, whose certificate must be valid for `server_name`
+#[model]
+struct DnsTlsUpstream {
+    address: IpAddr,
+    port: u16,
+    server_name: ValidLinuxHostname,
 }
 
 // Kernel settings
@@ -392,7 +506,9 @@ struct BootSettings {
 #[model]
 struct AwsSettings {
     region: SingleLineString,
+    #[model(sensitive)]
     config: ValidBase64,
+    #[model(sensitive)]
     credentials: ValidBase64,
     profile: SingleLineString,
 }
@@ -403,6 +519,29 @@ struct MetricsSettings {
     metrics_url: Url,
     send_metrics: bool,
     service_checks: Vec<String>,
+    prometheus: PrometheusSettings,
+}
+
+// Serving metrics about host components to a local Prometheus scraper
+#[model]
+struct PrometheusSettings {
+    enabled: bool,
+    listen_address: SocketAddr,
+}
+
+// Logdog settings
+#[model]
+struct LogdogSettings {
+    requests: HashMap<Identifier, LogdogRequest>,
+}
+
+// A log request that logdog runs along with the ones built into the image; see logdog's
+// `LogRequest` for the meaning of each field.  Glob requests don't use a filename.
+#[model]
+struct LogdogRequest {
+    mode: LogdogRequestMode,
+    filename: LogdogFilename,
+    instructions: SingleLineString,
 }
 
 // CloudFormation settings
@@ -455,7 +594,7 @@ struct ConfigurationFile {
 
 ///// Metadata
 
-#[model(add_option = false, rename = "metadata")]
+#[model(add_option = false, rename = "metadata", schema = false)]
 struct Metadata {
     key: SingleLineString,
     md: SingleLineString,
@@ -468,6 +607,7 @@ struct Metadata {
 struct BootstrapContainer {
     source: Url,
     mode: BootstrapContainerMode,
+    #[model(sensitive)]
     user_data: ValidBase64,
     essential: bool,
 }
@@ -17,6 +17,7 @@ use std::ops::Deref;
 /// the original string and makes it accessible through standard traits.
 // https://docs.aws.amazon.com/AmazonECS/latest/APIReference/API_Attribute.html
 #[derive(Debug, Clone, Eq, PartialEq, Hash, Scalar)]
+#[scalar(schema = r#"{"pattern": "^[a-zA-Z0-9._/-]{1,128}$"}"#)]
 pub struct ECSAttributeKey {
     inner: String,
 }
@@ -127,7 +128,11 @@ impl TryFrom<&str> for ECSAttributeValue {
     }
 }
 
-string_impls_for!(ECSAttributeValue, "ECSAttributeValue");
+string_impls_for!(
+    ECSAttributeValue,
+    "ECSAttributeValue",
+    r#"{"pattern": "^[a-zA-Z0-9.@:_/\\\\-]([a-zA-Z0-9.@: _/\\\\-]{0,126}[a-zA-Z0-9.@:_/\\\\-])?$"}"#
+);
 
 #[cfg(test)]
 mod test_ecs_attribute_value {
@@ -1,5 +1,6 @@
 use lazy_static::lazy_static;
 use regex::Regex;
+use scalar::schema::{json, JsonSchema, Value};
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 // Just need serde's Error in scope to get its trait methods
 use super::error;
@@ -436,7 +437,11 @@ impl TryFrom<&str> for KubernetesBootstrapToken {
     }
 }
 
-string_impls_for!(KubernetesBootstrapToken, "KubernetesBootstrapToken");
+string_impls_for!(
+    KubernetesBootstrapToken,
+    "KubernetesBootstrapToken",
+    r#"{"pattern": "^[a-z0-9]{6}\\.[a-z0-9]{16}$"}"#
+);
 
 #[cfg(test)]
 mod test_kubernetes_bootstrap_token {
@@ -1157,6 +1162,15 @@ pub enum KubernetesClusterDnsIp {
     Vector(Vec<IpAddr>),
 }
 
+impl JsonSchema for KubernetesClusterDnsIp {
+    fn json_schema() -> Value {
+        json!({
+            "title": "KubernetesClusterDnsIp",
+            "anyOf": [IpAddr::json_schema(), Vec::<IpAddr>::json_schema()],
+        })
+    }
+}
+
 impl KubernetesClusterDnsIp {
     pub fn iter<'a>(&'a self) -> Box<dyn Iterator<Item = &'a IpAddr> + 'a> {
         match self {
@@ -1274,3 +1288,18 @@ pub struct CredentialProvider {
     image_patterns: Vec<SingleLineString>,
     cache_duration: Option<KubernetesDurationValue>,
 }
+
+impl JsonSchema for CredentialProvider {
+    fn json_schema() -> Value {
+        json!({
+            "type": "object",
+            "title": "CredentialProvider",
+            "properties": {
+                "enabled": bool::json_schema(),
+                "image-patterns": Vec::<SingleLineString>::json_schema(),
+                "cache-duration": KubernetesDurationValue::json_schema(),
+            },
+            "required": ["enabled", "image-patterns"],
+        })
+    }
+}
@@ -74,6 +74,33 @@ pub mod error {
         #[snafu(display("Invalid bootstrap container mode '{}'", input))]
         InvalidBootstrapContainerMode { input: String },
 
+        #[snafu(display("Invalid update channel '{}'", input))]
+        InvalidUpdateChannel { input: String },
+
+        #[snafu(display("Invalid day of week '{}', expected one of 'mon' through 'sun'", input))]
+        InvalidDayOfWeek { input: String },
+
+        #[snafu(display("Invalid time of day '{}', expected 'HH:MM'", input))]
+        InvalidTimeOfDay { input: String },
+
+        #[snafu(display("Invalid timezone offset '{}', expected 'UTC' or '+HH:MM'", input))]
+        InvalidTimezoneOffset { input: String },
+
+        #[snafu(display("Invalid logdog request mode '{}'", input))]
+        InvalidLogdogRequestMode { input: String },
+
+        #[snafu(display("Invalid logdog filename '{}': {}", input, msg))]
+        InvalidLogdogFilename { input: String, msg: &'static str },
+
+        #[snafu(display(
+            "Invalid network interface name '{}': must be 1 to 15 ASCII alphanumeric characters, '-', or '_'",
+            input
+        ))]
+        InvalidNetworkInterfaceName { input: String },
+
+        #[snafu(display("Invalid CIDR address '{}': {}", input, msg))]
+        InvalidCidrAddress { input: String, msg: String },
+
         #[snafu(display("Given invalid cluster name '{}': {}", name, msg))]
         InvalidClusterName { name: String, msg: String },
 
@@ -169,9 +196,24 @@ pub mod error {
 
 /// Helper macro for implementing the common string-like traits for a modeled type.
 /// Pass the name of the type, and the name of the type in quotes (to be used in string error
-/// messages, etc.).
+/// messages, etc.).  Optionally, pass the text of a JSON object containing JSON Schema keywords
+/// that describe the checks made by the type's TryFrom, like "pattern", so that clients can check
+/// values without calling the API.
 macro_rules! string_impls_for {
     ($for:ident, $for_str:expr) => {
+        string_impls_for!($for, $for_str, "{}");
+    };
+    ($for:ident, $for_str:expr, $schema:expr) => {
+        impl scalar::schema::JsonSchema for $for {
+            fn json_schema() -> scalar::schema::Value {
+                let schema = scalar::schema::with_keywords(
+                    <String as scalar::schema::JsonSchema>::json_schema(),
+                    $schema,
+                );
+                scalar::schema::with_keywords(schema, concat!(r#"{"title": ""#, $for_str, r#""}"#))
+            }
+        }
+
         impl TryFrom<String> for $for {
             type Error = $crate::modeled_types::error::Error;
 
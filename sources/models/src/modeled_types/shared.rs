@@ -1,6 +1,7 @@
 use super::error;
 use lazy_static::lazy_static;
 use regex::Regex;
+use scalar::schema::{with_keywords, JsonSchema, Value};
 use semver::Version;
 use serde::de::Error as _;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
@@ -96,7 +97,11 @@ impl TryFrom<&str> for SingleLineString {
     }
 }
 
-string_impls_for!(SingleLineString, "SingleLineString");
+string_impls_for!(
+    SingleLineString,
+    "SingleLineString",
+    r#"{"pattern": "^[^\\n\\r\\u000B\\u000C\\u0085\\u2028\\u2029]*$"}"#
+);
 
 #[cfg(test)]
 mod test_single_line_string {
@@ -186,7 +191,11 @@ impl TryFrom<&str> for ValidLinuxHostname {
     }
 }
 
-string_impls_for!(ValidLinuxHostname, "ValidLinuxHostname");
+string_impls_for!(
+    ValidLinuxHostname,
+    "ValidLinuxHostname",
+    r#"{"pattern": "^[0-9a-z][0-9a-z-]{0,62}(\\.[0-9a-z][0-9a-z-]{0,62})*$", "maxLength": 253}"#
+);
 
 #[cfg(test)]
 mod test_valid_linux_hostname {
@@ -257,6 +266,15 @@ impl EtcHostsEntries {
     }
 }
 
+impl JsonSchema for EtcHostsEntries {
+    fn json_schema() -> Value {
+        with_keywords(
+            Vec::<(IpAddr, Vec<ValidLinuxHostname>)>::json_schema(),
+            r#"{"title": "EtcHostsEntries"}"#,
+        )
+    }
+}
+
 #[cfg(test)]
 mod test_etc_hosts_entries {
     use super::{EtcHostsEntries, ValidLinuxHostname};
@@ -402,7 +420,11 @@ impl TryFrom<&str> for Identifier {
     }
 }
 
-string_impls_for!(Identifier, "Identifier");
+string_impls_for!(
+    Identifier,
+    "Identifier",
+    r#"{"pattern": "^[a-zA-Z0-9-]*$", "maxLength": 76}"#
+);
 
 #[cfg(test)]
 mod test_valid_identifier {
@@ -727,7 +749,11 @@ impl TryFrom<&str> for SysctlKey {
     }
 }
 
-string_impls_for!(SysctlKey, "SysctlKey");
+string_impls_for!(
+    SysctlKey,
+    "SysctlKey",
+    r#"{"pattern": "^(?!.*\\.\\.)[a-zA-Z0-9_-][a-zA-Z0-9./_-]{0,127}$"}"#
+);
 
 #[cfg(test)]
 mod test_sysctl_key {
@@ -818,7 +844,11 @@ impl TryFrom<&str> for BootConfigKey {
     }
 }
 
-string_impls_for!(BootConfigKey, "BootConfigKey");
+string_impls_for!(
+    BootConfigKey,
+    "BootConfigKey",
+    r#"{"pattern": "^[a-zA-Z0-9_-]+(\\.[a-zA-Z0-9_-]+)*$"}"#
+);
 
 #[cfg(test)]
 mod test_bootconfig_key {
@@ -882,7 +912,11 @@ impl TryFrom<&str> for BootConfigValue {
     }
 }
 
-string_impls_for!(BootConfigValue, "BootConfigValue");
+string_impls_for!(
+    BootConfigValue,
+    "BootConfigValue",
+    r#"{"pattern": "^(?!.*\".*')(?!.*'.*\")[\\x20-\\x7E]*$"}"#
+);
 
 #[cfg(test)]
 mod test_bootconfig_value {
@@ -936,7 +970,11 @@ impl TryFrom<&str> for Lockdown {
     }
 }
 
-string_impls_for!(Lockdown, "Lockdown");
+string_impls_for!(
+    Lockdown,
+    "Lockdown",
+    r#"{"enum": ["none", "integrity", "confidentiality"]}"#
+);
 
 // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
 
@@ -967,7 +1005,11 @@ impl Default for BootstrapContainerMode {
     }
 }
 
-string_impls_for!(BootstrapContainerMode, "BootstrapContainerMode");
+string_impls_for!(
+    BootstrapContainerMode,
+    "BootstrapContainerMode",
+    r#"{"enum": ["off", "once", "always"]}"#
+);
 
 #[cfg(test)]
 mod test_valid_container_mode {
@@ -987,6 +1029,212 @@ mod test_valid_container_mode {
     }
 }
 
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// UpdateChannel names the release channel a host follows.  Hosts on a channel accept updates
+/// released to it and to any more stable channel, so 'canary' hosts get every update, and
+/// 'stable' hosts only get stable updates.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct UpdateChannel {
+    inner: String,
+}
+
+impl TryFrom<&str> for UpdateChannel {
+    type Error = error::Error;
+
+    fn try_from(input: &str) -> Result<Self, error::Error> {
+        ensure!(
+            matches!(input, "stable" | "beta" | "canary"),
+            error::InvalidUpdateChannelSnafu { input }
+        );
+        Ok(UpdateChannel {
+            inner: input.to_string(),
+        })
+    }
+}
+
+string_impls_for!(
+    UpdateChannel,
+    "UpdateChannel",
+    r#"{"enum": ["stable", "beta", "canary"]}"#
+);
+
+#[cfg(test)]
+mod test_update_channel {
+    use super::UpdateChannel;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn valid_update_channel() {
+        for ok in &["stable", "beta", "canary"] {
+            assert!(UpdateChannel::try_from(*ok).is_ok());
+        }
+    }
+
+    #[test]
+    fn invalid_update_channel() {
+        for err in &["", "Stable", "latest", "canary "] {
+            assert!(UpdateChannel::try_from(*err).is_err());
+        }
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// DayOfWeek is a day of the week, written as its lowercase three-letter abbreviation, like 'mon'.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct DayOfWeek {
+    inner: String,
+}
+
+impl TryFrom<&str> for DayOfWeek {
+    type Error = error::Error;
+
+    fn try_from(input: &str) -> Result<Self, error::Error> {
+        ensure!(
+            matches!(input, "mon" | "tue" | "wed" | "thu" | "fri" | "sat" | "sun"),
+            error::InvalidDayOfWeekSnafu { input }
+        );
+        Ok(DayOfWeek {
+            inner: input.to_string(),
+        })
+    }
+}
+
+string_impls_for!(
+    DayOfWeek,
+    "DayOfWeek",
+    r#"{"enum": ["mon", "tue", "wed", "thu", "fri", "sat", "sun"]}"#
+);
+
+#[cfg(test)]
+mod test_day_of_week {
+    use super::DayOfWeek;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn valid_day_of_week() {
+        for ok in &["mon", "tue", "wed", "thu", "fri", "sat", "sun"] {
+            assert!(DayOfWeek::try_from(*ok).is_ok());
+        }
+    }
+
+    #[test]
+    fn invalid_day_of_week() {
+        for err in &["", "Mon", "monday", "m", "weekend"] {
+            assert!(DayOfWeek::try_from(*err).is_err());
+        }
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// TimeOfDay is a time on a 24-hour clock, in the format 'HH:MM', like '02:30'.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TimeOfDay {
+    inner: String,
+}
+
+lazy_static! {
+    pub(crate) static ref TIME_OF_DAY: Regex =
+        Regex::new(r"^([01][0-9]|2[0-3]):[0-5][0-9]$").unwrap();
+}
+
+impl TryFrom<&str> for TimeOfDay {
+    type Error = error::Error;
+
+    fn try_from(input: &str) -> Result<Self, error::Error> {
+        ensure!(
+            TIME_OF_DAY.is_match(input),
+            error::InvalidTimeOfDaySnafu { input }
+        );
+        Ok(TimeOfDay {
+            inner: input.to_string(),
+        })
+    }
+}
+
+string_impls_for!(
+    TimeOfDay,
+    "TimeOfDay",
+    r#"{"pattern": "^([01][0-9]|2[0-3]):[0-5][0-9]$"}"#
+);
+
+#[cfg(test)]
+mod test_time_of_day {
+    use super::TimeOfDay;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn valid_time_of_day() {
+        for ok in &["00:00", "02:30", "12:00", "23:59"] {
+            assert!(TimeOfDay::try_from(*ok).is_ok());
+        }
+    }
+
+    #[test]
+    fn invalid_time_of_day() {
+        for err in &["", "2:30", "24:00", "12:60", "12:00:00", "noon"] {
+            assert!(TimeOfDay::try_from(*err).is_err());
+        }
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// TimezoneOffset is a fixed offset from UTC, either 'UTC' itself or a signed offset in hours and
+/// minutes, like '-08:00' or '+05:30'.  Named timezones aren't supported, so offsets don't follow
+/// daylight saving time.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct TimezoneOffset {
+    inner: String,
+}
+
+lazy_static! {
+    pub(crate) static ref TIMEZONE_OFFSET: Regex =
+        Regex::new(r"^(UTC|[+-]([01][0-9]|2[0-3]):[0-5][0-9])$").unwrap();
+}
+
+impl TryFrom<&str> for TimezoneOffset {
+    type Error = error::Error;
+
+    fn try_from(input: &str) -> Result<Self, error::Error> {
+        ensure!(
+            TIMEZONE_OFFSET.is_match(input),
+            error::InvalidTimezoneOffsetSnafu { input }
+        );
+        Ok(TimezoneOffset {
+            inner: input.to_string(),
+        })
+    }
+}
+
+string_impls_for!(
+    TimezoneOffset,
+    "TimezoneOffset",
+    r#"{"pattern": "^(UTC|[+-]([01][0-9]|2[0-3]):[0-5][0-9])$"}"#
+);
+
+#[cfg(test)]
+mod test_timezone_offset {
+    use super::TimezoneOffset;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn valid_timezone_offset() {
+        for ok in &["UTC", "+00:00", "-08:00", "+05:30", "+23:59"] {
+            assert!(TimezoneOffset::try_from(*ok).is_ok());
+        }
+    }
+
+    #[test]
+    fn invalid_timezone_offset() {
+        for err in &["", "utc", "08:00", "+8:00", "-24:00", "America/New_York"] {
+            assert!(TimezoneOffset::try_from(*err).is_err());
+        }
+    }
+}
+
 // =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub struct PemCertificateString {
@@ -1103,7 +1351,11 @@ impl TryFrom<&str> for KmodKey {
     }
 }
 
-string_impls_for!(KmodKey, "KmodKey");
+string_impls_for!(
+    KmodKey,
+    "KmodKey",
+    r#"{"pattern": "^[a-zA-Z0-9_-]*$", "maxLength": 56}"#
+);
 
 #[cfg(test)]
 mod test_valid_kmod_key {
@@ -1130,3 +1382,300 @@ mod test_valid_kmod_key {
         assert!(KmodKey::try_from(vec!["z"; KMOD_KEY_LENGTH + 1].join("")).is_err());
     }
 }
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// LogdogRequestMode is the kind of a logdog log request, which says how its logs are collected.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct LogdogRequestMode {
+    inner: String,
+}
+
+impl TryFrom<&str> for LogdogRequestMode {
+    type Error = error::Error;
+
+    fn try_from(input: &str) -> Result<Self, error::Error> {
+        ensure!(
+            matches!(
+                input,
+                "exec" | "file" | "glob" | "http" | "https" | "settings"
+            ),
+            error::InvalidLogdogRequestModeSnafu { input }
+        );
+        Ok(LogdogRequestMode {
+            inner: input.to_string(),
+        })
+    }
+}
+
+string_impls_for!(
+    LogdogRequestMode,
+    "LogdogRequestMode",
+    r#"{"enum": ["exec", "file", "glob", "http", "https", "settings"]}"#
+);
+
+#[cfg(test)]
+mod test_logdog_request_mode {
+    use super::LogdogRequestMode;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn valid_logdog_request_mode() {
+        for ok in &["exec", "file", "glob", "http", "https", "settings"] {
+            assert!(LogdogRequestMode::try_from(*ok).is_ok());
+        }
+    }
+
+    #[test]
+    fn invalid_logdog_request_mode() {
+        for err in &["", "Exec", "copy", "exec "] {
+            assert!(LogdogRequestMode::try_from(*err).is_err());
+        }
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// LogdogFilename is the name of the file a logdog log request writes into the archive.  It must
+/// be a single path component, so requests can't write outside of the archive's directory, and it
+/// can't contain whitespace, which separates the parts of a log request.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct LogdogFilename {
+    inner: String,
+}
+
+impl TryFrom<&str> for LogdogFilename {
+    type Error = error::Error;
+
+    fn try_from(input: &str) -> Result<Self, error::Error> {
+        ensure!(
+            !input.is_empty(),
+            error::InvalidLogdogFilenameSnafu {
+                input,
+                msg: "must not be empty"
+            }
+        );
+        ensure!(
+            input != "." && input != "..",
+            error::InvalidLogdogFilenameSnafu {
+                input,
+                msg: "must name a file"
+            }
+        );
+        ensure!(
+            !input.contains('/'),
+            error::InvalidLogdogFilenameSnafu {
+                input,
+                msg: "must not contain '/'"
+            }
+        );
+        ensure!(
+            !input.chars().any(|c| c.is_whitespace() || c.is_control()),
+            error::InvalidLogdogFilenameSnafu {
+                input,
+                msg: "must not contain whitespace or control characters"
+            }
+        );
+        Ok(LogdogFilename {
+            inner: input.to_string(),
+        })
+    }
+}
+
+string_impls_for!(
+    LogdogFilename,
+    "LogdogFilename",
+    r#"{"pattern": "^(?!\\.{1,2}$)[^/\\s]+$"}"#
+);
+
+#[cfg(test)]
+mod test_logdog_filename {
+    use super::LogdogFilename;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn valid_logdog_filename() {
+        for ok in &["my-app.log", "app_config.json", ".hidden", "..."] {
+            assert!(LogdogFilename::try_from(*ok).is_ok(), "{}", ok);
+        }
+    }
+
+    #[test]
+    fn invalid_logdog_filename() {
+        for err in &[
+            "",
+            ".",
+            "..",
+            "../etc/passwd",
+            "logs/app.log",
+            "my app.log",
+            "app.log\n",
+            "tab\tlog",
+        ] {
+            assert!(LogdogFilename::try_from(*err).is_err(), "{}", err);
+        }
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// NetworkInterfaceName can only be created from a string that's a valid Linux network interface
+/// name made of ASCII alphanumeric characters, hyphens, and underscores.  The kernel allows more
+/// characters, but interface names are used as setting keys and in netdog's TOML configuration, so
+/// we limit them to characters that are safe in both.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct NetworkInterfaceName {
+    inner: String,
+}
+
+// The kernel's IFNAMSIZ is 16, including the trailing null byte.
+//   include/uapi/linux/if.h
+const NETWORK_INTERFACE_NAME_LENGTH: usize = 15;
+
+impl TryFrom<&str> for NetworkInterfaceName {
+    type Error = error::Error;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        let valid_name = !input.is_empty()
+            && input.len() <= NETWORK_INTERFACE_NAME_LENGTH
+            && input
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        ensure!(
+            valid_name,
+            error::InvalidNetworkInterfaceNameSnafu { input }
+        );
+        Ok(NetworkInterfaceName {
+            inner: input.to_string(),
+        })
+    }
+}
+
+string_impls_for!(
+    NetworkInterfaceName,
+    "NetworkInterfaceName",
+    r#"{"pattern": "^[a-zA-Z0-9_-]+$", "maxLength": 15}"#
+);
+
+#[cfg(test)]
+mod test_network_interface_name {
+    use super::{NetworkInterfaceName, NETWORK_INTERFACE_NAME_LENGTH};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn valid_network_interface_name() {
+        for ok in &["eth0", "eno1", "enp0s16", "bond0", "my-vlan_42"] {
+            assert!(NetworkInterfaceName::try_from(*ok).is_ok(), "{}", ok);
+        }
+        assert!(
+            NetworkInterfaceName::try_from(vec!["a"; NETWORK_INTERFACE_NAME_LENGTH].join(""))
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn invalid_network_interface_name() {
+        for err in &["", "eth0.42", "eth/0", "eth 0", "eth0\n", "eth:0", "🐡"] {
+            assert!(NetworkInterfaceName::try_from(*err).is_err(), "{}", err);
+        }
+        assert!(NetworkInterfaceName::try_from(
+            vec!["a"; NETWORK_INTERFACE_NAME_LENGTH + 1].join("")
+        )
+        .is_err());
+    }
+}
+
+// =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=   =^..^=
+
+/// CidrAddress can only be created from an IPv4 or IPv6 address followed by a prefix length, like
+/// "192.168.1.10/24" or "2001:db8::10/64".  It's used for interface addresses and route
+/// destinations in network settings.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct CidrAddress {
+    inner: String,
+}
+
+impl TryFrom<&str> for CidrAddress {
+    type Error = error::Error;
+
+    fn try_from(input: &str) -> Result<Self, Self::Error> {
+        let (address, prefix) = match input.split_once('/') {
+            Some(parts) => parts,
+            None => {
+                return error::InvalidCidrAddressSnafu {
+                    input,
+                    msg: "missing '/' and prefix length",
+                }
+                .fail()
+            }
+        };
+        let address = match IpAddr::from_str(address) {
+            Ok(address) => address,
+            Err(_) => {
+                return error::InvalidCidrAddressSnafu {
+                    input,
+                    msg: "invalid IP address",
+                }
+                .fail()
+            }
+        };
+        let max_prefix = if address.is_ipv4() { 32 } else { 128 };
+        // u8::from_str accepts a leading '+', which other tools won't
+        let valid_prefix =
+            !prefix.starts_with('+') && matches!(u8::from_str(prefix), Ok(p) if p <= max_prefix);
+        ensure!(
+            valid_prefix,
+            error::InvalidCidrAddressSnafu {
+                input,
+                msg: format!("prefix length must be 0 to {}", max_prefix),
+            }
+        );
+        Ok(CidrAddress {
+            inner: input.to_string(),
+        })
+    }
+}
+
+string_impls_for!(
+    CidrAddress,
+    "CidrAddress",
+    r#"{"pattern": "^[0-9A-Fa-f:.]+/[0-9]{1,3}$"}"#
+);
+
+#[cfg(test)]
+mod test_cidr_address {
+    use super::CidrAddress;
+    use std::convert::TryFrom;
+
+    #[test]
+    fn valid_cidr_address() {
+        for ok in &[
+            "192.168.1.10/24",
+            "10.0.0.0/8",
+            "0.0.0.0/0",
+            "1.2.3.4/32",
+            "2001:db8::10/64",
+            "::/0",
+            "fe80::1/128",
+        ] {
+            assert!(CidrAddress::try_from(*ok).is_ok(), "{}", ok);
+        }
+    }
+
+    #[test]
+    fn invalid_cidr_address() {
+        for err in &[
+            "",
+            "192.168.1.10",
+            "192.168.1.10/",
+            "192.168.1.10/33",
+            "192.168.1.10/+24",
+            "192.168.1/24",
+            "2001:db8::10/129",
+            "2001:db8::10/64/64",
+            "example.com/24",
+        ] {
+            assert!(CidrAddress::try_from(*err).is_err(), "{}", err);
+        }
+    }
+}
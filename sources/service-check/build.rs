@@ -0,0 +1,3 @@
+fn main() {
+    generate_readme::from_lib().unwrap();
+}
@@ -1,22 +1,50 @@
-use crate::error::{self, Result};
+/*!
+# Introduction
+
+This library checks whether systemd services are healthy.
+A service is healthy if it's active and hasn't failed; for unhealthy services, the exit code of the service's main process is reported if systemd knows it.
+
+It's used by `metricdog` to report the health of a host, and by `signpost` to decide whether an update booted successfully.
+Checks go through the `ServiceCheck` trait so that callers can replace `systemctl` in tests.
+*/
+
 use log::trace;
 use snafu::ResultExt;
 use std::process::Command;
 
+mod error {
+    use snafu::Snafu;
+
+    #[derive(Debug, Snafu)]
+    #[snafu(visibility(pub(super)))]
+    pub enum Error {
+        #[snafu(display("Command '{}' with args '{:?}' failed: {}", command, args, source))]
+        Command {
+            command: String,
+            args: Vec<String>,
+            source: std::io::Error,
+        },
+    }
+}
+
+pub use error::Error;
+pub type Result<T> = std::result::Result<T, error::Error>;
+
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd)]
-pub(crate) struct ServiceHealth {
+pub struct ServiceHealth {
     /// Whether or not the service is healthy.
-    pub(crate) is_healthy: bool,
+    pub is_healthy: bool,
     /// In the event of an unhealthy service, the service's exit code (if found).
-    pub(crate) exit_code: Option<i32>,
+    pub exit_code: Option<i32>,
 }
 
-pub(crate) trait ServiceCheck {
+pub trait ServiceCheck {
     /// Checks the given service to see if it is healthy.
     fn check(&self, service_name: &str) -> Result<ServiceHealth>;
 }
 
-pub(crate) struct SystemdCheck {}
+/// Checks services by calling `systemctl`.
+pub struct SystemdCheck {}
 
 impl ServiceCheck for SystemdCheck {
     fn check(&self, service_name: &str) -> Result<ServiceHealth> {
@@ -22,6 +22,18 @@ pub enum Error {
         source: block_party::Error,
     },
 
+    #[snafu(display("Failed to parse config file {}: {}", path.display(), source))]
+    ConfigParse {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+
+    #[snafu(display("Failed to read config file {}: {}", path.display(), source))]
+    ConfigRead {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+
     #[snafu(display("Failed to get disk from partition {}: {}", device.display(), source))]
     DiskFromPartition {
         device: PathBuf,
@@ -74,6 +86,12 @@ pub enum Error {
     #[snafu(display("Failed to find device for partition {} on {}", num, device.display()))]
     PartitionNotFoundOnDevice { num: u32, device: PathBuf },
 
+    #[snafu(display("Failed to reboot: {}", source))]
+    Reboot { source: std::io::Error },
+
+    #[snafu(display("Failed to reboot: 'systemctl reboot' exited {}", status))]
+    RebootStatus { status: std::process::ExitStatus },
+
     #[snafu(display("Root device {} has no lower root devices", root.display()))]
     RootHasNoLowerDevices { root: PathBuf },
 
@@ -85,6 +103,12 @@ pub enum Error {
 
     #[snafu(display("Block device {} is not a partition", device.display()))]
     RootNotPartition { device: PathBuf },
+
+    #[snafu(display("Failed to check health of service '{}': {}", service, source))]
+    ServiceCheck {
+        service: String,
+        source: service_check::Error,
+    },
 }
 
 #[derive(Debug)]
@@ -0,0 +1,206 @@
+use crate::error::{self, Error};
+use serde::Deserialize;
+use service_check::ServiceCheck;
+use snafu::{ensure, ResultExt};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The config file written from `settings.updates.health-checks`.
+pub const HEALTH_CHECKS_CONFIG: &str = "/etc/signpost.toml";
+
+/// How long to wait for units to become healthy if no timeout is configured.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How long to wait between checks of the units.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Deserialize)]
+struct Config {
+    #[serde(default)]
+    health_checks: Vec<String>,
+    health_check_timeout_seconds: Option<u64>,
+}
+
+/// Systemd units that must be healthy after an update before its partitions are marked as
+/// successfully booted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HealthChecks {
+    units: Vec<String>,
+    timeout: Duration,
+}
+
+impl HealthChecks {
+    /// Loads the health checks from the given config file.  Returns `None` if the file doesn't
+    /// exist or doesn't list any units, in which case there's nothing to wait for.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Option<Self>, Error> {
+        let path = path.as_ref();
+        let s = match fs::read_to_string(path) {
+            Ok(s) => s,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context(error::ConfigReadSnafu { path }),
+        };
+        let config: Config = toml::from_str(&s).context(error::ConfigParseSnafu { path })?;
+        if config.health_checks.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            units: config.health_checks,
+            timeout: config
+                .health_check_timeout_seconds
+                .map_or(DEFAULT_TIMEOUT, Duration::from_secs),
+        }))
+    }
+
+    pub fn units(&self) -> &[String] {
+        &self.units
+    }
+
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Checks the units with `checker` until they're all healthy or the timeout passes.  Returns
+    /// the units that weren't healthy at the last check, described with their exit code if known,
+    /// so an empty list means the checks passed.
+    pub fn wait(&self, checker: &dyn ServiceCheck) -> Result<Vec<String>, Error> {
+        self.wait_with_interval(checker, CHECK_INTERVAL)
+    }
+
+    fn wait_with_interval(
+        &self,
+        checker: &dyn ServiceCheck,
+        interval: Duration,
+    ) -> Result<Vec<String>, Error> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let mut unhealthy = Vec::new();
+            for unit in &self.units {
+                let health = checker
+                    .check(unit)
+                    .context(error::ServiceCheckSnafu { service: unit })?;
+                if !health.is_healthy {
+                    unhealthy.push(match health.exit_code {
+                        Some(exit_code) => format!("{unit}:{exit_code}"),
+                        None => unit.clone(),
+                    });
+                }
+            }
+            if unhealthy.is_empty() || Instant::now() >= deadline {
+                return Ok(unhealthy);
+            }
+            thread::sleep(interval);
+        }
+    }
+}
+
+/// Reboots the host with `systemctl reboot`.
+pub fn reboot() -> Result<(), Error> {
+    let status = Command::new("systemctl")
+        .arg("reboot")
+        .status()
+        .context(error::RebootSnafu)?;
+    ensure!(status.success(), error::RebootStatusSnafu { status });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use service_check::{Result, ServiceHealth};
+    use std::cell::Cell;
+
+    /// Reports units ending in "fail" as failed, and other units as healthy after `healthy_after`
+    /// rounds of checks.
+    struct MockCheck {
+        checks: Cell<usize>,
+        healthy_after: usize,
+    }
+
+    impl ServiceCheck for MockCheck {
+        fn check(&self, service_name: &str) -> Result<ServiceHealth> {
+            self.checks.set(self.checks.get() + 1);
+            if service_name.ends_with("fail") {
+                Ok(ServiceHealth {
+                    is_healthy: false,
+                    exit_code: Some(1),
+                })
+            } else {
+                Ok(ServiceHealth {
+                    is_healthy: self.checks.get() > self.healthy_after,
+                    exit_code: None,
+                })
+            }
+        }
+    }
+
+    fn health_checks(units: &[&str], timeout: Duration) -> HealthChecks {
+        HealthChecks {
+            units: units.iter().map(|&unit| unit.to_string()).collect(),
+            timeout,
+        }
+    }
+
+    #[test]
+    fn load_config() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("signpost.toml");
+
+        assert_eq!(HealthChecks::load(&path).unwrap(), None);
+
+        fs::write(&path, "health_checks = []\n").unwrap();
+        assert_eq!(HealthChecks::load(&path).unwrap(), None);
+
+        fs::write(&path, "health_checks = [\"kubelet\"]\n").unwrap();
+        assert_eq!(
+            HealthChecks::load(&path).unwrap(),
+            Some(health_checks(&["kubelet"], DEFAULT_TIMEOUT))
+        );
+
+        fs::write(
+            &path,
+            "health_checks = [\"containerd\", \"kubelet\"]\nhealth_check_timeout_seconds = 60\n",
+        )
+        .unwrap();
+        assert_eq!(
+            HealthChecks::load(&path).unwrap(),
+            Some(health_checks(
+                &["containerd", "kubelet"],
+                Duration::from_secs(60)
+            ))
+        );
+
+        fs::write(&path, "health_checks = \"kubelet\"\n").unwrap();
+        assert!(HealthChecks::load(&path).is_err());
+    }
+
+    #[test]
+    fn wait_until_healthy() {
+        let checker = MockCheck {
+            checks: Cell::new(0),
+            healthy_after: 2,
+        };
+        let checks = health_checks(&["kubelet"], Duration::from_secs(60));
+        let unhealthy = checks
+            .wait_with_interval(&checker, Duration::from_millis(1))
+            .unwrap();
+        assert!(unhealthy.is_empty());
+        assert_eq!(checker.checks.get(), 3);
+    }
+
+    #[test]
+    fn wait_times_out() {
+        let checker = MockCheck {
+            checks: Cell::new(0),
+            healthy_after: 0,
+        };
+        let checks = health_checks(&["kubelet", "ecs-fail"], Duration::from_millis(20));
+        let unhealthy = checks
+            .wait_with_interval(&checker, Duration::from_millis(1))
+            .unwrap();
+        assert_eq!(unhealthy, vec!["ecs-fail:1"]);
+    }
+}
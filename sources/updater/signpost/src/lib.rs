@@ -1,10 +1,12 @@
 mod error;
 mod gptprio;
 mod guid;
+mod health;
 mod set;
 mod state;
 
 pub use error::{Error, GPTError};
 pub use guid::uuid_to_guid;
-pub use set::PartitionSet;
+pub use health::{reboot, HealthChecks, HEALTH_CHECKS_CONFIG};
+pub use set::{PartitionSet, SetSelect};
 pub use state::State;
@@ -2,13 +2,15 @@
 #![warn(clippy::pedantic)]
 
 use serde::Deserialize;
-use signpost::State;
+use service_check::SystemdCheck;
+use signpost::{HealthChecks, State, HEALTH_CHECKS_CONFIG};
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 enum Command {
     Status,
     MarkSuccessfulBoot,
+    CheckHealth,
     MarkInactiveValid,
     ClearInactive,
     UpgradeToInactive,
@@ -24,7 +26,8 @@ USAGE:
 
 SUBCOMMANDS:
     status                  Show partition sets and priority status
-    mark-successful-boot    Mark the active partitions as successfully booted
+    mark-successful-boot    Mark the active partitions as successfully booted, unless health checks are configured for an update
+    check-health            Wait for health checks after an update, then mark the active partitions as successfully booted or roll back and reboot
     clear-inactive          Clears inactive priority information to prepare writing images to disk
     mark-inactive-valid     Marks the inactive partition as having a valid image
     upgrade-to-inactive     Sets the inactive partitions as new upgrade partitions if marked valid
@@ -34,6 +37,36 @@ SUBCOMMANDS:
     std::process::exit(1)
 }
 
+/// Marks the active partitions as successfully booted once the configured health checks pass.  If
+/// they don't pass in time, rolls back to the inactive partitions and reboots.
+fn check_health(state: &mut State) -> Result<(), signpost::Error> {
+    if state.active_successful() {
+        println!("Active partitions are already marked as successfully booted");
+        return Ok(());
+    }
+    if let Some(health_checks) = HealthChecks::load(HEALTH_CHECKS_CONFIG)? {
+        println!(
+            "Waiting up to {}s for units to be healthy: {}",
+            health_checks.timeout().as_secs(),
+            health_checks.units().join(", ")
+        );
+        let unhealthy = health_checks.wait(&SystemdCheck {})?;
+        if !unhealthy.is_empty() {
+            eprintln!(
+                "Units not healthy after update, rolling back: {}",
+                unhealthy.join(", ")
+            );
+            state.rollback_to_inactive()?;
+            state.write()?;
+            return signpost::reboot();
+        }
+    }
+    state.mark_successful_boot();
+    state.write()?;
+    println!("Marked the active partitions as successfully booted");
+    Ok(())
+}
+
 fn main() {
     let command_str = std::env::args().nth(1).unwrap_or_else(|| usage());
     let command = serde_plain::from_str::<Command>(&command_str).unwrap_or_else(|_| usage());
@@ -46,9 +79,17 @@ fn main() {
                 state.write()?;
             }
             Command::MarkSuccessfulBoot => {
-                state.mark_successful_boot();
-                state.write()?;
+                // After an update, leave marking the boot to check-health if there's anything to
+                // check, so the update can still be rolled back.
+                if !state.active_successful() && HealthChecks::load(HEALTH_CHECKS_CONFIG)?.is_some()
+                {
+                    println!("Waiting for health checks to mark the active partitions as successfully booted");
+                } else {
+                    state.mark_successful_boot();
+                    state.write()?;
+                }
             }
+            Command::CheckHealth => check_health(&mut state)?,
             Command::MarkInactiveValid => {
                 state.mark_inactive_valid();
                 state.write()?;
@@ -186,6 +186,29 @@ impl State {
         }
     }
 
+    /// Returns the priority of the given partition set.  Of the sets that can boot, the one with
+    /// the highest priority boots next.
+    pub fn priority(&self, select: SetSelect) -> u64 {
+        self.gptprio(select).priority()
+    }
+
+    /// Returns how many more times the given partition set may be tried if it hasn't booted
+    /// successfully.
+    pub fn tries_left(&self, select: SetSelect) -> u64 {
+        self.gptprio(select).tries_left()
+    }
+
+    /// Returns whether the given partition set has been marked as successfully booted.
+    pub fn successful(&self, select: SetSelect) -> bool {
+        self.gptprio(select).successful()
+    }
+
+    /// Returns whether the active partition has been marked as successfully booted.  It hasn't on
+    /// the first boot after an update until `mark_successful_boot` is called.
+    pub fn active_successful(&self) -> bool {
+        self.gptprio(self.active()).successful()
+    }
+
     /// Sets the active partition as successfully booted, but **does not write to the disk**.
     pub fn mark_successful_boot(&mut self) {
         let mut flags = self.gptprio(self.active());
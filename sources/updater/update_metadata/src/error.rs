@@ -56,6 +56,9 @@ pub enum Error {
         source: parse_datetime::Error,
     },
 
+    #[snafu(display("Unknown channel '{}', expected stable, beta, or canary", channel))]
+    BadChannel { channel: String },
+
     #[snafu(display("Duplicate key ID: {}", keyid))]
     DuplicateKeyId { backtrace: Backtrace, keyid: u32 },
 
@@ -10,11 +10,13 @@ use serde::{Deserialize, Serialize};
 use snafu::{ensure, ResultExt};
 use std::cmp::Ordering;
 use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::fs::File;
 use std::io::Read;
 use std::ops::Bound::{Excluded, Included};
 use std::path::Path;
+use std::str::FromStr;
 
 pub const MAX_SEED: u32 = 2048;
 
@@ -77,30 +79,124 @@ pub struct UpdateWave {
     pub fleet_percentage: u32,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+/// The release channels an update can be published to, ordered from most to least stable.  A host
+/// follows one channel, and accepts updates published to it or to any more stable channel, so
+/// releases can be tried on a subset of hosts before they reach the rest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Stable,
+    Beta,
+    Canary,
+}
+
+impl Channel {
+    /// Returns whether a host following this channel accepts updates published to `other`.
+    #[must_use]
+    pub fn accepts(self, other: Channel) -> bool {
+        other <= self
+    }
+}
+
+/// Updates published before channels existed are stable releases.
+impl Default for Channel {
+    fn default() -> Self {
+        Self::Stable
+    }
+}
+
+impl FromStr for Channel {
+    type Err = error::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "stable" => Ok(Self::Stable),
+            "beta" => Ok(Self::Beta),
+            "canary" => Ok(Self::Canary),
+            _ => error::BadChannelSnafu { channel: s }.fail(),
+        }
+    }
+}
+
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Stable => "stable",
+            Self::Beta => "beta",
+            Self::Canary => "canary",
+        };
+        f.write_str(name)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Images {
     pub boot: String,
     pub root: String,
     pub hash: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Update {
     pub variant: String,
     pub arch: String,
     pub version: Version,
     pub max_version: Version,
+    #[serde(default)]
+    pub channel: Channel,
     #[serde(deserialize_with = "de::deserialize_bound")]
     pub waves: BTreeMap<u32, DateTime<Utc>>,
     pub images: Images,
+    /// Delta images that build this update from the images of an earlier version, keyed by that
+    /// version.  Hosts running one of these versions can download a delta rather than the full
+    /// images.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub deltas: BTreeMap<Version, Images>,
 }
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(from = "ManifestFile", into = "ManifestFile")]
 pub struct Manifest {
     pub updates: Vec<Update>,
+    pub migrations: BTreeMap<(Version, Version), Vec<String>>,
+}
+
+/// The stored form of a Manifest.  Updates released to a channel other than 'stable' are kept
+/// out of `updates`, because versions of updog from before channels existed read every update in
+/// that list as a stable release, and ignore fields they don't know about.
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestFile {
+    updates: Vec<Update>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    channel_updates: Vec<Update>,
     #[serde(deserialize_with = "de::deserialize_migration")]
     #[serde(serialize_with = "se::serialize_migration")]
-    pub migrations: BTreeMap<(Version, Version), Vec<String>>,
+    migrations: BTreeMap<(Version, Version), Vec<String>>,
+}
+
+impl From<ManifestFile> for Manifest {
+    fn from(file: ManifestFile) -> Self {
+        let mut updates = file.updates;
+        updates.extend(file.channel_updates);
+        Self {
+            updates,
+            migrations: file.migrations,
+        }
+    }
+}
+
+impl From<Manifest> for ManifestFile {
+    fn from(manifest: Manifest) -> Self {
+        let (updates, channel_updates) = manifest
+            .updates
+            .into_iter()
+            .partition(|update| update.channel == Channel::Stable);
+        Self {
+            updates,
+            channel_updates,
+            migrations: manifest.migrations,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -149,6 +245,7 @@ impl Manifest {
         max_version: Option<Version>,
         arch: String,
         variant: String,
+        channel: Channel,
         images: Images,
     ) -> Result<()> {
         let max_version = if let Some(version) = max_version {
@@ -166,8 +263,10 @@ impl Manifest {
             arch,
             version: image_version,
             max_version,
+            channel,
             images,
             waves: BTreeMap::new(),
+            deltas: BTreeMap::new(),
         };
         self.update_max_version(
             &update.max_version,
@@ -218,6 +317,41 @@ impl Manifest {
         Ok(())
     }
 
+    /// Moves the updates matching variant, arch, and version to the given channel, for example to
+    /// promote a release from 'canary' to 'stable'.  Returns the number of matching updates.
+    pub fn set_channel(
+        &mut self,
+        variant: String,
+        arch: String,
+        image_version: Version,
+        channel: Channel,
+    ) -> usize {
+        let matching = self.get_matching_updates(variant, arch, image_version);
+        let num_matching = matching.len();
+        for update in matching {
+            update.channel = channel;
+        }
+        num_matching
+    }
+
+    /// Adds delta images to the updates matching variant, arch, and version, which build the update
+    /// from the images of `from_version`.  Returns the number of matching updates.
+    pub fn add_delta(
+        &mut self,
+        variant: String,
+        arch: String,
+        image_version: Version,
+        from_version: Version,
+        images: Images,
+    ) -> usize {
+        let matching = self.get_matching_updates(variant, arch, image_version);
+        let num_matching = matching.len();
+        for update in matching {
+            update.deltas.insert(from_version.clone(), images.clone());
+        }
+        num_matching
+    }
+
     /// Returns Updates matching variant, arch, and version
     fn get_matching_updates(
         &mut self,
@@ -467,12 +601,14 @@ mod tests {
             arch: "test".to_string(),
             version: Version::parse("1.1.1").unwrap(),
             max_version: Version::parse("1.1.1").unwrap(),
+            channel: Channel::Stable,
             waves: BTreeMap::new(),
             images: Images {
                 boot: String::from("boot"),
                 root: String::from("root"),
                 hash: String::from("hash"),
             },
+            deltas: BTreeMap::new(),
         }
     }
 
@@ -632,12 +768,14 @@ mod tests {
             arch: String::from("test"),
             version: Version::parse("1.0.0").unwrap(),
             max_version: Version::parse("1.1.0").unwrap(),
+            channel: Channel::Stable,
             waves: BTreeMap::new(),
             images: Images {
                 boot: String::from("boot"),
                 root: String::from("root"),
                 hash: String::from("hash"),
             },
+            deltas: BTreeMap::new(),
         };
         let seed = 1024;
         // Construct a DateTime object for 1/1/2000 00:00:00
@@ -692,4 +830,90 @@ mod tests {
         assert!(i.next().unwrap() == "migration_1.1.0_b");
         assert!(i.next().unwrap() == "migration_1.1.0_a");
     }
+
+    #[test]
+    fn test_channels() {
+        assert!(Channel::Stable.accepts(Channel::Stable));
+        assert!(!Channel::Stable.accepts(Channel::Beta));
+        assert!(Channel::Beta.accepts(Channel::Stable));
+        assert!(!Channel::Beta.accepts(Channel::Canary));
+        assert!(Channel::Canary.accepts(Channel::Beta));
+
+        // Updates published before channels existed are stable.
+        let path = "./tests/data/migrations.json";
+        let mut manifest: Manifest = serde_json::from_reader(File::open(path).unwrap()).unwrap();
+        assert_eq!(manifest.updates[0].channel, Channel::Stable);
+
+        let version = Version::parse("1.6.0").unwrap();
+        let images = Images {
+            boot: String::from("boot"),
+            root: String::from("root"),
+            hash: String::from("hash"),
+        };
+        manifest
+            .add_update(
+                version.clone(),
+                None,
+                "x86_64".into(),
+                "aws-dev".into(),
+                Channel::Canary,
+                images,
+            )
+            .unwrap();
+        let json = serde_json::to_string(&manifest).unwrap();
+
+        // Older clients only read 'updates', so they must not see the canary update there.
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["updates"].as_array().unwrap().len(), 1);
+        assert_eq!(value["channel_updates"][0]["version"], "1.6.0");
+
+        let mut manifest: Manifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(manifest.updates[1].channel, Channel::Canary);
+
+        let promoted =
+            manifest.set_channel("aws-dev".into(), "x86_64".into(), version, Channel::Stable);
+        assert_eq!(promoted, 1);
+        assert_eq!(manifest.updates[1].channel, Channel::Stable);
+        let value = serde_json::to_value(&manifest).unwrap();
+        assert_eq!(value["updates"].as_array().unwrap().len(), 2);
+        assert!(value.get("channel_updates").is_none());
+    }
+
+    #[test]
+    fn test_deltas() {
+        // Updates published without deltas have none, and don't serialize them.
+        let path = "./tests/data/migrations.json";
+        let mut manifest: Manifest = serde_json::from_reader(File::open(path).unwrap()).unwrap();
+        assert!(manifest.updates[0].deltas.is_empty());
+        assert!(!serde_json::to_string(&manifest).unwrap().contains("deltas"));
+
+        let update = &manifest.updates[0];
+        let (variant, arch, version) = (
+            update.variant.clone(),
+            update.arch.clone(),
+            update.version.clone(),
+        );
+        let from = Version::parse("1.0.0").unwrap();
+        let images = Images {
+            boot: String::from("boot-delta"),
+            root: String::from("root-delta"),
+            hash: String::from("hash-delta"),
+        };
+        let matching = manifest.add_delta(
+            variant.clone(),
+            arch.clone(),
+            version.clone(),
+            from.clone(),
+            images.clone(),
+        );
+        assert_eq!(matching, 1);
+        let missing =
+            manifest.add_delta(variant, arch, Version::new(9, 9, 9), from.clone(), images);
+        assert_eq!(missing, 0);
+
+        let json = serde_json::to_string(&manifest).unwrap();
+        let manifest: Manifest = serde_json::from_str(&json).unwrap();
+        assert_eq!(manifest.updates[0].version, version);
+        assert_eq!(manifest.updates[0].deltas[&from].root, "root-delta");
+    }
 }
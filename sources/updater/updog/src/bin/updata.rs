@@ -11,11 +11,11 @@ use crate::error::Result;
 use chrono::{DateTime, Utc};
 use semver::Version;
 use simplelog::{Config as LogConfig, LevelFilter, SimpleLogger};
-use snafu::{ErrorCompat, OptionExt, ResultExt};
+use snafu::{ensure, ErrorCompat, OptionExt, ResultExt};
 use std::fs;
 use std::path::PathBuf;
 use structopt::StructOpt;
-use update_metadata::{Images, Manifest, Release, UpdateWaves};
+use update_metadata::{Channel, Images, Manifest, Release, UpdateWaves};
 
 #[derive(Debug, StructOpt)]
 struct GeneralArgs {
@@ -44,6 +44,10 @@ struct AddUpdateArgs {
     #[structopt(short = "m", long = "max-version")]
     max_version: Option<Version>,
 
+    // release channel: stable, beta, or canary
+    #[structopt(short = "c", long = "channel", default_value = "stable")]
+    channel: Channel,
+
     // root image target name
     #[structopt(short = "r", long = "root")]
     root: String,
@@ -65,6 +69,7 @@ impl AddUpdateArgs {
             self.max_version,
             self.arch,
             self.variant,
+            self.channel,
             Images {
                 root: self.root,
                 boot: self.boot,
@@ -182,6 +187,111 @@ impl WaveArgs {
     }
 }
 
+#[derive(Debug, StructOpt)]
+struct ChannelArgs {
+    // metadata file to create/modify
+    file: PathBuf,
+
+    // image 'variant', eg. 'aws-ecs-1'
+    #[structopt(short = "l", long = "variant")]
+    variant: String,
+
+    // image version
+    #[structopt(short = "v", long = "version")]
+    image_version: Version,
+
+    // architecture image is built for
+    #[structopt(short = "a", long = "arch")]
+    arch: String,
+
+    // release channel: stable, beta, or canary
+    #[structopt(short = "c", long = "channel")]
+    channel: Channel,
+}
+
+impl ChannelArgs {
+    fn run(self) -> Result<()> {
+        let mut manifest: Manifest = update_metadata::load_file(&self.file)?;
+        let num_matching = manifest.set_channel(
+            self.variant.clone(),
+            self.arch.clone(),
+            self.image_version.clone(),
+            self.channel,
+        );
+        ensure!(
+            num_matching > 0,
+            error::NoMatchingUpdateSnafu {
+                variant: self.variant,
+                arch: self.arch,
+                version: self.image_version,
+            }
+        );
+        update_metadata::write_file(&self.file, &manifest)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, StructOpt)]
+struct DeltaArgs {
+    // metadata file to create/modify
+    file: PathBuf,
+
+    // image 'variant', eg. 'aws-ecs-1'
+    #[structopt(short = "l", long = "variant")]
+    variant: String,
+
+    // image version
+    #[structopt(short = "v", long = "version")]
+    image_version: Version,
+
+    // architecture image is built for
+    #[structopt(short = "a", long = "arch")]
+    arch: String,
+
+    // version the delta images apply to
+    #[structopt(short = "f", long = "from")]
+    from_version: Version,
+
+    // root delta target name
+    #[structopt(short = "r", long = "root")]
+    root: String,
+
+    // boot delta target name
+    #[structopt(short = "b", long = "boot")]
+    boot: String,
+
+    // verity "hash" delta target name
+    #[structopt(short = "h", long = "hash")]
+    hash: String,
+}
+
+impl DeltaArgs {
+    fn run(self) -> Result<()> {
+        let mut manifest: Manifest = update_metadata::load_file(&self.file)?;
+        let num_matching = manifest.add_delta(
+            self.variant.clone(),
+            self.arch.clone(),
+            self.image_version.clone(),
+            self.from_version,
+            Images {
+                root: self.root,
+                boot: self.boot,
+                hash: self.hash,
+            },
+        );
+        ensure!(
+            num_matching > 0,
+            error::NoMatchingUpdateSnafu {
+                variant: self.variant,
+                arch: self.arch,
+                version: self.image_version,
+            }
+        );
+        update_metadata::write_file(&self.file, &manifest)?;
+        Ok(())
+    }
+}
+
 #[derive(Debug, StructOpt)]
 struct MigrationArgs {
     // file to get migrations from (probably Release.toml)
@@ -240,6 +350,10 @@ enum Command {
     AddUpdate(AddUpdateArgs),
     /// Set waves for an update
     SetWaves(WaveArgs),
+    /// Move an update to another release channel, for example to promote it to stable
+    SetChannel(ChannelArgs),
+    /// Add delta images that build an update from the images of an earlier version
+    AddDelta(DeltaArgs),
     /// Set the global maximum image version
     SetMaxVersion(MaxVersionArgs),
     /// Remove an update from the manifest, including wave information
@@ -263,6 +377,8 @@ fn main_inner() -> Result<()> {
         }
         Command::AddUpdate(args) => args.run(),
         Command::SetWaves(args) => args.set(),
+        Command::SetChannel(args) => args.run(),
+        Command::AddDelta(args) => args.run(),
         Command::SetMaxVersion(args) => args.run(),
         Command::RemoveUpdate(args) => args.run(),
         Command::SetMigrations(args) => args.set(),
@@ -380,6 +496,7 @@ mod tests {
             arch: String::from("x86_64"),
             image_version: Version::parse("1.2.3").unwrap(),
             max_version: Some(Version::parse("1.2.3").unwrap()),
+            channel: Channel::Stable,
             boot: String::from("boot"),
             root: String::from("root"),
             hash: String::from("hash"),
@@ -392,6 +509,7 @@ mod tests {
             arch: String::from("x86_64"),
             image_version: Version::parse("1.2.5").unwrap(),
             max_version: Some(Version::parse("1.2.3").unwrap()),
+            channel: Channel::Stable,
             boot: String::from("boot"),
             root: String::from("root"),
             hash: String::from("hash"),
@@ -404,6 +522,7 @@ mod tests {
             arch: String::from("x86_64"),
             image_version: Version::parse("1.2.4").unwrap(),
             max_version: Some(Version::parse("1.2.4").unwrap()),
+            channel: Channel::Stable,
             boot: String::from("boot"),
             root: String::from("root"),
             hash: String::from("hash"),
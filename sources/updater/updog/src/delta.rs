@@ -0,0 +1,201 @@
+//! Delta images build a partition image for an update from the image of the running version, so
+//! hosts only download what changed.
+//!
+//! Deltas are listed per source version in the `deltas` of an update in the manifest.  Like full
+//! images, delta targets are lz4-compressed.  Decompressed, a delta is:
+//!
+//! * the magic bytes `BRDELTA1`
+//! * the size of the new image, as a little-endian u64
+//! * the SHA-256 digest of the new image, which is checked after the delta is applied
+//! * records, until the new image is complete, each made of:
+//!   * a diff length, an extra length, and a seek offset, as little-endian u64, u64, and i64
+//!   * diff bytes, which are added bytewise to the old image at the current old offset
+//!   * extra bytes, which are copied to the new image as-is
+//!   * after which the old offset moves by the seek offset
+//!
+//! This is the control scheme of bsdiff, with its three blocks interleaved so a delta can be
+//! applied as it's downloaded.
+
+use crate::error::{self, Result};
+use ring::digest::{Context, SHA256, SHA256_OUTPUT_LEN};
+use snafu::{ensure, ResultExt};
+use std::convert::TryFrom;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+const MAGIC: &[u8; 8] = b"BRDELTA1";
+
+/// How much of the delta and old image we process at once.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Builds a new image by applying `delta` to `old`, writing the new image to `new`.  Fails if the
+/// result doesn't match the digest in the delta, for example because `old` isn't the image the
+/// delta was made from.
+pub(crate) fn apply<D, O, N>(mut delta: D, mut old: O, mut new: N) -> Result<()>
+where
+    D: Read,
+    O: Read + Seek,
+    N: Write,
+{
+    let mut magic = [0; 8];
+    delta
+        .read_exact(&mut magic)
+        .context(error::DeltaApplySnafu)?;
+    ensure!(
+        &magic == MAGIC,
+        error::DeltaFormatSnafu {
+            reason: "unknown magic bytes"
+        }
+    );
+    let size = read_u64(&mut delta)?;
+    let mut expected = [0; SHA256_OUTPUT_LEN];
+    delta
+        .read_exact(&mut expected)
+        .context(error::DeltaApplySnafu)?;
+
+    let mut digest = Context::new(&SHA256);
+    let mut old_buf = vec![0; CHUNK_SIZE];
+    let mut buf = vec![0; CHUNK_SIZE];
+    let mut written = 0;
+    while written < size {
+        let diff_len = read_u64(&mut delta)?;
+        let extra_len = read_u64(&mut delta)?;
+        let seek = read_u64(&mut delta)?;
+        ensure!(
+            matches!(
+                diff_len
+                    .checked_add(extra_len)
+                    .and_then(|len| len.checked_add(written)),
+                Some(end) if end <= size
+            ),
+            error::DeltaFormatSnafu {
+                reason: "records are longer than the new image"
+            }
+        );
+
+        let mut remaining = diff_len;
+        while remaining > 0 {
+            let len = chunk_len(remaining);
+            delta
+                .read_exact(&mut buf[..len])
+                .context(error::DeltaApplySnafu)?;
+            old.read_exact(&mut old_buf[..len])
+                .context(error::DeltaApplySnafu)?;
+            for (byte, old_byte) in buf[..len].iter_mut().zip(&old_buf[..len]) {
+                *byte = byte.wrapping_add(*old_byte);
+            }
+            digest.update(&buf[..len]);
+            new.write_all(&buf[..len]).context(error::DeltaApplySnafu)?;
+            remaining -= len as u64;
+        }
+
+        let mut remaining = extra_len;
+        while remaining > 0 {
+            let len = chunk_len(remaining);
+            delta
+                .read_exact(&mut buf[..len])
+                .context(error::DeltaApplySnafu)?;
+            digest.update(&buf[..len]);
+            new.write_all(&buf[..len]).context(error::DeltaApplySnafu)?;
+            remaining -= len as u64;
+        }
+
+        // The seek offset is signed; reinterpret the bits we read.
+        #[allow(clippy::cast_possible_wrap)]
+        old.seek(SeekFrom::Current(seek as i64))
+            .context(error::DeltaApplySnafu)?;
+        written += diff_len + extra_len;
+    }
+    new.flush().context(error::DeltaApplySnafu)?;
+
+    let actual = digest.finish();
+    ensure!(
+        actual.as_ref() == expected,
+        error::DeltaDigestSnafu {
+            expected: hex::encode(expected),
+            actual: hex::encode(actual),
+        }
+    );
+    Ok(())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64> {
+    let mut bytes = [0; 8];
+    reader
+        .read_exact(&mut bytes)
+        .context(error::DeltaApplySnafu)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Returns how much of `remaining` bytes to process next.
+fn chunk_len(remaining: u64) -> usize {
+    usize::try_from(remaining).map_or(CHUNK_SIZE, |remaining| remaining.min(CHUNK_SIZE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::digest::digest;
+    use std::io::Cursor;
+
+    /// Builds a delta with the given records of (diff bytes, extra bytes, seek).
+    fn make_delta(new: &[u8], records: &[(&[u8], &[u8], i64)]) -> Vec<u8> {
+        let mut delta = MAGIC.to_vec();
+        delta.extend_from_slice(&(new.len() as u64).to_le_bytes());
+        delta.extend_from_slice(digest(&SHA256, new).as_ref());
+        for (diff, extra, seek) in records {
+            delta.extend_from_slice(&(diff.len() as u64).to_le_bytes());
+            delta.extend_from_slice(&(extra.len() as u64).to_le_bytes());
+            delta.extend_from_slice(&seek.to_le_bytes());
+            delta.extend_from_slice(diff);
+            delta.extend_from_slice(extra);
+        }
+        delta
+    }
+
+    #[test]
+    fn apply_delta() {
+        let old = b"hello world, goodbye world";
+        let new = b"hello there world!";
+        // "hello " unchanged, then "there " is new; skip "world, goodbye " and diff "world" into
+        // "world" before adding "!".
+        let delta = make_delta(new, &[(&[0; 6], b"there ", 15), (&[0; 5], b"!", 0)]);
+        let mut out = Vec::new();
+        apply(&delta[..], Cursor::new(&old[..]), &mut out).unwrap();
+        assert_eq!(out, new);
+    }
+
+    #[test]
+    fn apply_delta_adds_diff_bytes() {
+        let old = b"abc";
+        let new = b"bcd";
+        let delta = make_delta(new, &[(&[1, 1, 1], b"", 0)]);
+        let mut out = Vec::new();
+        apply(&delta[..], Cursor::new(&old[..]), &mut out).unwrap();
+        assert_eq!(out, new);
+    }
+
+    #[test]
+    fn wrong_old_image() {
+        let new = b"hello there";
+        let delta = make_delta(new, &[(&[0; 6], b"there", 0)]);
+        let mut out = Vec::new();
+        let result = apply(&delta[..], Cursor::new(&b"jello world"[..]), &mut out);
+        assert!(matches!(result, Err(error::Error::DeltaDigest { .. })));
+    }
+
+    #[test]
+    fn records_too_long() {
+        let delta = make_delta(b"hi", &[(&[], b"hello", 0)]);
+        let mut out = Vec::new();
+        let result = apply(&delta[..], Cursor::new(&b""[..]), &mut out);
+        assert!(matches!(result, Err(error::Error::DeltaFormat { .. })));
+    }
+
+    #[test]
+    fn bad_magic() {
+        let mut delta = make_delta(b"", &[]);
+        delta[0] = b'X';
+        let result = apply(&delta[..], Cursor::new(&b""[..]), Vec::new());
+        assert!(matches!(result, Err(error::Error::DeltaFormat { .. })));
+    }
+}
@@ -53,6 +53,29 @@ pub(crate) enum Error {
         path: PathBuf,
     },
 
+    #[snafu(display("Failed to apply delta: {}", source))]
+    DeltaApply {
+        source: std::io::Error,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display(
+        "Delta built an image with SHA-256 digest {}, expected {}",
+        actual,
+        expected
+    ))]
+    DeltaDigest {
+        expected: String,
+        actual: String,
+        backtrace: Backtrace,
+    },
+
+    #[snafu(display("Invalid delta: {}", reason))]
+    DeltaFormat {
+        reason: &'static str,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Logger setup error: {}", source))]
     Logger { source: log::SetLoggerError },
 
@@ -103,6 +126,14 @@ pub(crate) enum Error {
         name: String,
     },
 
+    #[snafu(display("No update in the manifest for {} {} {}", variant, arch, version))]
+    NoMatchingUpdate {
+        variant: String,
+        arch: String,
+        version: semver::Version,
+        backtrace: Backtrace,
+    },
+
     #[snafu(display("Failed to open partition {}: {}", path.display(), source))]
     OpenPartition {
         path: PathBuf,
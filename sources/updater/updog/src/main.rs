@@ -1,6 +1,7 @@
 #![deny(rust_2018_idioms)]
 #![warn(clippy::pedantic)]
 
+mod delta;
 mod error;
 mod transport;
 
@@ -8,7 +9,7 @@ use crate::error::Result;
 use crate::transport::{HttpQueryTransport, QueryParams};
 use bottlerocket_release::BottlerocketRelease;
 use chrono::Utc;
-use log::debug;
+use log::{debug, warn};
 use model::modeled_types::FriendlyVersion;
 use semver::Version;
 use serde::{Deserialize, Serialize};
@@ -24,8 +25,9 @@ use std::path::Path;
 use std::process;
 use std::str::FromStr;
 use std::thread;
+use std::time::Duration;
 use tough::{Repository, RepositoryLoader};
-use update_metadata::{find_migrations, Manifest, Update};
+use update_metadata::{find_migrations, Channel, Manifest, Update};
 use url::Url;
 
 #[cfg(target_arch = "x86_64")]
@@ -42,6 +44,13 @@ const MIGRATION_PATH: &str = "/var/lib/bottlerocket-migrations";
 /// This is where we store the TUF metadata used by migrator after reboot.
 const METADATA_PATH: &str = "/var/cache/bottlerocket-metadata";
 
+/// This is where we save downloads of targets as they happen, so we can continue an interrupted
+/// download rather than start over.
+const DOWNLOAD_PATH: &str = "/var/lib/updog";
+
+/// Saved downloads that haven't been continued for this long are removed when updog starts.
+const DOWNLOAD_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
 #[derive(Debug, Deserialize, PartialEq)]
 #[serde(rename_all = "kebab-case")]
 enum Command {
@@ -61,6 +70,8 @@ struct Config {
     seed: u32,
     version_lock: String,
     ignore_waves: bool,
+    #[serde(default)]
+    channel: Channel,
     https_proxy: Option<String>,
     no_proxy: Option<Vec<String>>,
     // TODO API sourced configuration, eg.
@@ -81,7 +92,7 @@ USAGE:
     updog <SUBCOMMAND> <OPTIONS>
 
 SUBCOMMANDS:
-    check-update            Show if an update is available
+    check-update            Show if an update is available, and the channel it's released to
         [ -a | --all ]                Output all available updates, even if they're not upgrades
         [ --ignore-waves ]            Ignore release schedule when checking
                                       for a new update
@@ -136,9 +147,12 @@ fn load_repository(transport: HttpQueryTransport, config: &Config) -> Result<Rep
     .context(error::MetadataSnafu)
 }
 
+/// Returns the updates this host may take: those for its variant and architecture, released to a
+/// channel it accepts, and not held back by waves.
 fn applicable_updates<'a>(
     manifest: &'a Manifest,
     variant: &str,
+    channel: Channel,
     ignore_waves: bool,
     seed: u32,
 ) -> Vec<&'a Update> {
@@ -149,6 +163,7 @@ fn applicable_updates<'a>(
             u.variant == *variant
                 && u.arch == TARGET_ARCH
                 && u.version <= u.max_version
+                && channel.accepts(u.channel)
                 && (ignore_waves || u.update_ready(seed, Utc::now()))
         })
         .collect();
@@ -163,16 +178,18 @@ fn applicable_updates<'a>(
 //  Ignore Specific Target Version
 //  Ignore Any Target
 //  ...
+#[allow(clippy::too_many_arguments)]
 fn update_required<'a>(
     manifest: &'a Manifest,
     version: &Version,
     variant: &str,
+    channel: Channel,
     ignore_waves: bool,
     seed: u32,
     version_lock: &str,
     force_version: Option<Version>,
 ) -> Result<Option<&'a Update>> {
-    let updates = applicable_updates(manifest, variant, ignore_waves, seed);
+    let updates = applicable_updates(manifest, variant, channel, ignore_waves, seed);
 
     if let Some(forced_version) = force_version {
         return Ok(updates.into_iter().find(|u| u.version == forced_version));
@@ -241,6 +258,60 @@ fn write_target_to_disk<P: AsRef<Path>>(
     Ok(())
 }
 
+/// Builds an image on disk by applying a delta target to the image in `old_path`.
+fn write_delta_to_disk<P: AsRef<Path>>(
+    repository: &Repository,
+    target: &str,
+    old_path: P,
+    disk_path: P,
+) -> Result<()> {
+    let target = target
+        .try_into()
+        .context(error::TargetNameSnafu { target })?;
+    let reader = repository
+        .read_target(&target)
+        .context(error::MetadataSnafu)?
+        .context(error::TargetNotFoundSnafu {
+            target: target.raw(),
+        })?;
+    let reader = lz4::Decoder::new(reader).context(error::Lz4DecodeSnafu {
+        target: target.raw(),
+    })?;
+    let old = File::open(old_path.as_ref()).context(error::OpenPartitionSnafu {
+        path: old_path.as_ref(),
+    })?;
+    let f = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(disk_path.as_ref())
+        .context(error::OpenPartitionSnafu {
+            path: disk_path.as_ref(),
+        })?;
+    delta::apply(reader, io::BufReader::new(old), io::BufWriter::new(f))
+}
+
+/// Writes an image to the inactive partition.  If there's a delta from the active partition, we
+/// try that first, and fall back to the full image if the delta can't be applied.
+fn write_image(
+    repository: &Repository,
+    image: &str,
+    delta: Option<&str>,
+    active: &Path,
+    inactive: &Path,
+) -> Result<()> {
+    if let Some(delta) = delta {
+        match write_delta_to_disk(repository, delta, active, inactive) {
+            Ok(()) => return Ok(()),
+            Err(e) => warn!(
+                "Unable to apply delta '{}', writing full image '{}': {}",
+                delta, image, e
+            ),
+        }
+    }
+    write_target_to_disk(repository, image, inactive)
+}
+
 /// Store required migrations for an update in persistent storage. All intermediate migrations
 /// between the current version and the target version must be retrieved.
 fn retrieve_migrations(
@@ -275,7 +346,7 @@ fn retrieve_migrations(
     Ok(())
 }
 
-fn update_image(update: &Update, repository: &Repository) -> Result<()> {
+fn update_image(update: &Update, repository: &Repository, current_version: &Version) -> Result<()> {
     let mut gpt_state = State::load().context(error::PartitionTableReadSnafu)?;
     gpt_state.clear_inactive();
     // Write out the clearing of the inactive partition immediately, because we're about to
@@ -283,12 +354,33 @@ fn update_image(update: &Update, repository: &Repository) -> Result<()> {
     // know we're done with all components.
     gpt_state.write().context(error::PartitionTableWriteSnafu)?;
 
+    let active = gpt_state.active_set();
     let inactive = gpt_state.inactive_set();
+    // Deltas are made from the images of the version we're running, which are in the active set.
+    let deltas = update.deltas.get(current_version);
 
     // TODO Do we want to recover the inactive side on an error?
-    write_target_to_disk(repository, &update.images.root, &inactive.root)?;
-    write_target_to_disk(repository, &update.images.boot, &inactive.boot)?;
-    write_target_to_disk(repository, &update.images.hash, &inactive.hash)?;
+    write_image(
+        repository,
+        &update.images.root,
+        deltas.map(|d| d.root.as_str()),
+        &active.root,
+        &inactive.root,
+    )?;
+    write_image(
+        repository,
+        &update.images.boot,
+        deltas.map(|d| d.boot.as_str()),
+        &active.boot,
+        &inactive.boot,
+    )?;
+    write_image(
+        repository,
+        &update.images.hash,
+        deltas.map(|d| d.hash.as_str()),
+        &active.hash,
+        &inactive.hash,
+    )?;
 
     gpt_state.mark_inactive_valid();
     gpt_state.write().context(error::PartitionTableWriteSnafu)?;
@@ -324,11 +416,12 @@ fn set_common_query_params(
 fn list_updates(
     manifest: &Manifest,
     variant: &str,
+    channel: Channel,
     json: bool,
     ignore_waves: bool,
     seed: u32,
 ) -> Result<()> {
-    let updates = applicable_updates(manifest, variant, ignore_waves, seed);
+    let updates = applicable_updates(manifest, variant, channel, ignore_waves, seed);
     if json {
         println!(
             "{}",
@@ -427,7 +520,7 @@ fn parse_args(args: std::env::Args) -> Arguments {
 }
 
 fn fmt_full_version(update: &Update) -> String {
-    format!("{} {}", update.variant, update.version)
+    format!("{} {} ({})", update.variant, update.version, update.channel)
 }
 
 fn output<T: Serialize>(json: bool, object: T, string: &str) -> Result<()> {
@@ -504,7 +597,20 @@ fn main_inner() -> Result<()> {
     set_https_proxy_environment_variables(&config.https_proxy, &config.no_proxy);
     let current_release = BottlerocketRelease::new().context(error::ReleaseVersionSnafu)?;
     let variant = arguments.variant.unwrap_or(current_release.variant_id);
-    let transport = HttpQueryTransport::new();
+    let mut transport = HttpQueryTransport::new();
+    if matches!(command, Command::Update | Command::UpdateImage) {
+        fs::create_dir_all(DOWNLOAD_PATH).context(error::DirCreateSnafu {
+            path: DOWNLOAD_PATH,
+        })?;
+        // A failure here only costs space, so it shouldn't stop the update.
+        if let Err(e) = transport::prune_downloads(Path::new(DOWNLOAD_PATH), DOWNLOAD_MAX_AGE) {
+            warn!(
+                "Unable to remove stale downloads in '{}': {}",
+                DOWNLOAD_PATH, e
+            );
+        }
+        transport = transport.with_download_dir(DOWNLOAD_PATH);
+    }
     // get a shared pointer to the transport's query_params so we can add metrics information to
     // the transport's HTTP calls.
     let mut query_params = transport.query_params();
@@ -518,6 +624,7 @@ fn main_inner() -> Result<()> {
                 return list_updates(
                     &manifest,
                     &variant,
+                    config.channel,
                     arguments.json,
                     ignore_waves,
                     config.seed,
@@ -528,6 +635,7 @@ fn main_inner() -> Result<()> {
                 &manifest,
                 &current_release.version_id,
                 &variant,
+                config.channel,
                 ignore_waves,
                 config.seed,
                 &config.version_lock,
@@ -542,6 +650,7 @@ fn main_inner() -> Result<()> {
                 &manifest,
                 &current_release.version_id,
                 &variant,
+                config.channel,
                 ignore_waves,
                 config.seed,
                 &config.version_lock,
@@ -556,7 +665,7 @@ fn main_inner() -> Result<()> {
                     u,
                     &current_release.version_id,
                 )?;
-                update_image(u, &repository)?;
+                update_image(u, &repository, &current_release.version_id)?;
                 if command == Command::Update {
                     update_flags()?;
                     if arguments.reboot {
@@ -678,6 +787,7 @@ mod tests {
             seed: 123,
             version_lock: "latest".to_string(),
             ignore_waves: false,
+            channel: Channel::Stable,
             https_proxy: None,
             no_proxy: None,
         };
@@ -689,6 +799,7 @@ mod tests {
                 &manifest,
                 &version,
                 &variant,
+                config.channel,
                 config.ignore_waves,
                 config.seed,
                 &config.version_lock,
@@ -712,6 +823,7 @@ mod tests {
             seed: 1487,
             version_lock: "latest".to_string(),
             ignore_waves: false,
+            channel: Channel::Stable,
             https_proxy: None,
             no_proxy: None,
         };
@@ -722,6 +834,7 @@ mod tests {
             &manifest,
             &version,
             &variant,
+            config.channel,
             config.ignore_waves,
             config.seed,
             &config.version_lock,
@@ -750,6 +863,7 @@ mod tests {
             seed: 123,
             version_lock: "latest".to_string(),
             ignore_waves: false,
+            channel: Channel::Stable,
             https_proxy: None,
             no_proxy: None,
         };
@@ -760,6 +874,7 @@ mod tests {
             &manifest,
             &version,
             &variant,
+            config.channel,
             config.ignore_waves,
             config.seed,
             &config.version_lock,
@@ -792,6 +907,7 @@ mod tests {
             seed: 123,
             version_lock: "latest".to_string(),
             ignore_waves: false,
+            channel: Channel::Stable,
             https_proxy: None,
             no_proxy: None,
         };
@@ -803,6 +919,7 @@ mod tests {
             &manifest,
             &version,
             &variant,
+            config.channel,
             config.ignore_waves,
             config.seed,
             &config.version_lock,
@@ -858,12 +975,14 @@ mod tests {
             arch: String::from(TARGET_ARCH),
             version: Version::parse("1.1.1").unwrap(),
             max_version: Version::parse("1.1.1").unwrap(),
+            channel: Channel::Stable,
             waves: BTreeMap::new(),
             images: Images {
                 boot: String::from("boot"),
                 root: String::from("boot"),
                 hash: String::from("boot"),
             },
+            deltas: BTreeMap::new(),
         };
 
         let current_version = Version::parse("1.0.0").unwrap();
@@ -875,6 +994,7 @@ mod tests {
             seed: first_wave_seed,
             version_lock: "latest".to_string(),
             ignore_waves: false,
+            channel: Channel::Stable,
             https_proxy: None,
             no_proxy: None,
         };
@@ -891,6 +1011,7 @@ mod tests {
                 &manifest,
                 &current_version,
                 &variant,
+                config.channel,
                 config.ignore_waves,
                 config.seed,
                 &config.version_lock,
@@ -906,6 +1027,7 @@ mod tests {
                 &manifest,
                 &current_version,
                 &variant,
+                config.channel,
                 config.ignore_waves,
                 2000,
                 &config.version_lock,
@@ -916,4 +1038,52 @@ mod tests {
             "Later wave incorrectly sees update"
         );
     }
+
+    #[test]
+    /// Make sure that hosts only see updates released to channels they accept.
+    fn check_update_channels() {
+        let mut manifest = Manifest::default();
+        for (version, channel) in [("1.1.0", Channel::Stable), ("1.2.0", Channel::Canary)] {
+            manifest.updates.push(Update {
+                variant: String::from("aws-k8s-1.15"),
+                arch: String::from(TARGET_ARCH),
+                version: Version::parse(version).unwrap(),
+                max_version: Version::parse("1.2.0").unwrap(),
+                channel,
+                waves: BTreeMap::new(),
+                images: Images {
+                    boot: String::from("boot"),
+                    root: String::from("root"),
+                    hash: String::from("hash"),
+                },
+                deltas: BTreeMap::new(),
+            });
+        }
+
+        let current_version = Version::parse("1.0.0").unwrap();
+        let variant = String::from("aws-k8s-1.15");
+        let chosen = |channel| {
+            update_required(
+                &manifest,
+                &current_version,
+                &variant,
+                channel,
+                false,
+                123,
+                "latest",
+                None,
+            )
+            .unwrap()
+            .map(|u| u.version.to_string())
+        };
+        assert_eq!(chosen(Channel::Stable), Some(String::from("1.1.0")));
+        assert_eq!(chosen(Channel::Beta), Some(String::from("1.1.0")));
+        assert_eq!(chosen(Channel::Canary), Some(String::from("1.2.0")));
+
+        let listed = applicable_updates(&manifest, &variant, Channel::Canary, false, 123);
+        assert_eq!(
+            listed.iter().map(|u| u.channel).collect::<Vec<_>>(),
+            vec![Channel::Canary, Channel::Stable]
+        );
+    }
 }
@@ -1,9 +1,29 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
 
-use log::error;
-use tough::{HttpTransport, Transport, TransportError};
+use log::{debug, error, warn};
+use reqwest::blocking::{Client, Response};
+use reqwest::header::RANGE;
+use reqwest::StatusCode;
+use tough::{HttpTransport, Transport, TransportError, TransportErrorKind};
 use url::Url;
 
+/// How many times we try to continue a download before giving up, if none of the tries make any
+/// progress.
+const DOWNLOAD_TRIES: u32 = 4;
+
+/// How long we wait before the first retry of a download; this grows with each failed try.
+const DOWNLOAD_BACKOFF: Duration = Duration::from_millis(500);
+
+/// Timeouts for each request of a download.  A request that times out while reading the body is
+/// continued from where it left off, so large targets don't need to finish within the timeout.
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
 /// A shared pointer to a list of query params that the transport will add to HTTP calls.
 #[derive(Debug, Clone, Default)]
 pub(crate) struct QueryParams(Arc<RwLock<Vec<(String, String)>>>);
@@ -14,6 +34,7 @@ pub(crate) struct QueryParams(Arc<RwLock<Vec<(String, String)>>>);
 pub(crate) struct HttpQueryTransport {
     pub inner: HttpTransport,
     parameters: QueryParams,
+    download_dir: Option<PathBuf>,
 }
 
 impl QueryParams {
@@ -58,9 +79,18 @@ impl HttpQueryTransport {
         Self {
             inner: HttpTransport::default(),
             parameters: QueryParams::default(),
+            download_dir: None,
         }
     }
 
+    /// Save downloads of targets to `dir` as they're read, so a download that's interrupted by
+    /// updog being killed or the host rebooting can be continued with a range request rather than
+    /// started over.
+    pub fn with_download_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.download_dir = Some(dir.into());
+        self
+    }
+
     /// Obtain a shared pointer to the query params for this transport.
     pub fn query_params(&self) -> QueryParams {
         QueryParams(Arc::clone(&self.parameters.0))
@@ -72,6 +102,322 @@ impl Transport for HttpQueryTransport {
         &self,
         url: Url,
     ) -> std::result::Result<Box<dyn std::io::Read + Send>, TransportError> {
-        self.inner.fetch(self.parameters.add_params_to_url(url))
+        let url = self.parameters.add_params_to_url(url);
+        if let Some(dir) = &self.download_dir {
+            if let Some(name) = content_addressed_name(&url) {
+                let path = dir.join(name);
+                return ResumableRead::start(url, path);
+            }
+        }
+        self.inner.fetch(url)
+    }
+}
+
+/// Returns the file name of `url` if it starts with a SHA-256 digest, as the names of targets do
+/// in repositories with consistent snapshots.  What's behind such a URL never changes, so it's
+/// safe to continue a partial download of it, even from an earlier run.
+fn content_addressed_name(url: &Url) -> Option<String> {
+    let name = url.path_segments()?.next_back()?;
+    let (digest, rest) = name.split_once('.')?;
+    if digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit()) && !rest.is_empty() {
+        Some(name.to_string())
+    } else {
+        None
+    }
+}
+
+/// A download that's saved to a file as it's read.  If a read fails, the download continues from
+/// where it left off with a range request.  The file is removed once the download is complete,
+/// or if the reader is dropped before then for any reason other than a transport error, such as
+/// tough finding the download longer than its metadata allows.  A download cut short by a
+/// transport error or by updog dying is left behind to continue, and `prune_downloads` removes it
+/// if it isn't continued.
+struct ResumableRead {
+    client: Client,
+    url: Url,
+    path: PathBuf,
+    /// Where we save what we read; `None` if we had trouble saving and gave up on it.
+    file: Option<File>,
+    /// The response we're reading from; `None` if we need to continue with a new request.
+    response: Option<Response>,
+    /// How much of the download we've read so far.
+    offset: u64,
+    /// Whether a transport error ended the download, in which case we keep what we saved.
+    interrupted: bool,
+}
+
+impl ResumableRead {
+    /// Starts or continues the download of `url`.  If `path` holds part of the download from
+    /// earlier, the returned reader replays it before reading the rest from the server.
+    fn start(url: Url, path: PathBuf) -> Result<Box<dyn Read + Send>, TransportError> {
+        let client = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| TransportError::new_with_cause(TransportErrorKind::Other, &url, e))?;
+
+        let saved = fs::metadata(&path).map_or(0, |m| m.len());
+        let mut tries = 0;
+        let response = loop {
+            match request(&client, &url, saved) {
+                Ok(response) => break response,
+                Err(e) if matches!(e.kind(), TransportErrorKind::FileNotFound) => return Err(e),
+                Err(e) => {
+                    tries += 1;
+                    if tries >= DOWNLOAD_TRIES {
+                        return Err(e);
+                    }
+                    debug!("Retrying request for '{}': {}", url, e);
+                    thread::sleep(DOWNLOAD_BACKOFF * tries);
+                }
+            }
+        };
+
+        // The server only sends part of the download if it supports range requests and what we
+        // saved isn't already the whole thing; otherwise we start over.
+        let offset = if response.status() == StatusCode::PARTIAL_CONTENT {
+            debug!("Continuing download of '{}' from byte {}", url, saved);
+            saved
+        } else {
+            0
+        };
+        let saved_reader = File::open(&path)
+            .ok()
+            .filter(|_| offset > 0)
+            .map(|f| f.take(offset));
+        let file = match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(f) if offset == 0 => f.set_len(0).map(|()| f),
+            result => result,
+        };
+        let file = match file {
+            Ok(f) => Some(f),
+            Err(e) => {
+                warn!("Unable to save download to '{}': {}", path.display(), e);
+                None
+            }
+        };
+
+        let reader = ResumableRead {
+            client,
+            url,
+            path,
+            file,
+            response: Some(response),
+            offset,
+            interrupted: false,
+        };
+        Ok(match saved_reader {
+            Some(saved_reader) => Box::new(saved_reader.chain(reader)),
+            None => Box::new(reader),
+        })
+    }
+
+    /// Requests the rest of the download after a read failed.
+    fn resume(&mut self) -> io::Result<()> {
+        let response = request(&self.client, &self.url, self.offset)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        if self.offset > 0 && response.status() != StatusCode::PARTIAL_CONTENT {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "unable to continue download of '{}': server didn't honor range request",
+                    self.url
+                ),
+            ));
+        }
+        self.response = Some(response);
+        Ok(())
+    }
+
+    /// Saves bytes we've read, giving up on saving if that fails, since we can still download.
+    fn save(&mut self, buf: &[u8]) {
+        if let Some(file) = &mut self.file {
+            if let Err(e) = file.write_all(buf) {
+                warn!(
+                    "Unable to save download to '{}': {}",
+                    self.path.display(),
+                    e
+                );
+                self.discard();
+            }
+        }
+    }
+
+    /// Removes what we've saved of the download, if we haven't already.
+    fn discard(&mut self) {
+        if self.file.take().is_some() {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+impl Drop for ResumableRead {
+    fn drop(&mut self) {
+        // Callers stop reading early when the download doesn't match the target's metadata, or
+        // when a delta can't be applied, and an abandoned download shouldn't take up space on the
+        // data partition.
+        if !self.interrupted {
+            self.discard();
+        }
+    }
+}
+
+impl Read for ResumableRead {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut tries = 0;
+        loop {
+            let result = match &mut self.response {
+                Some(response) => response.read(buf),
+                None => match self.resume() {
+                    Ok(()) => continue,
+                    Err(e) => Err(e),
+                },
+            };
+            match result {
+                Ok(0) => {
+                    // The download is complete, so there's nothing left to resume.  If it doesn't
+                    // match its digest, tough rejects it, and it's no use continuing later either.
+                    self.discard();
+                    return Ok(0);
+                }
+                Ok(len) => {
+                    self.save(&buf[..len]);
+                    self.offset += len as u64;
+                    return Ok(len);
+                }
+                Err(e) => {
+                    tries += 1;
+                    if tries >= DOWNLOAD_TRIES {
+                        self.interrupted = true;
+                        return Err(e);
+                    }
+                    debug!(
+                        "Error reading '{}' at byte {}, retrying: {}",
+                        self.url, self.offset, e
+                    );
+                    self.response = None;
+                    thread::sleep(DOWNLOAD_BACKOFF * tries);
+                }
+            }
+        }
+    }
+}
+
+/// Removes saved downloads in `dir` that haven't been written to within `max_age`.  Downloads are
+/// only left behind if updog dies partway through, and one that isn't continued soon after is
+/// unlikely to be wanted again.
+pub(crate) fn prune_downloads(dir: &Path, max_age: Duration) -> io::Result<()> {
+    let now = SystemTime::now();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        // A modification time in the future counts as fresh.
+        let age = now.duration_since(metadata.modified()?).unwrap_or_default();
+        if age >= max_age {
+            debug!("Removing stale download '{}'", entry.path().display());
+            fs::remove_file(entry.path())?;
+        }
+    }
+    Ok(())
+}
+
+/// Sends a request for `url`, asking for the part starting at `offset` if it's not zero.
+fn request(client: &Client, url: &Url, offset: u64) -> Result<Response, TransportError> {
+    let mut builder = client.get(url.clone());
+    if offset > 0 {
+        builder = builder.header(RANGE, format!("bytes={offset}-"));
+    }
+    let response = builder
+        .send()
+        .map_err(|e| TransportError::new_with_cause(TransportErrorKind::Other, url, e))?;
+    match response.status() {
+        // Match tough's HttpTransport, since tough relies on knowing when files don't exist.
+        StatusCode::FORBIDDEN | StatusCode::NOT_FOUND | StatusCode::GONE => {
+            Err(TransportError::new(TransportErrorKind::FileNotFound, url))
+        }
+        // What we saved is already as long as the download; it must be stale, so start over.
+        StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => request(client, url, 0),
+        _ => response
+            .error_for_status()
+            .map_err(|e| TransportError::new_with_cause(TransportErrorKind::Other, url, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn content_addressed_names() {
+        let digest = "0123456789abcdef0123456789abcdef0123456789abcdef0123456789abcdef";
+        let url = Url::parse(&format!(
+            "https://example.com/targets/{digest}.root.ext4.lz4?version=1.0.0"
+        ))
+        .unwrap();
+        assert_eq!(
+            content_addressed_name(&url),
+            Some(format!("{digest}.root.ext4.lz4"))
+        );
+
+        for name in &[
+            "1.root.json",
+            "timestamp.json",
+            "manifest.json",
+            "0123.root.ext4.lz4",
+        ] {
+            let url = Url::parse(&format!("https://example.com/{name}")).unwrap();
+            assert_eq!(content_addressed_name(&url), None, "{name}");
+        }
+    }
+
+    fn partial_download(dir: &Path) -> ResumableRead {
+        // Nothing listens on the port once the listener is dropped, so every request fails
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let path = dir.join("download");
+        fs::write(&path, "partial").unwrap();
+        ResumableRead {
+            client: Client::new(),
+            url: Url::parse(&format!("http://127.0.0.1:{port}/download")).unwrap(),
+            file: Some(OpenOptions::new().append(true).open(&path).unwrap()),
+            path,
+            response: None,
+            offset: 7,
+            interrupted: false,
+        }
+    }
+
+    // A download cut short by a transport error is kept to continue later, but one the caller
+    // abandons isn't
+    #[test]
+    fn interrupted_download_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut download = partial_download(dir.path());
+        let path = download.path.clone();
+        assert!(download.read(&mut [0; 16]).is_err());
+        drop(download);
+        assert!(path.exists());
+
+        drop(partial_download(dir.path()));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn prune_stale_downloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("download");
+        fs::write(&path, "partial").unwrap();
+
+        prune_downloads(dir.path(), Duration::from_secs(3600)).unwrap();
+        assert!(path.exists());
+
+        prune_downloads(dir.path(), Duration::from_secs(0)).unwrap();
+        assert!(!path.exists());
     }
 }
@@ -31,7 +31,7 @@ use tough::{
 };
 use tough_kms::{KmsKeySource, KmsSigningAlgorithm};
 use tough_ssm::SsmKeySource;
-use update_metadata::{Images, Manifest, Release, UpdateWaves};
+use update_metadata::{Channel, Images, Manifest, Release, UpdateWaves};
 use url::Url;
 
 lazy_static! {
@@ -55,6 +55,9 @@ pub(crate) struct RepoArgs {
     #[structopt(long)]
     /// The variant of the update being added
     variant: String,
+    #[structopt(long, default_value = "stable")]
+    /// The release channel of the update being added: stable, beta, or canary
+    channel: Channel,
 
     // The images to add in this update
     #[structopt(long, parse(from_os_str))]
@@ -123,8 +126,8 @@ fn update_manifest(repo_args: &RepoArgs, manifest: &mut Manifest) -> Result<()>
     };
 
     info!(
-        "Adding update to manifest for version: {}, arch: {}, variant: {}",
-        repo_args.version, repo_args.arch, repo_args.variant
+        "Adding update to manifest for version: {}, arch: {}, variant: {}, channel: {}",
+        repo_args.version, repo_args.arch, repo_args.variant, repo_args.channel
     );
     manifest
         .add_update(
@@ -132,6 +135,7 @@ fn update_manifest(repo_args: &RepoArgs, manifest: &mut Manifest) -> Result<()>
             None,
             repo_args.arch.clone(),
             repo_args.variant.clone(),
+            repo_args.channel,
             images,
         )
         .context(error::AddUpdateSnafu)?;